@@ -0,0 +1,20 @@
+#version 450
+
+layout(location = 0) in vec2 fragLocal;
+layout(location = 1) in vec2 fragHalfSize;
+layout(location = 2) in vec4 fragColor;
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    // A pill: the quad with its ends rounded by half its height, a circle when it is square
+    float radius = fragHalfSize.y;
+    vec2 straight = vec2(max(fragHalfSize.x - radius, 0.0), 0.0);
+    float distance = length(max(abs(fragLocal) - straight, vec2(0.0))) - radius;
+
+    // Fully on or off, see dithered() in indexFromRust.frag.glsl
+    if (distance > 0.0) {
+        discard;
+    }
+
+    outColor = vec4(fragColor.rgb, 1.0);
+}
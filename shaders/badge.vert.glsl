@@ -0,0 +1,17 @@
+#version 450
+
+layout(location = 0) in vec2 inPosition; // NDC, Y pointing down
+layout(location = 1) in vec2 inLocal;    // Pixels from the badge's center
+layout(location = 2) in vec2 inHalfSize; // Half the badge's width and height in pixels
+layout(location = 3) in vec4 inColor;
+
+layout(location = 0) out vec2 fragLocal;
+layout(location = 1) out vec2 fragHalfSize;
+layout(location = 2) out vec4 fragColor;
+
+void main() {
+    gl_Position = vec4(inPosition, 0.0, 1.0);
+    fragLocal = inLocal;
+    fragHalfSize = inHalfSize;
+    fragColor = inColor;
+}
@@ -16,9 +16,6 @@ layout(binding = 0) uniform Uniforms {
 
 void main() {
 
-    float inner_radius = 0.02; // Inner radius of the cutout
-    float segment_gap = 0.1;  // Gap between segments in radians
-
     // Step 1: Set the center of the menu (fixed at origin)
     vec2 menu_center = vec2(0.0, 0.0); // Center of the window
 
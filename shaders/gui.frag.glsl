@@ -0,0 +1,26 @@
+#version 450
+
+layout(location = 0) in vec2 fragUv;
+layout(location = 1) in vec4 fragColor;
+layout(location = 0) out vec4 outColor;
+
+layout(binding = 0) uniform sampler2D fontAtlas; // egui's font atlas as coverage in the red channel
+
+void main() {
+    float coverage = texture(fontAtlas, fragUv).r * fragColor.a;
+
+    // Fully on or off, see dithered() in indexFromRust.frag.glsl
+    if (coverage < 0.5) {
+        discard;
+    }
+
+    // Undo egui's premultiplied alpha, since nothing is blended
+    vec3 color = fragColor.rgb / fragColor.a;
+
+    // A magenta widget would otherwise punch a hole
+    if (color.r > 0.99 && color.g < 0.01 && color.b > 0.99) {
+        color.b = 0.98;
+    }
+
+    outColor = vec4(color, 1.0);
+}
@@ -0,0 +1,22 @@
+#version 450
+
+layout(location = 0) in vec2 fragUv;
+layout(location = 0) out vec4 outColor;
+
+layout(binding = 0) uniform sampler2D icons; // One icon per segment, side by side; alpha 0 where see-through
+
+void main() {
+    vec4 color = texture(icons, fragUv);
+
+    // Fully on or off, see dithered() in indexFromRust.frag.glsl
+    if (color.a < 0.5) {
+        discard;
+    }
+
+    // A magenta pixel in an icon would otherwise punch a hole
+    if (color.r > 0.99 && color.g < 0.01 && color.b > 0.99) {
+        color.b = 0.98;
+    }
+
+    outColor = vec4(color.rgb, 1.0);
+}
@@ -4,78 +4,307 @@
 layout(location = 0) in vec2 fragCoord; // NDC coordinates ranging from -1 to 1
 layout(location = 0) out vec4 outColor;
 
+const int MAX_SEGMENTS = 16;
+const int MAX_RINGS = 4;
+
 layout(binding = 0) uniform Uniforms {
     float radius;       // Outer radius of the menu
     float inner_radius;
     int segments;       // Number of segments
-    float time;         // Time for animations
+    float time;         // Seconds since the renderer started, for spinners; the theme's animations come posed
     vec2 mouse_pos;     // Mouse position in NDC
     float segment_gap;
     int item_selected;
+    float dwell_progress; // 0..1 progress of the dwell-to-select timer
+    int style;          // Bit 1 = high contrast, bit 2 = reduced motion
+    vec2 unit_scale;    // Ring units per NDC unit on each axis, so rings keep their pixel size and stay round
+    vec4 segment_state[MAX_SEGMENTS]; // x = progress, y = running, z = indeterminate, w = bit 1 disabled, bit 2 on
+    int ring_count;     // Revealed rings, from the inside out
+    vec2 resolution;    // Window size in pixels
+    vec4 rings[MAX_RINGS]; // x = inner radius, y = outer radius, z = first segment, w = segment count
+    vec4 ring_angles[MAX_RINGS]; // x = start angle of the first segment, y = 1 clockwise or -1 counter-clockwise
+    vec4 segment_angles[MAX_SEGMENTS]; // x = start, y = end of each segment's slice, gap included, from its ring's start
+    vec4 segment_pulse[MAX_SEGMENTS]; // x = pulsing, y = seconds since the pulse began
+    vec4 fill;          // x = 0 built-in colors, 1 linear, 2 radial, 3 texture; y = linear angle; zw = texture repeats per ring unit
+    vec4 fill_colors[2]; // Start and end of a gradient
+    vec4 shadow;        // x = size, y = opacity, zw = offset, in ring units; zero size for none
+    vec4 shadow_color;
+    vec4 backdrop;      // x = snapshot drawn, y = segment opacity over it, z = blur radius in pixels
+    vec4 animation;     // x = how far the hovered segment reaches past its ring
+    vec4 segment_motion[MAX_SEGMENTS]; // x = scale about the segment's middle, y = ring units slid outward
+    vec4 wedge;         // x = opacity of the direction wedge, zero for none
 } ubo;
 
-void main() {
+layout(binding = 1) uniform sampler2D fillTexture; // The theme's texture, or a white pixel
+layout(binding = 2) uniform sampler2D backdrop; // The screen under the window as the menu opened, for frosted glass
+
+// Whether this pixel is one of the `coverage` share of its neighbourhood drawn by an ordered dither. The window is
+// color-keyed on magenta, so translucent pixels would blend with the key color into a pink fringe; soft effects are
+// dithered into fully opaque pixels instead
+bool dithered(float coverage) {
+    const float bayer[16] = float[16](0.0, 8.0, 2.0, 10.0, 12.0, 4.0, 14.0, 6.0, 3.0, 11.0, 1.0, 9.0, 15.0, 7.0, 13.0, 5.0);
+    ivec2 cell = ivec2(gl_FragCoord.xy) % 4;
+    return coverage > (bayer[cell.y * 4 + cell.x] + 0.5) / 16.0;
+}
+
+// Draws the theme's shadow where no segment covers the pixel: darkest under the offset rings and fading out over
+// the shadow's size, by the distance to the nearest ring's band. Discards the pixel without a shadow
+void drawShadow(vec2 coord) {
+    float size = ubo.shadow.x;
+    if (size <= 0.0) {
+        discard;
+    }
+    float dist = length(coord - ubo.shadow.zw);
+    float edge = 1e6;
+    for (int i = 0; i < ubo.ring_count && i < MAX_RINGS; i++) {
+        edge = min(edge, max(ubo.rings[i].x - dist, dist - ubo.rings[i].y));
+    }
+    float alpha = ubo.shadow.y * (1.0 - smoothstep(0.0, size, edge));
+    if (!dithered(alpha)) {
+        discard;
+    }
+    outColor = vec4(ubo.shadow_color.rgb, 1.0);
+}
+
+// Draws a faint wedge inside the innermost ring pointing from the center towards the cursor, widening outwards and
+// fading in as the cursor leaves the center, so the direction shows before a segment highlights. Its faintness is
+// dithered, with hard sides. Returns whether the pixel is drawn
+bool drawWedge(vec2 coord) {
+    if (ubo.wedge.x <= 0.0 || ubo.ring_count < 1) {
+        return false;
+    }
+    float reach = ubo.rings[0].x;
+    float dist = length(coord);
+    vec2 cursor = vec2(ubo.mouse_pos.x, -ubo.mouse_pos.y) * ubo.unit_scale; // mouse_pos has y up
+    float cursorDist = length(cursor);
+    if (dist >= reach || cursorDist < 0.001) {
+        return false;
+    }
+    const float HALF_WIDTH = 0.2; // Radians either side of the cursor's direction
+    float offset = abs(atan(cursor.x * coord.y - cursor.y * coord.x, dot(cursor, coord)));
+    if (offset > HALF_WIDTH) {
+        return false;
+    }
+    float alpha = ubo.wedge.x
+        * smoothstep(0.0, reach, dist) // From nothing at the center to full at the ring
+        * smoothstep(0.0, 0.5 * reach, cursorDist); // Fades in as the cursor leaves the center
+    if ((ubo.style & 1) != 0) {
+        alpha = alpha > 0.05 ? 1.0 : 0.0; // Solid in high contrast
+    }
+    if (!dithered(alpha)) {
+        return false;
+    }
+    outColor = vec4(1.0, 1.0, 1.0, 1.0);
+    return true;
+}
 
-    float inner_radius = 0.02; // Inner radius of the cutout
-    float segment_gap = 0.1;  // Gap between segments in radians
+void drawRing() {
 
     // Step 1: Set the center of the menu (fixed at origin)
     vec2 menu_center = vec2(0.0, 0.0); // Center of the window
 
-    // Step 2: Calculate coordinates relative to the menu center
-    vec2 coord = fragCoord - menu_center;
+    // Step 2: Calculate coordinates relative to the menu center, in ring units
+    vec2 coord = (fragCoord - menu_center) * ubo.unit_scale;
 
     // Optional: Invert Y-axis if your coordinate system requires it
     //coord.y = -coord.y;
 
     // Step 3: Calculate distance from the center
     float dist = length(coord);
+    float rawAngle = atan(coord.y, coord.x);
 
-    // Step 4: Discard pixels outside the ring
-    if (dist < ubo.inner_radius || dist > ubo.radius) {
-        discard;
-    }
+    // Step 4: Find the ring and segment this pixel is in, discarding pixels outside every revealed ring. Each
+    // ring's segment under the pixel is found by angle, then the distance and angle are taken back through the
+    // segment's motion, so it is drawn scaled about its middle and slid outward. A segment scaled past its slice
+    // reaches over its neighbours, so they are taken back too and the largest one covering the pixel is drawn
+    int ring = -1;
+    int segmentIndex = -1;
+    float angle = 0.0;
+    float pixelDist = dist;
+    // A ring reaches as far as its hovered segment grows; outer rings take precedence
+    for (int i = 0; i < ubo.ring_count && i < MAX_RINGS; i++) {
+        // Step 5: Calculate angle from the center to the current pixel, measured from the start of the ring's
+        // first segment in segment order
+        float ringAngle = (rawAngle - ubo.ring_angles[i].x) * ubo.ring_angles[i].y;
+        ringAngle = mod(ringAngle, 2.0 * 3.14159265359); // Normalize angle to [0, 2π]
 
-    // Step 5: Calculate angle from the center to the current pixel
-    float angle = atan(coord.y, coord.x);
-    if (angle < 0.0) {
-        angle += 2.0 * 3.14159265359; // Normalize angle to [0, 2π]
+        // Step 6: Find the segment whose slice contains the angle; segments share the ring by weight
+        int firstSegment = int(ubo.rings[i].z);
+        int ringSegments = int(ubo.rings[i].w);
+        int localIndex = ringSegments - 1;
+        for (int j = 0; j < ringSegments && firstSegment + j < MAX_SEGMENTS; j++) {
+            if (ringAngle < ubo.segment_angles[firstSegment + j].y) {
+                localIndex = j;
+                break;
+            }
+        }
+        float middleDist = 0.5 * (ubo.rings[i].x + ubo.rings[i].y);
+        float covering = 0.0;
+        for (int k = -1; k <= 1 && ringSegments > 0; k++) {
+            int segment = firstSegment + (localIndex + k + ringSegments) % ringSegments;
+            if (segment >= MAX_SEGMENTS) {
+                continue;
+            }
+            vec4 motion = ubo.segment_motion[segment];
+            float scale = max(motion.x, 0.001);
+            float start = ubo.segment_angles[segment].x;
+            float end = ubo.segment_angles[segment].y - ubo.segment_gap;
+            float middleAngle = 0.5 * (start + end);
+            // The short way around from the middle, so the last and first segments reach over each other too
+            float offset = mod(ringAngle - middleAngle + 3.14159265359, 2.0 * 3.14159265359) - 3.14159265359;
+            float segmentAngle = middleAngle + offset / scale;
+            float segmentDist = middleDist + (pixelDist - motion.y - middleDist) / scale;
+            bool covers = segmentDist >= ubo.rings[i].x && segmentDist <= ubo.rings[i].y + max(ubo.animation.x, 0.0)
+                && segmentAngle >= start && segmentAngle <= end;
+            if (covers && scale > covering) {
+                covering = scale;
+                ring = i;
+                segmentIndex = segment;
+                dist = segmentDist;
+                angle = segmentAngle;
+            }
+        }
     }
+    if (ring < 0) {
+        if (!drawWedge(coord)) {
+            drawShadow(coord);
+        }
+        return;
+    }
+    float ringInner = ubo.rings[ring].x;
+    float ringOuter = ubo.rings[ring].y;
 
-    // Step 6: Calculate the total angle per segment including gaps
-    float segmentAngleWithGap = (2.0 * 3.14159265359) / float(ubo.segments);
-    float segmentAngle = segmentAngleWithGap - ubo.segment_gap; // Angular width of a segment
-
-    // Step 7: Calculate the index of the current segment
-    int segmentIndex = int(angle / segmentAngleWithGap);
+    // Step 7: Calculate the angular width of the segment without its gap
+    float segmentAngle = ubo.segment_angles[segmentIndex].y - ubo.segment_angles[segmentIndex].x - ubo.segment_gap;
 
     // Step 8: Determine the start and end angle of the current segment
-    float segmentStartAngle = float(segmentIndex) * segmentAngleWithGap;
+    float segmentStartAngle = ubo.segment_angles[segmentIndex].x;
     float segmentEndAngle = segmentStartAngle + segmentAngle;
 
     // Step 9: Discard pixels that fall into the gap between segments
     if (angle < segmentStartAngle || angle > segmentEndAngle) {
-        discard;
+        drawShadow(coord);
+        return;
     }
 
-    // Step 10: Apply pulsing effect to the item selected
-    float pulsingRadius = ubo.radius;
+    // Step 10: Draw the dwell progress arc along the inner edge of the hovered segment
+    if (segmentIndex == ubo.item_selected && ubo.dwell_progress > 0.0) {
+        float sweep = (angle - segmentStartAngle) / segmentAngle;
+        if (dist < ringInner + 0.015 && sweep <= ubo.dwell_progress) {
+            outColor = vec4(1.0, 1.0, 1.0, 1.0);
+            return;
+        }
+    }
+
+    bool highContrast = (ubo.style & 1) != 0;
+    bool reducedMotion = (ubo.style & 2) != 0;
+
+    // Step 11: Move the hovered segment's outer edge as the theme's hover animation says
+    float pulsingRadius = ringOuter;
     if (segmentIndex == ubo.item_selected) {
-        pulsingRadius += 0.05 * sin(ubo.time * 2.0); // Adjust pulse amplitude as needed
+        pulsingRadius += ubo.animation.x;
     }
 
-    // Step 11: Discard pixel if it's outside the pulsing segment radius
+    // Step 12: Discard pixel if it's outside the pulsing segment radius
     if (dist > pulsingRadius) {
-        discard;
+        drawShadow(coord);
+        return;
     }
 
-    // Step 12: Set color for the current pixel
+    // Step 13: Draw the progress of a running action along the outer edge of its segment
+    if (segmentIndex < MAX_SEGMENTS && ubo.segment_state[segmentIndex].y > 0.0) {
+        vec4 progress = ubo.segment_state[segmentIndex];
+        float sweep = (angle - segmentStartAngle) / segmentAngle;
+        bool lit = progress.z > 0.0
+            ? (reducedMotion
+                ? fract(sweep * 4.0) < 0.5 // Still dashes while the fraction is unknown
+                : abs(sweep - fract(ubo.time * 0.75)) < 0.15) // Spinner while the fraction is unknown
+            : sweep <= progress.x;
+        if (dist > pulsingRadius - 0.015 && lit) {
+            outColor = vec4(1.0, 1.0, 1.0, 1.0);
+            return;
+        }
+    }
+
+    // Step 14: Set color for the current pixel
     // For debugging, assign different colors to different segments
     vec3 segmentColor = vec3(float(segmentIndex) / float(ubo.segments), 1.0, 1.0);
-    if (segmentIndex == ubo.item_selected) {
+    int fillMode = int(ubo.fill.x);
+    if (fillMode == 1) {
+        // A linear gradient across the whole menu, along the theme's angle
+        vec2 direction = vec2(cos(ubo.fill.y), -sin(ubo.fill.y)); // Counter-clockwise on screen, where y is down
+        float t = clamp(dot(coord, direction) / (2.0 * ubo.radius) + 0.5, 0.0, 1.0);
+        segmentColor = mix(ubo.fill_colors[0].rgb, ubo.fill_colors[1].rgb, t);
+    } else if (fillMode == 2) {
+        // A radial gradient from each ring's inner edge to its outer edge
+        float t = clamp((dist - ringInner) / (ringOuter - ringInner), 0.0, 1.0);
+        segmentColor = mix(ubo.fill_colors[0].rgb, ubo.fill_colors[1].rgb, t);
+    } else if (fillMode == 3) {
+        // The texture tiled from the center, upright since ring coordinates grow downwards like its rows
+        segmentColor = texture(fillTexture, coord * ubo.fill.zw).rgb;
+    }
+    if (ubo.backdrop.x > 0.0) {
+        // Frosted glass: the segment's color over a box blur of the screen behind it
+        vec2 uv = fragCoord * 0.5 + 0.5;
+        vec2 step = 0.5 * ubo.backdrop.z / ubo.resolution;
+        vec3 blurred = vec3(0.0);
+        for (int x = -2; x <= 2; x++) {
+            for (int y = -2; y <= 2; y++) {
+                blurred += texture(backdrop, uv + vec2(x, y) * step).rgb;
+            }
+        }
+        segmentColor = mix(blurred / 25.0, segmentColor, ubo.backdrop.y);
+    }
+    int flags = segmentIndex < MAX_SEGMENTS ? int(ubo.segment_state[segmentIndex].w) : 0;
+    bool disabled = (flags & 1) != 0;
+    bool on = (flags & 2) != 0; // Toggle segments whose state is on
+    const float ON_BAND = 0.02; // Width of the lit band along the inner edge of an on segment
+    if (highContrast) {
+        // Near-black segments outlined in white, gray when disabled, and a solid yellow highlight
+        const float OUTLINE = 0.006;
+        bool outline = dist < ringInner + OUTLINE
+            || dist > pulsingRadius - OUTLINE
+            || (angle - segmentStartAngle) * dist < OUTLINE
+            || (segmentEndAngle - angle) * dist < OUTLINE;
+        if (outline) {
+            outColor = vec4(disabled ? vec3(0.5) : vec3(1.0), 1.0);
+        } else if (on && dist < ringInner + ON_BAND) {
+            outColor = vec4(0.0, 1.0, 1.0, 1.0);
+        } else if (segmentIndex == ubo.item_selected && !disabled) {
+            outColor = vec4(1.0, 1.0, 0.0, 1.0);
+        } else {
+            outColor = vec4(0.02, 0.02, 0.02, 1.0);
+        }
+    } else if (disabled) {
+        // Disabled segments are drawn gray and dark
+        outColor = vec4(vec3(0.25), 1.0);
+    } else if (on) {
+        // On segments get a green band along the inner edge and a green tint, brighter when hovered
+        vec3 onColor = vec3(0.2, 0.85, 0.45);
+        if (dist < ringInner + ON_BAND) {
+            outColor = vec4(onColor, 1.0);
+        } else {
+            vec3 tinted = mix(segmentColor, onColor, 0.35);
+            outColor = vec4(segmentIndex == ubo.item_selected ? tinted : tinted * 0.6, 1.0);
+        }
+    } else if (segmentIndex == ubo.item_selected) {
         // Highlight the hovered segment
         outColor = vec4(segmentColor, 1.0);
     } else {
         outColor = vec4(segmentColor * 0.5, 1.0); // Dim other segments
     }
+
+    // Step 15: Pulse segments that call for attention, e.g. while recording, by glowing towards red. The phase
+    // starts dark when the pulse begins; with reduced motion the glow holds still
+    if (!disabled && ubo.segment_pulse[segmentIndex].x > 0.0) {
+        float glow = reducedMotion ? 0.5 : 0.5 - 0.5 * cos(ubo.segment_pulse[segmentIndex].y * 4.0);
+        vec3 attention = highContrast ? vec3(1.0, 0.0, 0.0) : vec3(1.0, 0.25, 0.2);
+        // The rim glows fully, the body only partly, so labels and icons stay readable
+        float rim = dist > pulsingRadius - 0.02 ? 1.0 : 0.4;
+        outColor = vec4(mix(outColor.rgb, attention, glow * rim), 1.0);
+    }
+}
+
+void main() {
+    drawRing();
 }
@@ -0,0 +1,42 @@
+#version 450
+
+layout(location = 0) in vec2 fragUv;
+layout(location = 0) out vec4 outColor;
+
+layout(binding = 0) uniform sampler2D capture; // Screen pixels around the cursor; alpha 0 while the picker is off
+
+const float GRID_LINE = 0.06;   // Fraction of a magnified pixel drawn as the grid between pixels
+const float CENTER_LINE = 0.14; // Fraction of the middle pixel drawn as its outline
+const float FRAME = 0.01;       // Fraction of the loupe drawn as its frame
+
+void main() {
+    vec4 color = texture(capture, fragUv);
+    if (color.a < 0.5) {
+        discard;
+    }
+
+    vec2 size = vec2(textureSize(capture, 0));
+    vec2 cell = fragUv * size;
+    vec2 inCell = fract(cell);
+
+    // Outline the picked pixel in the middle and the loupe itself
+    bool center = all(equal(floor(cell), floor(size / 2.0)));
+    bool centerEdge = center && (any(lessThan(inCell, vec2(CENTER_LINE))) || any(greaterThan(inCell, vec2(1.0 - CENTER_LINE))));
+    bool frame = any(lessThan(fragUv, vec2(FRAME))) || any(greaterThan(fragUv, vec2(1.0 - FRAME)));
+    if (centerEdge || frame) {
+        outColor = vec4(1.0, 1.0, 1.0, 1.0);
+        return;
+    }
+
+    // Darken thin lines between pixels so neighbours with close colors can be told apart
+    if (any(lessThan(inCell, vec2(GRID_LINE)))) {
+        color.rgb *= 0.7;
+    }
+
+    // A magenta pixel on screen would otherwise punch a hole
+    if (color.r > 0.99 && color.g < 0.01 && color.b > 0.99) {
+        color.b = 0.98;
+    }
+
+    outColor = vec4(color.rgb, 1.0);
+}
@@ -0,0 +1,19 @@
+#version 450
+
+// Loupe rectangle in NDC, Y pointing down: left, top, right, bottom
+layout(push_constant) uniform Loupe {
+    vec4 rect;
+} loupe;
+
+layout(location = 0) out vec2 fragUv;
+
+const vec2 corners[6] = vec2[](
+    vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(0.0, 1.0),
+    vec2(0.0, 1.0), vec2(1.0, 0.0), vec2(1.0, 1.0)
+);
+
+void main() {
+    vec2 corner = corners[gl_VertexIndex];
+    gl_Position = vec4(mix(loupe.rect.xy, loupe.rect.zw, corner), 0.0, 1.0);
+    fragUv = corner;
+}
@@ -0,0 +1,18 @@
+#version 450
+
+layout(location = 0) in vec2 fragUv;
+layout(location = 1) in vec4 fragColor;
+layout(location = 0) out vec4 outColor;
+
+layout(binding = 0) uniform sampler2D glyphAtlas; // Coverage in the red channel
+
+void main() {
+    float coverage = texture(glyphAtlas, fragUv).r * fragColor.a;
+
+    // Fully on or off, see dithered() in indexFromRust.frag.glsl
+    if (coverage < 0.5) {
+        discard;
+    }
+
+    outColor = vec4(fragColor.rgb, 1.0);
+}
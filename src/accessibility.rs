@@ -0,0 +1,72 @@
+// High-contrast and reduced-motion modes, following the Windows settings unless the config forces them, and
+// spoken announcements of the selection
+
+use winapi::shared::minwindef::{BOOL, FALSE, TRUE};
+use winapi::um::winuser::{
+    SystemParametersInfoW, HCF_HIGHCONTRASTON, HIGHCONTRASTW, SPI_GETCLIENTAREAANIMATION, SPI_GETHIGHCONTRAST,
+};
+use std::mem::size_of;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use crate::config::AccessibilityConfig;
+
+static CONFIG: Mutex<AccessibilityConfig> = Mutex::new(AccessibilityConfig {
+    high_contrast: None,
+    reduced_motion: None,
+    announce: false,
+});
+static HIGH_CONTRAST: AtomicBool = AtomicBool::new(false);
+static REDUCED_MOTION: AtomicBool = AtomicBool::new(false);
+
+/// Takes the forced modes from a newly loaded config and applies them.
+pub fn configure(config: &AccessibilityConfig) {
+    *CONFIG.lock().unwrap() = config.clone();
+    refresh();
+}
+
+/// Reads the Windows settings again; called when the menu opens, so changing them needs no restart.
+pub fn refresh() {
+    let config = CONFIG.lock().unwrap().clone();
+    let high_contrast = config.high_contrast.unwrap_or_else(system_high_contrast);
+    let reduced_motion = config.reduced_motion.unwrap_or_else(system_reduced_motion);
+    HIGH_CONTRAST.store(high_contrast, Ordering::SeqCst);
+    REDUCED_MOTION.store(reduced_motion, Ordering::SeqCst);
+}
+
+/// Draw the ring as outlined segments with a solid highlight instead of colors.
+pub fn high_contrast() -> bool {
+    HIGH_CONTRAST.load(Ordering::SeqCst)
+}
+
+/// Keep the pulse, spinners and sliding progress still.
+pub fn reduced_motion() -> bool {
+    REDUCED_MOTION.load(Ordering::SeqCst)
+}
+
+/// Read the selected segment's label aloud as the selection moves.
+pub fn announces() -> bool {
+    CONFIG.lock().unwrap().announce
+}
+
+/// Whether a Windows high-contrast theme is on.
+fn system_high_contrast() -> bool {
+    let mut high_contrast = HIGHCONTRASTW {
+        cbSize: size_of::<HIGHCONTRASTW>() as u32,
+        dwFlags: 0,
+        lpszDefaultScheme: null_mut(),
+    };
+    let read = unsafe {
+        SystemParametersInfoW(SPI_GETHIGHCONTRAST, high_contrast.cbSize, &mut high_contrast as *mut _ as *mut _, 0)
+    } != 0;
+    read && high_contrast.dwFlags & HCF_HIGHCONTRASTON != 0
+}
+
+/// Whether "Show animations in Windows" is off.
+fn system_reduced_motion() -> bool {
+    let mut animations: BOOL = TRUE;
+    let read = unsafe {
+        SystemParametersInfoW(SPI_GETCLIENTAREAANIMATION, 0, &mut animations as *mut _ as *mut _, 0)
+    } != 0;
+    read && animations == FALSE
+}
@@ -0,0 +1,419 @@
+// Menu item actions and how they run; the executor calls them off the UI thread
+
+use serde::{Deserialize, Serialize};
+use winapi::shared::windef::HWND;
+use winapi::um::shellapi::ShellExecuteW;
+use winapi::um::winuser::SW_SHOWNORMAL;
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::process::{Command, Output, Stdio};
+use std::ptr::null_mut;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use crate::brightness;
+use crate::clipboard;
+use crate::focus;
+use crate::hotkey::Hotkey;
+use crate::keys;
+use crate::mic;
+use crate::mqtt;
+use crate::template;
+use crate::panel::{self, PanelContent};
+use crate::picker::{self, ColorFormat};
+use crate::power::{self, PowerMode};
+use crate::process;
+use crate::profile_switcher;
+use crate::settings;
+use crate::variables;
+use crate::window::to_wstring;
+
+/// How often a running command is checked for having exited or run out of time.
+const COMMAND_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Action attached to a menu item, as declared in the config file.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Action {
+    /// Starts a program without waiting for it to exit, or opens a URL such as "https://..." in its default app.
+    Launch {
+        path: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    /// Opens a file or folder with its associated app, like double-clicking it in Explorer. Shortcuts open what
+    /// they point to, so the recent files menu opens the entries of the Recent Items folder directly.
+    Open { path: String },
+    /// Runs a command line through `cmd /C` and waits for it to finish.
+    /// With `show_output`, its output is listed in a panel afterwards.
+    Command {
+        command: String,
+        #[serde(default)]
+        show_output: bool,
+    },
+    /// Presses a key combination in the focused app, written like hotkeys: "Ctrl+Win+Right".
+    SendKeys { keys: String },
+    /// Types text, including emoji and other non-ASCII characters, into the focused app.
+    /// With `placeholders`, "{date}", "{time}" and variables are filled in first, for snippet menus.
+    TypeText {
+        text: String,
+        #[serde(default)]
+        placeholders: bool,
+    },
+    /// Sets a variable, e.g. to switch a mode that labels and conditions read.
+    SetVariable { name: String, value: String },
+    /// Asks for confirmation in a panel before running the wrapped action.
+    Confirm { message: String, action: Box<Action> },
+    /// Runs actions one after another, stopping at the first failure. Use `wait` steps for delays.
+    Macro { steps: Vec<Action> },
+    /// Pauses a macro, e.g. until a launched app has opened its window.
+    Wait { ms: u64 },
+    /// Sends an HTTP request, e.g. to a Home Assistant webhook, and toasts the response status.
+    Http {
+        url: String,
+        #[serde(default)]
+        method: HttpMethod,
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+        #[serde(default)]
+        body: String,
+    },
+    /// Publishes a message to the `[mqtt]` broker, e.g. to toggle a smart light.
+    Mqtt {
+        topic: String,
+        #[serde(default)]
+        payload: String,
+        #[serde(default)]
+        qos: u8, // 0, 1 or 2
+        #[serde(default)]
+        retain: bool,
+    },
+    /// Shows a magnified loupe that follows the cursor; Enter copies the color of the pixel in its middle.
+    PickColor {
+        #[serde(default)]
+        format: ColorFormat,
+    },
+    /// Sets the brightness of the monitor under the cursor over DDC/CI, to `level` percent or by `step` points
+    /// such as -10. Laptop panels and monitors without DDC/CI report an error instead.
+    Brightness {
+        #[serde(default)]
+        level: Option<u8>,
+        #[serde(default)]
+        step: i32,
+    },
+    /// Brings a window to the front, restoring it when minimized. The window switcher builds these from the
+    /// handles of open windows; handles don't outlive their windows, so the action is no use in the config file.
+    ActivateWindow { handle: usize },
+    /// Puts text on the clipboard and presses Ctrl+V in the focused app, e.g. for clips from the clipboard history.
+    Paste { text: String },
+    /// Opens the settings screen on the overlay, for editing hotkeys, segment labels and display modes.
+    Settings,
+    /// Locks the workstation, sleeps, hibernates, shuts down, restarts or turns the displays off.
+    Power { mode: PowerMode },
+    /// Ends every process with this exe name, e.g. a hung game. With `restart`, starts it again once it has gone,
+    /// e.g. to restart "explorer.exe".
+    KillProcess {
+        name: String,
+        #[serde(default)]
+        restart: bool,
+    },
+    /// Mutes or unmutes the default microphone. With `keys`, presses an app's own mute hotkey instead, e.g.
+    /// Discord's "Ctrl+Shift+M" set up as a global keybind. A "{mic_muted}" badge shows the state.
+    ToggleMic {
+        #[serde(default)]
+        keys: Option<String>,
+    },
+    /// Makes the named profile the active one, saving it as `profile` in the config file so it stays active after
+    /// a restart. The profile switcher page builds these.
+    SwitchProfile { name: String },
+}
+
+/// What running actions get from the executor besides the action itself.
+#[derive(Clone, Default)]
+pub struct ActionContext {
+    pub timeout: Option<Duration>, // Commands and HTTP requests still running after this long are stopped
+    pub mqtt: Option<rumqttc::Client>, // Connection to the configured broker, None without one
+}
+
+/// Request methods of the `http` action, written in capitals like "POST".
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HttpMethod {
+    #[default]
+    Get,
+    Post,
+    Put,
+    Delete,
+}
+
+impl HttpMethod {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HttpMethod::Get => "GET",
+            HttpMethod::Post => "POST",
+            HttpMethod::Put => "PUT",
+            HttpMethod::Delete => "DELETE",
+        }
+    }
+}
+
+/// Events sent back to the main loop by running actions.
+#[derive(Clone, Debug)]
+pub enum ActionEvent {
+    /// `fraction` is None while the action can't estimate how far along it is.
+    Progress { segment: i32, fraction: Option<f32> },
+    /// `outcome` holds the message to toast on success, if any, or the error.
+    Finished { segment: i32, label: String, outcome: Result<Option<String>, String> },
+}
+
+/// Performs the action, calling `report` whenever progress is known.
+/// Returns the message to toast on success, if any.
+pub(crate) fn run_action(
+    action: &Action,
+    label: &str,
+    report: &dyn Fn(Option<f32>),
+    context: &ActionContext,
+) -> Result<Option<String>, String> {
+    let timeout = context.timeout;
+    match action {
+        Action::Launch { path, .. } if is_url(path) => {
+            shell_open(path)?;
+            Ok(Some(format!("Opened {}", label)))
+        }
+        Action::Launch { path, args } => {
+            Command::new(path)
+                .args(args)
+                .spawn()
+                .map_err(|e| format!("Failed to launch {}: {:?}", path, e))?;
+            Ok(Some(format!("Launched {}", label)))
+        }
+        Action::Open { path } => {
+            shell_open(path)?;
+            Ok(Some(format!("Opened {}", label)))
+        }
+        Action::Command { command, show_output: false } => {
+            report(None);
+            let status = run_command(command, false, timeout)?.status;
+            if status.success() {
+                Ok(Some(format!("Finished {}", label)))
+            } else {
+                Err(format!("Command exited with {}", status))
+            }
+        }
+        Action::Command { command, show_output: true } => {
+            report(None);
+            let output = {
+                let _progress = panel::open(PanelContent::Progress {
+                    title: format!("Running {}", label),
+                    fraction: None,
+                });
+                run_command(command, true, timeout)?
+            };
+            let lines = String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .chain(String::from_utf8_lossy(&output.stderr).lines())
+                .map(str::to_string)
+                .collect();
+            panel::show(PanelContent::List {
+                title: format!("{} ({})", label, output.status),
+                lines,
+            });
+            if output.status.success() {
+                Ok(None)
+            } else {
+                Err(format!("Command exited with {}", output.status))
+            }
+        }
+        Action::SendKeys { keys } => {
+            keys::send_combo(&Hotkey::parse(keys)?)?;
+            Ok(None)
+        }
+        Action::TypeText { text, placeholders } => {
+            if *placeholders {
+                keys::type_text(&template::expand(text))?;
+            } else {
+                keys::type_text(text)?;
+            }
+            Ok(None)
+        }
+        Action::SetVariable { name, value } => {
+            variables::set(name.as_str(), value.as_str());
+            Ok(None)
+        }
+        Action::Confirm { message, action } => {
+            if panel::confirm(label, message) {
+                run_action(action, label, report, context)
+            } else {
+                Ok(None)
+            }
+        }
+        Action::Macro { steps } => {
+            // Progress counts finished steps; the steps' own messages would flood the toasts
+            for (index, step) in steps.iter().enumerate() {
+                report(Some(index as f32 / steps.len() as f32));
+                run_action(step, label, &|_| {}, context).map_err(|e| format!("Step {} failed: {}", index + 1, e))?;
+            }
+            Ok(Some(format!("Finished {}", label)))
+        }
+        Action::Wait { ms } => {
+            thread::sleep(Duration::from_millis(*ms));
+            Ok(None)
+        }
+        Action::Http { url, method, headers, body } => {
+            report(None);
+            let mut request = ureq::request(method.as_str(), url);
+            if let Some(timeout) = timeout {
+                request = request.timeout(timeout);
+            }
+            for (name, value) in headers {
+                request = request.set(name, value);
+            }
+            let response = if body.is_empty() { request.call() } else { request.send_string(body) };
+            match response {
+                Ok(response) => {
+                    let done = format!("{}: {} {}", label, response.status(), response.status_text());
+                    read_body(response, report)?;
+                    Ok(Some(done))
+                }
+                Err(ureq::Error::Status(status, response)) => Err(format!("{} {}", status, response.status_text())),
+                Err(e) => Err(format!("Request failed: {}", e)),
+            }
+        }
+        Action::Mqtt { topic, payload, qos, retain } => {
+            let client = context.mqtt.as_ref().ok_or("No MQTT broker configured; set [mqtt] host")?;
+            mqtt::publish(client, topic, payload, *qos, *retain)?;
+            Ok(Some(format!("Sent {}", label)))
+        }
+        Action::PickColor { format } => {
+            picker::start(*format);
+            Ok(None)
+        }
+        Action::Brightness { level, step } => {
+            let percent = match level {
+                Some(level) => *level as f32,
+                None => brightness::get()? * 100.0 + *step as f32,
+            }
+            .clamp(0.0, 100.0);
+            brightness::set(percent / 100.0)?;
+            Ok(Some(format!("Brightness {:.0}%", percent)))
+        }
+        Action::ActivateWindow { handle } => {
+            focus::activate(*handle as HWND)?;
+            Ok(None)
+        }
+        Action::Paste { text } => {
+            clipboard::set_text(text)?;
+            keys::send_combo(&Hotkey::parse("Ctrl+V")?)?;
+            Ok(None)
+        }
+        Action::Settings => {
+            settings::open();
+            Ok(None)
+        }
+        Action::Power { mode } => {
+            power::run(*mode)?;
+            Ok(mode.done_message().map(str::to_string))
+        }
+        Action::KillProcess { name, restart: false } => {
+            let count = process::kill(name)?;
+            Ok(Some(if count == 1 { format!("Ended {}", name) } else { format!("Ended {} {} processes", count, name) }))
+        }
+        Action::ToggleMic { keys } => {
+            let muted = mic::toggle(keys.as_deref())?;
+            Ok(Some(if muted { "Microphone muted" } else { "Microphone unmuted" }.to_string()))
+        }
+        Action::KillProcess { name, restart: true } => {
+            report(None);
+            process::restart(name)?;
+            Ok(Some(format!("Restarted {}", name)))
+        }
+        Action::SwitchProfile { name } => profile_switcher::switch(name),
+    }
+}
+
+/// Runs a command line through `cmd /C`, stopping it if it is still running after `timeout`.
+/// With `capture` its output is collected, otherwise it goes to the overlay's console.
+fn run_command(command: &str, capture: bool, timeout: Option<Duration>) -> Result<Output, String> {
+    let pipe = || if capture { Stdio::piped() } else { Stdio::inherit() };
+    let mut child = Command::new("cmd")
+        .args(["/C", command])
+        .stdout(pipe())
+        .stderr(pipe())
+        .spawn()
+        .map_err(|e| format!("Failed to run command: {:?}", e))?;
+
+    // Drain the pipes while waiting, or a chatty command blocks once they fill up
+    let stdout = child.stdout.take().map(read_in_background);
+    let stderr = child.stderr.take().map(read_in_background);
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|e| format!("Failed to wait for command: {:?}", e))? {
+            break status;
+        }
+        if deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+            // Only cmd itself is killed; programs it started keep running on their own
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("Timed out after {} s, command stopped", timeout.unwrap_or_default().as_secs()));
+        }
+        thread::sleep(COMMAND_POLL_INTERVAL);
+    };
+
+    let collect = |reader: Option<JoinHandle<Vec<u8>>>| reader.and_then(|reader| reader.join().ok()).unwrap_or_default();
+    Ok(Output {
+        status,
+        stdout: collect(stdout),
+        stderr: collect(stderr),
+    })
+}
+
+/// Reads a response body to its end, reporting how much of it arrived when its length is known.
+fn read_body(response: ureq::Response, report: &dyn Fn(Option<f32>)) -> Result<(), String> {
+    let length = response
+        .header("Content-Length")
+        .and_then(|length| length.parse::<u64>().ok())
+        .filter(|&length| length > 0);
+    let mut reader = response.into_reader();
+    let mut buffer = [0u8; 16 * 1024];
+    let mut received = 0u64;
+    loop {
+        let read = reader.read(&mut buffer).map_err(|e| format!("Reading the response failed: {}", e))?;
+        if read == 0 {
+            return Ok(());
+        }
+        received += read as u64;
+        if let Some(length) = length {
+            report(Some((received as f32 / length as f32).min(1.0)));
+        }
+    }
+}
+
+fn read_in_background<R: Read + Send + 'static>(mut pipe: R) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = pipe.read_to_end(&mut bytes);
+        bytes
+    })
+}
+
+/// Whether a launch path is a URL ("scheme://...") rather than a program.
+pub fn is_url(path: &str) -> bool {
+    path.split_once("://").map_or(false, |(scheme, _)| {
+        scheme.len() > 1 && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+    })
+}
+
+/// Opens a URL with the app registered for its scheme, or a file or folder with its associated app.
+fn shell_open(target: &str) -> Result<(), String> {
+    let operation = to_wstring("open");
+    let target_w = to_wstring(target);
+    let result = unsafe {
+        ShellExecuteW(null_mut(), operation.as_ptr(), target_w.as_ptr(), null_mut(), null_mut(), SW_SHOWNORMAL)
+    };
+    // Values above 32 mean success
+    if result as usize > 32 {
+        Ok(())
+    } else {
+        Err(format!("Failed to open {} (error {})", target, result as usize))
+    }
+}
@@ -0,0 +1,72 @@
+// Finds which GPU drives the overlay's monitor, so hybrid-graphics laptops render on the adapter that presents
+
+use ash::{vk, Instance};
+use winapi::shared::dxgi::{CreateDXGIFactory1, IDXGIAdapter1, IDXGIFactory1, IDXGIOutput, DXGI_ADAPTER_DESC1, DXGI_OUTPUT_DESC};
+use winapi::shared::windef::HWND;
+use winapi::shared::winerror::SUCCEEDED;
+use winapi::um::winuser::{MonitorFromWindow, MONITOR_DEFAULTTONEAREST};
+use winapi::Interface;
+use std::ffi::c_void;
+use std::mem::zeroed;
+use std::ptr::null_mut;
+
+/// Locally unique adapter identifier, as reported by both DXGI and Vulkan.
+pub type Luid = [u8; 8];
+
+/// The adapter connected to the monitor the window is on, with its name.
+pub fn presenting_adapter(hwnd: HWND) -> Option<(Luid, String)> {
+    unsafe {
+        let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+
+        let mut factory: *mut IDXGIFactory1 = null_mut();
+        if !SUCCEEDED(CreateDXGIFactory1(&IDXGIFactory1::uuidof(), &mut factory as *mut _ as *mut *mut c_void)) {
+            return None;
+        }
+
+        let mut found = None;
+        let mut adapter_index = 0;
+        let mut adapter: *mut IDXGIAdapter1 = null_mut();
+        while found.is_none() && SUCCEEDED((*factory).EnumAdapters1(adapter_index, &mut adapter)) {
+            let mut output_index = 0;
+            let mut output: *mut IDXGIOutput = null_mut();
+            while found.is_none() && SUCCEEDED((*adapter).EnumOutputs(output_index, &mut output)) {
+                let mut output_desc: DXGI_OUTPUT_DESC = zeroed();
+                if SUCCEEDED((*output).GetDesc(&mut output_desc)) && output_desc.Monitor == monitor {
+                    let mut adapter_desc: DXGI_ADAPTER_DESC1 = zeroed();
+                    if SUCCEEDED((*adapter).GetDesc1(&mut adapter_desc)) {
+                        let luid = adapter_desc.AdapterLuid;
+                        let mut bytes = [0u8; 8];
+                        bytes[..4].copy_from_slice(&luid.LowPart.to_le_bytes());
+                        bytes[4..].copy_from_slice(&luid.HighPart.to_le_bytes());
+                        let length = adapter_desc.Description.iter().position(|&c| c == 0).unwrap_or(adapter_desc.Description.len());
+                        found = Some((bytes, String::from_utf16_lossy(&adapter_desc.Description[..length])));
+                    }
+                }
+                (*output).Release();
+                output_index += 1;
+            }
+            (*adapter).Release();
+            adapter_index += 1;
+        }
+
+        (*factory).Release();
+        found
+    }
+}
+
+/// The LUID of a Vulkan physical device, if the driver reports one (Vulkan 1.1 and later).
+pub fn device_luid(instance: &Instance, device: vk::PhysicalDevice) -> Option<Luid> {
+    let properties = unsafe { instance.get_physical_device_properties(device) };
+    if properties.api_version < vk::API_VERSION_1_1 {
+        return None;
+    }
+
+    let mut id_properties = vk::PhysicalDeviceIDProperties::default();
+    let mut properties2 = vk::PhysicalDeviceProperties2::builder().push_next(&mut id_properties);
+    unsafe { instance.get_physical_device_properties2(device, &mut properties2) };
+    if id_properties.device_luid_valid == vk::TRUE {
+        Some(id_properties.device_luid)
+    } else {
+        None
+    }
+}
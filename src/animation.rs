@@ -0,0 +1,241 @@
+// Keyframe animations described in the theme: how the menu scales, fades and turns in as it opens, what it does
+// while it stays open, and how the hovered segment moves. Evaluated on the CPU each frame into the ring's layout and
+// uniforms, so icons and badges follow the ring.
+
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use std::sync::Mutex;
+use std::time::Duration;
+use crate::accessibility;
+use crate::config::AnimationConfig;
+use crate::menu::MAX_SEGMENTS;
+use crate::overlay::OverlayContent;
+use crate::rings::{MenuLayout, UNIT_PIXELS};
+
+static ANIMATION: Mutex<Option<AnimationConfig>> = Mutex::new(None);
+
+pub fn configure(config: &AnimationConfig) {
+    *ANIMATION.lock().unwrap() = Some(config.clone());
+}
+
+/// How a value moves from the previous keyframe to the next.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    /// Overshoots a little before settling, for a springy pop.
+    BackOut,
+}
+
+impl Easing {
+    /// Eased progress for linear progress `t` in 0..1.
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Easing::BackOut => {
+                const OVERSHOOT: f32 = 1.70158;
+                let u = t - 1.0;
+                1.0 + (OVERSHOOT + 1.0) * u.powi(3) + OVERSHOOT * u.powi(2)
+            }
+        }
+    }
+}
+
+/// A value at a time into a timeline, reached from the previous keyframe with `easing`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Keyframe {
+    pub at_ms: u32,
+    pub value: f32,
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+/// Tracks that run together from the same start. Each holds its first keyframe's value before it and its last
+/// one's after; a track without keyframes leaves its value at rest.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct Timeline {
+    pub scale: Vec<Keyframe>, // Size of the menu, 1 at rest
+    pub opacity: Vec<Keyframe>, // 0..1 opacity of the ring, 1 at rest
+    pub rotation_degrees: Vec<Keyframe>, // Turn of the menu, clockwise, 0 at rest
+    pub grow: Vec<Keyframe>, // Pixels the hovered segment reaches past the ring, 0 at rest; hover only
+    pub segment_scale: Vec<Keyframe>, // Size of each segment about its middle, 1 at rest
+    pub segment_slide: Vec<Keyframe>, // Pixels each segment is pushed out from the center, 0 at rest
+    pub stagger_ms: u32, // Delay of each segment's tracks after the previous segment's, in segment order
+    pub repeat: bool, // Start over once the longest track ends
+}
+
+impl Timeline {
+    /// When the longest track ends, for the first segment.
+    pub fn duration_ms(&self) -> u32 {
+        [&self.scale, &self.opacity, &self.rotation_degrees, &self.grow, &self.segment_scale, &self.segment_slide]
+            .iter()
+            .filter_map(|track| track.last())
+            .map(|keyframe| keyframe.at_ms)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Milliseconds into the timeline `elapsed` after it started, wrapped when it repeats.
+    fn local_ms(&self, elapsed: Duration) -> f32 {
+        let ms = elapsed.as_secs_f32() * 1000.0;
+        let duration = self.duration_ms() as f32;
+        if self.repeat && duration > 0.0 {
+            ms % duration
+        } else {
+            ms
+        }
+    }
+}
+
+/// The value of a track `ms` into its timeline, `rest` without keyframes.
+pub fn sample(track: &[Keyframe], ms: f32, rest: f32) -> f32 {
+    let next = match track.iter().position(|keyframe| keyframe.at_ms as f32 > ms) {
+        Some(0) => return track[0].value,
+        Some(next) => next,
+        None => return track.last().map_or(rest, |keyframe| keyframe.value),
+    };
+    let (from, to) = (&track[next - 1], &track[next]);
+    let t = (ms - from.at_ms as f32) / (to.at_ms - from.at_ms) as f32;
+    from.value + (to.value - from.value) * to.easing.apply(t)
+}
+
+/// The animated state of the menu for a frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pose {
+    pub scale: f32,
+    pub opacity: f32,
+    pub rotation: f32, // Radians, clockwise on screen
+    pub grow: f32, // Ring units the hovered segment reaches past the ring
+    pub segments: [SegmentMotion; MAX_SEGMENTS],
+}
+
+/// How one segment is moved from its place in the layout.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SegmentMotion {
+    pub scale: f32, // About the middle of the segment
+    pub slide: f32, // Ring units out from the center
+}
+
+impl SegmentMotion {
+    pub const REST: SegmentMotion = SegmentMotion { scale: 1.0, slide: 0.0 };
+
+    /// Packed for the shader: x = scale, y = slide.
+    pub fn as_uniform(&self) -> [f32; 4] {
+        [self.scale, self.slide, 0.0, 0.0]
+    }
+}
+
+impl Pose {
+    /// The menu at rest, as golden images and reduced motion draw it.
+    pub const REST: Pose = Pose {
+        scale: 1.0,
+        opacity: 1.0,
+        rotation: 0.0,
+        grow: 0.0,
+        segments: [SegmentMotion::REST; MAX_SEGMENTS],
+    };
+
+    /// Adds a timeline `ms` into it, for the segments `stagger` behind each other when the segment tracks
+    /// are staggered. Scales and opacities multiply, the rest adds up.
+    fn add(&mut self, timeline: &Timeline, ms: f32, segments: impl Iterator<Item = (usize, f32)>) {
+        self.scale *= sample(&timeline.scale, ms, 1.0);
+        self.opacity *= sample(&timeline.opacity, ms, 1.0).clamp(0.0, 1.0);
+        self.rotation += sample(&timeline.rotation_degrees, ms, 0.0).to_radians();
+        self.grow += sample(&timeline.grow, ms, 0.0) / UNIT_PIXELS;
+        for (segment, delay) in segments {
+            let motion = &mut self.segments[segment];
+            motion.scale *= sample(&timeline.segment_scale, ms - delay, 1.0).max(0.0);
+            motion.slide += sample(&timeline.segment_slide, ms - delay, 0.0) / UNIT_PIXELS;
+        }
+    }
+}
+
+/// The pose `since_open` after a menu of `segments` segments opened, with `hovered` the hovered segment and how
+/// long it has been hovered. The open timeline runs first, each segment `stagger_ms` after the one before, and
+/// the idle one follows once the last segment's part ends. The hover timeline moves the hovered segment alone.
+pub fn pose(since_open: Duration, segments: usize, hovered: Option<(i32, Duration)>) -> Pose {
+    let animation = ANIMATION.lock().unwrap().clone().unwrap_or_default();
+    let segments = segments.min(MAX_SEGMENTS);
+    let mut pose = Pose::REST;
+
+    let open = &animation.open;
+    let open_ms = open.local_ms(since_open);
+    pose.add(open, open_ms, (0..segments).map(|segment| (segment, (segment as u32 * open.stagger_ms) as f32)));
+
+    let open_end = open.duration_ms() + open.stagger_ms * segments.saturating_sub(1) as u32;
+    if since_open.as_millis() as u32 >= open_end {
+        let idle = &animation.idle;
+        let idle_ms = idle.local_ms(since_open - Duration::from_millis(open_end as u64));
+        pose.add(idle, idle_ms, (0..segments).map(|segment| (segment, (segment as u32 * idle.stagger_ms) as f32)));
+    }
+
+    if let Some((segment, hovered)) = hovered.filter(|&(segment, _)| segment >= 0 && (segment as usize) < segments) {
+        let hover = &animation.hover;
+        pose.add(hover, hover.local_ms(hovered), std::iter::once((segment as usize, 0.0)));
+    }
+    pose
+}
+
+/// The open menu's pose this frame, at rest with reduced motion, and faded while the cursor keeps still.
+pub fn current(overlay_content: &OverlayContent) -> Pose {
+    let reduced_motion = accessibility::reduced_motion();
+    let mut pose = if reduced_motion {
+        Pose::REST
+    } else {
+        let hovered = overlay_content.selected_segment.zip(overlay_content.hover_duration());
+        pose(overlay_content.opened_at.elapsed(), overlay_content.segment_count() as usize, hovered)
+    };
+    // Fades while the cursor keeps still, even with reduced motion
+    pose.opacity *= opacity(overlay_content.still_duration(), reduced_motion);
+    pose
+}
+
+/// Opacity of the whole menu with the cursor kept still for `still`: the theme's opacity, faded towards the
+/// inactive opacity once the cursor has kept still long enough. Reduced motion skips the fade's easing.
+pub fn opacity(still: Duration, reduced_motion: bool) -> f32 {
+    let animation = ANIMATION.lock().unwrap().clone().unwrap_or_default();
+    let waited = still.as_secs_f32() * 1000.0 - animation.inactive_after_ms as f32;
+    let faded = if waited < 0.0 {
+        0.0
+    } else if reduced_motion || animation.inactive_fade_ms == 0 {
+        1.0
+    } else {
+        Easing::EaseInOut.apply((waited / animation.inactive_fade_ms as f32).min(1.0))
+    };
+    let inactive = animation.inactive_opacity.clamp(0.0, 1.0);
+    animation.opacity.clamp(0.0, 1.0) * (1.0 + (inactive - 1.0) * faded)
+}
+
+/// The layout as this frame draws it, scaled and turned into the current pose, so the cursor is hit-tested against
+/// the segments where they are on screen.
+pub fn posed_layout(overlay_content: &OverlayContent) -> MenuLayout {
+    let mut layout = MenuLayout::of(overlay_content);
+    apply(&mut layout, &current(overlay_content));
+    layout
+}
+
+/// Scales and turns the layout into the pose, so the ring and everything placed on it move together.
+pub fn apply(layout: &mut MenuLayout, pose: &Pose) {
+    layout.motion = pose.segments;
+    let scale = pose.scale.max(0.0);
+    layout.inner_radius *= scale;
+    for ring in layout.rings.iter_mut() {
+        ring.inner *= scale;
+        ring.outer *= scale;
+        ring.start = (ring.start + pose.rotation).rem_euclid(2.0 * PI);
+    }
+}
@@ -0,0 +1,107 @@
+// Start with Windows: the overlay registers itself under the current user's Run key, so it is up after login
+
+use winapi::shared::minwindef::DWORD;
+use winapi::shared::winerror::{ERROR_FILE_NOT_FOUND, ERROR_SUCCESS};
+use winapi::um::winnt::REG_SZ;
+use winapi::um::winreg::{RegDeleteKeyValueW, RegGetValueW, RegSetKeyValueW, HKEY_CURRENT_USER, RRF_RT_REG_SZ};
+use std::fs;
+use std::ptr::null_mut;
+use crate::config::{config_path, StartupConfig, CONFIG_ARG};
+use crate::diagnostics;
+use crate::window::to_wstring;
+
+const RUN_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Run";
+const VALUE_NAME: &str = "RadialMenuOverlay";
+/// Passed by the Run key when the console window should start minimized.
+pub const MINIMIZED_ARG: &str = "--minimized";
+
+/// Adds the overlay to the Run key or removes it, as configured. Called whenever the config is loaded, so the
+/// entry also follows the exe when it is moved.
+pub fn apply(config: &StartupConfig) {
+    let wanted = if config.with_windows { command(config.minimized) } else { None };
+    if registered() == wanted {
+        return;
+    }
+    let result = match &wanted {
+        Some(command) => register(command),
+        None => unregister(),
+    };
+    match result {
+        Ok(()) => diagnostics::record_event(if wanted.is_some() { "Registered to start with Windows" } else { "No longer starting with Windows" }),
+        Err(e) => diagnostics::report_error(e),
+    }
+}
+
+/// The command line the Run key starts, None if the exe's path can't be found. The config file is passed along,
+/// as login starts the overlay in another working directory.
+fn command(minimized: bool) -> Option<String> {
+    let exe = std::env::current_exe().ok()?;
+    let config = fs::canonicalize(config_path()).ok()?;
+    let mut command = format!("\"{}\" {} \"{}\"", exe.display(), CONFIG_ARG, config.display());
+    if minimized {
+        command.push(' ');
+        command.push_str(MINIMIZED_ARG);
+    }
+    Some(command)
+}
+
+/// The command line registered now, if any.
+fn registered() -> Option<String> {
+    let key = to_wstring(RUN_KEY);
+    let name = to_wstring(VALUE_NAME);
+    let mut size: DWORD = 0;
+    let status = unsafe {
+        RegGetValueW(HKEY_CURRENT_USER, key.as_ptr(), name.as_ptr(), RRF_RT_REG_SZ, null_mut(), null_mut(), &mut size)
+    };
+    if status != ERROR_SUCCESS as i32 || size == 0 {
+        return None;
+    }
+    let mut buffer = vec![0u16; size as usize / 2];
+    let status = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            key.as_ptr(),
+            name.as_ptr(),
+            RRF_RT_REG_SZ,
+            null_mut(),
+            buffer.as_mut_ptr() as *mut _,
+            &mut size,
+        )
+    };
+    if status != ERROR_SUCCESS as i32 {
+        return None;
+    }
+    // The size includes the terminating null
+    let length = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+    Some(String::from_utf16_lossy(&buffer[..length]))
+}
+
+fn register(command: &str) -> Result<(), String> {
+    let key = to_wstring(RUN_KEY);
+    let name = to_wstring(VALUE_NAME);
+    let data = to_wstring(command);
+    let status = unsafe {
+        RegSetKeyValueW(
+            HKEY_CURRENT_USER,
+            key.as_ptr(),
+            name.as_ptr(),
+            REG_SZ,
+            data.as_ptr() as *const _,
+            (data.len() * 2) as DWORD,
+        )
+    };
+    if status != ERROR_SUCCESS as i32 {
+        return Err(format!("Failed to register the overlay to start with Windows. Error code: {}", status));
+    }
+    Ok(())
+}
+
+fn unregister() -> Result<(), String> {
+    let key = to_wstring(RUN_KEY);
+    let name = to_wstring(VALUE_NAME);
+    let status = unsafe { RegDeleteKeyValueW(HKEY_CURRENT_USER, key.as_ptr(), name.as_ptr()) };
+    if status != ERROR_SUCCESS as i32 && status != ERROR_FILE_NOT_FOUND as i32 {
+        return Err(format!("Failed to stop the overlay starting with Windows. Error code: {}", status));
+    }
+    Ok(())
+}
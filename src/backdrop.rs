@@ -0,0 +1,104 @@
+// The frosted-glass backdrop: the latest screen snapshot, written into a window-sized texture the ring shader
+// samples at binding 2 and blurs behind the segments
+
+use ash::{vk, Device, Instance};
+use crate::duplication;
+use crate::memory::{self, Allocation};
+use crate::fill::{create_image, create_view, transition_to_general, write_pixels};
+
+/// A window-sized texture in GENERAL layout, black until a snapshot of the window's size is written to it.
+pub struct BackdropTexture {
+    image: vk::Image,
+    memory: Allocation,
+    view: vk::ImageView,
+    sampler: vk::Sampler,
+    extent: vk::Extent2D,
+    /// Count of the snapshot last checked, and whether it was written.
+    written: (u64, bool),
+}
+
+impl BackdropTexture {
+    /// Creates the texture for a swapchain of this size. Like the swapchain, it is rebuilt when the window resizes.
+    pub fn new(
+        instance: &Instance,
+        device: &Device,
+        physical_device: vk::PhysicalDevice,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+        extent: vk::Extent2D,
+    ) -> Result<Self, String> {
+        let (image, memory) = create_image(instance, device, physical_device, extent.width, extent.height)?;
+        let black = vec![0xFF00_0000; (extent.width * extent.height) as usize];
+        write_pixels(device, image, memory, extent.width, extent.height, &black)?;
+        transition_to_general(device, command_pool, queue, image)?;
+        let view = create_view(device, image)?;
+        let sampler = create_sampler(device)?;
+        Ok(Self {
+            image,
+            memory,
+            view,
+            sampler,
+            extent,
+            written: (0, false),
+        })
+    }
+
+    /// Writes the latest snapshot if it is new and the size of the texture. A snapshot taken as the window
+    /// resized waits for the swapchain, and this texture, to be rebuilt at the new size.
+    pub fn upload(&mut self, device: &Device) -> Result<(), String> {
+        let (count, snapshot) = duplication::latest();
+        if count == self.written.0 && (self.written.1 || snapshot.is_none()) {
+            return Ok(());
+        }
+        let fits = snapshot
+            .as_ref()
+            .filter(|snapshot| snapshot.width == self.extent.width && snapshot.height == self.extent.height);
+        if let Some(snapshot) = fits {
+            write_pixels(device, self.image, self.memory, snapshot.width, snapshot.height, &snapshot.pixels)?;
+        }
+        self.written = (count, fits.is_some());
+        Ok(())
+    }
+
+    /// Whether the latest snapshot is in the texture, for the shader to draw it.
+    pub fn is_ready(&self) -> bool {
+        self.written.1
+    }
+
+    /// The image info binding 2 of the ring's descriptor sets points at.
+    pub fn descriptor_info(&self) -> vk::DescriptorImageInfo {
+        vk::DescriptorImageInfo {
+            sampler: self.sampler,
+            image_view: self.view,
+            image_layout: vk::ImageLayout::GENERAL,
+        }
+    }
+
+    /// Destroys the texture. The device must be idle.
+    pub fn destroy(&self, device: &Device) {
+        unsafe {
+            device.destroy_sampler(self.sampler, None);
+            device.destroy_image_view(self.view, None);
+            device.destroy_image(self.image, None);
+        }
+        memory::free(device, self.memory);
+    }
+}
+
+/// Filtered and clamped, as the blur reads between pixels and past the window's edges.
+fn create_sampler(device: &Device) -> Result<vk::Sampler, String> {
+    let sampler_info = vk::SamplerCreateInfo::builder()
+        .mag_filter(vk::Filter::LINEAR)
+        .min_filter(vk::Filter::LINEAR)
+        .mipmap_mode(vk::SamplerMipmapMode::NEAREST)
+        .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .max_lod(0.0);
+
+    unsafe {
+        device
+            .create_sampler(&sampler_info, None)
+            .map_err(|e| format!("Failed to create backdrop sampler: {:?}", e))
+    }
+}
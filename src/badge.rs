@@ -0,0 +1,208 @@
+// Small badges drawn on segments, e.g. an unread count, a dot for something active or a variable's value, and the
+// segments' number keys. Badges are rounded quads drawn with their own pipeline, their text by the text batch
+
+use ash::{vk, Device, Instance};
+use crate::icons::ICON_SIZE;
+use crate::menu::MAX_SEGMENTS;
+use crate::overlay::OverlayContent;
+use crate::render::{create_quad_pipeline, VertexRing};
+use crate::rings::{MenuLayout, UNIT_PIXELS};
+use crate::text::TextBatch;
+
+/// Badge text that draws a plain dot, e.g. for a toggle that is on.
+pub const DOT: &str = "•";
+/// Distance of the number keys from the ring's inner edge, in NDC units.
+const NUMBER_INSET: f32 = 0.035;
+/// Counts above this are shown as "99+".
+const MAX_COUNT: u32 = 99;
+const PADDING: f32 = 3.0;
+/// Diameter of a dot badge, in pixels.
+const DOT_SIZE: f32 = 10.0;
+const VERTICES_PER_QUAD: usize = 6;
+/// One quad per segment. Unused quads are zeroed so they rasterize nothing, like the text's.
+const VERTEX_CAPACITY: usize = MAX_SEGMENTS * VERTICES_PER_QUAD;
+
+const BADGE_BACKGROUND: [f32; 4] = [0.85, 0.2, 0.2, 1.0];
+const BADGE_TEXT: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const NUMBER_TEXT: [f32; 4] = [0.85, 0.85, 0.85, 1.0];
+
+/// A badge placed for one frame: a rounded quad centered on a point in window pixels.
+pub struct PlacedBadge {
+    pub segment: usize,
+    pub center: (f32, f32),
+    pub half_size: (f32, f32), // Half the width and height; the ends are rounded by half the height
+    pub color: [f32; 4],
+}
+
+/// What a badge's text is drawn as: a dot, or a pill holding a count or other text. None hides the badge,
+/// as for a count of zero.
+fn badge_text(text: &str) -> Option<Option<String>> {
+    let text = text.trim();
+    if text == DOT {
+        return Some(None);
+    }
+    match text.parse::<u32>() {
+        Ok(0) => None,
+        Ok(count) if count > MAX_COUNT => Some(Some(format!("{}+", MAX_COUNT))),
+        Ok(count) => Some(Some(count.to_string())),
+        Err(_) => Some(Some(text.to_string())),
+    }
+}
+
+/// Places the badges on their segments' centroids, for the revealed rings only, and lays out their text. Next to
+/// an icon, the badge sits on its top right corner like an app's unread count.
+pub fn layout_badges(batch: &mut TextBatch, overlay_content: &OverlayContent, layout: &MenuLayout) -> Vec<PlacedBadge> {
+    let (width, height) = batch.size();
+    let line_height = batch.metrics().cell_height;
+
+    let mut badges = Vec::new();
+    for (index, text) in overlay_content.item_text.iter().enumerate().take(MAX_SEGMENTS) {
+        let text = match text.badge.as_deref().and_then(badge_text) {
+            Some(text) => text,
+            None => continue,
+        };
+        let segment = index as i32;
+        let (angle, distance) = match layout.segment_centroid(segment) {
+            Some((angle, distance)) => match layout.moved_distance(segment, distance) {
+                Some(distance) => (angle, distance),
+                None => continue,
+            },
+            None => continue,
+        };
+
+        // NDC y points down like window pixels
+        let mut center_x = width / 2.0 + angle.cos() * distance * UNIT_PIXELS;
+        let mut center_y = height / 2.0 + angle.sin() * distance * UNIT_PIXELS;
+        if overlay_content.item_icons.get(index).map_or(false, Option::is_some) {
+            center_x += ICON_SIZE as f32 / 2.0;
+            center_y -= ICON_SIZE as f32 / 2.0;
+        }
+
+        let half_size = match &text {
+            None => (DOT_SIZE / 2.0, DOT_SIZE / 2.0),
+            Some(text) => {
+                let box_height = line_height + PADDING * 2.0;
+                // At least round, so single digits sit in a circle
+                let box_width = (batch.measure(text).0 + PADDING * 2.0).max(box_height);
+                batch.text_centered(center_x, center_y - line_height / 2.0, text, BADGE_TEXT);
+                (box_width / 2.0, box_height / 2.0)
+            }
+        };
+        badges.push(PlacedBadge { segment: index, center: (center_x, center_y), half_size, color: BADGE_BACKGROUND });
+    }
+    badges
+}
+
+/// Draws the number key that selects each of the first nine segments near their inner edge.
+pub fn layout_number_badges(batch: &mut TextBatch, overlay_content: &OverlayContent, layout: &MenuLayout) {
+    if !overlay_content.number_badges {
+        return;
+    }
+
+    let (width, height) = batch.size();
+    let line_height = batch.metrics().cell_height;
+    for segment in 0..overlay_content.segment_count().min(9) {
+        let (ring, angle) = match (layout.ring_of(segment), layout.segment_middle(segment)) {
+            (Some(ring), Some(angle)) => (ring, angle),
+            _ => continue,
+        };
+        let distance = match layout.moved_distance(segment, ring.inner + NUMBER_INSET) {
+            Some(distance) => distance,
+            None => continue,
+        };
+        let center_x = width / 2.0 + angle.cos() * distance * UNIT_PIXELS;
+        let center_y = height / 2.0 + angle.sin() * distance * UNIT_PIXELS;
+        batch.text_centered(center_x, center_y - line_height / 2.0, &(segment + 1).to_string(), NUMBER_TEXT);
+    }
+}
+
+/// Vertex consumed by shaders/badge.vert.glsl.
+#[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq)]
+struct BadgeVertex {
+    position: [f32; 2], // NDC, Y pointing down
+    local: [f32; 2], // Pixels from the badge's center
+    half_size: [f32; 2],
+    color: [f32; 4],
+}
+
+/// Vulkan objects for the badges: a pipeline drawing rounded quads, and their vertices.
+pub struct BadgeRenderer {
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    vertices: VertexRing<BadgeVertex>,
+    extent: vk::Extent2D,
+}
+
+impl BadgeRenderer {
+    /// Creates the vertex buffers, empty, and the badge pipeline.
+    pub fn new(
+        instance: &Instance,
+        device: &Device,
+        physical_device: vk::PhysicalDevice,
+        render_pass: vk::RenderPass,
+        extent: vk::Extent2D,
+        image_count: usize,
+    ) -> Result<Self, String> {
+        // Vertex input: position, local position, half size, color
+        let (pipeline_layout, pipeline) = create_quad_pipeline(
+            device,
+            render_pass,
+            extent,
+            "badge",
+            &[
+                vk::Format::R32G32_SFLOAT,
+                vk::Format::R32G32_SFLOAT,
+                vk::Format::R32G32_SFLOAT,
+                vk::Format::R32G32B32A32_SFLOAT,
+            ],
+            vk::DescriptorSetLayout::null(),
+            &[],
+        )?;
+        let vertices = VertexRing::new(instance, device, physical_device, VERTEX_CAPACITY, image_count, "badge")?;
+        Ok(Self { pipeline_layout, pipeline, vertices, extent })
+    }
+
+    /// Writes a frame's badges into the vertex buffer of the given swapchain image.
+    pub fn upload(&mut self, device: &Device, image_index: usize, badges: &[PlacedBadge]) -> Result<(), String> {
+        let mut vertices = vec![BadgeVertex::default(); VERTEX_CAPACITY];
+        let (width, height) = (self.extent.width as f32, self.extent.height as f32);
+        let to_ndc = |x: f32, y: f32| [x / width * 2.0 - 1.0, y / height * 2.0 - 1.0];
+        for badge in badges {
+            let (half_width, half_height) = badge.half_size;
+            let (x, y) = badge.center;
+            let corner = |dx: f32, dy: f32| BadgeVertex {
+                position: to_ndc(x + dx, y + dy),
+                local: [dx, dy],
+                half_size: [half_width, half_height],
+                color: badge.color,
+            };
+            let top_left = corner(-half_width, -half_height);
+            let top_right = corner(half_width, -half_height);
+            let bottom_left = corner(-half_width, half_height);
+            let bottom_right = corner(half_width, half_height);
+            let first = badge.segment * VERTICES_PER_QUAD;
+            vertices[first..first + VERTICES_PER_QUAD]
+                .copy_from_slice(&[top_left, top_right, bottom_left, bottom_left, top_right, bottom_right]);
+        }
+
+        self.vertices.upload(device, image_index, &vertices)
+    }
+
+    /// Records the badge draw into a command buffer inside an active render pass.
+    pub fn record(&self, device: &Device, command_buffer: vk::CommandBuffer, image_index: usize) {
+        unsafe {
+            device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.pipeline);
+        }
+        self.vertices.record_draw(device, command_buffer, image_index);
+    }
+
+    /// Destroys the badge resources. The device must be idle.
+    pub fn destroy(&self, device: &Device) {
+        self.vertices.destroy(device);
+        unsafe {
+            device.destroy_pipeline(self.pipeline, None);
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+        }
+    }
+}
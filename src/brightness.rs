@@ -0,0 +1,103 @@
+// Monitor brightness over DDC/CI through the DXVA2 physical monitor API, for the brightness action and dial
+
+use winapi::shared::minwindef::DWORD;
+use winapi::shared::windef::POINT;
+use winapi::um::highlevelmonitorconfigurationapi::{GetMonitorBrightness, SetMonitorBrightness};
+use winapi::um::physicalmonitorenumerationapi::{
+    DestroyPhysicalMonitors, GetNumberOfPhysicalMonitorsFromHMONITOR, GetPhysicalMonitorsFromHMONITOR, PHYSICAL_MONITOR,
+};
+use winapi::um::winnt::HANDLE;
+use winapi::um::winuser::{GetCursorPos, MonitorFromPoint, MONITOR_DEFAULTTONEAREST};
+use std::mem::zeroed;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use crate::diagnostics;
+
+/// Latest brightness a dial asked for that the writer thread hasn't sent yet.
+static PENDING: Mutex<Option<f32>> = Mutex::new(None);
+static WRITING: AtomicBool = AtomicBool::new(false);
+
+/// Brightness of the monitor under the cursor as a share of its range.
+/// DDC/CI is slow; a call takes tens of milliseconds.
+pub fn get() -> Result<f32, String> {
+    with_physical_monitors(|monitors| {
+        monitors
+            .iter()
+            .find_map(|monitor| range(monitor.hPhysicalMonitor))
+            .map(|(min, current, max)| (current.saturating_sub(min) as f32 / (max - min) as f32).min(1.0))
+            .ok_or_else(|| "The monitor doesn't report its brightness over DDC/CI".to_string())
+    })
+}
+
+/// Sets every physical monitor behind the monitor under the cursor to a share of its range.
+pub fn set(fraction: f32) -> Result<(), String> {
+    with_physical_monitors(|monitors| {
+        let mut changed = false;
+        for monitor in monitors {
+            if let Some((min, _, max)) = range(monitor.hPhysicalMonitor) {
+                let level = min + ((max - min) as f32 * fraction.clamp(0.0, 1.0)).round() as DWORD;
+                changed |= unsafe { SetMonitorBrightness(monitor.hPhysicalMonitor, level) } != 0;
+            }
+        }
+        if changed {
+            Ok(())
+        } else {
+            Err("The monitor doesn't accept brightness changes over DDC/CI".to_string())
+        }
+    })
+}
+
+/// Sets the brightness on a worker thread, for dials that change it every frame. Values that arrive while a
+/// write is in flight replace each other, so the monitor catches up with the latest one instead of every step.
+pub fn set_in_background(fraction: f32) {
+    *PENDING.lock().unwrap() = Some(fraction);
+    if WRITING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    thread::spawn(|| loop {
+        let next = PENDING.lock().unwrap().take();
+        match next {
+            Some(fraction) => {
+                if let Err(e) = set(fraction) {
+                    diagnostics::report_error(e);
+                }
+            }
+            None => {
+                WRITING.store(false, Ordering::SeqCst);
+                // A value may have arrived after the take; keep writing unless another thread took over
+                if PENDING.lock().unwrap().is_none() || WRITING.swap(true, Ordering::SeqCst) {
+                    return;
+                }
+            }
+        }
+    });
+}
+
+/// Minimum, current and maximum brightness of a physical monitor, None without DDC/CI support.
+fn range(monitor: HANDLE) -> Option<(DWORD, DWORD, DWORD)> {
+    let (mut min, mut current, mut max): (DWORD, DWORD, DWORD) = (0, 0, 0);
+    let read = unsafe { GetMonitorBrightness(monitor, &mut min, &mut current, &mut max) } != 0;
+    (read && max > min).then_some((min, current, max))
+}
+
+/// Runs `f` on the physical monitors of the monitor under the cursor, released afterwards.
+fn with_physical_monitors<T>(f: impl FnOnce(&[PHYSICAL_MONITOR]) -> Result<T, String>) -> Result<T, String> {
+    unsafe {
+        let mut cursor: POINT = POINT { x: 0, y: 0 };
+        GetCursorPos(&mut cursor);
+        let monitor = MonitorFromPoint(cursor, MONITOR_DEFAULTTONEAREST);
+
+        let mut count: DWORD = 0;
+        if GetNumberOfPhysicalMonitorsFromHMONITOR(monitor, &mut count) == 0 || count == 0 {
+            return Err("No physical monitor found for the brightness".to_string());
+        }
+        let mut monitors: Vec<PHYSICAL_MONITOR> = vec![zeroed(); count as usize];
+        if GetPhysicalMonitorsFromHMONITOR(monitor, count, monitors.as_mut_ptr()) == 0 {
+            return Err("Failed to open the monitor for the brightness".to_string());
+        }
+        let result = f(&monitors);
+        DestroyPhysicalMonitors(count, monitors.as_mut_ptr());
+        result
+    }
+}
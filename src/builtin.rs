@@ -0,0 +1,33 @@
+// Ready-made profiles shipped with the app, selectable with `profile = "<name>"`
+
+use std::sync::OnceLock;
+use crate::config::Profile;
+use crate::preset::parse_preset;
+
+/// Preset files compiled into the binary.
+const BUILTIN_PRESETS: [&str; 6] = [
+    include_str!("../presets/media.preset.toml"),
+    include_str!("../presets/windows.preset.toml"),
+    include_str!("../presets/emoji.preset.toml"),
+    include_str!("../presets/desktops.preset.toml"),
+    include_str!("../presets/snippets.preset.toml"),
+    include_str!("../presets/power.preset.toml"),
+];
+
+static BUILTIN_PROFILES: OnceLock<Vec<Profile>> = OnceLock::new();
+
+/// The built-in profiles, parsed on first use.
+pub fn builtin_profiles() -> &'static [Profile] {
+    BUILTIN_PROFILES.get_or_init(|| {
+        BUILTIN_PRESETS
+            .iter()
+            .filter_map(|text| match parse_preset(text) {
+                Ok(profile) => Some(profile),
+                Err(e) => {
+                    eprintln!("Invalid built-in preset: {}", e);
+                    None
+                }
+            })
+            .collect()
+    })
+}
@@ -0,0 +1,110 @@
+// Command-line subcommands that talk to a running instance instead of starting a new one
+
+use crate::config::config_path;
+use crate::golden;
+use crate::ipc::send_command;
+use crate::preset::{export_preset, import_preset};
+use crate::preview::save_preview;
+use crate::validate::validate_file;
+
+/// Runs the subcommand named by the first argument, if any.
+/// Returns the process exit code when a subcommand was handled, or None to start the overlay.
+pub fn run(args: &[String]) -> Option<i32> {
+    let command = args.get(1)?;
+
+    match command.as_str() {
+        "status" => Some(forward("status")),
+        "diagnostics" => Some(forward("diagnostics")),
+        "health" => Some(forward("health")),
+        "pair" => Some(forward("pair")),
+        "quit" => Some(forward("quit")),
+        "pause" => Some(forward("pause")),
+        "resume" => Some(forward("resume")),
+        // Draws the ring offscreen and compares it with the reference images; `--update` replaces them
+        "golden" => Some(golden::run(args.get(2).map_or(false, |arg| arg == "--update"))),
+        "validate-config" => Some(validate_config(args.get(2).map_or(config_path(), |path| path.as_str()))),
+        "export-preset" => {
+            let profile = args.get(2).map(String::as_str);
+            let output = args.get(3).map(String::as_str);
+            Some(report(export_preset(config_path(), profile, output).map(|path| format!("Exported to {}", path))))
+        }
+        "preview" => {
+            let profile = args.get(2).map(String::as_str);
+            let output = args.get(3).map(String::as_str);
+            Some(report(save_preview(config_path(), profile, output).map(|path| format!("Saved to {}", path))))
+        }
+        "import-preset" => match args.get(2) {
+            Some(path) => Some(report(
+                import_preset(config_path(), path).map(|name| format!("Imported as profile \"{}\"", name)),
+            )),
+            None => {
+                eprintln!("Usage: {} import-preset <file>", args[0]);
+                Some(2)
+            }
+        },
+        "get" => Some(forward("get")),
+        "set" => {
+            // Lets scripts feed values into labels and conditions: `set volume 40`
+            if args.len() < 4 {
+                eprintln!("Usage: {} set <name> <value>", args[0]);
+                return Some(2);
+            }
+            Some(forward(&format!("set {} {}", args[2], args[3..].join(" "))))
+        }
+        "record" | "replay" => match args.get(2) {
+            Some(file) if command == "record" && file == "stop" => Some(forward("record stop")),
+            // The running instance may have a different working directory
+            Some(file) => Some(forward(&format!("{} {}", command, absolute_path(file)))),
+            None => {
+                eprintln!("Usage: {} {}", args[0], if command == "record" { "record <file|stop>" } else { "replay <file>" });
+                Some(2)
+            }
+        },
+        _ => None,
+    }
+}
+
+fn absolute_path(path: &str) -> String {
+    std::env::current_dir()
+        .map(|dir| dir.join(path).display().to_string())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+/// Checks a config file without starting the overlay. Exits with 1 if anything was found.
+fn validate_config(path: &str) -> i32 {
+    match validate_file(path) {
+        Ok(issues) if issues.is_empty() => {
+            println!("{}: OK", path);
+            0
+        }
+        Ok(issues) => {
+            for issue in &issues {
+                println!("{}: {}", path, issue);
+            }
+            1
+        }
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            1
+        }
+    }
+}
+
+/// Forwards a command over IPC and prints the response.
+fn forward(command: &str) -> i32 {
+    report(send_command(command))
+}
+
+/// Prints a command's outcome and returns the matching exit code.
+fn report(result: Result<String, String>) -> i32 {
+    match result {
+        Ok(response) => {
+            println!("{}", response);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
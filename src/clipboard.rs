@@ -0,0 +1,255 @@
+// Clipboard access and history: the overlay window listens for clipboard changes and keeps the last text clips,
+// which the "clipboard" menu offers for pasting
+
+use serde::{Deserialize, Serialize};
+use winapi::shared::windef::HWND;
+use winapi::um::winbase::{GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
+use winapi::um::winuser::{
+    AddClipboardFormatListener, CloseClipboard, EmptyClipboard, GetClipboardData, IsClipboardFormatAvailable,
+    OpenClipboard, RegisterClipboardFormatW, RemoveClipboardFormatListener, SetClipboardData, CF_UNICODETEXT,
+};
+use std::fs;
+use std::mem::size_of;
+use std::path::PathBuf;
+use std::ptr::null_mut;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::action::Action;
+use crate::config::{config_dir, ClipboardConfig};
+use crate::menu::{shorten, MenuItem, MAX_SEGMENTS};
+use crate::provider::{Menu, MenuContext, MenuProvider};
+use crate::template;
+use crate::window::to_wstring;
+
+/// Name of the file the history is kept in with `persist`, next to the config file.
+pub const CLIPBOARD_FILE_NAME: &str = "clipboard.json";
+/// Longest label before a clip is cut short; the whole clip is in the tooltip.
+const MAX_LABEL_CHARS: usize = 20;
+/// Clips longer than this aren't remembered, so copying a whole file doesn't bloat the history.
+const MAX_CLIP_CHARS: usize = 10_000;
+/// Formats password managers and the like put on the clipboard with a clip to keep it out of histories.
+const EXCLUDE_FORMATS: [&str; 2] = ["ExcludeClipboardContentFromMonitorProcessing", "Clipboard Viewer Ignore"];
+/// Format holding a DWORD that is 0 for clips kept out of the Windows clipboard history, and so out of this one.
+const HISTORY_FORMAT: &str = "CanIncludeInClipboardHistory";
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Clip {
+    text: String,
+    copied_at: u64, // Seconds since the Unix epoch
+}
+
+struct History {
+    config: ClipboardConfig,
+    listening: bool,
+    clips: Option<Vec<Clip>>, // Most recent first; None until first loaded
+}
+
+static HISTORY: Mutex<History> = Mutex::new(History {
+    config: ClipboardConfig { enabled: false, hotkey: String::new(), size: 0, ttl_minutes: 0, persist: false },
+    listening: false,
+    clips: None,
+});
+
+/// Starts or stops listening for clipboard changes on the overlay window.
+pub fn configure(hwnd: HWND, config: &ClipboardConfig) {
+    let mut history = HISTORY.lock().unwrap();
+    if history.listening != config.enabled {
+        let changed = unsafe {
+            if config.enabled {
+                AddClipboardFormatListener(hwnd)
+            } else {
+                RemoveClipboardFormatListener(hwnd)
+            }
+        };
+        history.listening = config.enabled && changed != 0;
+    }
+    history.config = config.clone();
+    if !config.enabled {
+        history.clips = None;
+    }
+}
+
+/// Called on WM_CLIPBOARDUPDATE; remembers the new clip if it is text and not marked private.
+pub fn handle_update() {
+    if is_private() {
+        return;
+    }
+    let text = match get_text() {
+        Some(text) if !text.trim().is_empty() && text.chars().count() <= MAX_CLIP_CHARS => text,
+        _ => return,
+    };
+    let mut history = HISTORY.lock().unwrap();
+    if !history.config.enabled {
+        return;
+    }
+    let (size, persist) = (history.config.size, history.config.persist);
+    let clips = history.clips.get_or_insert_with(|| load(persist));
+    // Copying a clip again, or pasting one from the menu, moves it to the front
+    clips.retain(|clip| clip.text != text);
+    clips.insert(0, Clip { text, copied_at: now() });
+    clips.truncate(size);
+    if persist {
+        save(clips);
+    }
+}
+
+/// The remembered clips that haven't expired, most recent first.
+fn clips() -> Vec<String> {
+    let mut history = HISTORY.lock().unwrap();
+    if !history.config.enabled {
+        return Vec::new();
+    }
+    let (ttl_minutes, persist) = (history.config.ttl_minutes, history.config.persist);
+    let clips = history.clips.get_or_insert_with(|| load(persist));
+    if ttl_minutes > 0 {
+        let oldest = now().saturating_sub(ttl_minutes * 60);
+        clips.retain(|clip| clip.copied_at >= oldest);
+    }
+    clips.iter().map(|clip| clip.text.clone()).collect()
+}
+
+/// The clipboard history's provider; selecting a clip pastes it.
+pub struct ClipboardHistory;
+
+impl MenuProvider for ClipboardHistory {
+    fn name(&self) -> &'static str {
+        "clipboard"
+    }
+
+    fn build(&self, _context: &MenuContext) -> Menu {
+        let items = clips()
+            .into_iter()
+            .take(MAX_SEGMENTS)
+            .map(|text| {
+                let first_line = text.lines().find(|line| !line.trim().is_empty()).unwrap_or("").trim();
+                MenuItem {
+                    label: template::escape(&shorten(first_line, MAX_LABEL_CHARS)),
+                    description: template::escape(&text),
+                    action: Some(Action::Paste { text }),
+                    enabled_if: None,
+                    visible_if: None,
+                    badge: None,
+                    weight: None,
+                    uses: None,
+                    dial: None,
+                    icon: None,
+                    confirm: false,
+                    toggle: None,
+                    pulse_if: None,
+                }
+            })
+            .collect();
+        Menu { items }
+    }
+
+    fn empty_message(&self) -> &'static str {
+        "Nothing copied yet"
+    }
+}
+
+/// The clipboard's text, None when it holds something else.
+pub fn get_text() -> Option<String> {
+    unsafe {
+        if OpenClipboard(null_mut()) == 0 {
+            return None;
+        }
+        let data = GetClipboardData(CF_UNICODETEXT);
+        let locked = if data.is_null() { null_mut() } else { GlobalLock(data) as *const u16 };
+        let text = if locked.is_null() {
+            None
+        } else {
+            let length = (0..).take_while(|&index| *locked.add(index) != 0).count();
+            let text = String::from_utf16_lossy(std::slice::from_raw_parts(locked, length));
+            GlobalUnlock(data);
+            Some(text)
+        };
+        CloseClipboard();
+        text
+    }
+}
+
+/// Whether the app that set the clipboard asked for the clip to stay out of clipboard histories. A clip whose
+/// answer can't be read counts as private.
+fn is_private() -> bool {
+    unsafe {
+        let format = |name: &str| RegisterClipboardFormatW(to_wstring(name).as_ptr());
+        if EXCLUDE_FORMATS.iter().any(|&name| IsClipboardFormatAvailable(format(name)) != 0) {
+            return true;
+        }
+        let history = format(HISTORY_FORMAT);
+        if IsClipboardFormatAvailable(history) == 0 {
+            return false;
+        }
+        if OpenClipboard(null_mut()) == 0 {
+            return true;
+        }
+        let data = GetClipboardData(history);
+        let locked = if data.is_null() { None } else { Some(GlobalLock(data) as *const u32).filter(|locked| !locked.is_null()) };
+        let allowed = locked.map_or(false, |locked| *locked != 0);
+        if locked.is_some() {
+            GlobalUnlock(data);
+        }
+        CloseClipboard();
+        !allowed
+    }
+}
+
+/// Puts `text` on the clipboard as Unicode text.
+pub fn set_text(text: &str) -> Result<(), String> {
+    let wide: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+    unsafe {
+        if OpenClipboard(null_mut()) == 0 {
+            return Err("Failed to open the clipboard".to_string());
+        }
+        EmptyClipboard();
+        let memory = GlobalAlloc(GMEM_MOVEABLE, wide.len() * size_of::<u16>());
+        let locked = if memory.is_null() { null_mut() } else { GlobalLock(memory) as *mut u16 };
+        if locked.is_null() {
+            if !memory.is_null() {
+                GlobalFree(memory);
+            }
+            CloseClipboard();
+            return Err("Failed to allocate clipboard memory".to_string());
+        }
+        locked.copy_from_nonoverlapping(wide.as_ptr(), wide.len());
+        GlobalUnlock(memory);
+        // The clipboard owns the memory once it is set
+        if SetClipboardData(CF_UNICODETEXT, memory).is_null() {
+            GlobalFree(memory);
+            CloseClipboard();
+            return Err("Failed to set the clipboard".to_string());
+        }
+        CloseClipboard();
+    }
+    Ok(())
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
+
+fn path() -> PathBuf {
+    config_dir().join(CLIPBOARD_FILE_NAME)
+}
+
+/// A missing or unreadable file, or a history that isn't persisted, starts empty.
+fn load(persist: bool) -> Vec<Clip> {
+    if !persist {
+        return Vec::new();
+    }
+    fs::read_to_string(path())
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn save(clips: &[Clip]) {
+    match serde_json::to_string_pretty(clips) {
+        Ok(json) => {
+            if let Err(e) = fs::write(path(), json) {
+                eprintln!("Failed to save {}: {:?}", path().display(), e);
+            }
+        }
+        Err(e) => eprintln!("Failed to save {}: {}", path().display(), e),
+    }
+}
@@ -0,0 +1,131 @@
+// Conditions that menu items can depend on, evaluated when the overlay opens
+
+use serde::{Deserialize, Serialize};
+use winapi::um::tlhelp32::{CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W, TH32CS_SNAPPROCESS};
+use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+use winapi::um::winuser::{GetForegroundWindow, GetWindowTextW};
+use winapi::um::sysinfoapi::GetLocalTime;
+use winapi::um::minwinbase::SYSTEMTIME;
+use std::mem::{size_of, zeroed};
+use std::path::Path;
+use crate::variables;
+
+/// A check against the current system state.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    /// A process with this executable name is running, e.g. "obs64.exe".
+    ProcessRunning(String),
+    /// The foreground window's title contains this text (case-insensitive).
+    WindowTitle(String),
+    /// A file or directory exists at this path.
+    FileExists(String),
+    /// Local time is within ["HH:MM", "HH:MM"]; the range may wrap past midnight.
+    TimeBetween(String, String),
+    /// A variable equals `equals`, or is set to a non-empty value when `equals` is omitted.
+    Variable {
+        name: String,
+        #[serde(default)]
+        equals: Option<String>,
+    },
+    /// Every nested condition holds.
+    All(Vec<Condition>),
+    /// At least one nested condition holds.
+    Any(Vec<Condition>),
+    /// The nested condition does not hold.
+    Not(Box<Condition>),
+}
+
+impl Condition {
+    pub fn evaluate(&self) -> bool {
+        match self {
+            Condition::ProcessRunning(name) => is_process_running(name),
+            Condition::WindowTitle(text) => foreground_window_title()
+                .to_lowercase()
+                .contains(&text.to_lowercase()),
+            Condition::FileExists(path) => Path::new(path).exists(),
+            Condition::TimeBetween(start, end) => match (parse_time(start), parse_time(end)) {
+                (Some(start), Some(end)) => {
+                    let now = local_minutes();
+                    if start <= end {
+                        now >= start && now < end
+                    } else {
+                        now >= start || now < end
+                    }
+                }
+                _ => {
+                    eprintln!("Invalid time range: {} - {}", start, end);
+                    false
+                }
+            },
+            Condition::Variable { name, equals } => match (variables::get(name), equals) {
+                (Some(value), Some(expected)) => value == *expected,
+                (Some(value), None) => !value.is_empty(),
+                (None, _) => false,
+            },
+            Condition::All(conditions) => conditions.iter().all(|condition| condition.evaluate()),
+            Condition::Any(conditions) => conditions.iter().any(|condition| condition.evaluate()),
+            Condition::Not(condition) => !condition.evaluate(),
+        }
+    }
+}
+
+/// Parses "HH:MM" into minutes since midnight.
+pub(crate) fn parse_time(text: &str) -> Option<u32> {
+    let (hours, minutes) = text.trim().split_once(':')?;
+    let hours: u32 = hours.parse().ok()?;
+    let minutes: u32 = minutes.parse().ok()?;
+    if hours < 24 && minutes < 60 {
+        Some(hours * 60 + minutes)
+    } else {
+        None
+    }
+}
+
+/// Current local time in minutes since midnight.
+fn local_minutes() -> u32 {
+    let mut time: SYSTEMTIME = unsafe { zeroed() };
+    unsafe {
+        GetLocalTime(&mut time);
+    }
+    time.wHour as u32 * 60 + time.wMinute as u32
+}
+
+/// Checks the process list for an executable name, ignoring case.
+pub fn is_process_running(name: &str) -> bool {
+    !process_ids(name).is_empty()
+}
+
+/// IDs of every process with this executable name, ignoring case.
+pub fn process_ids(name: &str) -> Vec<u32> {
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+        if snapshot == INVALID_HANDLE_VALUE {
+            return Vec::new();
+        }
+
+        let mut entry: PROCESSENTRY32W = zeroed();
+        entry.dwSize = size_of::<PROCESSENTRY32W>() as u32;
+
+        let mut ids = Vec::new();
+        let mut has_entry = Process32FirstW(snapshot, &mut entry) != 0;
+        while has_entry {
+            let length = entry.szExeFile.iter().position(|&c| c == 0).unwrap_or(entry.szExeFile.len());
+            let exe_name = String::from_utf16_lossy(&entry.szExeFile[..length]);
+            if exe_name.eq_ignore_ascii_case(name) {
+                ids.push(entry.th32ProcessID);
+            }
+            has_entry = Process32NextW(snapshot, &mut entry) != 0;
+        }
+
+        CloseHandle(snapshot);
+        ids
+    }
+}
+
+/// Title of the window that currently has focus.
+pub fn foreground_window_title() -> String {
+    let mut buffer = [0u16; 512];
+    let length = unsafe { GetWindowTextW(GetForegroundWindow(), buffer.as_mut_ptr(), buffer.len() as i32) };
+    String::from_utf16_lossy(&buffer[..length.max(0) as usize])
+}
@@ -0,0 +1,1220 @@
+// Loads user configuration for the overlay from a TOML file
+
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::os::windows::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime};
+use winapi::shared::minwindef::FALSE;
+use winapi::um::fileapi::{FindCloseChangeNotification, FindFirstChangeNotificationW, FindNextChangeNotification};
+use winapi::um::handleapi::INVALID_HANDLE_VALUE;
+use winapi::um::synchapi::WaitForSingleObject;
+use winapi::um::winbase::WAIT_OBJECT_0;
+use winapi::um::winnt::{FILE_NOTIFY_CHANGE_FILE_NAME, FILE_NOTIFY_CHANGE_LAST_WRITE, HANDLE};
+use crate::action::Action;
+use crate::animation::{Easing, Keyframe, Timeline};
+use crate::builtin::builtin_profiles;
+use crate::menu::MenuItem;
+
+/// Name of the configuration file, in the user's config folder or, for portable setups, the working directory.
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// Command-line option naming the configuration file.
+pub const CONFIG_ARG: &str = "--config";
+/// The configuration file in use, decided on first use.
+static CONFIG_PATH: OnceLock<String> = OnceLock::new();
+
+/// Name of the profile made of the top-level `items`.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Deepest chain of includes followed, which also stops include cycles.
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Top-level configuration. Every field has a default so a missing or partial file still works.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Config {
+    pub include: Vec<String>, // Files merged into this one, relative to it
+    pub profile: String, // Active profile; "default" uses the top-level items
+    pub anchor: Anchor, // Where the top-level menu opens, and profiles without their own anchor
+    pub hotkey: HotkeyConfig,
+    pub dwell: DwellConfig,
+    pub feedback: FeedbackConfig,
+    pub edges: EdgesConfig,
+    pub rings: RingsConfig,
+    pub orientation: OrientationConfig,
+    pub relative: RelativeConfig,
+    pub gamepad: GamepadConfig,
+    pub selection: SelectionConfig,
+    pub recent: RecentConfig,
+    pub switcher: SwitcherConfig,
+    pub profile_switcher: ProfileSwitcherConfig,
+    pub clipboard: ClipboardConfig,
+    pub recent_files: RecentFilesConfig,
+    pub search: SearchConfig,
+    pub accessibility: AccessibilityConfig,
+    pub remote: RemoteConfig,
+    pub hook: HookConfig,
+    pub renderer: RendererConfig,
+    pub shader: ShaderConfig,
+    pub theme: ThemeConfig,
+    pub hud: HudConfig,
+    pub debug: DebugConfig,
+    pub startup: StartupConfig,
+    pub suppress: SuppressConfig,
+    pub actions: ActionsConfig,
+    pub mqtt: MqttConfig,
+    pub items: Vec<MenuItem>,
+    pub profiles: Vec<Profile>,
+    pub shared: BTreeMap<String, MenuItem>, // Item definitions referenced with `use = "<name>"`
+    #[serde(skip)]
+    pub sources: Vec<PathBuf>, // The config file and every file it includes
+}
+
+/// A named menu that can be switched to instead of the top-level items.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Profile {
+    pub name: String,
+    #[serde(default)]
+    pub items: Vec<MenuItem>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<Anchor>, // Pins this profile's menu to a spot on the screen
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub processes: Vec<String>, // Exe names, e.g. "obs64.exe", that switch to this profile while they are in front
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passthrough: Option<bool>, // Overrides `passthrough` of [hotkey] while this profile is active
+}
+
+/// Where a menu opens: under the cursor, or pinned to a fixed spot on the cursor's monitor like a dock.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Anchor {
+    Cursor,
+    Center,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Config {
+    /// Items of the named profile, None if there is no such profile.
+    /// Profiles in the config file take precedence over built-in ones with the same name.
+    pub fn profile_items(&self, name: &str) -> Option<&[MenuItem]> {
+        if name == DEFAULT_PROFILE {
+            return Some(&self.items);
+        }
+        self.profiles
+            .iter()
+            .chain(builtin_profiles())
+            .find(|profile| profile.name == name)
+            .map(|profile| profile.items.as_slice())
+    }
+
+    /// Where the named profile's menu opens; the top-level anchor applies unless the profile has its own.
+    pub fn profile_anchor(&self, name: &str) -> Anchor {
+        self.profiles
+            .iter()
+            .find(|profile| profile.name == name)
+            .and_then(|profile| profile.anchor)
+            .unwrap_or(self.anchor)
+    }
+
+    /// Whether the trigger passes through to other apps while the named profile is active.
+    pub fn profile_passthrough(&self, name: &str) -> bool {
+        self.profiles
+            .iter()
+            .find(|profile| profile.name == name)
+            .and_then(|profile| profile.passthrough)
+            .unwrap_or(self.hotkey.passthrough)
+    }
+
+    /// The profile to use while the app with this exe name is in front: the first one listing it in `processes`,
+    /// otherwise the active profile.
+    pub fn profile_for(&self, process: Option<&str>) -> &str {
+        process
+            .and_then(|process| {
+                self.profiles
+                    .iter()
+                    .find(|profile| profile.processes.iter().any(|name| name.eq_ignore_ascii_case(process)))
+            })
+            .map_or(self.profile.as_str(), |profile| profile.name.as_str())
+    }
+
+    /// Items of the active profile, falling back to the top-level items if it doesn't exist.
+    pub fn active_items(&self) -> &[MenuItem] {
+        self.profile_items(&self.profile).unwrap_or(&self.items)
+    }
+}
+
+/// Global hotkeys, written like "Ctrl+Shift+Space".
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct HotkeyConfig {
+    pub trigger: String, // Shows the overlay while held
+    pub repeat: String, // Runs the last action again without opening the menu; empty to disable
+    pub pause: String, // Pauses the overlay, releasing its other hotkeys, hooks and GPU, or resumes it; empty to disable
+    pub mouse_button: String, // "Middle", "Mouse4" or "Mouse5" also shows the overlay while held; empty to disable
+    pub activation: Activation,
+    pub tap_ms: u64, // Presses shorter than this count as taps in "auto" activation
+    pub sequence_timeout_ms: u64, // Longest pause between the steps of a trigger like "Ctrl+Space, R" or "Alt, Alt"
+    pub long_press: String, // A single key, e.g. "CapsLock", that opens the overlay when held; a quick tap still reaches the focused app
+    pub long_press_ms: u64, // How long `long_press` has to be held
+    pub number_keys: bool, // 1-9 run the matching segment while the menu is shown
+    pub number_badges: bool, // Draws each segment's number key on it
+    pub passthrough: bool, // The trigger reaches other apps unless the overlay opens on it; watched by a keyboard hook
+}
+
+/// How the trigger opens and closes the menu.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Activation {
+    /// Shown while held; releasing selects.
+    Hold,
+    /// Each press opens or closes the menu; closing selects.
+    Toggle,
+    /// A tap opens the menu until the next press, a longer press works like `hold`.
+    Auto,
+}
+
+impl Activation {
+    /// Whether releasing the trigger after holding it for `held` leaves the menu open.
+    pub fn stays_open(self, held: Duration, tap: Duration) -> bool {
+        match self {
+            Activation::Hold => false,
+            Activation::Toggle => true,
+            Activation::Auto => held < tap,
+        }
+    }
+}
+
+/// Dwell-to-select: hovering a segment for `time_ms` selects it without releasing the hotkey.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct DwellConfig {
+    pub enabled: bool,
+    pub time_ms: u64,
+}
+
+/// Click sound and gamepad rumble on selection changes and fired actions.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct FeedbackConfig {
+    pub sound: bool,
+    pub sound_file: Option<String>, // WAV file; the system menu sound is used when unset
+    pub volume: f32,                // 0.0..1.0
+    pub rumble: bool,
+}
+
+/// Zones past the outer ring in the four cardinal directions, snapping the focused window by default.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct EdgesConfig {
+    pub enabled: bool,
+    pub distance: f32, // Distance from the center where the zones start, in the ring's units (outer radius 0.25)
+    pub left: Option<Action>, // Replaces the default snap action for that direction
+    pub right: Option<Action>,
+    pub top: Option<Action>,
+    pub bottom: Option<Action>,
+}
+
+/// Concentric rings revealed in stages as the cursor moves outward: the first items form the inner
+/// ring, e.g. favorites, and the rest appear around it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RingsConfig {
+    pub enabled: bool,
+    pub sizes: Vec<usize>, // Items per ring from the inside out; the remaining items form the outermost ring
+    pub width: f32, // Radial width of each ring, in the ring's units (the single ring spans 0.08 to 0.25)
+    pub breakpoints: Vec<f32>, // Cursor distance revealing each ring after the first; defaults to the edge of the ring inside it
+}
+
+/// Where the first segment sits and which way the others follow it around the ring.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct OrientationConfig {
+    pub rotation_degrees: f32, // Turns the menu clockwise; 0 starts the first segment at 3 o'clock
+    pub clockwise: bool, // Order of the items around the ring
+    pub center_first: bool, // The rotation places the middle of the first segment rather than its start
+}
+
+/// Relative selection for games that lock the cursor to the screen center: mouse movement since the
+/// overlay opened picks the segment instead of the cursor position.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RelativeConfig {
+    pub enabled: bool,
+    pub sensitivity: f32, // Pixels of overlay movement per mouse count
+}
+
+/// Selecting segments with the left stick of the first XInput controller.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct GamepadConfig {
+    pub enabled: bool,
+    pub dead_zone: f32, // Fraction of full deflection around the center that is ignored
+    pub snap: bool, // The selection stays when the stick springs back, so releasing the trigger still runs it
+}
+
+/// How the segment under the cursor or stick is picked.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct SelectionConfig {
+    pub hysteresis_degrees: f32, // How far past a boundary the selection changes, so it doesn't flicker; 0 to disable
+    pub inner_grace_ms: u64, // How long the selection survives the cursor slipping inside the inner radius; 0 to disable
+    pub angle_smoothing_ms: u64, // Time constant the cursor's angle is smoothed over against hand jitter; 0 to disable
+    pub warp_cursor: bool, // Moves the cursor to the ring's center when the menu opens, and back when it closes
+    pub reanchor: bool, // A pinned menu under the cursor measures it from where it was when the menu opened, not the ring's center
+}
+
+/// The "recent" page: the last items run from any menu, opened with its own hotkey.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RecentConfig {
+    pub enabled: bool,
+    pub hotkey: String, // Shows the recent page while held
+    pub size: usize, // Items remembered, most recent first
+}
+
+/// The window switcher page: a segment for each open window, listed when its hotkey is pressed.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct SwitcherConfig {
+    pub enabled: bool,
+    pub hotkey: String, // Shows the open windows while held; releasing over one brings it to the front
+}
+
+/// The profile switcher page: a segment for each profile, making the one picked the active profile.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ProfileSwitcherConfig {
+    pub enabled: bool,
+    pub hotkey: String, // Shows the profiles while held; empty to open them only by scrolling
+    pub scroll: bool, // Turning the mouse wheel while the menu is open shows the profiles in its place
+}
+
+/// Clipboard history: the last text clips, pasted into the focused app from their own page.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ClipboardConfig {
+    pub enabled: bool,
+    pub hotkey: String, // Shows the clips while held; empty to use the history only as the "clipboard" profile
+    pub size: usize, // Clips remembered, most recent first
+    pub ttl_minutes: u64, // Clips older than this are forgotten; 0 keeps them
+    pub persist: bool, // Keeps the history in clipboard.json across restarts
+}
+
+/// Recent files page: the latest documents and folders, opened with their associated apps from their own page.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RecentFilesConfig {
+    pub enabled: bool,
+    pub hotkey: String, // Shows the files while held; empty to use them only as the "recent_files" profile
+    pub directory: String, // Folder whose newest entries are listed; empty for the Windows Recent Items folder
+    pub size: usize, // Entries listed, newest first
+}
+
+/// Search mode: typing while the menu is open filters the items of every profile onto the ring.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct SearchConfig {
+    pub enabled: bool,
+    pub results: usize, // Best matches shown as segments
+}
+
+/// High-contrast and reduced-motion modes, each following its Windows setting unless set here, and spoken
+/// announcements of the selection.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct AccessibilityConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub high_contrast: Option<bool>, // Outlined segments with a solid highlight instead of colors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reduced_motion: Option<bool>, // No pulsing selection, spinners or sliding progress
+    pub announce: bool, // Reads the selected segment's label aloud
+}
+
+/// Opt-in control from other devices on the LAN, e.g. a phone browser.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RemoteConfig {
+    pub enabled: bool,
+    pub port: u16,
+    pub token: String, // Required by clients; a random one is generated per run when empty
+}
+
+/// Shares every frame with a present hook injected into exclusive fullscreen games, where no window
+/// can be drawn over the game. The hook is a separate helper; takes effect on restart.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct HookConfig {
+    pub enabled: bool,
+    pub texture_name: String, // Name the helper opens the shared memory by
+}
+
+/// When the Vulkan device, swapchain and pipelines are created and destroyed, so the overlay can hold next to no
+/// GPU memory while nothing is shown. Neither applies in hook mode, whose helper keeps the shared texture open.
+/// A renderer that can't be created when it is needed falls back to the popup menu, as at startup. Takes effect
+/// on restart.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RendererConfig {
+    pub lazy: bool, // Created the first time something is shown instead of at startup
+    pub release_after_min: u32, // Destroyed after this many minutes with nothing shown, and created again when needed; 0 never
+}
+
+/// A SPIR-V fragment shader that draws the ring in place of the built-in one, for custom looks. It gets the
+/// built-in shader's inputs: the uniforms at binding 0 (time, resolution, selection and segment states) and the
+/// position in NDC at location 0. Takes effect on restart.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ShaderConfig {
+    pub fragment: String, // Path to the .spv file; empty for the built-in shader
+}
+
+/// How the ring's segments are filled in place of their built-in colors. Gradients follow config reloads; the
+/// texture is loaded when the overlay starts.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub fill: Fill,
+    pub colors: Vec<String>, // "#rrggbb" start and end of a gradient
+    pub angle_degrees: f32, // Direction a linear gradient runs in, counter-clockwise from the right
+    pub texture: String, // PNG tiled across the ring by a texture fill
+    pub texture_scale: f32, // Size the texture is drawn at; 1 is pixel for pixel
+    pub shadow: bool, // Soft shadow around the rings, so they stand out against bright scenes
+    pub shadow_color: String, // "#rrggbb"; a light color with no offset makes an outer glow
+    pub shadow_opacity: f32, // 0..1 where the shadow is darkest
+    pub shadow_size: f32, // How far the shadow fades out over, in pixels
+    pub shadow_offset: [f32; 2], // Pixels right and down the shadow is cast
+    pub adaptive_contrast: bool, // Darken the ring's surroundings when the menu opens over a bright screen
+    pub frosted_glass: bool, // Segments show a blurred copy of the screen behind them, taken as the menu opens
+    pub frosted_opacity: f32, // 0..1 how much of the segments' own colors covers the blurred screen
+    pub frosted_blur: f32, // Blur radius in pixels
+    pub wedge: bool, // A faint wedge in the middle of the menu points the way the cursor is heading
+    pub wedge_opacity: f32, // 0..1 share of the wedge's pixels drawn, dithered, once the cursor is well away from the center
+    pub animation: AnimationConfig,
+}
+
+/// Keyframe animations of the menu: `open` runs as it opens and `idle` after that, while `hover` runs from when a
+/// segment is hovered. The default hover timeline is the gentle in-and-out of the hovered segment's edge.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct AnimationConfig {
+    pub open: Timeline,
+    pub idle: Timeline,
+    pub hover: Timeline,
+    pub opacity: f32, // 0..1 opacity of the whole menu
+    pub inactive_opacity: f32, // 0..1 of that the menu fades to while the cursor keeps still
+    pub inactive_after_ms: u32, // How long the cursor keeps still before the fade starts
+    pub inactive_fade_ms: u32, // How long the fade takes; moving the cursor restores the menu at once
+}
+
+/// What segments are filled with.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Fill {
+    /// The built-in color of each segment.
+    Flat,
+    /// A gradient across the whole menu, along `angle_degrees`.
+    Linear,
+    /// A gradient from each ring's inner edge to its outer edge.
+    Radial,
+    /// The `texture` PNG, repeated.
+    Texture,
+}
+
+/// Always-on heads-up display of small widgets in a corner of the screen, apart from the menu.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct HudConfig {
+    pub enabled: bool, // Shown at startup
+    pub hotkey: String, // Shows or hides the HUD, e.g. "Ctrl+Shift+H"; empty, the default, to register none
+    pub corner: Anchor, // Where on the cursor's monitor the HUD sits
+    pub widgets: Vec<HudWidget>, // Drawn top to bottom
+}
+
+/// Panel of live renderer stats drawn on the overlay, for tracking down rendering and DPI problems.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct DebugConfig {
+    pub enabled: bool, // Shown at startup
+    pub hotkey: String, // Shows or hides the panel, e.g. "Ctrl+Shift+F12"; empty, the default, to register none
+}
+
+/// Starting the overlay when the user logs in to Windows.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct StartupConfig {
+    pub with_windows: bool, // Registered under the current user's Run key while set, removed from it otherwise
+    pub minimized: bool, // Started with `--minimized`, its console window minimized
+}
+
+/// Apps the overlay stays out of, e.g. games whose anti-cheat is wary of hooks, or kiosk apps.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct SuppressConfig {
+    pub blocklist: Vec<String>, // Exe names, e.g. "valorant.exe", the overlay doesn't open over
+    pub allowlist: Vec<String>, // When not empty, the overlay only opens over these apps
+    pub fullscreen: bool, // Also stays out of apps running fullscreen, as most games do
+    pub pass_through: bool, // Releases the hotkeys and hooks while suppressed, so the app gets the keys; otherwise they are swallowed
+}
+
+/// A line of the HUD.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HudWidget {
+    Clock,
+    Cpu, // Usage of all cores
+    Gpu, // 3D engine usage of all GPUs
+    Fps, // Frames the menu drew in the last second
+}
+
+/// Limits for running actions.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ActionsConfig {
+    pub timeout_ms: u64, // Commands still running after this long are stopped; 0 waits forever
+}
+
+/// The broker `mqtt` actions publish to.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct MqttConfig {
+    pub host: String, // No connection is made while empty
+    pub port: u16,
+    pub client_id: String,
+    pub username: String, // Sent with the password when set
+    pub password: String,
+}
+
+impl ActionsConfig {
+    pub fn timeout(&self) -> Option<Duration> {
+        match self.timeout_ms {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            profile: DEFAULT_PROFILE.to_string(),
+            anchor: Anchor::Cursor,
+            hotkey: HotkeyConfig::default(),
+            dwell: DwellConfig::default(),
+            feedback: FeedbackConfig::default(),
+            edges: EdgesConfig::default(),
+            rings: RingsConfig::default(),
+            orientation: OrientationConfig::default(),
+            relative: RelativeConfig::default(),
+            gamepad: GamepadConfig::default(),
+            selection: SelectionConfig::default(),
+            recent: RecentConfig::default(),
+            switcher: SwitcherConfig::default(),
+            profile_switcher: ProfileSwitcherConfig::default(),
+            clipboard: ClipboardConfig::default(),
+            recent_files: RecentFilesConfig::default(),
+            search: SearchConfig::default(),
+            accessibility: AccessibilityConfig::default(),
+            remote: RemoteConfig::default(),
+            hook: HookConfig::default(),
+            renderer: RendererConfig::default(),
+            shader: ShaderConfig::default(),
+            theme: ThemeConfig::default(),
+            hud: HudConfig::default(),
+            debug: DebugConfig::default(),
+            startup: StartupConfig::default(),
+            suppress: SuppressConfig::default(),
+            actions: ActionsConfig::default(),
+            mqtt: MqttConfig::default(),
+            items: Vec::new(),
+            profiles: Vec::new(),
+            shared: BTreeMap::new(),
+            sources: Vec::new(),
+        }
+    }
+}
+
+impl Default for HotkeyConfig {
+    fn default() -> Self {
+        Self {
+            trigger: "Alt+R".to_string(),
+            repeat: String::new(),
+            pause: String::new(),
+            mouse_button: String::new(),
+            activation: Activation::Hold,
+            tap_ms: 250,
+            sequence_timeout_ms: 500,
+            long_press: String::new(),
+            long_press_ms: 300,
+            number_keys: true,
+            number_badges: false,
+            passthrough: false,
+        }
+    }
+}
+
+impl Default for EdgesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            distance: 0.45,
+            left: None,
+            right: None,
+            top: None,
+            bottom: None,
+        }
+    }
+}
+
+impl Default for RingsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sizes: vec![4],
+            width: 0.17,
+            breakpoints: Vec::new(),
+        }
+    }
+}
+
+impl Default for OrientationConfig {
+    fn default() -> Self {
+        Self {
+            rotation_degrees: 0.0,
+            clockwise: true,
+            center_first: false,
+        }
+    }
+}
+
+impl Default for RelativeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sensitivity: 1.0,
+        }
+    }
+}
+
+impl Default for GamepadConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dead_zone: 0.25,
+            snap: true,
+        }
+    }
+}
+
+impl Default for SelectionConfig {
+    fn default() -> Self {
+        Self {
+            hysteresis_degrees: 0.0,
+            inner_grace_ms: 0,
+            angle_smoothing_ms: 0,
+            warp_cursor: false,
+            reanchor: false,
+        }
+    }
+}
+
+impl Default for HookConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            texture_name: "RadialMenuOverlayFrame".to_string(),
+        }
+    }
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        Self {
+            lazy: false,
+            release_after_min: 0,
+        }
+    }
+}
+
+impl Default for ShaderConfig {
+    fn default() -> Self {
+        Self {
+            fragment: String::new(),
+        }
+    }
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            fill: Fill::Flat,
+            colors: vec!["#3a7bd5".to_string(), "#00d2ff".to_string()],
+            angle_degrees: 90.0,
+            texture: String::new(),
+            texture_scale: 1.0,
+            shadow: false,
+            shadow_color: "#000000".to_string(),
+            shadow_opacity: 0.6,
+            shadow_size: 16.0,
+            shadow_offset: [3.0, 5.0],
+            adaptive_contrast: false,
+            frosted_glass: false,
+            frosted_opacity: 0.6,
+            frosted_blur: 8.0,
+            wedge: false,
+            wedge_opacity: 0.35,
+            animation: AnimationConfig::default(),
+        }
+    }
+}
+
+impl Default for AnimationConfig {
+    fn default() -> Self {
+        let keyframe = |at_ms, value, easing| Keyframe { at_ms, value, easing };
+        Self {
+            open: Timeline::default(),
+            idle: Timeline::default(),
+            // Close to 20 pixels times a sine with a period of π seconds
+            hover: Timeline {
+                grow: vec![
+                    keyframe(0, 0.0, Easing::Linear),
+                    keyframe(785, 20.0, Easing::EaseOut),
+                    keyframe(1571, 0.0, Easing::EaseIn),
+                    keyframe(2356, -20.0, Easing::EaseOut),
+                    keyframe(3142, 0.0, Easing::EaseIn),
+                ],
+                repeat: true,
+                ..Timeline::default()
+            },
+            opacity: 1.0,
+            inactive_opacity: 1.0,
+            inactive_after_ms: 1000,
+            inactive_fade_ms: 300,
+        }
+    }
+}
+
+impl Default for RecentConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hotkey: "Alt+Shift+R".to_string(),
+            size: 8,
+        }
+    }
+}
+
+impl Default for ProfileSwitcherConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hotkey: "Alt+Shift+P".to_string(),
+            scroll: true,
+        }
+    }
+}
+
+impl Default for SwitcherConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hotkey: "Alt+Shift+W".to_string(),
+        }
+    }
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hotkey: "Alt+Shift+V".to_string(),
+            size: 10,
+            ttl_minutes: 0,
+            persist: false,
+        }
+    }
+}
+
+impl Default for RecentFilesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hotkey: "Alt+Shift+F".to_string(),
+            directory: String::new(),
+            size: 10,
+        }
+    }
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            results: 8,
+        }
+    }
+}
+
+impl Default for AccessibilityConfig {
+    fn default() -> Self {
+        Self {
+            high_contrast: None,
+            reduced_motion: None,
+            announce: false,
+        }
+    }
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 8765,
+            token: String::new(),
+        }
+    }
+}
+
+impl Default for HudConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hotkey: String::new(),
+            corner: Anchor::TopRight,
+            widgets: vec![HudWidget::Clock, HudWidget::Cpu, HudWidget::Gpu, HudWidget::Fps],
+        }
+    }
+}
+
+impl Default for DebugConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hotkey: String::new(),
+        }
+    }
+}
+
+impl Default for StartupConfig {
+    fn default() -> Self {
+        Self {
+            with_windows: false,
+            minimized: true,
+        }
+    }
+}
+
+impl Default for SuppressConfig {
+    fn default() -> Self {
+        Self {
+            blocklist: Vec::new(),
+            allowlist: Vec::new(),
+            fullscreen: false,
+            pass_through: true,
+        }
+    }
+}
+
+impl Default for ActionsConfig {
+    fn default() -> Self {
+        Self {
+            timeout_ms: 300_000,
+        }
+    }
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            host: String::new(),
+            port: 1883,
+            client_id: "radial-menu-overlay".to_string(),
+            username: String::new(),
+            password: String::new(),
+        }
+    }
+}
+
+impl Default for DwellConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            time_ms: 600,
+        }
+    }
+}
+
+impl Default for FeedbackConfig {
+    fn default() -> Self {
+        Self {
+            sound: false,
+            sound_file: None,
+            volume: 0.5,
+            rumble: false,
+        }
+    }
+}
+
+/// Uses this configuration file instead of the default one, for `--config`. Must come before `config_path`.
+pub fn set_config_path(path: &str) {
+    let _ = CONFIG_PATH.set(path.to_string());
+}
+
+/// The configuration file: the one given with `--config`, a `config.toml` in the working directory for portable
+/// setups, or `%APPDATA%\RadialMenuOverlay\config\config.toml`.
+pub fn config_path() -> &'static str {
+    CONFIG_PATH.get_or_init(|| {
+        if Path::new(CONFIG_FILE_NAME).exists() {
+            return CONFIG_FILE_NAME.to_string();
+        }
+        match project_dirs() {
+            Some(dirs) => dirs.config_dir().join(CONFIG_FILE_NAME).display().to_string(),
+            None => CONFIG_FILE_NAME.to_string(),
+        }
+    })
+}
+
+/// The folder of the configuration file, where state such as the recent items is kept with it.
+pub fn config_dir() -> PathBuf {
+    match Path::new(config_path()).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Where diagnostics bundles and crash reports are written, `%LOCALAPPDATA%\RadialMenuOverlay\data`.
+/// Created if missing; the working directory if it can't be.
+pub fn data_dir() -> PathBuf {
+    project_dirs()
+        .map(|dirs| dirs.data_local_dir().to_path_buf())
+        .filter(|dir| fs::create_dir_all(dir).is_ok())
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("", "", "RadialMenuOverlay")
+}
+
+/// Writes the default configuration on first run, so there is a file to edit. Returns whether one was written.
+pub fn create_default_config() -> Result<bool, String> {
+    let path = Path::new(config_path());
+    if path.exists() {
+        return Ok(false);
+    }
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {:?}", dir.display(), e))?;
+    }
+    let text = toml::to_string(&Config::default()).map_err(|e| format!("Failed to write the default config: {}", e))?;
+    fs::write(path, format!("# Radial Menu Overlay settings; the README lists every option\n\n{}", text))
+        .map_err(|e| format!("Failed to write {}: {:?}", path.display(), e))?;
+    Ok(true)
+}
+
+/// Reads and parses the configuration file, merging in included files and shared item definitions.
+pub fn load_config<P: AsRef<Path>>(path: P) -> Result<Config, String> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read config file: {:?}", e))?;
+
+    let mut sources = vec![path.to_path_buf()];
+    let mut table = parse_table(path, &text)?;
+    merge_includes(&mut table, path, &mut sources, 0)?;
+    let referenced = resolve_references(&mut table)?;
+
+    let mut config: Config = if sources.len() == 1 && !referenced {
+        // Deserializing the text itself keeps line and column numbers in errors
+        toml::from_str(&text)
+    } else {
+        toml::Value::Table(table).try_into()
+    }
+    .map_err(|e| locate_error(&sources).unwrap_or_else(|| format!("Failed to parse config file: {}", e)))?;
+    config.sources = sources;
+    Ok(config)
+}
+
+/// Deserializes each file on its own to find the one a merged config fails in, so the error names that
+/// file and keeps its line and column. None if every file is fine alone, e.g. when merging caused it.
+fn locate_error(sources: &[PathBuf]) -> Option<String> {
+    sources.iter().find_map(|path| {
+        let text = fs::read_to_string(path).ok()?;
+        toml::from_str::<Config>(&standalone(&text)).err().map(|e| format!("Failed to parse {}: {}", path.display(), e))
+    })
+}
+
+/// `text` with the `use` key of items that get their label from a shared definition renamed to `label`, so
+/// they deserialize without it. Only keys are renamed, leaving every line where it was.
+fn standalone(text: &str) -> String {
+    let document = match toml_edit::ImDocument::parse(text) {
+        Ok(document) => document,
+        Err(_) => return text.to_string(),
+    };
+    let root = document.as_item();
+    let mut lists = vec![root.get("items")];
+    let profiles = root.get("profiles");
+    lists.extend((0..).map_while(|index| profiles.and_then(|profiles| profiles.get(index))).map(|profile| profile.get("items")));
+    let mut spans: Vec<_> = lists
+        .into_iter()
+        .flatten()
+        .flat_map(|items| (0..).map_while(move |index| items.get(index)))
+        .filter_map(|item| item.as_table_like())
+        .filter(|item| !item.contains_key("label"))
+        .filter_map(|item| item.key("use").and_then(|key| key.span()))
+        .collect();
+    // From the end so earlier spans stay valid
+    spans.sort_by_key(|span| std::cmp::Reverse(span.start));
+    let mut text = text.to_string();
+    for span in spans {
+        text.replace_range(span, "label");
+    }
+    text
+}
+
+fn parse_table(path: &Path, text: &str) -> Result<toml::Table, String> {
+    toml::from_str(text).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+/// Merges the files listed in `include` into `table`.
+/// Values already in `table` win; arrays such as `items` get the included entries appended.
+fn merge_includes(table: &mut toml::Table, path: &Path, sources: &mut Vec<PathBuf>, depth: usize) -> Result<(), String> {
+    let includes: Vec<String> = match table.get("include") {
+        None => return Ok(()),
+        Some(toml::Value::String(file)) => vec![file.clone()],
+        Some(toml::Value::Array(files)) => files
+            .iter()
+            .map(|file| file.as_str().map(str::to_string))
+            .collect::<Option<_>>()
+            .ok_or_else(|| format!("{}: include must list file names", path.display()))?,
+        Some(_) => return Err(format!("{}: include must be a file name or a list of them", path.display())),
+    };
+    if depth >= MAX_INCLUDE_DEPTH {
+        return Err(format!("{}: includes nest too deeply (is there a cycle?)", path.display()));
+    }
+    table.insert("include".to_string(), toml::Value::Array(includes.iter().cloned().map(toml::Value::String).collect()));
+
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    for file in includes {
+        let include_path = base.join(&file);
+        let text = fs::read_to_string(&include_path)
+            .map_err(|e| format!("Failed to read included file {}: {:?}", include_path.display(), e))?;
+        let mut included = parse_table(&include_path, &text)?;
+        // In the order their entries are merged, which `validate` relies on to find them again
+        sources.push(include_path.clone());
+        merge_includes(&mut included, &include_path, sources, depth + 1)?;
+        included.remove("include");
+        merge_missing(table, included);
+    }
+    Ok(())
+}
+
+/// Adds entries from `other` that `table` lacks, recursing into tables and appending to arrays.
+fn merge_missing(table: &mut toml::Table, other: toml::Table) {
+    for (key, value) in other {
+        match (table.get_mut(&key), value) {
+            (Some(toml::Value::Table(existing)), toml::Value::Table(value)) => merge_missing(existing, value),
+            (Some(toml::Value::Array(existing)), toml::Value::Array(value)) => existing.extend(value),
+            (Some(_), _) => {}
+            (None, value) => {
+                table.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Replaces items that say `use = "<name>"` with the shared definition, overridden by their own keys.
+/// Returns whether any item referenced a shared definition.
+fn resolve_references(table: &mut toml::Table) -> Result<bool, String> {
+    let shared = match table.get("shared") {
+        Some(toml::Value::Table(shared)) => shared.clone(),
+        _ => toml::Table::new(),
+    };
+
+    let mut referenced = false;
+    let mut resolve = |items: &mut toml::Value| -> Result<(), String> {
+        for item in items.as_array_mut().into_iter().flatten() {
+            let item = match item.as_table_mut() {
+                Some(item) => item,
+                None => continue,
+            };
+            let name = match item.get("use").and_then(|name| name.as_str()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            let mut resolved = match shared.get(&name) {
+                Some(toml::Value::Table(definition)) => definition.clone(),
+                _ => return Err(format!("Item uses \"{}\", which is not defined under [shared]", name)),
+            };
+            resolved.extend(std::mem::take(item));
+            *item = resolved;
+            referenced = true;
+        }
+        Ok(())
+    };
+
+    if let Some(items) = table.get_mut("items") {
+        resolve(items)?;
+    }
+    if let Some(profiles) = table.get_mut("profiles").and_then(|profiles| profiles.as_array_mut()) {
+        for profile in profiles.iter_mut() {
+            if let Some(items) = profile.get_mut("items") {
+                resolve(items)?;
+            }
+        }
+    }
+    Ok(referenced)
+}
+
+/// Loads the configuration, falling back to defaults if the file is missing or invalid.
+pub fn load_or_default<P: AsRef<Path>>(path: P) -> Config {
+    if !path.as_ref().exists() {
+        return Config::default();
+    }
+    match load_config(path) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            Config::default()
+        }
+    }
+}
+
+/// How often the files are checked when a folder of theirs can't be watched, e.g. one that doesn't exist yet.
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+/// How long the file must stay unchanged before it is reloaded, so editors that write in several steps
+/// only trigger one reload.
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Watches the configuration file and the files it includes for changes. Windows signals a change notification
+/// of each of their folders when a file in it is written, which the main loop waits on along with its messages.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    notifications: Vec<HANDLE>, // One per folder that could be watched
+    unwatched: bool, // Some folder couldn't be watched, so the files are checked every WATCH_INTERVAL instead
+    changed_at: Option<Instant>,
+    last_check: Instant,
+}
+
+impl ConfigWatcher {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let mut watcher = Self {
+            path: path.clone(),
+            files: Vec::new(),
+            notifications: Vec::new(),
+            unwatched: false,
+            changed_at: None,
+            last_check: Instant::now(),
+        };
+        watcher.watch(&[]);
+        watcher
+    }
+
+    /// Watches these files as well as the config file itself, e.g. the `sources` of the loaded config.
+    pub fn watch(&mut self, sources: &[PathBuf]) {
+        self.files = std::iter::once(&self.path)
+            .chain(sources.iter().filter(|source| **source != self.path))
+            .map(|path| (path.clone(), modified_time(path)))
+            .collect();
+
+        self.close_notifications();
+        // A bare file name is in the working directory
+        let mut folders: Vec<&Path> = self
+            .files
+            .iter()
+            .filter_map(|(path, _)| path.parent())
+            .map(|folder| if folder.as_os_str().is_empty() { Path::new(".") } else { folder })
+            .collect();
+        folders.sort();
+        folders.dedup();
+        self.unwatched = false;
+        for folder in folders {
+            let folder: Vec<u16> = folder.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+            let notification = unsafe {
+                FindFirstChangeNotificationW(
+                    folder.as_ptr(),
+                    FALSE,
+                    FILE_NOTIFY_CHANGE_LAST_WRITE | FILE_NOTIFY_CHANGE_FILE_NAME,
+                )
+            };
+            if notification == INVALID_HANDLE_VALUE {
+                self.unwatched = true;
+            } else {
+                self.notifications.push(notification);
+            }
+        }
+    }
+
+    /// Change notifications of the watched folders, signaled when a file in one of them changes.
+    pub fn notifications(&self) -> &[HANDLE] {
+        &self.notifications
+    }
+
+    /// How long until `poll` next has something to do without a folder being signaled: reloading after the
+    /// debounce, or checking folders that can't be watched. None while there is nothing to wait for.
+    pub fn next_check(&self) -> Option<Duration> {
+        let reload = self.changed_at.map(|changed_at| RELOAD_DEBOUNCE.saturating_sub(changed_at.elapsed()));
+        let check = self.unwatched.then(|| WATCH_INTERVAL.saturating_sub(self.last_check.elapsed()));
+        reload.into_iter().chain(check).min()
+    }
+
+    /// Returns the reloaded configuration once the file has changed and settled,
+    /// or the parse error if the new contents are invalid.
+    pub fn poll(&mut self) -> Option<Result<Config, String>> {
+        // Every signaled notification is rearmed, so it signals again with the next change
+        let mut signaled = false;
+        for &notification in &self.notifications {
+            if unsafe { WaitForSingleObject(notification, 0) } == WAIT_OBJECT_0 {
+                unsafe { FindNextChangeNotification(notification) };
+                signaled = true;
+            }
+        }
+        let due = self.unwatched && self.last_check.elapsed() >= WATCH_INTERVAL;
+        if signaled || due {
+            self.last_check = Instant::now();
+            let mut changed = false;
+            for (path, modified) in self.files.iter_mut() {
+                let current = modified_time(path);
+                if current != *modified {
+                    *modified = current;
+                    changed = true;
+                }
+            }
+            if changed {
+                self.changed_at = Some(Instant::now());
+                return None;
+            }
+        }
+
+        match self.changed_at {
+            Some(changed_at) if changed_at.elapsed() >= RELOAD_DEBOUNCE => {
+                self.changed_at = None;
+                // A deleted config file keeps the current configuration
+                self.files[0].1.map(|_| load_config(&self.path))
+            }
+            _ => None,
+        }
+    }
+
+    fn close_notifications(&mut self) {
+        for notification in self.notifications.drain(..) {
+            unsafe { FindCloseChangeNotification(notification) };
+        }
+    }
+}
+
+impl Drop for ConfigWatcher {
+    fn drop(&mut self) {
+        self.close_notifications();
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
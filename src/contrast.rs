@@ -0,0 +1,77 @@
+// Adaptive contrast: how bright the screen under the menu is when it opens, so a dark backdrop can be put behind
+// the ring over bright scenes
+
+use winapi::ctypes::c_void;
+use winapi::shared::windef::{HGDIOBJ, HWND, RECT};
+use winapi::um::wingdi::*;
+use winapi::um::winuser::{GetDC, GetWindowRect, ReleaseDC};
+use std::mem::{size_of, zeroed};
+use std::ptr::null_mut;
+
+/// Side of the thumbnail the screen under the window is shrunk to; enough for an average.
+const SAMPLE_SIZE: i32 = 16;
+
+/// Average luminance, 0..1, of the screen under the window. The overlay is a layered window, which BitBlt leaves
+/// out, so it doesn't darken its own sample.
+pub fn background_luminance(hwnd: HWND) -> Option<f32> {
+    let mut pixels = vec![0u32; (SAMPLE_SIZE * SAMPLE_SIZE) as usize];
+    unsafe {
+        let mut rect: RECT = zeroed();
+        if GetWindowRect(hwnd, &mut rect) == 0 {
+            return None;
+        }
+        let screen = GetDC(null_mut());
+        if screen.is_null() {
+            return None;
+        }
+        let memory_dc = CreateCompatibleDC(screen);
+        let mut info: BITMAPINFO = zeroed();
+        info.bmiHeader.biSize = size_of::<BITMAPINFOHEADER>() as u32;
+        info.bmiHeader.biWidth = SAMPLE_SIZE;
+        info.bmiHeader.biHeight = -SAMPLE_SIZE; // Top-down rows
+        info.bmiHeader.biPlanes = 1;
+        info.bmiHeader.biBitCount = 32;
+        info.bmiHeader.biCompression = BI_RGB;
+        let mut bits: *mut c_void = null_mut();
+        let bitmap = CreateDIBSection(memory_dc, &info, DIB_RGB_COLORS, &mut bits, null_mut(), 0);
+        let captured = !bitmap.is_null() && {
+            let previous = SelectObject(memory_dc, bitmap as HGDIOBJ);
+            // HALFTONE averages each block of pixels rather than picking one
+            SetStretchBltMode(memory_dc, HALFTONE);
+            let copied = StretchBlt(
+                memory_dc,
+                0,
+                0,
+                SAMPLE_SIZE,
+                SAMPLE_SIZE,
+                screen,
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                SRCCOPY,
+            );
+            GdiFlush();
+            if copied != 0 {
+                (bits as *const u32).copy_to_nonoverlapping(pixels.as_mut_ptr(), pixels.len());
+            }
+            SelectObject(memory_dc, previous);
+            DeleteObject(bitmap as HGDIOBJ);
+            copied != 0
+        };
+        DeleteDC(memory_dc);
+        ReleaseDC(null_mut(), screen);
+        if !captured {
+            return None;
+        }
+    }
+
+    let total: f32 = pixels
+        .iter()
+        .map(|&pixel| {
+            let channel = |shift: u32| ((pixel >> shift) & 0xFF) as f32 / 255.0;
+            0.2126 * channel(16) + 0.7152 * channel(8) + 0.0722 * channel(0)
+        })
+        .sum();
+    Some(total / pixels.len() as f32)
+}
@@ -0,0 +1,78 @@
+// Panic hook: writes a crash report and releases what would otherwise outlive a crashed overlay, a topmost
+// transparent window and hotkeys other apps then can't register
+
+use winapi::shared::windef::{HWND, HWND__};
+use winapi::um::winuser::{DestroyWindow, ShowWindowAsync, SW_HIDE};
+use std::backtrace::Backtrace;
+use std::panic;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use std::thread;
+use crate::hotkey::{unregister_hotkey, unregister_menu_hotkeys, unregister_panel_hotkeys, unregister_pause_hotkey};
+use crate::window::error_box;
+use crate::{diagnostics, render_thread, search, shutdown, wake};
+
+/// The overlay window, hidden or destroyed after a panic.
+static WINDOW: AtomicPtr<HWND__> = AtomicPtr::new(null_mut());
+/// Only the first panic that ends the overlay is handled; cleaning up after it may panic again.
+static PANICKED: AtomicBool = AtomicBool::new(false);
+
+/// Installs the hook for the overlay window. The default hook still prints the panic.
+pub fn install(hwnd: HWND) {
+    WINDOW.store(hwnd, Ordering::SeqCst);
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        handle_panic(&info.to_string());
+    }));
+}
+
+/// `panic` is the panic's message and where it happened.
+fn handle_panic(panic: &str) {
+    let thread = thread::current().name().unwrap_or("unnamed").to_string();
+    let report = format!(
+        "radial_menu_overlay {} panicked on thread '{}': {}\n\nBacktrace:\n{}",
+        env!("CARGO_PKG_VERSION"),
+        thread,
+        panic,
+        Backtrace::force_capture(),
+    );
+    let saved = diagnostics::save_crash_report(&report);
+
+    // Actions and servers run on threads of their own, which a panic ends without taking the overlay down
+    if !wake::on_main_thread() && thread != render_thread::THREAD_NAME {
+        match saved {
+            Ok(path) => diagnostics::report_error(format!("A background task stopped after an error, see {}", path)),
+            Err(e) => diagnostics::report_error(e),
+        }
+        return;
+    }
+    if PANICKED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    // Hotkeys and the window belong to the main thread, which only it can release; a render thread panic hides
+    // the window and asks the main loop to shut down, which does the rest
+    let hwnd = WINDOW.load(Ordering::SeqCst);
+    if wake::on_main_thread() {
+        unregister_panel_hotkeys();
+        unregister_menu_hotkeys();
+        unregister_hotkey();
+        unregister_pause_hotkey();
+        search::capture(false);
+        if !hwnd.is_null() {
+            unsafe { DestroyWindow(hwnd) };
+        }
+    } else {
+        if !hwnd.is_null() {
+            unsafe { ShowWindowAsync(hwnd, SW_HIDE) };
+        }
+        shutdown::request("panic");
+    }
+
+    let message = match saved {
+        Ok(path) => format!("The overlay stopped after an error:\n{}\n\nThe details were saved to {}.", panic, path),
+        Err(e) => format!("The overlay stopped after an error:\n{}\n\n{}", panic, e),
+    };
+    error_box(&message);
+}
@@ -0,0 +1,90 @@
+// User fragment shaders that draw the ring in place of the built-in one, checked against the interface the ring
+// pipeline offers before a pipeline is built from them
+
+use std::sync::Mutex;
+use crate::config::ShaderConfig;
+use crate::diagnostics;
+use crate::render::read_spirv_shader;
+
+/// The built-in ring shader, compiled from shaders/indexFromRust.frag.glsl.
+const BUILT_IN: &str = "shaders/frag.spv";
+const SPIRV_MAGIC: u32 = 0x0723_0203;
+/// Words before the first instruction: magic, version, generator, bound and schema.
+const HEADER_WORDS: usize = 5;
+const OP_ENTRY_POINT: u32 = 15;
+const OP_DECORATE: u32 = 71;
+const EXECUTION_MODEL_FRAGMENT: u32 = 4;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+
+/// Path of the configured shader, empty for the built-in one. Read whenever the ring pipeline is built.
+static FRAGMENT: Mutex<String> = Mutex::new(String::new());
+
+pub fn configure(config: &ShaderConfig) {
+    *FRAGMENT.lock().unwrap() = config.fragment.clone();
+}
+
+/// SPIR-V of the ring's fragment shader: the configured one if it loads and fits the interface, otherwise the
+/// built-in one, with the problem reported.
+pub fn fragment_code() -> Result<Vec<u32>, String> {
+    let path = FRAGMENT.lock().unwrap().clone();
+    if !path.is_empty() {
+        match read_spirv_shader(&path).and_then(|code| check(&code).map(|()| code)) {
+            Ok(code) => {
+                diagnostics::record_event(format!("Drawing the ring with {}", path));
+                return Ok(code);
+            }
+            Err(e) => diagnostics::report_error(format!("Custom shader {} not used: {}", path, e)),
+        }
+    }
+    read_spirv_shader(BUILT_IN)
+}
+
+/// Checks that a module is SPIR-V with a fragment entry point named "main" that only binds what the ring's
+/// descriptor sets hold: the uniform block at set 0, binding 0, the theme's texture at binding 1 and the backdrop
+/// at binding 2. A module that doesn't would fail pipeline creation, or crash some drivers.
+pub fn check(code: &[u32]) -> Result<(), String> {
+    if code.len() < HEADER_WORDS || code[0] != SPIRV_MAGIC {
+        return Err("not a SPIR-V module; compile GLSL with glslangValidator -V first".to_string());
+    }
+    let mut has_main = false;
+    let mut position = HEADER_WORDS;
+    while position < code.len() {
+        let word_count = (code[position] >> 16) as usize;
+        let opcode = code[position] & 0xFFFF;
+        if word_count == 0 || position + word_count > code.len() {
+            return Err(format!("malformed instruction at word {}", position));
+        }
+        let operands = &code[position + 1..position + word_count];
+        match opcode {
+            OP_ENTRY_POINT if operands.len() >= 3 && operands[0] == EXECUTION_MODEL_FRAGMENT => {
+                has_main |= literal_string(&operands[2..]) == "main";
+            }
+            OP_DECORATE if operands.len() >= 3 => match operands[1] {
+                DECORATION_DESCRIPTOR_SET if operands[2] != 0 => {
+                    return Err(format!("uses descriptor set {}; only set 0 is bound", operands[2]));
+                }
+                DECORATION_BINDING if operands[2] > 2 => {
+                    return Err(format!(
+                        "uses binding {}; only the uniforms, the theme's texture and the backdrop, at bindings 0 to 2, \
+                         are bound",
+                        operands[2]
+                    ));
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        position += word_count;
+    }
+    if !has_main {
+        return Err("has no fragment entry point named \"main\"".to_string());
+    }
+    Ok(())
+}
+
+/// A nul-terminated UTF-8 string packed four bytes to a word, as SPIR-V stores names.
+fn literal_string(words: &[u32]) -> String {
+    let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).take_while(|&byte| byte != 0).collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
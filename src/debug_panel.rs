@@ -0,0 +1,125 @@
+// Debug panel: live renderer stats in the top left corner of the overlay, toggled by hotkey
+
+use ash::vk;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::text::{TextBatch, TEXT_BACKGROUND};
+
+/// How often the frame rate is recounted.
+const FPS_INTERVAL: Duration = Duration::from_secs(1);
+const PADDING: f32 = 4.0;
+const COLUMN_GAP: f32 = 12.0;
+
+const LABEL_COLOR: [f32; 4] = [0.55, 0.55, 0.6, 1.0];
+const VALUE_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+/// Flipped by the debug hotkey; the overlay stays up while the panel is shown.
+static SHOWN: AtomicBool = AtomicBool::new(false);
+
+pub fn toggle() {
+    SHOWN.fetch_xor(true, Ordering::SeqCst);
+}
+
+pub fn set_shown(shown: bool) {
+    SHOWN.store(shown, Ordering::SeqCst);
+}
+
+pub fn is_shown() -> bool {
+    SHOWN.load(Ordering::SeqCst)
+}
+
+/// What the renderer last reported.
+struct RendererStats {
+    fps: f32,
+    frames: u32, // Frames since the frame rate was last counted
+    counted_at: Option<Instant>,
+    cpu_frame_ms: f32, // Laying out, uploading and submitting, without waiting for the GPU
+    gpu_frame_ms: Option<f32>, // None until measured, or when the GPU can't time its work
+    format: vk::Format,
+    present_mode: vk::PresentModeKHR,
+    composite_alpha: vk::CompositeAlphaFlagsKHR,
+    dpi: u32,
+    mouse: (i32, i32), // Pixels from the window's top left
+    normalized_mouse: (f32, f32), // -1 to 1 across the window, y up
+}
+
+static STATS: Mutex<RendererStats> = Mutex::new(RendererStats {
+    fps: 0.0,
+    frames: 0,
+    counted_at: None,
+    cpu_frame_ms: 0.0,
+    gpu_frame_ms: None,
+    format: vk::Format::UNDEFINED,
+    present_mode: vk::PresentModeKHR::FIFO,
+    composite_alpha: vk::CompositeAlphaFlagsKHR::OPAQUE,
+    dpi: 96,
+    mouse: (0, 0),
+    normalized_mouse: (0.0, 0.0),
+});
+
+/// Records the choices made for a newly created swapchain.
+pub fn record_swapchain(format: vk::Format, present_mode: vk::PresentModeKHR, composite_alpha: vk::CompositeAlphaFlagsKHR) {
+    let mut stats = STATS.lock().unwrap();
+    stats.format = format;
+    stats.present_mode = present_mode;
+    stats.composite_alpha = composite_alpha;
+}
+
+/// Records the cursor as the ring's hit-testing sees it.
+pub fn record_cursor(dpi: u32, mouse: (i32, i32), normalized_mouse: (f32, f32)) {
+    let mut stats = STATS.lock().unwrap();
+    stats.dpi = dpi;
+    stats.mouse = mouse;
+    stats.normalized_mouse = normalized_mouse;
+}
+
+/// Records a submitted frame; the GPU time is of an earlier frame, whose timestamps came back since.
+pub fn record_frame(cpu_frame_time: Duration, gpu_frame_ms: Option<f32>) {
+    let mut stats = STATS.lock().unwrap();
+    stats.cpu_frame_ms = cpu_frame_time.as_secs_f32() * 1000.0;
+    if gpu_frame_ms.is_some() {
+        stats.gpu_frame_ms = gpu_frame_ms;
+    }
+    stats.frames += 1;
+    let counted_at = *stats.counted_at.get_or_insert_with(Instant::now);
+    let elapsed = counted_at.elapsed();
+    if elapsed >= FPS_INTERVAL {
+        stats.fps = stats.frames as f32 / elapsed.as_secs_f32();
+        stats.frames = 0;
+        stats.counted_at = Some(Instant::now());
+    }
+}
+
+/// Lays out the stats as labeled rows in the window's top left corner, while the panel is shown.
+pub fn layout_debug(batch: &mut TextBatch) {
+    if !is_shown() {
+        return;
+    }
+    let stats = STATS.lock().unwrap();
+    let rows = [
+        ("FPS", format!("{:.0}", stats.fps)),
+        ("CPU", format!("{:.2} ms", stats.cpu_frame_ms)),
+        ("GPU", stats.gpu_frame_ms.map_or_else(|| "n/a".to_string(), |ms| format!("{:.2} ms", ms))),
+        ("Format", format!("{:?}", stats.format)),
+        ("Present", format!("{:?}", stats.present_mode)),
+        ("Alpha", format!("{:?}", stats.composite_alpha)),
+        ("DPI", format!("{} ({:.0}%)", stats.dpi, stats.dpi as f32 / 96.0 * 100.0)),
+        ("Mouse", format!("{}, {}", stats.mouse.0, stats.mouse.1)),
+        ("Normalized", format!("{:.3}, {:.3}", stats.normalized_mouse.0, stats.normalized_mouse.1)),
+    ];
+    drop(stats);
+
+    let line_height = batch.metrics().cell_height;
+    let label_width = rows.iter().map(|(label, _)| batch.measure(label).0).fold(0.0, f32::max);
+    let value_width = rows.iter().map(|(_, value)| batch.measure(value).0).fold(0.0, f32::max);
+    let width = PADDING * 2.0 + label_width + COLUMN_GAP + value_width;
+    let height = PADDING * 2.0 + line_height * rows.len() as f32;
+    batch.rect(0.0, 0.0, width, height, TEXT_BACKGROUND);
+    let mut y = PADDING;
+    for (label, value) in rows.iter() {
+        batch.text(PADDING, y, label, LABEL_COLOR);
+        batch.text(PADDING + label_width + COLUMN_GAP, y, value, VALUE_COLOR);
+        y += line_height;
+    }
+}
@@ -0,0 +1,147 @@
+// Collects recent events and writes a diagnostics bundle for bug reports
+
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use toml_edit::{DocumentMut, Item, Table, Value};
+use zip::write::FileOptions;
+use zip::ZipWriter;
+use crate::config::{config_path, data_dir};
+use crate::{status, toast};
+
+/// Number of recent events kept for the bundle.
+const MAX_EVENTS: usize = 200;
+
+/// Config keys whose values are replaced before the config is bundled.
+const SECRET_KEYS: [&str; 5] = ["token", "password", "secret", "api_key", "authorization"];
+const REDACTED: &str = "<redacted>";
+
+static EVENTS: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+fn unix_seconds() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// Appends an event to the recent-events log.
+pub fn record_event<S: AsRef<str>>(event: S) {
+    let mut events = EVENTS.lock().unwrap();
+    events.push_back(format!("[{}] {}", unix_seconds(), event.as_ref()));
+    while events.len() > MAX_EVENTS {
+        events.pop_front();
+    }
+}
+
+/// Surfaces an error to the user as a toast and remembers it for the status report.
+pub fn report_error<S: Into<String>>(error: S) {
+    let error = error.into();
+    status::record_error(error.clone());
+    toast::push(format!("{} (Alt+D saves diagnostics)", error));
+}
+
+/// Writes the bundle and tells the user where it went.
+pub fn save_bundle_and_notify() {
+    match save_bundle() {
+        Ok(path) => toast::push(format!("Saved diagnostics to {}", path)),
+        Err(e) => {
+            eprintln!("{}", e);
+            toast::push(e);
+        }
+    }
+}
+
+/// Writes a crash report followed by the recent events, for the panic hook. The events are left out if the
+/// panicking thread was recording one. Returns the path of the written file.
+pub fn save_crash_report(report: &str) -> Result<String, String> {
+    let path = data_dir().join(format!("crash-{}.txt", unix_seconds())).display().to_string();
+    let events = match EVENTS.try_lock() {
+        Ok(events) => events.iter().cloned().collect::<Vec<String>>().join("\n"),
+        Err(_) => "(unavailable)".to_string(),
+    };
+    fs::write(&path, format!("{}\n\nRecent events:\n{}\n", report, events))
+        .map_err(|e| format!("Failed to write crash report: {:?}", e))?;
+    Ok(path)
+}
+
+/// Writes status, recent events and the config with secrets stripped into a zip.
+/// Returns the path of the written file.
+pub fn save_bundle() -> Result<String, String> {
+    let path = data_dir().join(format!("diagnostics-{}.zip", unix_seconds())).display().to_string();
+    let file = File::create(&path).map_err(|e| format!("Failed to create diagnostics bundle: {:?}", e))?;
+
+    let mut zip = ZipWriter::new(file);
+    let options = FileOptions::default();
+
+    add_file(&mut zip, "status.json", &status::to_json(), options)?;
+
+    let events: Vec<String> = EVENTS.lock().unwrap().iter().cloned().collect();
+    add_file(&mut zip, "events.log", &events.join("\n"), options)?;
+
+    if let Ok(config) = fs::read_to_string(config_path()) {
+        add_file(&mut zip, "config.toml", &strip_secrets(&config), options)?;
+    }
+
+    zip.finish()
+        .map_err(|e| format!("Failed to finish diagnostics bundle: {:?}", e))?;
+
+    Ok(path)
+}
+
+fn add_file(zip: &mut ZipWriter<File>, name: &str, contents: &str, options: FileOptions) -> Result<(), String> {
+    zip.start_file(name, options)
+        .map_err(|e| format!("Failed to add {} to diagnostics bundle: {:?}", name, e))?;
+    zip.write_all(contents.as_bytes())
+        .map_err(|e| format!("Failed to write {} to diagnostics bundle: {:?}", name, e))
+}
+
+/// Replaces the values of secret-looking keys anywhere in the config, in inline tables and arrays too. A config
+/// that doesn't parse is left out, as its secrets can't be told apart.
+fn strip_secrets(config: &str) -> String {
+    match config.parse::<DocumentMut>() {
+        Ok(mut document) => {
+            redact_table(document.as_table_mut());
+            document.to_string()
+        }
+        Err(_) => "# Left out: the config doesn't parse, so its secrets can't be found\n".to_string(),
+    }
+}
+
+fn is_secret(key: &str) -> bool {
+    let key = key.to_lowercase();
+    SECRET_KEYS.iter().any(|secret| key.contains(secret))
+}
+
+fn redact_table(table: &mut Table) {
+    for (key, item) in table.iter_mut() {
+        if is_secret(key.get()) {
+            *item = Item::Value(REDACTED.into());
+        } else {
+            match item {
+                Item::Table(table) => redact_table(table),
+                Item::ArrayOfTables(tables) => tables.iter_mut().for_each(redact_table),
+                Item::Value(value) => redact_value(value),
+                Item::None => {}
+            }
+        }
+    }
+}
+
+fn redact_value(value: &mut Value) {
+    match value {
+        Value::InlineTable(table) => {
+            for (key, value) in table.iter_mut() {
+                if is_secret(key.get()) {
+                    *value = REDACTED.into();
+                } else {
+                    redact_value(value);
+                }
+            }
+        }
+        Value::Array(array) => array.iter_mut().for_each(redact_value),
+        _ => {}
+    }
+}
@@ -0,0 +1,197 @@
+// Dial segments: hovering one turns the ring into an analog dial, and circling the cursor around the center
+// adjusts a value such as the system volume in real time, shown in the middle of the ring
+
+use serde::{Deserialize, Serialize};
+use winapi::ctypes::c_void;
+use winapi::shared::winerror::SUCCEEDED;
+use winapi::um::combaseapi::{CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_ALL};
+use winapi::um::endpointvolume::IAudioEndpointVolume;
+use winapi::um::mmdeviceapi::{eConsole, eRender, EDataFlow, IMMDevice, IMMDeviceEnumerator, MMDeviceEnumerator};
+use winapi::um::objbase::COINIT_APARTMENTTHREADED;
+use winapi::{Class, Interface};
+use std::f32::consts::PI;
+use std::ptr::null_mut;
+use crate::brightness;
+use crate::overlay::OverlayContent;
+use crate::text::TextBatch;
+use crate::variables;
+
+/// Smallest change of the value, as a share of the range, that is written to the target.
+const WRITE_STEP: f32 = 0.01;
+/// Degrees the cursor circles for the whole range when a dial doesn't set `sweep`.
+const DEFAULT_SWEEP: f32 = 360.0;
+
+const LABEL_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 1.0];
+const VALUE_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const TRACK_COLOR: [f32; 4] = [0.25, 0.25, 0.3, 1.0];
+const FILL_COLOR: [f32; 4] = [0.3, 0.6, 1.0, 1.0];
+const BAR_WIDTH: f32 = 60.0;
+const BAR_HEIGHT: f32 = 4.0;
+
+/// Turns a segment into a dial, e.g. `dial = { target = "volume" }`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Dial {
+    #[serde(flatten)]
+    pub target: DialTarget,
+    /// Degrees of circling for the whole range; 360.0 when unset. Clockwise turns the value up.
+    #[serde(default)]
+    pub sweep: Option<f32>,
+}
+
+/// What a dial reads and writes as it turns.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "target", rename_all = "snake_case")]
+pub enum DialTarget {
+    /// Master volume of the default playback device.
+    Volume,
+    /// Brightness of the monitor under the cursor, over DDC/CI.
+    Brightness,
+    /// A variable between `min` and `max`, for scripts to act on, e.g. through the named pipe's `get`.
+    Variable {
+        name: String,
+        #[serde(default)]
+        min: f32,
+        max: f32,
+    },
+}
+
+impl DialTarget {
+    /// The current value as a share of the range, None when it can't be read.
+    pub fn read(&self) -> Option<f32> {
+        match self {
+            DialTarget::Volume => with_endpoint_volume(eRender, |volume| {
+                let mut level = 0.0;
+                SUCCEEDED(unsafe { volume.GetMasterVolumeLevelScalar(&mut level) }).then_some(level)
+            })
+            .flatten(),
+            // Blocks for a DDC/CI round trip, once when the dial engages
+            DialTarget::Brightness => brightness::get().ok(),
+            DialTarget::Variable { name, min, max } => {
+                let value: f32 = variables::get(name)?.trim().parse().ok()?;
+                Some(((value - min) / (max - min)).clamp(0.0, 1.0))
+            }
+        }
+    }
+
+    /// Sets the value from a share of the range.
+    pub fn write(&self, fraction: f32) {
+        match self {
+            DialTarget::Volume => {
+                with_endpoint_volume(eRender, |volume| unsafe { volume.SetMasterVolumeLevelScalar(fraction, null_mut()) });
+            }
+            DialTarget::Brightness => brightness::set_in_background(fraction),
+            DialTarget::Variable { name, min, max } => {
+                variables::set(name.as_str(), format!("{}", (min + (max - min) * fraction).round()));
+            }
+        }
+    }
+
+    /// Shown under the label in the middle of the ring.
+    fn format(&self, fraction: f32) -> String {
+        match self {
+            DialTarget::Volume | DialTarget::Brightness => format!("{:.0}%", fraction * 100.0),
+            DialTarget::Variable { min, max, .. } => format!("{}", (min + (max - min) * fraction).round()),
+        }
+    }
+}
+
+/// A dial being turned, from when its segment is hovered until the cursor returns to the center or the menu closes.
+#[derive(Clone)]
+pub struct DialState {
+    pub segment: i32,
+    dial: Dial,
+    value: f32, // 0.0..1.0, kept unrounded so slow turns add up
+    written: f32, // Last value given to the target
+    angle: Option<f32>, // Cursor angle at the last update, None until the first one
+}
+
+impl DialState {
+    /// Engages a dial starting from the target's current value; None when it can't be read.
+    pub fn engage(segment: i32, dial: &Dial) -> Option<Self> {
+        let value = dial.target.read()?;
+        Some(Self { segment, dial: dial.clone(), value, written: value, angle: None })
+    }
+
+    /// Turns the dial by how far the cursor moved around the center since the last call, writing the value once
+    /// it changed by a step. `angle` is in radians clockwise on screen from +x, as hit testing measures it with y
+    /// down, so circling clockwise turns the value up.
+    pub fn turn(&mut self, angle: f32) {
+        let previous = self.angle.replace(angle);
+        let previous = match previous {
+            Some(previous) => previous,
+            None => return,
+        };
+        // Shortest way around, so crossing the +x axis isn't a full turn
+        let delta = (angle - previous + PI).rem_euclid(2.0 * PI) - PI;
+        let sweep = self.dial.sweep.unwrap_or(DEFAULT_SWEEP).to_radians();
+        self.value = (self.value + delta / sweep).clamp(0.0, 1.0);
+
+        let at_end = (self.value == 0.0 || self.value == 1.0) && self.value != self.written;
+        if (self.value - self.written).abs() >= WRITE_STEP || at_end {
+            self.dial.target.write(self.value);
+            self.written = self.value;
+        }
+    }
+}
+
+/// Lays out the dial's label, value and a bar of it in the middle of the ring, which is centered in the window.
+pub fn layout_dial(batch: &mut TextBatch, overlay_content: &OverlayContent) {
+    let state = match &overlay_content.dial {
+        Some(state) => state,
+        None => return,
+    };
+    let label = overlay_content.item_text(state.segment).map_or("", |text| text.label.as_str());
+    let value = state.dial.target.format(state.value);
+
+    let line_height = batch.metrics().cell_height;
+    let (width, height) = batch.size();
+    let (center_x, center_y) = (width / 2.0, height / 2.0);
+    let mut y = center_y - line_height - BAR_HEIGHT / 2.0;
+    batch.text(center_x - batch.measure(label).0 / 2.0, y, label, LABEL_COLOR);
+    y += line_height;
+    batch.text(center_x - batch.measure(&value).0 / 2.0, y, &value, VALUE_COLOR);
+    y += line_height;
+    let x = center_x - BAR_WIDTH / 2.0;
+    batch.rect(x, y, BAR_WIDTH, BAR_HEIGHT, TRACK_COLOR);
+    batch.rect(x, y, BAR_WIDTH * state.value, BAR_HEIGHT, FILL_COLOR);
+}
+
+/// Runs `f` on the volume control of the default playback (`eRender`) or recording (`eCapture`) device, None when
+/// there is no such device.
+pub(crate) fn with_endpoint_volume<T>(flow: EDataFlow, f: impl FnOnce(&IAudioEndpointVolume) -> T) -> Option<T> {
+    unsafe {
+        // Already initialized on this thread is fine too; either way the call is balanced below
+        let initialized = SUCCEEDED(CoInitializeEx(null_mut(), COINIT_APARTMENTTHREADED));
+        let mut result = None;
+
+        let mut enumerator: *mut IMMDeviceEnumerator = null_mut();
+        if SUCCEEDED(CoCreateInstance(
+            &MMDeviceEnumerator::uuidof(),
+            null_mut(),
+            CLSCTX_ALL,
+            &IMMDeviceEnumerator::uuidof(),
+            &mut enumerator as *mut _ as *mut *mut c_void,
+        )) {
+            let mut device: *mut IMMDevice = null_mut();
+            if SUCCEEDED((*enumerator).GetDefaultAudioEndpoint(flow, eConsole, &mut device)) {
+                let mut volume: *mut IAudioEndpointVolume = null_mut();
+                if SUCCEEDED((*device).Activate(
+                    &IAudioEndpointVolume::uuidof(),
+                    CLSCTX_ALL,
+                    null_mut(),
+                    &mut volume as *mut _ as *mut *mut c_void,
+                )) {
+                    result = Some(f(&*volume));
+                    (*volume).Release();
+                }
+                (*device).Release();
+            }
+            (*enumerator).Release();
+        }
+
+        if initialized {
+            CoUninitialize();
+        }
+        result
+    }
+}
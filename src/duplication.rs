@@ -0,0 +1,350 @@
+// Snapshots of the screen under the menu, taken with DXGI Output Duplication as it opens and drawn blurred behind
+// the ring for frosted glass. Unlike BitBlt this reads the composed desktop, so games and video show up too.
+// The device and duplication are kept between opens, as creating them takes longer than the open animation.
+
+use winapi::shared::dxgi::{CreateDXGIFactory1, IDXGIAdapter, IDXGIAdapter1, IDXGIFactory1, IDXGIOutput, IDXGIResource, DXGI_OUTPUT_DESC};
+use winapi::shared::dxgi1_2::{IDXGIOutput1, IDXGIOutputDuplication, DXGI_OUTDUPL_FRAME_INFO};
+use winapi::shared::dxgiformat::DXGI_FORMAT_B8G8R8A8_UNORM;
+use winapi::shared::dxgitype::DXGI_SAMPLE_DESC;
+use winapi::shared::windef::{HMONITOR, HWND, RECT};
+use winapi::shared::winerror::{DXGI_ERROR_WAIT_TIMEOUT, SUCCEEDED};
+use winapi::um::d3d11::{
+    D3D11CreateDevice, ID3D11Device, ID3D11DeviceContext, ID3D11Resource, ID3D11Texture2D, D3D11_BOX,
+    D3D11_CPU_ACCESS_READ, D3D11_MAPPED_SUBRESOURCE, D3D11_MAP_READ, D3D11_SDK_VERSION, D3D11_TEXTURE2D_DESC,
+    D3D11_USAGE_DEFAULT, D3D11_USAGE_STAGING,
+};
+use winapi::um::d3dcommon::D3D_DRIVER_TYPE_UNKNOWN;
+use winapi::um::unknwnbase::IUnknown;
+use winapi::um::winuser::{GetWindowRect, MonitorFromWindow, MONITOR_DEFAULTTONEAREST};
+use winapi::Interface;
+use std::cell::RefCell;
+use std::ffi::c_void;
+use std::mem::zeroed;
+use std::ptr::{null, null_mut};
+use std::sync::{Arc, Mutex};
+use crate::diagnostics;
+
+/// How long to wait for a frame from a new duplication; its first frame is the desktop as it is. A kept one
+/// doesn't wait, as no new frame means the desktop is unchanged since the last.
+const FRAME_TIMEOUT_MS: u32 = 100;
+/// Every how many pixels in each direction the luminance is averaged over.
+const LUMINANCE_STRIDE: usize = 8;
+const OPAQUE_BLACK: u32 = 0xFF00_0000;
+
+/// The screen under a window as BGRA rows from the top, the size of the window. Parts of the window off its
+/// monitor are black.
+pub struct Snapshot {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u32>,
+}
+
+/// The latest snapshot with a count that goes up with each one, so the renderer uploads every snapshot once.
+static LATEST: Mutex<(u64, Option<Arc<Snapshot>>)> = Mutex::new((0, None));
+
+thread_local! {
+    /// Duplication of the monitor the menu last opened on. COM objects, so kept on the thread that made them, the
+    /// one opening the menu.
+    static DUPLICATION: RefCell<Option<Duplication>> = RefCell::new(None);
+}
+
+/// A monitor's output duplicated on a D3D11 device of its adapter, with a copy of its latest frame.
+struct Duplication {
+    monitor: HMONITOR,
+    desktop: RECT, // The output's place on the desktop
+    device: *mut ID3D11Device,
+    context: *mut ID3D11DeviceContext,
+    duplication: *mut IDXGIOutputDuplication,
+    frame: *mut ID3D11Texture2D, // Copy of the latest frame, null until the first one arrives
+}
+
+/// Takes a snapshot of the screen under the window for the renderer and returns its average luminance, 0..1.
+/// Without one, e.g. with HDR on or over a secure desktop, the ring is drawn opaque.
+pub fn refresh(hwnd: HWND) -> Option<f32> {
+    let snapshot = match capture(hwnd) {
+        Ok(snapshot) => Some(Arc::new(snapshot)),
+        Err(e) => {
+            diagnostics::record_event(format!("No backdrop snapshot: {}", e));
+            None
+        }
+    };
+    let luminance = snapshot.as_deref().map(Snapshot::luminance);
+    let mut latest = LATEST.lock().unwrap();
+    *latest = (latest.0 + 1, snapshot);
+    luminance
+}
+
+/// Drops the snapshot and the duplication, e.g. once frosted glass is turned off.
+pub fn discard() {
+    DUPLICATION.with(|duplication| duplication.borrow_mut().take());
+    let mut latest = LATEST.lock().unwrap();
+    if latest.1.is_some() {
+        *latest = (latest.0 + 1, None);
+    }
+}
+
+/// The latest snapshot and its count.
+pub fn latest() -> (u64, Option<Arc<Snapshot>>) {
+    LATEST.lock().unwrap().clone()
+}
+
+impl Snapshot {
+    /// Average luminance of a sample of the pixels.
+    fn luminance(&self) -> f32 {
+        let width = self.width as usize;
+        let mut total = 0.0;
+        let mut count = 0;
+        for row in (0..self.height as usize).step_by(LUMINANCE_STRIDE) {
+            for &pixel in self.pixels[row * width..(row + 1) * width].iter().step_by(LUMINANCE_STRIDE) {
+                let channel = |shift: u32| ((pixel >> shift) & 0xFF) as f32 / 255.0;
+                total += 0.2126 * channel(16) + 0.7152 * channel(8) + 0.0722 * channel(0);
+                count += 1;
+            }
+        }
+        total / count.max(1) as f32
+    }
+}
+
+/// Copies the screen under the window from the output of the monitor it is on, duplicating it if the last
+/// duplication was of another monitor or has been lost, e.g. to a display mode change or the secure desktop.
+pub fn capture(hwnd: HWND) -> Result<Snapshot, String> {
+    unsafe {
+        let mut window: RECT = zeroed();
+        if GetWindowRect(hwnd, &mut window) == 0 {
+            return Err("Failed to get the window's position".to_string());
+        }
+        let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+        DUPLICATION.with(|kept| {
+            let mut kept = kept.borrow_mut();
+            if kept.as_ref().map_or(false, |duplication| duplication.monitor != monitor) {
+                *kept = None;
+            }
+            if let Some(duplication) = kept.as_mut() {
+                match duplication.latest_frame(0) {
+                    Ok(()) => return duplication.read_window(&window),
+                    Err(e) => diagnostics::record_event(format!("Duplicating the output again: {}", e)),
+                }
+            }
+            *kept = None;
+            let duplication = kept.insert(Duplication::new(monitor)?);
+            duplication.latest_frame(FRAME_TIMEOUT_MS)?;
+            duplication.read_window(&window)
+        })
+    }
+}
+
+/// The adapter and output showing the monitor, with the output's place on the desktop. Both are released by
+/// the caller.
+unsafe fn find_output(monitor: HMONITOR) -> Result<(*mut IDXGIAdapter1, *mut IDXGIOutput, RECT), String> {
+    let mut factory: *mut IDXGIFactory1 = null_mut();
+    if !SUCCEEDED(CreateDXGIFactory1(&IDXGIFactory1::uuidof(), &mut factory as *mut _ as *mut *mut c_void)) {
+        return Err("Failed to create a DXGI factory".to_string());
+    }
+
+    let mut found = None;
+    let mut adapter_index = 0;
+    let mut adapter: *mut IDXGIAdapter1 = null_mut();
+    while found.is_none() && SUCCEEDED((*factory).EnumAdapters1(adapter_index, &mut adapter)) {
+        let mut output_index = 0;
+        let mut output: *mut IDXGIOutput = null_mut();
+        while found.is_none() && SUCCEEDED((*adapter).EnumOutputs(output_index, &mut output)) {
+            let mut output_desc: DXGI_OUTPUT_DESC = zeroed();
+            if SUCCEEDED((*output).GetDesc(&mut output_desc)) && output_desc.Monitor == monitor {
+                // Kept for the caller, so not released here
+                (*adapter).AddRef();
+                found = Some((adapter, output, output_desc.DesktopCoordinates));
+            } else {
+                (*output).Release();
+            }
+            output_index += 1;
+        }
+        (*adapter).Release();
+        adapter_index += 1;
+    }
+
+    (*factory).Release();
+    found.ok_or_else(|| "No DXGI output shows the window's monitor".to_string())
+}
+
+impl Duplication {
+    /// Duplicates the output showing the monitor on a D3D11 device of its adapter.
+    unsafe fn new(monitor: HMONITOR) -> Result<Self, String> {
+        let (adapter, output, desktop) = find_output(monitor)?;
+        let mut output1: *mut IDXGIOutput1 = null_mut();
+        let queried = (*output).QueryInterface(&IDXGIOutput1::uuidof(), &mut output1 as *mut _ as *mut *mut c_void);
+        (*output).Release();
+        if !SUCCEEDED(queried) {
+            (*adapter).Release();
+            return Err("Output duplication needs Windows 8 or later".to_string());
+        }
+
+        let mut device: *mut ID3D11Device = null_mut();
+        let mut context: *mut ID3D11DeviceContext = null_mut();
+        let hr = D3D11CreateDevice(
+            adapter as *mut IDXGIAdapter,
+            D3D_DRIVER_TYPE_UNKNOWN,
+            null_mut(),
+            0,
+            null(),
+            0,
+            D3D11_SDK_VERSION,
+            &mut device,
+            null_mut(),
+            &mut context,
+        );
+        (*adapter).Release();
+        if !SUCCEEDED(hr) {
+            (*output1).Release();
+            return Err(format!("Failed to create a D3D11 device (0x{:08X})", hr));
+        }
+
+        let mut duplication: *mut IDXGIOutputDuplication = null_mut();
+        let hr = (*output1).DuplicateOutput(device as *mut IUnknown, &mut duplication);
+        (*output1).Release();
+        if !SUCCEEDED(hr) {
+            (*context).Release();
+            (*device).Release();
+            // Also the error over the lock screen and UAC prompts, and with too many duplications open
+            return Err(format!("Failed to duplicate the output (0x{:08X})", hr));
+        }
+        Ok(Self { monitor, desktop, device, context, duplication, frame: null_mut() })
+    }
+
+    /// Brings the copy of the frame up to date, waiting up to `timeout_ms` for a new one if there is no copy yet.
+    unsafe fn latest_frame(&mut self, timeout_ms: u32) -> Result<(), String> {
+        let mut frame_info: DXGI_OUTDUPL_FRAME_INFO = zeroed();
+        let mut resource: *mut IDXGIResource = null_mut();
+        let hr = (*self.duplication).AcquireNextFrame(timeout_ms, &mut frame_info, &mut resource);
+        if hr == DXGI_ERROR_WAIT_TIMEOUT && !self.frame.is_null() {
+            return Ok(());
+        }
+        if !SUCCEEDED(hr) {
+            return Err(format!("No desktop frame to copy (0x{:08X})", hr));
+        }
+
+        let mut frame: *mut ID3D11Texture2D = null_mut();
+        let queried = (*resource).QueryInterface(&ID3D11Texture2D::uuidof(), &mut frame as *mut _ as *mut *mut c_void);
+        let result = if SUCCEEDED(queried) {
+            let result = self.keep_frame(frame);
+            (*frame).Release();
+            result
+        } else {
+            Err("The desktop frame isn't a D3D11 texture".to_string())
+        };
+        (*resource).Release();
+        // Released right away, so the desktop goes on updating into the next frame
+        (*self.duplication).ReleaseFrame();
+        result
+    }
+
+    /// Copies an acquired frame into `frame`, made to its size the first time.
+    unsafe fn keep_frame(&mut self, acquired: *mut ID3D11Texture2D) -> Result<(), String> {
+        if self.frame.is_null() {
+            let mut desc: D3D11_TEXTURE2D_DESC = zeroed();
+            (*acquired).GetDesc(&mut desc);
+            desc.Usage = D3D11_USAGE_DEFAULT;
+            desc.BindFlags = 0;
+            desc.CPUAccessFlags = 0;
+            desc.MiscFlags = 0;
+            if !SUCCEEDED((*self.device).CreateTexture2D(&desc, null(), &mut self.frame)) {
+                self.frame = null_mut();
+                return Err("Failed to create a texture for the desktop frame".to_string());
+            }
+        }
+        (*self.context).CopyResource(self.frame as *mut ID3D11Resource, acquired as *mut ID3D11Resource);
+        Ok(())
+    }
+
+    unsafe fn read_window(&self, window: &RECT) -> Result<Snapshot, String> {
+        read_window(self.device, self.context, self.frame, window, &self.desktop)
+    }
+}
+
+impl Drop for Duplication {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.frame.is_null() {
+                (*self.frame).Release();
+            }
+            (*self.duplication).Release();
+            (*self.context).Release();
+            (*self.device).Release();
+        }
+    }
+}
+
+/// Copies the part of the frame under the window through a staging texture the CPU can read.
+unsafe fn read_window(
+    device: *mut ID3D11Device,
+    context: *mut ID3D11DeviceContext,
+    frame: *mut ID3D11Texture2D,
+    window: &RECT,
+    desktop: &RECT,
+) -> Result<Snapshot, String> {
+    let mut frame_desc: D3D11_TEXTURE2D_DESC = zeroed();
+    (*frame).GetDesc(&mut frame_desc);
+    if frame_desc.Format != DXGI_FORMAT_B8G8R8A8_UNORM {
+        return Err("the desktop isn't 8-bit BGRA, as with HDR on".to_string());
+    }
+
+    // The part of the window on the output, in the output's pixels
+    let left = (window.left - desktop.left).clamp(0, frame_desc.Width as i32);
+    let top = (window.top - desktop.top).clamp(0, frame_desc.Height as i32);
+    let right = (window.right - desktop.left).clamp(0, frame_desc.Width as i32);
+    let bottom = (window.bottom - desktop.top).clamp(0, frame_desc.Height as i32);
+    if right <= left || bottom <= top {
+        return Err("the window is off its monitor".to_string());
+    }
+    let (copy_width, copy_height) = ((right - left) as usize, (bottom - top) as usize);
+
+    let staging_desc = D3D11_TEXTURE2D_DESC {
+        Width: copy_width as u32,
+        Height: copy_height as u32,
+        MipLevels: 1,
+        ArraySize: 1,
+        Format: DXGI_FORMAT_B8G8R8A8_UNORM,
+        SampleDesc: DXGI_SAMPLE_DESC { Count: 1, Quality: 0 },
+        Usage: D3D11_USAGE_STAGING,
+        BindFlags: 0,
+        CPUAccessFlags: D3D11_CPU_ACCESS_READ,
+        MiscFlags: 0,
+    };
+    let mut staging: *mut ID3D11Texture2D = null_mut();
+    if !SUCCEEDED((*device).CreateTexture2D(&staging_desc, null(), &mut staging)) {
+        return Err("Failed to create a staging texture".to_string());
+    }
+    let region = D3D11_BOX {
+        left: left as u32,
+        top: top as u32,
+        front: 0,
+        right: right as u32,
+        bottom: bottom as u32,
+        back: 1,
+    };
+    (*context).CopySubresourceRegion(staging as *mut ID3D11Resource, 0, 0, 0, 0, frame as *mut ID3D11Resource, 0, &region);
+
+    let width = (window.right - window.left).max(1) as usize;
+    let height = (window.bottom - window.top).max(1) as usize;
+    // Where the copied part starts in the window
+    let x = (left + desktop.left - window.left) as usize;
+    let y = (top + desktop.top - window.top) as usize;
+    let mut mapped: D3D11_MAPPED_SUBRESOURCE = zeroed();
+    let result = if SUCCEEDED((*context).Map(staging as *mut ID3D11Resource, 0, D3D11_MAP_READ, 0, &mut mapped)) {
+        let mut pixels = vec![OPAQUE_BLACK; width * height];
+        for row in 0..copy_height.min(height - y) {
+            let source = (mapped.pData as *const u8).add(row * mapped.RowPitch as usize) as *const u32;
+            let start = (y + row) * width + x;
+            source.copy_to_nonoverlapping(pixels[start..].as_mut_ptr(), copy_width.min(width - x));
+        }
+        (*context).Unmap(staging as *mut ID3D11Resource, 0);
+        // The desktop's alpha isn't meant to be shown
+        for pixel in pixels.iter_mut() {
+            *pixel |= OPAQUE_BLACK;
+        }
+        Ok(Snapshot { width: width as u32, height: height as u32, pixels })
+    } else {
+        Err("Failed to read the copied desktop".to_string())
+    };
+    (*staging).Release();
+    result
+}
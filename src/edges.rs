@@ -0,0 +1,104 @@
+// Directional quick zones past the outer ring that snap the focused window, like Windows 11 snap layouts
+
+use crate::action::Action;
+use crate::config::EdgesConfig;
+use crate::overlay::OverlayContent;
+use crate::rings::UNIT_PIXELS;
+use crate::text::TextBatch;
+
+/// Stands in for a segment index when an edge action runs; it has no segment to show progress on.
+pub const EDGE_SEGMENT: i32 = -1;
+
+const PADDING: f32 = 4.0;
+const EDGE_BACKGROUND: [f32; 4] = [0.12, 0.12, 0.12, 1.0];
+const EDGE_SELECTED: [f32; 4] = [0.35, 0.75, 1.0, 1.0];
+const EDGE_TEXT: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl Edge {
+    pub const ALL: [Edge; 4] = [Edge::Left, Edge::Right, Edge::Top, Edge::Bottom];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Edge::Left => "Snap left",
+            Edge::Right => "Snap right",
+            Edge::Top => "Maximize",
+            Edge::Bottom => "Restore / minimize",
+        }
+    }
+
+    /// The configured action for this edge, or the matching Windows snap shortcut.
+    pub fn action(self, config: &EdgesConfig) -> Action {
+        let configured = match self {
+            Edge::Left => &config.left,
+            Edge::Right => &config.right,
+            Edge::Top => &config.top,
+            Edge::Bottom => &config.bottom,
+        };
+        configured.clone().unwrap_or_else(|| Action::SendKeys {
+            keys: match self {
+                Edge::Left => "Win+Left",
+                Edge::Right => "Win+Right",
+                Edge::Top => "Win+Up",
+                Edge::Bottom => "Win+Down",
+            }
+            .to_string(),
+        })
+    }
+
+    /// Unit direction from the ring's center; y points down like window pixels.
+    fn direction(self) -> (f32, f32) {
+        match self {
+            Edge::Left => (-1.0, 0.0),
+            Edge::Right => (1.0, 0.0),
+            Edge::Top => (0.0, -1.0),
+            Edge::Bottom => (0.0, 1.0),
+        }
+    }
+}
+
+/// The zone under a position relative to the ring's center (NDC, y down): past `distance`
+/// and within 45 degrees of a cardinal direction, which splits the outside into four quadrants.
+pub fn edge_at(x: f32, y: f32, distance: f32) -> Option<Edge> {
+    if (x * x + y * y).sqrt() < distance {
+        return None;
+    }
+    Some(if x.abs() >= y.abs() {
+        if x < 0.0 { Edge::Left } else { Edge::Right }
+    } else if y < 0.0 {
+        Edge::Top
+    } else {
+        Edge::Bottom
+    })
+}
+
+/// Labels each zone where it starts, highlighting the one under the cursor.
+pub fn layout_edges(batch: &mut TextBatch, overlay_content: &OverlayContent) {
+    let distance = match &overlay_content.edges {
+        Some(edges) => edges.distance,
+        None => return,
+    };
+
+    let (width, height) = batch.size();
+    let line_height = batch.metrics().cell_height;
+    for edge in Edge::ALL {
+        let (dx, dy) = edge.direction();
+        let center_x = width / 2.0 + dx * distance * UNIT_PIXELS;
+        let center_y = height / 2.0 + dy * distance * UNIT_PIXELS;
+
+        let label = edge.label();
+        let box_width = batch.measure(label).0 + PADDING * 2.0;
+        let box_height = line_height + PADDING * 2.0;
+        let top = center_y - box_height / 2.0;
+        let background = if overlay_content.selected_edge == Some(edge) { EDGE_SELECTED } else { EDGE_BACKGROUND };
+        batch.rect(center_x - box_width / 2.0, top, box_width, box_height, background);
+        batch.text_centered(center_x, top + PADDING, label, EDGE_TEXT);
+    }
+}
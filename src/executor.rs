@@ -0,0 +1,137 @@
+// Dedicated executor for menu item actions: the UI thread only queues them, and outcomes come back as events
+
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use crate::action::{run_action, Action, ActionContext, ActionEvent};
+use crate::config::{Config, MqttConfig};
+use crate::{diagnostics, mqtt, toast, wake};
+
+/// Repeated actions aren't tied to a segment that could show their progress.
+const REPEAT_SEGMENT: i32 = -1;
+/// How long shutting down waits for running actions; hung ones are left behind.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+
+#[derive(Clone)]
+struct Job {
+    segment: i32,
+    label: String,
+    action: Action,
+}
+
+/// Queues actions for the executor thread, which runs each on its own worker so a hung
+/// one never holds up the others. Progress and outcomes are sent to the events channel.
+/// The executor also holds what actions share, like the MQTT connection.
+pub struct Executor {
+    jobs: Sender<Job>,
+    context: Arc<Mutex<ActionContext>>,
+    mqtt_config: Option<MqttConfig>, // What the current connection was made with
+    last: Mutex<Option<Job>>, // Most recently submitted action, for the repeat hotkey
+    dispatcher: JoinHandle<()>,
+}
+
+impl Executor {
+    pub fn new(events: Sender<ActionEvent>, config: &Config) -> Self {
+        let (jobs, queue) = channel::<Job>();
+        let context = Arc::new(Mutex::new(ActionContext::default()));
+        let dispatcher_context = context.clone();
+        let dispatcher = thread::Builder::new()
+            .name("action-executor".to_string())
+            .spawn(move || {
+                let mut workers: Vec<JoinHandle<()>> = Vec::new();
+                for job in queue {
+                    workers.retain(|worker| !worker.is_finished());
+                    let context = dispatcher_context.lock().unwrap().clone();
+                    let events = events.clone();
+                    workers.push(thread::spawn(move || run_job(job, &context, events)));
+                }
+                // The queue closes on shutdown; running actions get a moment to finish
+                let deadline = Instant::now() + SHUTDOWN_GRACE;
+                while workers.iter().any(|worker| !worker.is_finished()) && Instant::now() < deadline {
+                    thread::sleep(Duration::from_millis(20));
+                }
+                for worker in workers.into_iter().filter(|worker| worker.is_finished()) {
+                    let _ = worker.join();
+                }
+            })
+            .expect("Failed to start the action executor");
+
+        let mut executor = Self {
+            jobs,
+            context,
+            mqtt_config: None,
+            last: Mutex::new(None),
+            dispatcher,
+        };
+        executor.configure(config);
+        executor
+    }
+
+    /// Stops taking actions and waits briefly for running ones, then for the executor thread to end.
+    pub fn shutdown(self) {
+        let Self { jobs, dispatcher, .. } = self;
+        drop(jobs);
+        let _ = dispatcher.join();
+    }
+
+    /// Applies the action settings to actions submitted from now on, e.g. after a config reload.
+    /// The MQTT connection is only remade when its settings changed.
+    pub fn configure(&mut self, config: &Config) {
+        let mut context = self.context.lock().unwrap();
+        context.timeout = config.actions.timeout();
+        if self.mqtt_config.as_ref() != Some(&config.mqtt) {
+            context.mqtt = mqtt::connect(&config.mqtt);
+            self.mqtt_config = Some(config.mqtt.clone());
+        }
+    }
+
+    /// Queues the action of `segment`; `label` names it in toasts and errors.
+    pub fn submit(&self, segment: i32, label: String, action: Action) {
+        let job = Job { segment, label, action };
+        *self.last.lock().unwrap() = Some(job.clone());
+        let _ = self.jobs.send(job);
+    }
+
+    /// Queues the action of an item that asks for confirmation, e.g. a shutdown. It isn't kept for `repeat_last`,
+    /// which would run it again without asking.
+    pub fn submit_confirmed(&self, segment: i32, label: String, action: Action) {
+        let _ = self.jobs.send(Job { segment, label, action });
+    }
+
+    /// Runs the most recently submitted action again. Returns false if nothing has run yet.
+    pub fn repeat_last(&self) -> bool {
+        let last = self.last.lock().unwrap().clone();
+        match last {
+            Some(job) => {
+                diagnostics::record_event(format!("Repeating \"{}\"", job.label));
+                let _ = self.jobs.send(Job { segment: REPEAT_SEGMENT, ..job });
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn run_job(job: Job, context: &ActionContext, events: Sender<ActionEvent>) {
+    let segment = job.segment;
+    let report = |fraction: Option<f32>| {
+        let _ = events.send(ActionEvent::Progress { segment, fraction });
+        wake::wake();
+    };
+    let outcome = run_action(&job.action, &job.label, &report, context);
+    let _ = events.send(ActionEvent::Finished { segment, label: job.label, outcome });
+    wake::wake();
+}
+
+/// Toasts a finished action's message or reports its failure.
+pub fn report_outcome(segment: i32, label: &str, outcome: &Result<Option<String>, String>) {
+    match outcome {
+        Ok(Some(message)) => toast::push(message.as_str()),
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("Action for segment {} failed: {}", segment, e);
+            diagnostics::report_error(format!("{} failed: {}", label, e));
+        }
+    }
+}
@@ -0,0 +1,141 @@
+// Plain Win32 popup menu used when the overlay window or renderer can't be created
+
+use winapi::um::winuser::*;
+use winapi::shared::windef::{HWND, POINT};
+use winapi::shared::minwindef::UINT;
+use std::ptr::null_mut;
+use std::mem::zeroed;
+use std::sync::mpsc;
+use std::thread;
+use crate::action::ActionEvent;
+use crate::config::Config;
+use crate::executor::{self, Executor};
+use crate::hotkey::{WM_HOTKEY_ID, DIAGNOSTICS_HOTKEY_ID, REPEAT_HOTKEY_ID};
+use crate::{diagnostics, focus, panel, recent};
+use crate::menu::MenuItem;
+use crate::window::to_wstring;
+
+/// Runs a message loop that shows the menu items as a popup list whenever the hotkey fires.
+/// Keeps the user's actions reachable while the GPU path is broken. Returns on WM_QUIT.
+pub fn run_fallback_menu(config: &Config) {
+    let items = config.active_items();
+    panel::use_message_boxes();
+
+    // A hidden owner window is required for TrackPopupMenu; the STATIC class needs no registration
+    let class_name = to_wstring("STATIC");
+    let title = to_wstring("Radial Menu Fallback");
+    let owner: HWND = unsafe {
+        CreateWindowExW(
+            WS_EX_TOOLWINDOW,
+            class_name.as_ptr(),
+            title.as_ptr(),
+            WS_POPUP,
+            0,
+            0,
+            0,
+            0,
+            null_mut(),
+            null_mut(),
+            null_mut(),
+            null_mut(),
+        )
+    };
+    if owner.is_null() {
+        eprintln!("Failed to create fallback menu window");
+        return;
+    }
+
+    // There are no segments to show progress on; outcomes are reported as they arrive,
+    // since the message loop below blocks until the next message
+    let (action_sender, action_receiver) = mpsc::channel();
+    let executor = Executor::new(action_sender, config);
+    thread::spawn(move || {
+        for event in action_receiver {
+            if let ActionEvent::Finished { segment, label, outcome } = event {
+                executor::report_outcome(segment, &label, &outcome);
+            }
+        }
+    });
+
+    let mut msg: MSG = unsafe { zeroed() };
+    unsafe {
+        while GetMessageW(&mut msg, null_mut(), 0, 0) > 0 {
+            if msg.message == WM_HOTKEY && msg.wParam as i32 == WM_HOTKEY_ID {
+                if let Some(index) = show_popup(owner, items) {
+                    recent::record(&items[index]);
+                    if let Some(action) = items[index].action.clone() {
+                        println!("Executing action for segment {}", index);
+                        let label = items[index].display_label();
+                        if items[index].confirm {
+                            executor.submit_confirmed(index as i32, label, action);
+                        } else {
+                            executor.submit(index as i32, label, action);
+                        }
+                    }
+                }
+                continue;
+            }
+            if msg.message == WM_HOTKEY && msg.wParam as i32 == REPEAT_HOTKEY_ID {
+                executor.repeat_last();
+                continue;
+            }
+            if msg.message == WM_HOTKEY && msg.wParam as i32 == DIAGNOSTICS_HOTKEY_ID {
+                diagnostics::save_bundle_and_notify();
+                continue;
+            }
+            TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+
+        DestroyWindow(owner);
+    }
+}
+
+/// Shows the popup at the cursor and returns the index of the chosen item.
+fn show_popup(owner: HWND, items: &[MenuItem]) -> Option<usize> {
+    unsafe {
+        let menu = CreatePopupMenu();
+        if menu.is_null() {
+            return None;
+        }
+
+        if items.is_empty() {
+            let text = to_wstring("No menu items configured");
+            AppendMenuW(menu, MF_STRING | MF_GRAYED, 0, text.as_ptr());
+        }
+        for (index, item) in items.iter().enumerate().filter(|(_, item)| item.is_visible()) {
+            // Command ids start at 1 because TrackPopupMenu returns 0 when nothing was chosen
+            let text = to_wstring(&item.display_label());
+            let state = if item.is_enabled() { MF_ENABLED } else { MF_GRAYED };
+            AppendMenuW(menu, MF_STRING | state, index + 1, text.as_ptr());
+        }
+
+        let mut point: POINT = POINT { x: 0, y: 0 };
+        GetCursorPos(&mut point);
+
+        // The owner must be foreground or the menu won't close when clicking elsewhere
+        let focus_before = focus::foreground();
+        SetForegroundWindow(owner);
+        let command = TrackPopupMenu(
+            menu,
+            (TPM_RETURNCMD | TPM_NONOTIFY | TPM_RIGHTBUTTON) as UINT,
+            point.x,
+            point.y,
+            0,
+            owner,
+            null_mut(),
+        );
+        PostMessageW(owner, WM_NULL, 0, 0);
+        DestroyMenu(menu);
+        // Actions like sending keys are meant for the window the menu opened over
+        if let Some(hwnd) = focus_before {
+            focus::restore(hwnd);
+        }
+
+        if command > 0 {
+            Some(command as usize - 1)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,88 @@
+// Audio and gamepad rumble feedback for selection changes and fired actions
+
+use winapi::um::playsoundapi::{PlaySoundW, SND_ALIAS, SND_ASYNC, SND_FILENAME, SND_NODEFAULT};
+use winapi::um::mmeapi::waveOutSetVolume;
+use winapi::um::xinput::{XInputSetState, XINPUT_VIBRATION};
+use winapi::shared::winerror::ERROR_SUCCESS;
+use std::ptr::null_mut;
+use std::thread;
+use std::time::Duration;
+use crate::config::FeedbackConfig;
+use crate::gamepad;
+use crate::window::to_wstring;
+
+/// System sound played when no sound file is configured.
+const DEFAULT_SOUND_ALIAS: &str = "MenuCommand";
+
+pub struct Feedback {
+    config: FeedbackConfig,
+}
+
+impl Feedback {
+    pub fn new(config: &FeedbackConfig) -> Self {
+        if config.sound {
+            // Scale the app's wave output volume; the low word is the left channel, the high word the right
+            let level = (config.volume.clamp(0.0, 1.0) * 0xFFFF as f32) as u32;
+            unsafe {
+                waveOutSetVolume(null_mut(), level | (level << 16));
+            }
+        }
+        Self { config: config.clone() }
+    }
+
+    /// Called whenever the hovered segment changes.
+    pub fn selection_changed(&self) {
+        self.play_click();
+        self.rumble(0x3000, 40);
+    }
+
+    /// Called when a segment's action is executed.
+    pub fn action_fired(&self) {
+        self.play_click();
+        self.rumble(0x8000, 80);
+    }
+
+    fn play_click(&self) {
+        if !self.config.sound {
+            return;
+        }
+        unsafe {
+            match &self.config.sound_file {
+                Some(path) => {
+                    let path = to_wstring(path);
+                    PlaySoundW(path.as_ptr(), null_mut(), SND_FILENAME | SND_ASYNC | SND_NODEFAULT);
+                }
+                None => {
+                    let alias = to_wstring(DEFAULT_SOUND_ALIAS);
+                    PlaySoundW(alias.as_ptr(), null_mut(), SND_ALIAS | SND_ASYNC | SND_NODEFAULT);
+                }
+            }
+        }
+    }
+
+    /// Briefly vibrates the first connected XInput controller, if the stick has been steering the menu since it
+    /// opened; a controller lying around while the mouse is used stays still.
+    fn rumble(&self, strength: u16, duration_ms: u64) {
+        if !self.config.rumble || gamepad::position().is_none() {
+            return;
+        }
+        let mut vibration = XINPUT_VIBRATION {
+            wLeftMotorSpeed: strength,
+            wRightMotorSpeed: strength,
+        };
+        if unsafe { XInputSetState(0, &mut vibration) } != ERROR_SUCCESS {
+            return;
+        }
+        // Stop the motors again without blocking the message loop
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(duration_ms));
+            let mut stop = XINPUT_VIBRATION {
+                wLeftMotorSpeed: 0,
+                wRightMotorSpeed: 0,
+            };
+            unsafe {
+                XInputSetState(0, &mut stop);
+            }
+        });
+    }
+}
@@ -0,0 +1,393 @@
+// Fills for the ring's segments from the [theme] section: the built-in colors, a linear or radial gradient, or a
+// PNG tiled across the ring, sampled by the ring shader at binding 1. Also the shadow the theme casts around the rings
+
+use ash::{vk, Device, Instance};
+use std::fs::File;
+use std::sync::Mutex;
+use crate::config::{Fill, ThemeConfig};
+use crate::diagnostics;
+use crate::memory::{self, Allocation, Resource};
+use crate::rings::UNIT_PIXELS;
+
+/// Luminance of the screen under the menu above which adaptive contrast puts a backdrop behind the ring.
+const BRIGHT_LUMINANCE: f32 = 0.5;
+/// Opacity of the backdrop over a white screen; it fades in from BRIGHT_LUMINANCE.
+const BACKDROP_OPACITY: f32 = 0.75;
+/// Least distance the backdrop fades out over, in pixels.
+const BACKDROP_SIZE: f32 = 24.0;
+
+/// The ring's fill. Gradients follow config reloads; the texture is loaded when the renderer starts.
+static THEME: Mutex<Option<ThemeConfig>> = Mutex::new(None);
+
+pub fn configure(config: &ThemeConfig) {
+    *THEME.lock().unwrap() = Some(config.clone());
+}
+
+/// Whether the screen under the menu is sampled when it opens, for `uniforms`.
+pub fn adaptive_contrast() -> bool {
+    THEME.lock().unwrap().as_ref().map_or(false, |theme| theme.adaptive_contrast)
+}
+
+/// Whether the screen under the menu is copied when it opens, to draw blurred behind the segments.
+pub fn frosted_glass() -> bool {
+    THEME.lock().unwrap().as_ref().map_or(false, |theme| theme.frosted_glass)
+}
+
+/// Fill uniforms of a frame: the mode and its parameters, the gradient's two colors, and the shadow.
+pub struct FillUniforms {
+    pub fill: [f32; 4], // x = 0 built-in, 1 linear, 2 radial, 3 texture; y = angle; zw = texture repeats per ring unit
+    pub colors: [[f32; 4]; 2],
+    pub shadow: [f32; 4], // x = size, y = opacity, zw = offset, in ring units with y down; zero size for none
+    pub shadow_color: [f32; 4],
+    pub backdrop: [f32; 4], // x = snapshot drawn, y = segment opacity over it, z = blur radius in pixels
+    pub wedge: [f32; 4], // x = opacity of the direction wedge, zero for none
+}
+
+/// Parses a "#rrggbb" color into 0..1 channels with alpha 1.
+pub fn parse_color(text: &str) -> Result<[f32; 4], String> {
+    let hex = text.strip_prefix('#').unwrap_or(text);
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("{} is not a #rrggbb color", text));
+    }
+    let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).unwrap() as f32 / 255.0;
+    Ok([channel(0), channel(2), channel(4), 1.0])
+}
+
+/// The texture a texture fill tiles, or a white pixel for the other fills, sampled in GENERAL layout from
+/// host-visible memory like the icon atlases.
+pub struct FillTexture {
+    image: vk::Image,
+    memory: Allocation,
+    view: vk::ImageView,
+    sampler: vk::Sampler,
+    /// Size of the configured PNG, None when there is none or it failed to load.
+    size: Option<(u32, u32)>,
+}
+
+impl FillTexture {
+    /// Loads the configured texture, falling back to a white pixel with the problem reported.
+    pub fn new(
+        instance: &Instance,
+        device: &Device,
+        physical_device: vk::PhysicalDevice,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+    ) -> Result<Self, String> {
+        let path = THEME
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|theme| theme.fill == Fill::Texture)
+            .map(|theme| theme.texture.clone());
+        let loaded = match path {
+            Some(path) => match read_texture(&path) {
+                Ok(texture) => Some(texture),
+                Err(e) => {
+                    diagnostics::report_error(format!("Theme texture not used: {}", e));
+                    None
+                }
+            },
+            None => None,
+        };
+        let (width, height, pixels) = loaded.clone().unwrap_or((1, 1, vec![0xFFFF_FFFF]));
+
+        let (image, memory) = create_image(instance, device, physical_device, width, height)?;
+        write_pixels(device, image, memory, width, height, &pixels)?;
+        transition_to_general(device, command_pool, queue, image)?;
+        let view = create_view(device, image)?;
+        let sampler = create_sampler(device)?;
+        Ok(Self {
+            image,
+            memory,
+            view,
+            sampler,
+            size: loaded.map(|(width, height, _)| (width, height)),
+        })
+    }
+
+    /// The image info binding 1 of the ring's descriptor sets points at.
+    pub fn descriptor_info(&self) -> vk::DescriptorImageInfo {
+        vk::DescriptorImageInfo {
+            sampler: self.sampler,
+            image_view: self.view,
+            image_layout: vk::ImageLayout::GENERAL,
+        }
+    }
+
+    /// The fill uniforms for the current theme. A texture fill whose texture didn't load draws the built-in
+    /// colors instead of white. With adaptive contrast, a `background` brighter than BRIGHT_LUMINANCE strengthens
+    /// the shadow, or adds a black one, into a backdrop the ring stays readable against. Frosted glass is drawn
+    /// once `backdrop_ready` says the screen's snapshot is uploaded.
+    pub fn uniforms(&self, background: Option<f32>, backdrop_ready: bool) -> FillUniforms {
+        let theme = THEME.lock().unwrap().clone().unwrap_or_default();
+        let colors = [0, 1].map(|index| {
+            theme
+                .colors
+                .get(index)
+                .and_then(|color| parse_color(color).ok())
+                .unwrap_or([1.0, 1.0, 1.0, 1.0])
+        });
+        let mode = match (theme.fill, self.size) {
+            (Fill::Flat, _) | (Fill::Texture, None) => 0.0,
+            (Fill::Linear, _) => 1.0,
+            (Fill::Radial, _) => 2.0,
+            (Fill::Texture, Some(_)) => 3.0,
+        };
+        // A ring unit is UNIT_PIXELS pixels, and the texture covers its own size times the scale
+        let (width, height) = self.size.unwrap_or((1, 1));
+        let scale = theme.texture_scale.max(0.01);
+        let mut shadow = if theme.shadow {
+            [
+                theme.shadow_size.max(1.0) / UNIT_PIXELS,
+                theme.shadow_opacity.clamp(0.0, 1.0),
+                theme.shadow_offset[0] / UNIT_PIXELS,
+                theme.shadow_offset[1] / UNIT_PIXELS,
+            ]
+        } else {
+            [0.0; 4]
+        };
+        let mut shadow_color = parse_color(&theme.shadow_color).unwrap_or([0.0, 0.0, 0.0, 1.0]);
+        if let Some(luminance) = background.filter(|_| theme.adaptive_contrast) {
+            let strength = ((luminance - BRIGHT_LUMINANCE) / (1.0 - BRIGHT_LUMINANCE)).clamp(0.0, 1.0);
+            if strength > 0.0 {
+                if !theme.shadow {
+                    shadow_color = [0.0, 0.0, 0.0, 1.0];
+                }
+                shadow[0] = shadow[0].max(BACKDROP_SIZE / UNIT_PIXELS);
+                shadow[1] = shadow[1].max(BACKDROP_OPACITY * strength);
+            }
+        }
+        FillUniforms {
+            fill: [
+                mode,
+                theme.angle_degrees.to_radians(),
+                UNIT_PIXELS / (width as f32 * scale),
+                UNIT_PIXELS / (height as f32 * scale),
+            ],
+            colors,
+            shadow,
+            shadow_color,
+            backdrop: if theme.frosted_glass && backdrop_ready {
+                [1.0, theme.frosted_opacity.clamp(0.0, 1.0), theme.frosted_blur.max(0.0), 0.0]
+            } else {
+                [0.0; 4]
+            },
+            wedge: [if theme.wedge { theme.wedge_opacity.clamp(0.0, 1.0) } else { 0.0 }, 0.0, 0.0, 0.0],
+        }
+    }
+
+    /// Destroys the texture. The device must be idle.
+    pub fn destroy(&self, device: &Device) {
+        unsafe {
+            device.destroy_sampler(self.sampler, None);
+            device.destroy_image_view(self.view, None);
+            device.destroy_image(self.image, None);
+        }
+        memory::free(device, self.memory);
+    }
+}
+
+/// Reads a PNG as BGRA pixels, top row first. Palette, gray and 16-bit images are converted.
+pub fn read_texture(path: &str) -> Result<(u32, u32, Vec<u32>), String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let mut bytes = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut bytes).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let bgra = |r: u8, g: u8, b: u8, a: u8| u32::from_le_bytes([b, g, r, a]);
+    let bytes = &bytes[..info.buffer_size()];
+    let pixels: Vec<u32> = match info.color_type {
+        png::ColorType::Rgba => bytes.chunks_exact(4).map(|p| bgra(p[0], p[1], p[2], p[3])).collect(),
+        png::ColorType::Rgb => bytes.chunks_exact(3).map(|p| bgra(p[0], p[1], p[2], 255)).collect(),
+        png::ColorType::GrayscaleAlpha => bytes.chunks_exact(2).map(|p| bgra(p[0], p[0], p[0], p[1])).collect(),
+        png::ColorType::Grayscale => bytes.iter().map(|&v| bgra(v, v, v, 255)).collect(),
+        png::ColorType::Indexed => return Err(format!("{} has a palette that couldn't be expanded", path)),
+    };
+    Ok((info.width, info.height, pixels))
+}
+
+/// Creates a linear image in host-visible memory, so the texture is written without a copy.
+pub(crate) fn create_image(
+    instance: &Instance,
+    device: &Device,
+    physical_device: vk::PhysicalDevice,
+    width: u32,
+    height: u32,
+) -> Result<(vk::Image, Allocation), String> {
+    let image_info = vk::ImageCreateInfo::builder()
+        .image_type(vk::ImageType::TYPE_2D)
+        .format(vk::Format::B8G8R8A8_UNORM)
+        .extent(vk::Extent3D { width, height, depth: 1 })
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .tiling(vk::ImageTiling::LINEAR)
+        .usage(vk::ImageUsageFlags::SAMPLED)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+        .initial_layout(vk::ImageLayout::PREINITIALIZED);
+
+    let image = unsafe {
+        device
+            .create_image(&image_info, None)
+            .map_err(|e| format!("Failed to create texture ({}x{}): {:?}", width, height, e))?
+    };
+
+    let mem_requirements = unsafe { device.get_image_memory_requirements(image) };
+    let mem_properties = unsafe { instance.get_physical_device_memory_properties(physical_device) };
+    let memory = memory::allocate(
+        device,
+        mem_properties,
+        mem_requirements,
+        vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        Resource::Linear,
+    )
+    .map_err(|e| format!("Failed to allocate texture memory: {}", e))?;
+
+    unsafe {
+        device
+            .bind_image_memory(image, memory.memory, memory.offset)
+            .map_err(|e| format!("Failed to bind texture memory: {:?}", e))?;
+    }
+
+    Ok((image, memory))
+}
+
+/// Copies the pixels in row by row, following the image's row pitch.
+pub(crate) fn write_pixels(
+    device: &Device,
+    image: vk::Image,
+    memory: Allocation,
+    width: u32,
+    height: u32,
+    pixels: &[u32],
+) -> Result<(), String> {
+    let subresource = vk::ImageSubresource {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        mip_level: 0,
+        array_layer: 0,
+    };
+    let width = width as usize;
+    unsafe {
+        let layout = device.get_image_subresource_layout(image, subresource);
+        let data_ptr = memory::mapped(device, &memory).map_err(|e| format!("Failed to map texture memory: {}", e))?;
+        for row in 0..height as usize {
+            let row_ptr = data_ptr.add((layout.offset + row as u64 * layout.row_pitch) as usize) as *mut u32;
+            row_ptr.copy_from_nonoverlapping(pixels[row * width..].as_ptr(), width);
+        }
+    }
+    Ok(())
+}
+
+/// Moves the texture into GENERAL layout once, where the shader samples it.
+pub(crate) fn transition_to_general(
+    device: &Device,
+    command_pool: vk::CommandPool,
+    queue: vk::Queue,
+    image: vk::Image,
+) -> Result<(), String> {
+    let allocate_info = vk::CommandBufferAllocateInfo::builder()
+        .command_pool(command_pool)
+        .level(vk::CommandBufferLevel::PRIMARY)
+        .command_buffer_count(1);
+
+    let command_buffer = unsafe {
+        device
+            .allocate_command_buffers(&allocate_info)
+            .map_err(|e| format!("Failed to allocate texture command buffer: {:?}", e))?[0]
+    };
+
+    let barrier = vk::ImageMemoryBarrier::builder()
+        .old_layout(vk::ImageLayout::PREINITIALIZED)
+        .new_layout(vk::ImageLayout::GENERAL)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(
+            vk::ImageSubresourceRange::builder()
+                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                .base_mip_level(0)
+                .level_count(1)
+                .base_array_layer(0)
+                .layer_count(1)
+                .build(),
+        )
+        .src_access_mask(vk::AccessFlags::HOST_WRITE)
+        .dst_access_mask(vk::AccessFlags::SHADER_READ)
+        .build();
+
+    let begin_info = vk::CommandBufferBeginInfo::builder()
+        .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+
+    unsafe {
+        device
+            .begin_command_buffer(command_buffer, &begin_info)
+            .map_err(|e| format!("Failed to begin texture command buffer: {:?}", e))?;
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::HOST,
+            vk::PipelineStageFlags::FRAGMENT_SHADER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[barrier],
+        );
+        device
+            .end_command_buffer(command_buffer)
+            .map_err(|e| format!("Failed to end texture command buffer: {:?}", e))?;
+
+        let command_buffers = [command_buffer];
+        let submit_info = vk::SubmitInfo::builder()
+            .command_buffers(&command_buffers)
+            .build();
+        device
+            .queue_submit(queue, &[submit_info], vk::Fence::null())
+            .map_err(|e| format!("Failed to submit texture layout change: {:?}", e))?;
+        device
+            .queue_wait_idle(queue)
+            .map_err(|e| format!("Failed to wait for texture layout change: {:?}", e))?;
+        device.free_command_buffers(command_pool, &command_buffers);
+    }
+
+    Ok(())
+}
+
+pub(crate) fn create_view(device: &Device, image: vk::Image) -> Result<vk::ImageView, String> {
+    let create_info = vk::ImageViewCreateInfo::builder()
+        .image(image)
+        .view_type(vk::ImageViewType::TYPE_2D)
+        .format(vk::Format::B8G8R8A8_UNORM)
+        .subresource_range(
+            vk::ImageSubresourceRange::builder()
+                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                .base_mip_level(0)
+                .level_count(1)
+                .base_array_layer(0)
+                .layer_count(1)
+                .build(),
+        );
+
+    unsafe {
+        device
+            .create_image_view(&create_info, None)
+            .map_err(|e| format!("Failed to create texture view: {:?}", e))
+    }
+}
+
+/// The texture repeats in both directions, filtered since it is drawn at any scale.
+fn create_sampler(device: &Device) -> Result<vk::Sampler, String> {
+    let sampler_info = vk::SamplerCreateInfo::builder()
+        .mag_filter(vk::Filter::LINEAR)
+        .min_filter(vk::Filter::LINEAR)
+        .mipmap_mode(vk::SamplerMipmapMode::NEAREST)
+        .address_mode_u(vk::SamplerAddressMode::REPEAT)
+        .address_mode_v(vk::SamplerAddressMode::REPEAT)
+        .address_mode_w(vk::SamplerAddressMode::REPEAT)
+        .max_lod(0.0);
+
+    unsafe {
+        device
+            .create_sampler(&sampler_info, None)
+            .map_err(|e| format!("Failed to create theme texture sampler: {:?}", e))
+    }
+}
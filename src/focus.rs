@@ -0,0 +1,61 @@
+// Hands keyboard focus back to the window that had it when a menu opened, so sent keystrokes reach it
+
+use winapi::shared::minwindef::{FALSE, TRUE};
+use winapi::shared::windef::HWND;
+use winapi::um::processthreadsapi::GetCurrentThreadId;
+use winapi::um::winuser::{
+    AttachThreadInput, GetForegroundWindow, GetWindowThreadProcessId, IsIconic, IsWindow, SetForegroundWindow, ShowWindow,
+    SW_RESTORE,
+};
+use std::ptr::null_mut;
+use crate::diagnostics;
+
+/// The window with focus right now, None when no window has it.
+pub fn foreground() -> Option<HWND> {
+    let hwnd = unsafe { GetForegroundWindow() };
+    if hwnd.is_null() {
+        None
+    } else {
+        Some(hwnd)
+    }
+}
+
+/// Gives focus back to `hwnd` unless it still has it or has closed since. Windows only lets the
+/// foreground thread move focus, so the input of the current foreground is attached while asking.
+pub fn restore(hwnd: HWND) {
+    unsafe {
+        if IsWindow(hwnd) == 0 {
+            return;
+        }
+        let current = GetForegroundWindow();
+        if current == hwnd {
+            return;
+        }
+
+        let own_thread = GetCurrentThreadId();
+        let foreground_thread = if current.is_null() { 0 } else { GetWindowThreadProcessId(current, null_mut()) };
+        let attached = foreground_thread != 0
+            && foreground_thread != own_thread
+            && AttachThreadInput(own_thread, foreground_thread, TRUE) != 0;
+        if SetForegroundWindow(hwnd) == 0 {
+            diagnostics::record_event("Couldn't give focus back to the window the menu opened over");
+        }
+        if attached {
+            AttachThreadInput(own_thread, foreground_thread, FALSE);
+        }
+    }
+}
+
+/// Brings `hwnd` to the front, restoring it first when minimized, e.g. for the window switcher.
+pub fn activate(hwnd: HWND) -> Result<(), String> {
+    unsafe {
+        if IsWindow(hwnd) == 0 {
+            return Err("The window has been closed".to_string());
+        }
+        if IsIconic(hwnd) != 0 {
+            ShowWindow(hwnd, SW_RESTORE);
+        }
+    }
+    restore(hwnd);
+    Ok(())
+}
@@ -0,0 +1,160 @@
+// Watches the foreground window for what keeps the overlay from showing over it or reacting to input:
+// apps running as administrator and exclusive fullscreen games. Also tells the main loop when another app comes
+// to the front, so profiles can follow it
+
+use winapi::ctypes::c_void;
+use winapi::shared::minwindef::{DWORD, FALSE};
+use winapi::shared::ntdef::LONG;
+use winapi::shared::windef::{HWINEVENTHOOK, HWND};
+use winapi::um::handleapi::CloseHandle;
+use winapi::um::processthreadsapi::{GetCurrentProcess, GetCurrentProcessId, OpenProcess, OpenProcessToken};
+use winapi::um::securitybaseapi::GetTokenInformation;
+use winapi::um::shellapi::{SHQueryUserNotificationState, QUNS_BUSY, QUNS_RUNNING_D3D_FULL_SCREEN};
+use winapi::um::winnt::{TokenElevation, HANDLE, PROCESS_QUERY_LIMITED_INFORMATION, TOKEN_ELEVATION, TOKEN_QUERY};
+use winapi::um::winuser::{
+    GetForegroundWindow, GetWindowThreadProcessId, SetWinEventHook, EVENT_SYSTEM_FOREGROUND, WINEVENT_OUTOFCONTEXT,
+    WINEVENT_SKIPOWNPROCESS,
+};
+use std::mem::size_of;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use winapi::shared::windef::HWND__;
+use crate::{diagnostics, process, toast, wake};
+
+/// The foreground window as of the last check, so each window is only inspected once.
+static LAST_FOREGROUND: AtomicPtr<HWND__> = AtomicPtr::new(null_mut());
+/// Each problem is explained once per run rather than every time such a window comes to the front.
+static WARNED_ELEVATED: AtomicBool = AtomicBool::new(false);
+static WARNED_FULLSCREEN: AtomicBool = AtomicBool::new(false);
+/// Set when another app came to the front and the main loop hasn't taken the change yet. Starts set, so the app in
+/// front at startup counts as a change.
+static CHANGED: AtomicBool = AtomicBool::new(true);
+
+/// Starts telling the main loop when another app comes to the front. The hook's events arrive through the calling
+/// thread's messages, so it must be the main loop's thread.
+pub fn watch() {
+    let hook = unsafe {
+        SetWinEventHook(
+            EVENT_SYSTEM_FOREGROUND,
+            EVENT_SYSTEM_FOREGROUND,
+            null_mut(),
+            Some(foreground_changed),
+            0,
+            0,
+            WINEVENT_OUTOFCONTEXT | WINEVENT_SKIPOWNPROCESS,
+        )
+    };
+    if hook.is_null() {
+        diagnostics::report_error("Failed to watch the foreground window; profiles won't follow the app in front");
+    }
+}
+
+unsafe extern "system" fn foreground_changed(
+    _hook: HWINEVENTHOOK,
+    _event: DWORD,
+    _hwnd: HWND,
+    _object: LONG,
+    _child: LONG,
+    _thread: DWORD,
+    _time: DWORD,
+) {
+    CHANGED.store(true, Ordering::SeqCst);
+    wake::wake();
+}
+
+/// Whether another app came to the front since the last call.
+pub fn take_change() -> bool {
+    CHANGED.swap(false, Ordering::SeqCst)
+}
+
+/// Exe name of the app in front, e.g. "obs64.exe"; None without a foreground window or if its process can't be
+/// queried.
+pub fn process_name() -> Option<String> {
+    let hwnd = unsafe { GetForegroundWindow() };
+    if hwnd.is_null() {
+        return None;
+    }
+    let mut process_id: DWORD = 0;
+    unsafe { GetWindowThreadProcessId(hwnd, &mut process_id) };
+    if process_id == 0 {
+        return None;
+    }
+    let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, FALSE, process_id) };
+    if process.is_null() {
+        return None;
+    }
+    let path = process::image_path(process);
+    unsafe { CloseHandle(process) };
+    path?.file_name().map(|name| name.to_string_lossy().into_owned())
+}
+
+/// Inspects the foreground window when it changes and explains once if the overlay can't work over it.
+/// Called from the main loop so the warning comes even when the hotkey never reaches the overlay.
+pub fn check() {
+    let hwnd = unsafe { GetForegroundWindow() };
+    if hwnd.is_null() || LAST_FOREGROUND.swap(hwnd, Ordering::SeqCst) == hwnd {
+        return;
+    }
+
+    // Windows keeps input to elevated windows from reaching hooks and key state polling of lower ones
+    if is_elevated_window(hwnd) && !is_elevated() && !WARNED_ELEVATED.swap(true, Ordering::SeqCst) {
+        diagnostics::record_event("Foreground app runs as administrator");
+        toast::push("An app running as administrator has focus; run the overlay as administrator to use it there");
+    }
+
+    // Exclusive fullscreen owns the display, so no other window can be drawn over it
+    let mut state = 0;
+    let exclusive = unsafe { SHQueryUserNotificationState(&mut state) } == 0 && state == QUNS_RUNNING_D3D_FULL_SCREEN;
+    if exclusive && !WARNED_FULLSCREEN.swap(true, Ordering::SeqCst) {
+        diagnostics::record_event("Foreground app runs in exclusive fullscreen");
+        toast::push("A game is in exclusive fullscreen; switch it to borderless to see the overlay over it");
+    }
+}
+
+/// Whether the app in front runs fullscreen, borderless or exclusive, as games and presentations do.
+pub fn is_fullscreen() -> bool {
+    let mut state = 0;
+    let queried = unsafe { SHQueryUserNotificationState(&mut state) } == 0;
+    queried && (state == QUNS_BUSY || state == QUNS_RUNNING_D3D_FULL_SCREEN)
+}
+
+/// Whether this process runs as administrator.
+pub fn is_elevated() -> bool {
+    process_elevated(unsafe { GetCurrentProcess() })
+}
+
+fn is_elevated_window(hwnd: HWND) -> bool {
+    let mut process_id: DWORD = 0;
+    unsafe { GetWindowThreadProcessId(hwnd, &mut process_id) };
+    if process_id == 0 || process_id == unsafe { GetCurrentProcessId() } {
+        return false;
+    }
+    let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, FALSE, process_id) };
+    if process.is_null() {
+        // Only elevated processes refuse even limited queries from a normal one
+        return true;
+    }
+    let elevated = process_elevated(process);
+    unsafe { CloseHandle(process) };
+    elevated
+}
+
+fn process_elevated(process: HANDLE) -> bool {
+    unsafe {
+        let mut token: HANDLE = null_mut();
+        if OpenProcessToken(process, TOKEN_QUERY, &mut token) == 0 {
+            return false;
+        }
+        let mut elevation = TOKEN_ELEVATION { TokenIsElevated: 0 };
+        let mut size: DWORD = 0;
+        let queried = GetTokenInformation(
+            token,
+            TokenElevation,
+            &mut elevation as *mut TOKEN_ELEVATION as *mut c_void,
+            size_of::<TOKEN_ELEVATION>() as DWORD,
+            &mut size,
+        );
+        CloseHandle(token);
+        queried != 0 && elevation.TokenIsElevated != 0
+    }
+}
@@ -0,0 +1,55 @@
+// Segment selection with the left stick of the first XInput controller
+
+use winapi::shared::winerror::ERROR_SUCCESS;
+use winapi::um::xinput::{XInputGetState, XINPUT_STATE};
+use std::mem::zeroed;
+use std::sync::Mutex;
+use crate::config::GamepadConfig;
+
+struct State {
+    enabled: bool,
+    dead_zone: f32, // Fraction of full deflection ignored around the center
+    snap: bool, // Keep the last direction when the stick springs back to the center
+    last: Option<(f32, f32)>, // Last stick position past the dead zone since the reset, None while untouched
+}
+
+static STATE: Mutex<State> = Mutex::new(State { enabled: false, dead_zone: 0.25, snap: true, last: None });
+
+pub fn configure(config: &GamepadConfig) {
+    let mut state = STATE.lock().unwrap();
+    state.enabled = config.enabled;
+    // Validation only warns; a dead zone reaching full deflection would leave nothing to rescale the stick over
+    state.dead_zone = config.dead_zone.clamp(0.0, 0.9);
+    state.snap = config.snap;
+}
+
+/// Hands control back to the mouse until the stick is moved again, when the overlay opens.
+pub fn reset() {
+    STATE.lock().unwrap().last = None;
+}
+
+/// Stick position scaled so the dead zone's edge is 0.0 and full deflection 1.0, y down.
+/// None while the stick hasn't left the dead zone since the reset, so the mouse keeps working,
+/// and when no controller is connected.
+pub fn position() -> Option<(f32, f32)> {
+    let mut state = STATE.lock().unwrap();
+    if !state.enabled {
+        return None;
+    }
+    let mut input: XINPUT_STATE = unsafe { zeroed() };
+    if unsafe { XInputGetState(0, &mut input) } != ERROR_SUCCESS {
+        return None;
+    }
+
+    let x = input.Gamepad.sThumbLX as f32 / i16::MAX as f32;
+    let y = -(input.Gamepad.sThumbLY as f32 / i16::MAX as f32); // XInput's y points up
+    let magnitude = (x * x + y * y).sqrt();
+    if magnitude > state.dead_zone {
+        // Rescale so the selection moves smoothly from the dead zone's edge instead of jumping
+        let scale = (magnitude.min(1.0) - state.dead_zone) / (1.0 - state.dead_zone) / magnitude;
+        state.last = Some((x * scale, y * scale));
+    } else if !state.snap && state.last.is_some() {
+        state.last = Some((0.0, 0.0));
+    }
+    state.last
+}
@@ -0,0 +1,195 @@
+// Golden-image checks: the ring is drawn offscreen for a few fixed scenes and compared with reference PNGs, so
+// shader and pipeline changes can be checked locally without a window
+
+use std::fs::{self, File};
+use std::path::Path;
+use crate::config::RingsConfig;
+use crate::menu::MenuItem;
+use crate::offscreen::write_png;
+use crate::overlay::OverlayContent;
+use crate::render::Renderer;
+
+/// Where the reference images are kept, one PNG per scene, with a `.actual.png` next to each that differs.
+const GOLDEN_DIR: &str = "golden";
+const SIZE: u32 = 256;
+/// Largest difference in a channel that still counts as the same, as drivers round blending differently.
+const CHANNEL_TOLERANCE: u8 = 2;
+/// Pixels allowed to differ by more, for antialiased edges that land on the other side of a pixel.
+const MAX_DIFFERENT_PIXELS: usize = 16;
+
+/// Fixed inputs for one image; the time is always zero so animations hold still.
+struct Scene {
+    name: &'static str,
+    segments: usize,
+    selected: Option<i32>,
+    mouse_pos: [f32; 2], // -1..1 across the image, y up
+    dwell_progress: f32,
+    running: Option<(i32, Option<f32>)>, // A segment with an action running, and its progress
+    disabled: Option<i32>,
+    ring_sizes: Option<Vec<usize>>, // Staged rings, all revealed
+    style: i32, // 1 = high contrast, 2 = reduced motion
+}
+
+impl Scene {
+    fn plain(name: &'static str, segments: usize) -> Self {
+        Self {
+            name,
+            segments,
+            selected: None,
+            mouse_pos: [0.0, 0.0],
+            dwell_progress: 0.0,
+            running: None,
+            disabled: None,
+            ring_sizes: None,
+            style: 0,
+        }
+    }
+
+    /// The open menu the scene shows, with unlabeled items; only the ring is drawn.
+    fn overlay_content(&self) -> OverlayContent {
+        let mut overlay_content = OverlayContent::new();
+        overlay_content.visible = true;
+        overlay_content.menu_items = (0..self.segments)
+            .map(|index| MenuItem {
+                label: format!("Item {}", index + 1),
+                description: String::new(),
+                action: None,
+                enabled_if: None,
+                visible_if: None,
+                badge: None,
+                weight: None,
+                uses: None,
+                dial: None,
+                icon: None,
+                confirm: false,
+                toggle: None,
+                pulse_if: None,
+            })
+            .collect();
+        if let Some(sizes) = &self.ring_sizes {
+            overlay_content.rings = Some(RingsConfig { enabled: true, sizes: sizes.clone(), ..RingsConfig::default() });
+            overlay_content.ring_stage = sizes.len();
+        }
+        overlay_content.refresh_items();
+        overlay_content.selected_segment = self.selected;
+        overlay_content.dwell_progress = self.dwell_progress;
+        if let Some((segment, progress)) = self.running {
+            overlay_content.segment_progress.insert(segment, progress);
+        }
+        if let Some(segment) = self.disabled {
+            overlay_content.segment_enabled[segment as usize] = false;
+        }
+        overlay_content
+    }
+}
+
+fn scenes() -> Vec<Scene> {
+    vec![
+        Scene::plain("ring_6", 6),
+        Scene::plain("ring_1", 1),
+        Scene::plain("ring_16", 16),
+        Scene { selected: Some(1), mouse_pos: [0.2, -0.35], ..Scene::plain("ring_6_selected", 6) },
+        Scene { selected: Some(0), dwell_progress: 0.5, ..Scene::plain("ring_6_dwell", 6) },
+        Scene { running: Some((2, Some(0.4))), ..Scene::plain("ring_6_progress", 6) },
+        Scene { running: Some((2, None)), ..Scene::plain("ring_6_indeterminate", 6) },
+        Scene { disabled: Some(3), ..Scene::plain("ring_6_disabled", 6) },
+        Scene { ring_sizes: Some(vec![4]), ..Scene::plain("rings_4_8", 12) },
+        Scene { selected: Some(1), style: 1, ..Scene::plain("ring_6_high_contrast", 6) },
+    ]
+}
+
+/// Draws every scene and compares it with its reference, or replaces the references with `update`.
+/// Returns the exit code: 1 if any scene differs or has no reference yet.
+pub fn run(update: bool) -> i32 {
+    let mut renderer = match Renderer::new_offscreen(SIZE, SIZE) {
+        Ok(renderer) => renderer,
+        Err(e) => {
+            eprintln!("Failed to create the offscreen renderer: {}", e);
+            return 1;
+        }
+    };
+    if let Err(e) = fs::create_dir_all(GOLDEN_DIR) {
+        eprintln!("Failed to create {}: {}", GOLDEN_DIR, e);
+        return 1;
+    }
+
+    // Without a single reference there is nothing to compare with; every scene would fail the same way
+    let has_references = scenes().iter().any(|scene| Path::new(GOLDEN_DIR).join(format!("{}.png", scene.name)).exists());
+    if !update && !has_references {
+        eprintln!(
+            "No reference images in {}; run `golden --update` on a machine with a Vulkan GPU and commit the PNGs",
+            GOLDEN_DIR
+        );
+        return 1;
+    }
+
+    let mut failures = 0;
+    for scene in scenes() {
+        let outcome = renderer
+            .render_ring(&scene.overlay_content(), scene.mouse_pos, 0.0, scene.style)
+            .and_then(|pixels| check(scene.name, &pixels, update));
+        match outcome {
+            Ok(message) => println!("{}: {}", scene.name, message),
+            Err(e) => {
+                println!("{}: {}", scene.name, e);
+                failures += 1;
+            }
+        }
+    }
+    renderer.cleanup();
+
+    if failures > 0 {
+        println!("{} of {} scenes failed", failures, scenes().len());
+        1
+    } else {
+        0
+    }
+}
+
+/// Compares a drawn scene with its reference, leaving the drawn image next to it when they differ.
+fn check(name: &str, pixels: &[u8], update: bool) -> Result<&'static str, String> {
+    let reference_path = Path::new(GOLDEN_DIR).join(format!("{}.png", name));
+    let actual_path = Path::new(GOLDEN_DIR).join(format!("{}.actual.png", name));
+    if update {
+        write_png(&reference_path, SIZE, SIZE, pixels)?;
+        let _ = fs::remove_file(&actual_path);
+        return Ok("updated");
+    }
+    if !reference_path.exists() {
+        write_png(&actual_path, SIZE, SIZE, pixels)?;
+        return Err(format!("no reference yet; check {} and run `golden --update`", actual_path.display()));
+    }
+
+    let reference = read_png(&reference_path)?;
+    let different = reference
+        .chunks_exact(4)
+        .zip(pixels.chunks_exact(4))
+        .filter(|(expected, actual)| expected.iter().zip(actual.iter()).any(|(e, a)| e.abs_diff(*a) > CHANNEL_TOLERANCE))
+        .count();
+    if reference.len() == pixels.len() && different <= MAX_DIFFERENT_PIXELS {
+        let _ = fs::remove_file(&actual_path);
+        return Ok("OK");
+    }
+    write_png(&actual_path, SIZE, SIZE, pixels)?;
+    if reference.len() != pixels.len() {
+        return Err(format!("reference is not {}x{}; see {}", SIZE, SIZE, actual_path.display()));
+    }
+    Err(format!("{} pixels differ; see {}", different, actual_path.display()))
+}
+
+/// Reads an 8-bit RGBA PNG as rows from the top.
+fn read_png(path: &Path) -> Result<Vec<u8>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut reader = png::Decoder::new(file)
+        .read_info()
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut pixels)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+        return Err(format!("{} is not an 8-bit RGBA PNG", path.display()));
+    }
+    pixels.truncate(info.buffer_size());
+    Ok(pixels)
+}
@@ -0,0 +1,543 @@
+// Widgets described with egui each frame, behind the "egui" feature. egui tessellates them into triangles that
+// sample its font atlas, which is kept as coverage like the text's glyph atlas and drawn over everything else.
+// The pipeline itself needs no egui; without the feature it is never created.
+#![cfg_attr(not(feature = "egui"), allow(dead_code))]
+
+use ash::{vk, Device, Instance};
+use crate::memory::{self, Allocation, Resource};
+use crate::render::{create_quad_pipeline, VertexRing};
+
+/// Side of the font atlas, which egui is told is the largest texture it may use.
+pub const ATLAS_SIDE: u32 = 2048;
+/// Triangle corners the vertex buffers hold; egui's indexed meshes are unrolled into them. Unused vertices are
+/// zeroed so they rasterize nothing, which lets the command buffers be recorded once with a fixed draw count.
+pub const MAX_GUI_VERTICES: usize = 48 * 1024;
+
+/// Vertex consumed by shaders/gui.vert.glsl.
+#[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct GuiVertex {
+    position: [f32; 2], // NDC, Y pointing down
+    uv: [f32; 2],
+    color: [f32; 4], // Premultiplied alpha, as egui gives it
+}
+
+/// What the widgets came out as in one frame.
+pub struct GuiFrame<'a> {
+    pub vertices: Vec<GuiVertex>,
+    pub atlas: &'a [u8], // Coverage, ATLAS_SIDE pixels square
+    pub atlas_generation: u64, // Changes whenever egui adds glyphs to the atlas
+}
+
+#[cfg(feature = "egui")]
+pub use context::{Gui, UiHook};
+
+#[cfg(feature = "egui")]
+mod context {
+    use ash::vk;
+    use winapi::um::winuser::{GetAsyncKeyState, VK_LBUTTON};
+    use super::{GuiFrame, GuiVertex, ATLAS_SIDE, MAX_GUI_VERTICES};
+
+    /// Called every frame with the full-window area to add widgets to.
+    pub type UiHook = Box<dyn FnMut(&mut egui::Ui)>;
+
+    /// egui's state across frames and the hooks that describe the widgets.
+    pub struct Gui {
+        context: egui::Context,
+        hooks: Vec<UiHook>,
+        pressed: bool, // Left button state sent to egui last
+        atlas: Vec<u8>,
+        atlas_size: [usize; 2], // Size egui gives the font atlas; its uvs are relative to it
+        atlas_generation: u64,
+    }
+
+    impl Gui {
+        pub fn new() -> Self {
+            let context = egui::Context::default();
+            // Anti-aliased edges would be translucent, see dithered() in shaders/indexFromRust.frag.glsl
+            context.options_mut(|options| options.tessellation_options.feathering = false);
+            Self {
+                context,
+                hooks: Vec::new(),
+                pressed: false,
+                atlas: vec![0; (ATLAS_SIDE * ATLAS_SIDE) as usize],
+                atlas_size: [ATLAS_SIDE as usize, ATLAS_SIDE as usize],
+                atlas_generation: 0,
+            }
+        }
+
+        pub fn add(&mut self, hook: UiHook) {
+            self.hooks.push(hook);
+        }
+
+        /// Runs the hooks and tessellates what they drew, with the cursor at `cursor` window pixels. Nothing is
+        /// drawn, and egui isn't run, while there are no hooks.
+        pub fn run(&mut self, extent: vk::Extent2D, cursor: (f32, f32), time: f64) -> GuiFrame<'_> {
+            if self.hooks.is_empty() {
+                return GuiFrame { vertices: Vec::new(), atlas: &self.atlas, atlas_generation: self.atlas_generation };
+            }
+
+            // The window is click-through, so the button is read like the click-outside check does
+            let pointer = egui::pos2(cursor.0, cursor.1);
+            let pressed = unsafe { GetAsyncKeyState(VK_LBUTTON) } < 0;
+            let mut events = vec![egui::Event::PointerMoved(pointer)];
+            if pressed != self.pressed {
+                events.push(egui::Event::PointerButton {
+                    pos: pointer,
+                    button: egui::PointerButton::Primary,
+                    pressed,
+                    modifiers: egui::Modifiers::default(),
+                });
+                self.pressed = pressed;
+            }
+            let (width, height) = (extent.width as f32, extent.height as f32);
+            let input = egui::RawInput {
+                screen_rect: Some(egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(width, height))),
+                max_texture_side: Some(ATLAS_SIDE as usize),
+                time: Some(time),
+                events,
+                ..Default::default()
+            };
+
+            let hooks = &mut self.hooks;
+            let output = self.context.run(input, |context| {
+                egui::CentralPanel::default().frame(egui::Frame::none()).show(context, |ui| {
+                    for hook in hooks.iter_mut() {
+                        hook(ui);
+                    }
+                });
+            });
+            for (id, delta) in &output.textures_delta.set {
+                if *id == egui::TextureId::default() {
+                    self.apply_atlas_delta(delta);
+                }
+            }
+
+            // Meshes on other textures, i.e. images, aren't supported, and clip rectangles are ignored
+            let to_ndc = |position: egui::Pos2| [position.x / width * 2.0 - 1.0, position.y / height * 2.0 - 1.0];
+            let uv_scale = [
+                self.atlas_size[0] as f32 / ATLAS_SIDE as f32,
+                self.atlas_size[1] as f32 / ATLAS_SIDE as f32,
+            ];
+            let mut vertices = Vec::new();
+            for clipped in self.context.tessellate(output.shapes, output.pixels_per_point) {
+                let mesh = match clipped.primitive {
+                    egui::epaint::Primitive::Mesh(mesh) if mesh.texture_id == egui::TextureId::default() => mesh,
+                    _ => continue,
+                };
+                for triangle in mesh.indices.chunks_exact(3) {
+                    if vertices.len() + 3 > MAX_GUI_VERTICES {
+                        break;
+                    }
+                    vertices.extend(triangle.iter().map(|&index| {
+                        let vertex = mesh.vertices[index as usize];
+                        let [r, g, b, a] = vertex.color.to_array();
+                        GuiVertex {
+                            position: to_ndc(vertex.pos),
+                            uv: [vertex.uv.x * uv_scale[0], vertex.uv.y * uv_scale[1]],
+                            color: [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, a as f32 / 255.0],
+                        }
+                    }));
+                }
+            }
+            GuiFrame { vertices, atlas: &self.atlas, atlas_generation: self.atlas_generation }
+        }
+
+        /// Copies new glyphs into the coverage atlas; a delta without a position replaces the whole atlas.
+        fn apply_atlas_delta(&mut self, delta: &egui::epaint::ImageDelta) {
+            let font = match &delta.image {
+                egui::ImageData::Font(font) => font,
+                egui::ImageData::Color(_) => return,
+            };
+            let side = ATLAS_SIDE as usize;
+            let [left, top] = delta.pos.unwrap_or([0, 0]);
+            if delta.pos.is_none() {
+                self.atlas.fill(0);
+                self.atlas_size = font.size;
+            }
+            for row in 0..font.size[1].min(side.saturating_sub(top)) {
+                for column in 0..font.size[0].min(side.saturating_sub(left)) {
+                    let coverage = font.pixels[row * font.size[0] + column].clamp(0.0, 1.0);
+                    self.atlas[(top + row) * side + left + column] = (coverage * 255.0).round() as u8;
+                }
+            }
+            self.atlas_generation += 1;
+        }
+    }
+}
+
+/// Vulkan objects for the egui pipeline. Drawn in the same render pass, after the text. Each swapchain image
+/// has its own atlas, rewritten when egui's has changed since, so one in use by an earlier frame is never touched.
+pub struct GuiRenderer {
+    images: Vec<vk::Image>,
+    memories: Vec<Allocation>,
+    views: Vec<vk::ImageView>,
+    layout: vk::SubresourceLayout, // Where the rows are in each atlas' memory
+    sampler: vk::Sampler,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_sets: Vec<vk::DescriptorSet>,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    vertices: VertexRing<GuiVertex>,
+    atlas_generations: Vec<Option<u64>>, // Generation of egui's atlas each image's atlas holds
+}
+
+impl GuiRenderer {
+    /// Creates the atlases and vertex buffers, both empty, and the egui pipeline.
+    pub fn new(
+        instance: &Instance,
+        device: &Device,
+        physical_device: vk::PhysicalDevice,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+        render_pass: vk::RenderPass,
+        extent: vk::Extent2D,
+        image_count: usize,
+    ) -> Result<Self, String> {
+        let mut images = Vec::with_capacity(image_count);
+        let mut memories = Vec::with_capacity(image_count);
+        let mut views = Vec::with_capacity(image_count);
+        for _ in 0..image_count {
+            let (image, memory) = create_atlas_image(instance, device, physical_device)?;
+            views.push(create_atlas_view(device, image)?);
+            images.push(image);
+            memories.push(memory);
+        }
+        transition_to_general(device, command_pool, queue, &images)?;
+
+        let subresource = vk::ImageSubresource {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level: 0,
+            array_layer: 0,
+        };
+        let layout = unsafe { device.get_image_subresource_layout(images[0], subresource) };
+
+        let sampler = create_sampler(device)?;
+        let descriptor_set_layout = create_descriptor_set_layout(device)?;
+        let (descriptor_pool, descriptor_sets) =
+            create_descriptor_sets(device, descriptor_set_layout, &views, sampler)?;
+        // Vertex input: position, uv, color
+        let (pipeline_layout, pipeline) = create_quad_pipeline(
+            device,
+            render_pass,
+            extent,
+            "gui",
+            &[vk::Format::R32G32_SFLOAT, vk::Format::R32G32_SFLOAT, vk::Format::R32G32B32A32_SFLOAT],
+            descriptor_set_layout,
+            &[],
+        )?;
+        let vertices = VertexRing::new(instance, device, physical_device, MAX_GUI_VERTICES, image_count, "egui")?;
+
+        Ok(Self {
+            images,
+            memories,
+            views,
+            layout,
+            sampler,
+            descriptor_set_layout,
+            descriptor_pool,
+            descriptor_sets,
+            pipeline_layout,
+            pipeline,
+            vertices,
+            atlas_generations: vec![None; image_count],
+        })
+    }
+
+    /// Writes a frame's triangles, and egui's atlas if it changed, for the given swapchain image.
+    pub fn upload(&mut self, device: &Device, image_index: usize, frame: &GuiFrame) -> Result<(), String> {
+        if self.atlas_generations[image_index] != Some(frame.atlas_generation) {
+            write_atlas(device, self.memories[image_index], self.layout, frame.atlas)?;
+            self.atlas_generations[image_index] = Some(frame.atlas_generation);
+        }
+        self.vertices.upload(device, image_index, &frame.vertices)
+    }
+
+    /// Records the egui draw into a command buffer inside an active render pass.
+    pub fn record(&self, device: &Device, command_buffer: vk::CommandBuffer, image_index: usize) {
+        unsafe {
+            device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.pipeline);
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline_layout,
+                0,
+                &[self.descriptor_sets[image_index]],
+                &[],
+            );
+        }
+        self.vertices.record_draw(device, command_buffer, image_index);
+    }
+
+    /// Destroys the egui resources. The device must be idle.
+    pub fn destroy(&self, device: &Device) {
+        self.vertices.destroy(device);
+        unsafe {
+            device.destroy_pipeline(self.pipeline, None);
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+            device.destroy_descriptor_pool(self.descriptor_pool, None);
+            device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+            device.destroy_sampler(self.sampler, None);
+            for &view in self.views.iter() {
+                device.destroy_image_view(view, None);
+            }
+            for &image in self.images.iter() {
+                device.destroy_image(image, None);
+            }
+        }
+        for &allocation in self.memories.iter() {
+            memory::free(device, allocation);
+        }
+    }
+}
+
+/// Copies the whole coverage atlas into an image's memory, row by row.
+fn write_atlas(device: &Device, memory: Allocation, layout: vk::SubresourceLayout, atlas: &[u8]) -> Result<(), String> {
+    let side = ATLAS_SIDE as usize;
+    let data_ptr = memory::mapped(device, &memory).map_err(|e| format!("Failed to map egui atlas memory: {}", e))?;
+    unsafe {
+        for row in 0..side {
+            let row_ptr = data_ptr.add((layout.offset + row as u64 * layout.row_pitch) as usize);
+            row_ptr.copy_from_nonoverlapping(atlas[row * side..].as_ptr(), side);
+        }
+    }
+    Ok(())
+}
+
+/// Creates a linear single-channel atlas in host-visible memory, so glyphs are written without a copy.
+fn create_atlas_image(
+    instance: &Instance,
+    device: &Device,
+    physical_device: vk::PhysicalDevice,
+) -> Result<(vk::Image, Allocation), String> {
+    let image_info = vk::ImageCreateInfo::builder()
+        .image_type(vk::ImageType::TYPE_2D)
+        .format(vk::Format::R8_UNORM)
+        .extent(vk::Extent3D {
+            width: ATLAS_SIDE,
+            height: ATLAS_SIDE,
+            depth: 1,
+        })
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .tiling(vk::ImageTiling::LINEAR)
+        .usage(vk::ImageUsageFlags::SAMPLED)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+        .initial_layout(vk::ImageLayout::PREINITIALIZED);
+
+    let image = unsafe {
+        device
+            .create_image(&image_info, None)
+            .map_err(|e| format!("Failed to create egui atlas image: {:?}", e))?
+    };
+
+    let mem_requirements = unsafe { device.get_image_memory_requirements(image) };
+    let mem_properties = unsafe { instance.get_physical_device_memory_properties(physical_device) };
+    let memory = memory::allocate(
+        device,
+        mem_properties,
+        mem_requirements,
+        vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        Resource::Linear,
+    )
+    .map_err(|e| format!("Failed to allocate egui atlas memory: {}", e))?;
+
+    unsafe {
+        device
+            .bind_image_memory(image, memory.memory, memory.offset)
+            .map_err(|e| format!("Failed to bind egui atlas memory: {:?}", e))?;
+    }
+
+    Ok((image, memory))
+}
+
+/// Moves the atlases into GENERAL layout once, where the host writes them and the shader samples them.
+fn transition_to_general(
+    device: &Device,
+    command_pool: vk::CommandPool,
+    queue: vk::Queue,
+    images: &[vk::Image],
+) -> Result<(), String> {
+    let allocate_info = vk::CommandBufferAllocateInfo::builder()
+        .command_pool(command_pool)
+        .level(vk::CommandBufferLevel::PRIMARY)
+        .command_buffer_count(1);
+
+    let command_buffer = unsafe {
+        device
+            .allocate_command_buffers(&allocate_info)
+            .map_err(|e| format!("Failed to allocate egui command buffer: {:?}", e))?[0]
+    };
+
+    let subresource_range = vk::ImageSubresourceRange::builder()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .base_mip_level(0)
+        .level_count(1)
+        .base_array_layer(0)
+        .layer_count(1)
+        .build();
+    let barriers: Vec<vk::ImageMemoryBarrier> = images
+        .iter()
+        .map(|&image| {
+            vk::ImageMemoryBarrier::builder()
+                .old_layout(vk::ImageLayout::PREINITIALIZED)
+                .new_layout(vk::ImageLayout::GENERAL)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image)
+                .subresource_range(subresource_range)
+                .src_access_mask(vk::AccessFlags::HOST_WRITE)
+                .dst_access_mask(vk::AccessFlags::SHADER_READ)
+                .build()
+        })
+        .collect();
+
+    let begin_info = vk::CommandBufferBeginInfo::builder()
+        .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+
+    unsafe {
+        device
+            .begin_command_buffer(command_buffer, &begin_info)
+            .map_err(|e| format!("Failed to begin egui command buffer: {:?}", e))?;
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::HOST,
+            vk::PipelineStageFlags::FRAGMENT_SHADER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &barriers,
+        );
+        device
+            .end_command_buffer(command_buffer)
+            .map_err(|e| format!("Failed to end egui command buffer: {:?}", e))?;
+
+        let command_buffers = [command_buffer];
+        let submit_info = vk::SubmitInfo::builder()
+            .command_buffers(&command_buffers)
+            .build();
+        device
+            .queue_submit(queue, &[submit_info], vk::Fence::null())
+            .map_err(|e| format!("Failed to submit egui layout change: {:?}", e))?;
+        device
+            .queue_wait_idle(queue)
+            .map_err(|e| format!("Failed to wait for egui layout change: {:?}", e))?;
+        device.free_command_buffers(command_pool, &command_buffers);
+    }
+
+    Ok(())
+}
+
+fn create_atlas_view(device: &Device, image: vk::Image) -> Result<vk::ImageView, String> {
+    let create_info = vk::ImageViewCreateInfo::builder()
+        .image(image)
+        .view_type(vk::ImageViewType::TYPE_2D)
+        .format(vk::Format::R8_UNORM)
+        .subresource_range(
+            vk::ImageSubresourceRange::builder()
+                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                .base_mip_level(0)
+                .level_count(1)
+                .base_array_layer(0)
+                .layer_count(1)
+                .build(),
+        );
+
+    unsafe {
+        device
+            .create_image_view(&create_info, None)
+            .map_err(|e| format!("Failed to create egui atlas view: {:?}", e))
+    }
+}
+
+/// egui lays glyphs out on whole pixels at one pixel per point, so nearest filtering copies them as they are.
+fn create_sampler(device: &Device) -> Result<vk::Sampler, String> {
+    let sampler_info = vk::SamplerCreateInfo::builder()
+        .mag_filter(vk::Filter::NEAREST)
+        .min_filter(vk::Filter::NEAREST)
+        .mipmap_mode(vk::SamplerMipmapMode::NEAREST)
+        .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .max_lod(0.0);
+
+    unsafe {
+        device
+            .create_sampler(&sampler_info, None)
+            .map_err(|e| format!("Failed to create egui sampler: {:?}", e))
+    }
+}
+
+fn create_descriptor_set_layout(device: &Device) -> Result<vk::DescriptorSetLayout, String> {
+    let sampler_binding = vk::DescriptorSetLayoutBinding::builder()
+        .binding(0)
+        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(1)
+        .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+        .build();
+
+    let bindings = [sampler_binding];
+    let layout_info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(&bindings);
+
+    unsafe {
+        device
+            .create_descriptor_set_layout(&layout_info, None)
+            .map_err(|e| format!("Failed to create egui descriptor set layout: {:?}", e))
+    }
+}
+
+/// Allocates a descriptor set per atlas.
+fn create_descriptor_sets(
+    device: &Device,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    views: &[vk::ImageView],
+    sampler: vk::Sampler,
+) -> Result<(vk::DescriptorPool, Vec<vk::DescriptorSet>), String> {
+    let pool_sizes = [vk::DescriptorPoolSize::builder()
+        .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(views.len() as u32)
+        .build()];
+
+    let pool_info = vk::DescriptorPoolCreateInfo::builder()
+        .pool_sizes(&pool_sizes)
+        .max_sets(views.len() as u32);
+
+    let descriptor_pool = unsafe {
+        device
+            .create_descriptor_pool(&pool_info, None)
+            .map_err(|e| format!("Failed to create egui descriptor pool: {:?}", e))?
+    };
+
+    let layouts = vec![descriptor_set_layout; views.len()];
+    let alloc_info = vk::DescriptorSetAllocateInfo::builder()
+        .descriptor_pool(descriptor_pool)
+        .set_layouts(&layouts);
+
+    let descriptor_sets = unsafe {
+        device
+            .allocate_descriptor_sets(&alloc_info)
+            .map_err(|e| format!("Failed to allocate egui descriptor sets: {:?}", e))?
+    };
+
+    for (&descriptor_set, &view) in descriptor_sets.iter().zip(views) {
+        let image_info = vk::DescriptorImageInfo::builder()
+            .sampler(sampler)
+            .image_view(view)
+            .image_layout(vk::ImageLayout::GENERAL);
+
+        let descriptor_write = vk::WriteDescriptorSet::builder()
+            .dst_set(descriptor_set)
+            .dst_binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(std::slice::from_ref(&image_info));
+
+        unsafe {
+            device.update_descriptor_sets(&[descriptor_write.build()], &[]);
+        }
+    }
+
+    Ok((descriptor_pool, descriptor_sets))
+}
+
@@ -4,29 +4,424 @@ use winapi::um::winuser::*;
 use winapi::shared::minwindef::UINT;
 use std::ptr::null_mut;
 use winapi::um::errhandlingapi::GetLastError;
+use winapi::shared::winerror::ERROR_HOTKEY_ALREADY_REGISTERED;
+use crate::config::{Activation, Config};
+use std::time::Duration;
+use crate::diagnostics;
+use crate::mouse::{self, MouseButton};
+use crate::trigger_hook;
+use crate::provider::Page;
+use crate::status;
 
 pub const WM_HOTKEY_ID: i32 = 1;
+pub const DIAGNOSTICS_HOTKEY_ID: i32 = 2;
+pub const PANEL_ACCEPT_HOTKEY_ID: i32 = 3;
+pub const PANEL_CANCEL_HOTKEY_ID: i32 = 4;
+pub const RECENT_HOTKEY_ID: i32 = 5;
+pub const REPEAT_HOTKEY_ID: i32 = 6;
+pub const DISMISS_HOTKEY_ID: i32 = 7;
+pub const HUD_HOTKEY_ID: i32 = 8;
+pub const SWITCHER_HOTKEY_ID: i32 = 9;
+/// Keys 1-9 use this ID plus the digit minus one.
+pub const NUMBER_HOTKEY_BASE_ID: i32 = 10;
+pub const CLIPBOARD_HOTKEY_ID: i32 = 20;
+pub const RECENT_FILES_HOTKEY_ID: i32 = 21;
+pub const DEBUG_HOTKEY_ID: i32 = 22;
+pub const PROFILES_HOTKEY_ID: i32 = 23;
+/// Stays registered while the others are released, so it can resume the overlay.
+pub const PAUSE_HOTKEY_ID: i32 = 24;
+/// Esc with the trigger's modifiers still held.
+pub const HELD_DISMISS_HOTKEY_ID: i32 = 25;
+/// Keys 1-9 with the trigger's modifiers still held use this ID plus the digit minus one.
+pub const HELD_NUMBER_HOTKEY_BASE_ID: i32 = 30;
+/// Briefly registered to find out whether a combination is free.
+const PROBE_HOTKEY_ID: i32 = 99;
+/// Modifiers tried with a taken hotkey's key when suggesting a free combination instead, in order.
+const ALTERNATIVE_MODIFIERS: [(isize, &str); 6] = [
+    (MOD_CONTROL | MOD_ALT, "Ctrl+Alt"),
+    (MOD_CONTROL | MOD_SHIFT, "Ctrl+Shift"),
+    (MOD_ALT | MOD_SHIFT, "Alt+Shift"),
+    (MOD_CONTROL | MOD_ALT | MOD_SHIFT, "Ctrl+Alt+Shift"),
+    (MOD_WIN | MOD_ALT, "Win+Alt"),
+    (MOD_WIN | MOD_SHIFT, "Win+Shift"),
+];
+/// Most alternatives named in the message.
+const MAX_ALTERNATIVES: usize = 3;
 
-pub fn register_hotkey() -> bool {
+/// A modifier + key combination such as "Ctrl+Shift+Space".
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hotkey {
+    pub modifiers: UINT,
+    pub key: UINT,
+    pub name: String,
+}
+
+impl Default for Hotkey {
+    fn default() -> Self {
+        Self {
+            modifiers: MOD_ALT as UINT,
+            key: 0x52,
+            name: "Alt+R".to_string(),
+        }
+    }
+}
+
+impl Hotkey {
+    /// Alt+D, which saves a diagnostics bundle.
+    pub fn diagnostics() -> Self {
+        Self {
+            modifiers: MOD_ALT as UINT,
+            key: 0x44,
+            name: "Alt+D".to_string(),
+        }
+    }
+
+    /// Parses "Modifier+...+Key". Modifiers are Ctrl, Alt, Shift and Win; keys are letters,
+    /// digits, F1-F24 and named keys such as Space, Left or PlayPause. Matching is case-insensitive.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = text.split('+').map(|part| part.trim()).collect();
+        let (key_name, modifier_names) = match parts.split_last() {
+            Some((key, modifiers)) if !key.is_empty() => (*key, modifiers),
+            _ => return Err(format!("Invalid hotkey \"{}\": missing key", text)),
+        };
+
+        let mut modifiers = 0;
+        for name in modifier_names {
+            modifiers |= match name.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => MOD_CONTROL,
+                "alt" => MOD_ALT,
+                "shift" => MOD_SHIFT,
+                "win" => MOD_WIN,
+                _ => return Err(format!("Invalid hotkey \"{}\": unknown modifier \"{}\"", text, name)),
+            } as UINT;
+        }
+
+        let key = virtual_key(key_name)
+            .ok_or_else(|| format!("Invalid hotkey \"{}\": unknown key \"{}\"", text, key_name))?;
+
+        Ok(Self {
+            modifiers,
+            key,
+            name: text.to_string(),
+        })
+    }
+
+    /// Parses a trigger of one or more steps separated by commas: "Ctrl+Space, R" is Ctrl+Space followed by R, and
+    /// "Alt, Alt" a double-tap of Alt. The result has at least one step.
+    pub fn parse_sequence(text: &str) -> Result<Vec<Self>, String> {
+        text.split(',').map(|step| Self::parse(step.trim())).collect()
+    }
+
+    /// Whether the hotkey is still held down: all of its modifiers, or the key itself when it has none.
+    pub fn is_held(&self) -> bool {
+        let down = |vk: i32| unsafe { (GetAsyncKeyState(vk) as u16 & 0x8000) != 0 };
+        if self.modifiers == 0 {
+            return down(self.key as i32);
+        }
+        let held = |modifier: isize, vks: &[i32]| {
+            (self.modifiers & modifier as UINT) == 0 || vks.iter().any(|&vk| down(vk))
+        };
+        held(MOD_ALT, &[VK_MENU])
+            && held(MOD_CONTROL, &[VK_CONTROL])
+            && held(MOD_SHIFT, &[VK_SHIFT])
+            && held(MOD_WIN, &[VK_LWIN, VK_RWIN])
+    }
+}
+
+/// The configured hotkeys, parsed; invalid ones are reported and left out.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hotkeys {
+    pub trigger: Hotkey, // Falls back to the default when invalid; the last step of a sequence trigger
+    pub trigger_steps: Vec<Hotkey>, // Steps pressed before `trigger` in a sequence like "Ctrl+Space, R"; empty for one combination
+    pub sequence_timeout: Duration, // Longest pause between the steps
+    pub long_press: Option<Hotkey>, // Single key that opens the overlay when held; None unless set
+    pub long_press_delay: Duration, // How long it has to be held
+    pub recent: Option<Hotkey>, // None while the recent page is disabled
+    pub switcher: Option<Hotkey>, // None while the window switcher is disabled
+    pub clipboard: Option<Hotkey>, // None while the clipboard history is disabled or has no hotkey
+    pub recent_files: Option<Hotkey>, // None while the recent files page is disabled or has no hotkey
+    pub profiles: Option<Hotkey>, // None while the profile switcher is disabled or has no hotkey
+    pub repeat: Option<Hotkey>, // None unless set
+    pub pause: Option<Hotkey>, // Registered on its own, see `register_pause_hotkey`; None unless set
+    pub hud: Option<Hotkey>, // Shows or hides the HUD; None unless set
+    pub debug: Option<Hotkey>, // Shows or hides the debug panel; None unless set
+    pub mouse_button: Option<MouseButton>, // Extra mouse button acting as the trigger
+    pub activation: Activation,
+    pub tap: Duration, // Longest press that counts as a tap
+    pub number_keys: bool, // 1-9 select while the menu is shown
+}
+
+impl Hotkeys {
+    pub fn from_config(config: &Config) -> Self {
+        let optional = |enabled: bool, text: &str| {
+            if !enabled || text.is_empty() {
+                return None;
+            }
+            Hotkey::parse(text).map_err(diagnostics::report_error).ok()
+        };
+        let mut trigger_steps = Hotkey::parse_sequence(&config.hotkey.trigger).unwrap_or_else(|e| {
+            diagnostics::report_error(e);
+            vec![Hotkey::default()]
+        });
+        Self {
+            trigger: trigger_steps.pop().unwrap_or_default(),
+            trigger_steps,
+            sequence_timeout: Duration::from_millis(config.hotkey.sequence_timeout_ms),
+            long_press: optional(true, &config.hotkey.long_press),
+            long_press_delay: Duration::from_millis(config.hotkey.long_press_ms),
+            recent: optional(config.recent.enabled, &config.recent.hotkey),
+            switcher: optional(config.switcher.enabled, &config.switcher.hotkey),
+            clipboard: optional(config.clipboard.enabled, &config.clipboard.hotkey),
+            recent_files: optional(config.recent_files.enabled, &config.recent_files.hotkey),
+            profiles: optional(config.profile_switcher.enabled, &config.profile_switcher.hotkey),
+            repeat: optional(true, &config.hotkey.repeat),
+            pause: optional(true, &config.hotkey.pause),
+            hud: optional(true, &config.hud.hotkey),
+            debug: optional(true, &config.debug.hotkey),
+            mouse_button: match config.hotkey.mouse_button.as_str() {
+                "" => None,
+                name => MouseButton::parse(name).map_err(diagnostics::report_error).ok(),
+            },
+            activation: config.hotkey.activation,
+            tap: Duration::from_millis(config.hotkey.tap_ms),
+            number_keys: config.hotkey.number_keys,
+        }
+    }
+
+    /// The hotkey that opens a generated page, None when the page has none.
+    pub fn page(&self, page: Page) -> Option<&Hotkey> {
+        match page {
+            Page::Recent => self.recent.as_ref(),
+            Page::Windows => self.switcher.as_ref(),
+            Page::Clipboard => self.clipboard.as_ref(),
+            Page::Files => self.recent_files.as_ref(),
+            Page::Profiles => self.profiles.as_ref(),
+        }
+    }
+
+    /// Registers all of them along with the diagnostics hotkey. Returns false if the trigger is taken.
+    pub fn register(&self) -> bool {
+        let registered = trigger_hook::register_trigger(self);
+        register_diagnostics_hotkey();
+        if let Some(recent) = &self.recent {
+            register(RECENT_HOTKEY_ID, recent.modifiers, recent.key, &recent.name);
+        }
+        if let Some(switcher) = &self.switcher {
+            register(SWITCHER_HOTKEY_ID, switcher.modifiers, switcher.key, &switcher.name);
+        }
+        if let Some(clipboard) = &self.clipboard {
+            register(CLIPBOARD_HOTKEY_ID, clipboard.modifiers, clipboard.key, &clipboard.name);
+        }
+        if let Some(recent_files) = &self.recent_files {
+            register(RECENT_FILES_HOTKEY_ID, recent_files.modifiers, recent_files.key, &recent_files.name);
+        }
+        if let Some(profiles) = &self.profiles {
+            register(PROFILES_HOTKEY_ID, profiles.modifiers, profiles.key, &profiles.name);
+        }
+        if let Some(repeat) = &self.repeat {
+            register(REPEAT_HOTKEY_ID, repeat.modifiers, repeat.key, &repeat.name);
+        }
+        if let Some(hud) = &self.hud {
+            register(HUD_HOTKEY_ID, hud.modifiers, hud.key, &hud.name);
+        }
+        if let Some(debug) = &self.debug {
+            register(DEBUG_HOTKEY_ID, debug.modifiers, debug.key, &debug.name);
+        }
+        if let Some(button) = self.mouse_button {
+            mouse::bind(Some(button));
+            status::add_hotkey(format!("{:?} mouse button", button));
+        }
+        registered
+    }
+}
+
+/// Maps a key name to its virtual-key code.
+fn virtual_key(name: &str) -> Option<UINT> {
+    let upper = name.to_ascii_uppercase();
+    let mut chars = upper.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if c.is_ascii_uppercase() || c.is_ascii_digit() {
+            // Letter and digit virtual-key codes match their ASCII values
+            return Some(c as UINT);
+        }
+    }
+    if let Some(number) = upper.strip_prefix('F').and_then(|n| n.parse::<i32>().ok()) {
+        if (1..=24).contains(&number) {
+            return Some((VK_F1 + number - 1) as UINT);
+        }
+    }
+    let vk = match upper.as_str() {
+        // Bare modifiers, for steps like the double-tap of "Alt, Alt"
+        "ALT" => VK_MENU,
+        "CTRL" | "CONTROL" => VK_CONTROL,
+        "SHIFT" => VK_SHIFT,
+        "WIN" => VK_LWIN,
+        "CAPSLOCK" => VK_CAPITAL,
+        "SCROLLLOCK" => VK_SCROLL,
+        "APPS" | "MENU" => VK_APPS,
+        "SPACE" => VK_SPACE,
+        "TAB" => VK_TAB,
+        "ENTER" => VK_RETURN,
+        "INSERT" => VK_INSERT,
+        "DELETE" => VK_DELETE,
+        "HOME" => VK_HOME,
+        "END" => VK_END,
+        "PAGEUP" => VK_PRIOR,
+        "PAGEDOWN" => VK_NEXT,
+        "UP" => VK_UP,
+        "DOWN" => VK_DOWN,
+        "LEFT" => VK_LEFT,
+        "RIGHT" => VK_RIGHT,
+        "PAUSE" => VK_PAUSE,
+        "ESC" | "ESCAPE" => VK_ESCAPE,
+        "BACKSPACE" => VK_BACK,
+        "PERIOD" | "." => VK_OEM_PERIOD,
+        "COMMA" | "," => VK_OEM_COMMA,
+        "PLAYPAUSE" => VK_MEDIA_PLAY_PAUSE,
+        "NEXTTRACK" => VK_MEDIA_NEXT_TRACK,
+        "PREVTRACK" => VK_MEDIA_PREV_TRACK,
+        "STOPMEDIA" => VK_MEDIA_STOP,
+        "VOLUMEUP" => VK_VOLUME_UP,
+        "VOLUMEDOWN" => VK_VOLUME_DOWN,
+        "VOLUMEMUTE" => VK_VOLUME_MUTE,
+        _ => return None,
+    };
+    Some(vk as UINT)
+}
+
+/// Registers the hotkey that shows the overlay.
+pub fn register_hotkey(hotkey: &Hotkey) -> bool {
+    register(WM_HOTKEY_ID, hotkey.modifiers, hotkey.key, &hotkey.name)
+}
+
+/// Registers Alt+D, which saves a diagnostics bundle.
+pub fn register_diagnostics_hotkey() -> bool {
+    let hotkey = Hotkey::diagnostics();
+    register(DIAGNOSTICS_HOTKEY_ID, hotkey.modifiers, hotkey.key, &hotkey.name)
+}
+
+fn register(id: i32, modifiers: UINT, key: UINT, name: &str) -> bool {
+    // Without auto-repeat, holding a key sends one press, so holds and taps can be told apart
     let result = unsafe {
         RegisterHotKey(
             null_mut(),
-            WM_HOTKEY_ID,
-            MOD_ALT as UINT,
-            0x52 as UINT,
+            id,
+            modifiers | MOD_NOREPEAT as UINT,
+            key,
         )
     };
     if result == 0 {
         let error = unsafe { GetLastError() };
-        eprintln!("Failed to register hotkey. Error code: {}", error);
+        eprintln!("Failed to register hotkey {}. Error code: {}", name, error);
+        let message = if error != ERROR_HOTKEY_ALREADY_REGISTERED {
+            format!("Failed to register hotkey {} (error {})", name, error)
+        } else {
+            match free_alternatives(modifiers, key, name).as_slice() {
+                [] => format!("Hotkey {} is already used by another app; choose another in the config", name),
+                free => format!("Hotkey {} is already used by another app; free instead: {}", name, free.join(", ")),
+            }
+        };
+        diagnostics::report_error(message);
         false
     } else {
+        status::add_hotkey(name);
         true
     }
 }
 
+/// Combinations of the same key with other modifiers that no app has registered, by name.
+fn free_alternatives(modifiers: UINT, key: UINT, name: &str) -> Vec<String> {
+    let key_name = name.rsplit('+').next().unwrap_or(name).trim();
+    ALTERNATIVE_MODIFIERS
+        .iter()
+        .filter(|(alternative, _)| *alternative as UINT != modifiers)
+        .filter(|(alternative, _)| unsafe {
+            let free = RegisterHotKey(null_mut(), PROBE_HOTKEY_ID, *alternative as UINT | MOD_NOREPEAT as UINT, key) != 0;
+            if free {
+                UnregisterHotKey(null_mut(), PROBE_HOTKEY_ID);
+            }
+            free
+        })
+        .take(MAX_ALTERNATIVES)
+        .map(|(_, prefix)| format!("{}+{}", prefix, key_name))
+        .collect()
+}
+
+/// Claims Enter and Esc while a panel is open, so answering it doesn't also type into the focused app.
+pub fn register_panel_hotkeys() {
+    unsafe {
+        RegisterHotKey(null_mut(), PANEL_ACCEPT_HOTKEY_ID, 0, VK_RETURN as UINT);
+        RegisterHotKey(null_mut(), PANEL_CANCEL_HOTKEY_ID, 0, VK_ESCAPE as UINT);
+    }
+}
+
+/// Claims Esc while the menu is shown, so it closes the menu instead of reaching the focused app,
+/// and with `number_keys` also 1-9 for selecting segments. Each is claimed both bare and with
+/// `modifiers` (the trigger's), since those are often still held when the menu opens.
+pub fn register_menu_hotkeys(number_keys: bool, modifiers: UINT) {
+    unsafe {
+        RegisterHotKey(null_mut(), DISMISS_HOTKEY_ID, 0, VK_ESCAPE as UINT);
+        if modifiers != 0 {
+            RegisterHotKey(null_mut(), HELD_DISMISS_HOTKEY_ID, modifiers, VK_ESCAPE as UINT);
+        }
+        if number_keys {
+            for digit in 1..=9 {
+                // Digit virtual-key codes match their ASCII values
+                let key = ('0' as i32 + digit) as UINT;
+                RegisterHotKey(null_mut(), NUMBER_HOTKEY_BASE_ID + digit - 1, 0, key);
+                if modifiers != 0 {
+                    RegisterHotKey(null_mut(), HELD_NUMBER_HOTKEY_BASE_ID + digit - 1, modifiers, key);
+                }
+            }
+        }
+    }
+}
+
+pub fn unregister_menu_hotkeys() {
+    unsafe {
+        UnregisterHotKey(null_mut(), DISMISS_HOTKEY_ID);
+        UnregisterHotKey(null_mut(), HELD_DISMISS_HOTKEY_ID);
+        for digit in 1..=9 {
+            UnregisterHotKey(null_mut(), NUMBER_HOTKEY_BASE_ID + digit - 1);
+            UnregisterHotKey(null_mut(), HELD_NUMBER_HOTKEY_BASE_ID + digit - 1);
+        }
+    }
+}
+
+/// Registers the pause hotkey, which `unregister_hotkey` leaves alone so a paused overlay can be resumed.
+pub fn register_pause_hotkey(hotkey: Option<&Hotkey>) {
+    if let Some(hotkey) = hotkey {
+        register(PAUSE_HOTKEY_ID, hotkey.modifiers, hotkey.key, &hotkey.name);
+    }
+}
+
+pub fn unregister_pause_hotkey() {
+    unsafe {
+        UnregisterHotKey(null_mut(), PAUSE_HOTKEY_ID);
+    }
+}
+
+pub fn unregister_panel_hotkeys() {
+    unsafe {
+        UnregisterHotKey(null_mut(), PANEL_ACCEPT_HOTKEY_ID);
+        UnregisterHotKey(null_mut(), PANEL_CANCEL_HOTKEY_ID);
+    }
+}
+
 pub fn unregister_hotkey() {
+    status::clear_hotkeys();
+    mouse::bind(None);
+    trigger_hook::unregister_trigger();
     unsafe {
         UnregisterHotKey(null_mut(), WM_HOTKEY_ID);
+        UnregisterHotKey(null_mut(), DIAGNOSTICS_HOTKEY_ID);
+        UnregisterHotKey(null_mut(), RECENT_HOTKEY_ID);
+        UnregisterHotKey(null_mut(), SWITCHER_HOTKEY_ID);
+        UnregisterHotKey(null_mut(), CLIPBOARD_HOTKEY_ID);
+        UnregisterHotKey(null_mut(), RECENT_FILES_HOTKEY_ID);
+        UnregisterHotKey(null_mut(), PROFILES_HOTKEY_ID);
+        UnregisterHotKey(null_mut(), REPEAT_HOTKEY_ID);
+        UnregisterHotKey(null_mut(), HUD_HOTKEY_ID);
+        UnregisterHotKey(null_mut(), DEBUG_HOTKEY_ID);
     }
 }
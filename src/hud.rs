@@ -0,0 +1,297 @@
+// Always-on heads-up display: small widgets such as a clock and CPU usage, drawn in a corner of the screen
+// on a window of its own, which the renderer draws to with a second swapchain and text pipeline
+
+use winapi::shared::minwindef::FILETIME;
+use winapi::shared::windef::HWND;
+use winapi::um::pdh::*;
+use winapi::um::processthreadsapi::GetSystemTimes;
+use winapi::um::wingdi::RGB;
+use winapi::um::winuser::{SetLayeredWindowAttributes, LWA_ALPHA, LWA_COLORKEY};
+use std::mem::{size_of, zeroed};
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use crate::config::{Anchor, HudConfig, HudWidget};
+use crate::render::Renderer;
+use crate::text::{TextBatch, TEXT_BACKGROUND};
+use crate::window::{create_overlay_window, keep_topmost, place_window, to_wstring};
+use crate::{diagnostics, template};
+
+/// How often the readings are refreshed and the HUD redrawn.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+const HUD_WIDTH: u32 = 150;
+const ROW_HEIGHT: f32 = 20.0;
+const PADDING: f32 = 6.0;
+
+const LABEL_COLOR: [f32; 4] = [0.55, 0.55, 0.6, 1.0];
+const VALUE_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+/// 3D engine utilization of every process on every GPU, summed over the counter's instances.
+const GPU_COUNTER: &str = "\\GPU Engine(*engtype_3D)\\Utilization Percentage";
+/// PDH_MORE_DATA, returned while the buffer for a counter array is too small.
+const PDH_MORE_DATA: PDH_STATUS = 0x8000_07D2_u32 as PDH_STATUS;
+
+/// Flipped by the HUD hotkey; the main loop shows or hides the HUD to match.
+static SHOWN: AtomicBool = AtomicBool::new(false);
+
+pub fn toggle() {
+    SHOWN.fetch_xor(true, Ordering::SeqCst);
+}
+
+pub fn set_shown(shown: bool) {
+    SHOWN.store(shown, Ordering::SeqCst);
+}
+
+pub fn is_shown() -> bool {
+    SHOWN.load(Ordering::SeqCst)
+}
+
+/// The HUD's window, widgets and the readings they show.
+pub struct Hud {
+    widgets: Vec<HudWidget>,
+    corner: Anchor,
+    hwnd: HWND, // Created the first time the HUD is shown
+    attached: bool, // Whether the renderer has a surface on the window
+    cpu: CpuUsage,
+    gpu: Option<GpuUsage>, // None when the performance counter can't be opened
+    lines: Vec<(&'static str, String)>, // Label and value of each widget, as last sampled
+    sampled_at: Option<Instant>, // None until the first sample after showing, which also draws at once
+    frames: u32, // Menu frames since the last sample
+}
+
+impl Hud {
+    pub fn new(config: &HudConfig) -> Self {
+        set_shown(config.enabled);
+        Self {
+            widgets: config.widgets.clone(),
+            corner: config.corner,
+            hwnd: null_mut(),
+            attached: false,
+            cpu: CpuUsage::new(),
+            gpu: None,
+            lines: Vec::new(),
+            sampled_at: None,
+            frames: 0,
+        }
+    }
+
+    /// Applies reloaded settings; a shown HUD is resized and moved to its corner.
+    pub fn configure(&mut self, config: &HudConfig) {
+        self.widgets = config.widgets.clone();
+        self.corner = config.corner;
+        if self.attached {
+            self.place();
+            self.sampled_at = None;
+            if self.widgets.contains(&HudWidget::Gpu) && self.gpu.is_none() {
+                self.gpu = GpuUsage::open();
+            }
+        }
+    }
+
+    /// Counts a frame of the menu for the FPS widget.
+    pub fn count_frame(&mut self) {
+        self.frames += 1;
+    }
+
+    /// Shows or hides the HUD after its hotkey, and redraws it when the readings are due.
+    pub fn update(&mut self, renderer: &mut Renderer) {
+        if is_shown() != self.attached {
+            if is_shown() {
+                self.show(renderer);
+            } else {
+                self.hide(renderer);
+            }
+        }
+        if !self.attached || self.sampled_at.map_or(false, |at| at.elapsed() < SAMPLE_INTERVAL) {
+            return;
+        }
+
+        self.sample();
+        keep_topmost(self.hwnd);
+        if let Err(e) = renderer.render_hud(self) {
+            diagnostics::report_error(format!("Drawing the HUD failed: {}", e));
+            set_shown(false);
+            self.hide(renderer);
+        }
+    }
+
+    fn show(&mut self, renderer: &mut Renderer) {
+        if self.hwnd.is_null() {
+            let (width, height) = self.size();
+            self.hwnd = create_overlay_window("Radial Menu HUD", width, height);
+        }
+        self.place();
+        if let Err(e) = renderer.attach_hud(self.hwnd) {
+            diagnostics::report_error(format!("Failed to show the HUD: {}", e));
+            set_shown(false);
+            return;
+        }
+        // The GPU counter only runs while the HUD is up; collecting it costs more than the rest together
+        if self.widgets.contains(&HudWidget::Gpu) && self.gpu.is_none() {
+            self.gpu = GpuUsage::open();
+        }
+        unsafe {
+            SetLayeredWindowAttributes(self.hwnd, RGB(255, 0, 255), 0, LWA_COLORKEY);
+        }
+        self.attached = true;
+        self.sampled_at = None;
+        self.frames = 0;
+    }
+
+    /// Forgets the surface of a renderer that was replaced after a failure; the next update attaches to the new one.
+    pub fn renderer_replaced(&mut self) {
+        self.attached = false;
+    }
+
+    /// Takes the HUD off a renderer about to be cleaned up, e.g. while the overlay is paused. It shows again on
+    /// the next renderer once that is updated.
+    pub fn detach(&mut self, renderer: &mut Renderer) {
+        if self.attached {
+            self.hide(renderer);
+        }
+    }
+
+    fn hide(&mut self, renderer: &mut Renderer) {
+        renderer.detach_hud();
+        if !self.hwnd.is_null() {
+            unsafe {
+                SetLayeredWindowAttributes(self.hwnd, 0, 0, LWA_ALPHA);
+            }
+        }
+        self.gpu = None;
+        self.attached = false;
+    }
+
+    fn place(&self) {
+        let (width, height) = self.size();
+        place_window(self.hwnd, width, height, self.corner);
+    }
+
+    /// Window size that fits a row per widget.
+    fn size(&self) -> (u32, u32) {
+        let rows = self.widgets.len().max(1) as f32;
+        (HUD_WIDTH, (rows * ROW_HEIGHT + PADDING * 2.0).ceil() as u32)
+    }
+
+    fn sample(&mut self) {
+        let elapsed = self.sampled_at.map_or(SAMPLE_INTERVAL, |at| at.elapsed()).as_secs_f32();
+        let fps = self.frames as f32 / elapsed;
+        let cpu = self.cpu.sample();
+        let gpu = self.gpu.as_mut().and_then(GpuUsage::sample);
+        let percent = |value: Option<f32>| value.map_or_else(|| "--".to_string(), |value| format!("{:.0}%", value));
+
+        self.lines = self
+            .widgets
+            .iter()
+            .map(|widget| match widget {
+                HudWidget::Clock => ("Time", template::expand("{time}")),
+                HudWidget::Cpu => ("CPU", percent(cpu)),
+                HudWidget::Gpu => ("GPU", percent(gpu)),
+                HudWidget::Fps => ("FPS", format!("{:.0}", fps)),
+            })
+            .collect();
+        self.sampled_at = Some(Instant::now());
+        self.frames = 0;
+    }
+
+    /// Lays out the widgets as label and value rows filling the HUD window.
+    pub fn layout(&self, batch: &mut TextBatch) {
+        let (width, height) = batch.size();
+        batch.rect(0.0, 0.0, width, height, TEXT_BACKGROUND);
+        let mut y = PADDING;
+        for (label, value) in self.lines.iter() {
+            batch.text(PADDING, y, label, LABEL_COLOR);
+            let (value_width, _) = batch.measure(value);
+            batch.text(width - PADDING - value_width, y, value, VALUE_COLOR);
+            y += ROW_HEIGHT;
+        }
+    }
+}
+
+/// Busy share of all cores between samples, from the system's idle, kernel and user times.
+struct CpuUsage {
+    previous: Option<(u64, u64)>, // Idle and total time at the last sample, in 100 ns units
+}
+
+impl CpuUsage {
+    fn new() -> Self {
+        Self { previous: None }
+    }
+
+    /// Percentage since the last call; None the first time.
+    fn sample(&mut self) -> Option<f32> {
+        let (mut idle, mut kernel, mut user): (FILETIME, FILETIME, FILETIME) = unsafe { zeroed() };
+        if unsafe { GetSystemTimes(&mut idle, &mut kernel, &mut user) } == 0 {
+            return None;
+        }
+        let ticks = |time: FILETIME| ((time.dwHighDateTime as u64) << 32) | time.dwLowDateTime as u64;
+        // Kernel time includes the idle time
+        let current = (ticks(idle), ticks(kernel) + ticks(user));
+        let usage = self.previous.and_then(|(idle, total)| {
+            let total = current.1.checked_sub(total)?;
+            let idle = current.0.checked_sub(idle)?;
+            if total == 0 {
+                return None;
+            }
+            Some((1.0 - idle as f32 / total as f32) * 100.0)
+        });
+        self.previous = Some(current);
+        usage
+    }
+}
+
+/// Performance counter query for the GPU's 3D engines, closed when dropped.
+struct GpuUsage {
+    query: PDH_HQUERY,
+    counter: PDH_HCOUNTER,
+}
+
+impl GpuUsage {
+    /// None on systems without the GPU counters, e.g. before Windows 10 1709.
+    fn open() -> Option<Self> {
+        let mut query: PDH_HQUERY = null_mut();
+        if unsafe { PdhOpenQueryW(null_mut(), 0, &mut query) } != 0 {
+            return None;
+        }
+        let mut counter: PDH_HCOUNTER = null_mut();
+        let path = to_wstring(GPU_COUNTER);
+        if unsafe { PdhAddEnglishCounterW(query, path.as_ptr(), 0, &mut counter) } != 0 {
+            unsafe { PdhCloseQuery(query) };
+            return None;
+        }
+        // Rates need two collections; the first sample then already has a value
+        unsafe { PdhCollectQueryData(query) };
+        Some(Self { query, counter })
+    }
+
+    fn sample(&mut self) -> Option<f32> {
+        unsafe {
+            if PdhCollectQueryData(self.query) != 0 {
+                return None;
+            }
+            let (mut buffer_size, mut item_count) = (0u32, 0u32);
+            let status = PdhGetFormattedCounterArrayW(self.counter, PDH_FMT_DOUBLE, &mut buffer_size, &mut item_count, null_mut());
+            if status != PDH_MORE_DATA {
+                return None;
+            }
+            // The items are followed by their instance names in the same buffer
+            let mut buffer = vec![0u64; buffer_size as usize / size_of::<u64>() + 1];
+            let items = buffer.as_mut_ptr() as *mut PDH_FMT_COUNTERVALUE_ITEM_W;
+            if PdhGetFormattedCounterArrayW(self.counter, PDH_FMT_DOUBLE, &mut buffer_size, &mut item_count, items) != 0 {
+                return None;
+            }
+            let total: f64 = (0..item_count as usize)
+                .map(|index| &(*items.add(index)).FmtValue)
+                .filter(|value| value.CStatus <= 1) // PDH_CSTATUS_VALID_DATA or PDH_CSTATUS_NEW_DATA
+                .map(|value| *value.u.doubleValue())
+                .sum();
+            Some(total.min(100.0) as f32)
+        }
+    }
+}
+
+impl Drop for GpuUsage {
+    fn drop(&mut self) {
+        unsafe { PdhCloseQuery(self.query) };
+    }
+}
@@ -0,0 +1,580 @@
+// Icons drawn in the middle of segments, such as the app that opens a recent document, taken from the shell
+// and drawn with their own pipeline from a small atlas holding one cell per segment
+
+use ash::{vk, Device, Instance};
+use winapi::shared::windef::{HDC, HGDIOBJ, HICON};
+use winapi::shared::winerror::SUCCEEDED;
+use winapi::um::combaseapi::{CoInitializeEx, CoUninitialize};
+use winapi::um::objbase::COINIT_APARTMENTTHREADED;
+use winapi::um::shellapi::{SHGetFileInfoW, SHFILEINFOW, SHGFI_ICON, SHGFI_LARGEICON};
+use winapi::um::wingdi::*;
+use winapi::um::winuser::{DestroyIcon, DrawIconEx, DI_NORMAL};
+use std::collections::BTreeMap;
+use std::mem::{size_of, zeroed};
+use std::os::raw::c_void;
+use std::ptr::null_mut;
+use std::sync::{Arc, Mutex};
+use crate::menu::MAX_SEGMENTS;
+use crate::overlay::OverlayContent;
+use crate::memory::{self, Allocation, Resource};
+use crate::render::{create_quad_pipeline, VertexRing};
+use crate::rings::{MenuLayout, UNIT_PIXELS};
+use crate::window::to_wstring;
+
+/// Side of an icon in the atlas and on screen, in pixels; the shell's large icons are this size.
+pub const ICON_SIZE: u32 = 32;
+const ATLAS_WIDTH: u32 = ICON_SIZE * MAX_SEGMENTS as u32;
+const VERTICES_PER_QUAD: usize = 6;
+/// One quad per segment. Unused quads are zeroed so they rasterize nothing, like the text's.
+const VERTEX_CAPACITY: usize = MAX_SEGMENTS * VERTICES_PER_QUAD;
+
+/// BGRA pixels of an icon, top row first, with alpha 0 where it is transparent.
+pub type IconPixels = Arc<Vec<u32>>;
+
+/// Icons by path, None for paths without one, so the shell is asked once per file.
+static CACHE: Mutex<BTreeMap<String, Option<IconPixels>>> = Mutex::new(BTreeMap::new());
+
+/// The icon the shell shows for a file, folder or program: a document gets its associated app's icon.
+pub fn load(path: &str) -> Option<IconPixels> {
+    if let Some(icon) = CACHE.lock().unwrap().get(path) {
+        return icon.clone();
+    }
+    let icon = extract(path).map(Arc::new);
+    CACHE.lock().unwrap().insert(path.to_string(), icon.clone());
+    icon
+}
+
+fn extract(path: &str) -> Option<Vec<u32>> {
+    let path_w = to_wstring(path);
+    unsafe {
+        // The shell wants COM on the calling thread; already initialized is fine, the call is balanced below
+        let initialized = SUCCEEDED(CoInitializeEx(null_mut(), COINIT_APARTMENTTHREADED));
+        let mut info: SHFILEINFOW = zeroed();
+        let found = SHGetFileInfoW(
+            path_w.as_ptr(),
+            0,
+            &mut info,
+            size_of::<SHFILEINFOW>() as u32,
+            SHGFI_ICON | SHGFI_LARGEICON,
+        ) != 0;
+        if initialized {
+            CoUninitialize();
+        }
+        if !found || info.hIcon.is_null() {
+            return None;
+        }
+        let pixels = rasterize(info.hIcon);
+        DestroyIcon(info.hIcon);
+        pixels
+    }
+}
+
+/// Draws an icon on black and on white; where the two differ the icon is see-through, which recovers the
+/// alpha of both old masked icons and alpha-blended ones.
+unsafe fn rasterize(icon: HICON) -> Option<Vec<u32>> {
+    let on_black = draw_on(icon, 0x0000_0000)?;
+    let on_white = draw_on(icon, 0x00FF_FFFF)?;
+    let pixels = on_black
+        .iter()
+        .zip(&on_white)
+        .map(|(&black, &white)| {
+            let alpha = 255 - (((white >> 8) & 0xFF) as i32 - ((black >> 8) & 0xFF) as i32).clamp(0, 255) as u32;
+            if alpha == 0 {
+                return 0;
+            }
+            // Drawn on black the colors are premultiplied; undo that
+            let channel = |shift: u32| (((black >> shift) & 0xFF) * 255 / alpha).min(255) << shift;
+            (alpha << 24) | channel(16) | channel(8) | channel(0)
+        })
+        .collect();
+    Some(pixels)
+}
+
+/// The icon drawn at ICON_SIZE over a solid background, as BGRA rows top first.
+unsafe fn draw_on(icon: HICON, background: u32) -> Option<Vec<u32>> {
+    let dc: HDC = CreateCompatibleDC(null_mut());
+    if dc.is_null() {
+        return None;
+    }
+    let mut bitmap_info: BITMAPINFO = zeroed();
+    bitmap_info.bmiHeader.biSize = size_of::<BITMAPINFOHEADER>() as u32;
+    bitmap_info.bmiHeader.biWidth = ICON_SIZE as i32;
+    bitmap_info.bmiHeader.biHeight = -(ICON_SIZE as i32); // Top-down rows
+    bitmap_info.bmiHeader.biPlanes = 1;
+    bitmap_info.bmiHeader.biBitCount = 32;
+    bitmap_info.bmiHeader.biCompression = BI_RGB;
+
+    let mut bits: *mut c_void = null_mut();
+    let bitmap = CreateDIBSection(dc, &bitmap_info, DIB_RGB_COLORS, &mut bits, null_mut(), 0);
+    if bitmap.is_null() || bits.is_null() {
+        DeleteDC(dc);
+        return None;
+    }
+    let old_bitmap = SelectObject(dc, bitmap as HGDIOBJ);
+
+    let count = (ICON_SIZE * ICON_SIZE) as usize;
+    let pixels = std::slice::from_raw_parts_mut(bits as *mut u32, count);
+    pixels.fill(background);
+    let drawn = DrawIconEx(dc, 0, 0, icon, ICON_SIZE as i32, ICON_SIZE as i32, 0, null_mut(), DI_NORMAL) != 0;
+    GdiFlush();
+    let result = drawn.then(|| pixels.to_vec());
+
+    SelectObject(dc, old_bitmap);
+    DeleteObject(bitmap as HGDIOBJ);
+    DeleteDC(dc);
+    result
+}
+
+/// Loads the icons of the visible items, None for items without one. Called with the items' text.
+pub fn item_icons(overlay_content: &OverlayContent) -> Vec<Option<IconPixels>> {
+    overlay_content
+        .items
+        .iter()
+        .map(|item| item.icon.as_deref().and_then(load))
+        .collect()
+}
+
+/// An icon placed for one frame, centered on a point in window pixels.
+pub struct PlacedIcon {
+    pub segment: usize,
+    pub pixels: IconPixels,
+    pub center: (f32, f32),
+}
+
+/// Places each item's icon in the middle of its segment, for the revealed rings only.
+pub fn layout_icons(overlay_content: &OverlayContent, layout: &MenuLayout, extent: vk::Extent2D) -> Vec<PlacedIcon> {
+    let (width, height) = (extent.width as f32, extent.height as f32);
+    overlay_content
+        .item_icons
+        .iter()
+        .enumerate()
+        .take(MAX_SEGMENTS)
+        .filter_map(|(index, icon)| {
+            let pixels = icon.clone()?;
+            let segment = index as i32;
+            let ring = layout.ring_of(segment)?;
+            // NDC y points down like window pixels
+            let angle = layout.segment_middle(segment)?;
+            let distance = layout.moved_distance(segment, (ring.inner + ring.outer) / 2.0)?;
+            let center = (
+                width / 2.0 + angle.cos() * distance * UNIT_PIXELS,
+                height / 2.0 + angle.sin() * distance * UNIT_PIXELS,
+            );
+            Some(PlacedIcon { segment: index, pixels, center })
+        })
+        .collect()
+}
+
+/// Vertex consumed by shaders/icon.vert.glsl.
+#[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq)]
+struct IconVertex {
+    position: [f32; 2], // NDC, Y pointing down
+    uv: [f32; 2],
+}
+
+/// Vulkan objects for the icons. Each swapchain image gets its own linear, host-visible atlas, written directly
+/// while the frames using the others are still in flight, and vertex buffer.
+pub struct IconRenderer {
+    images: Vec<vk::Image>,
+    memories: Vec<Allocation>,
+    views: Vec<vk::ImageView>,
+    layout: vk::SubresourceLayout, // Where the rows are in each atlas' memory
+    sampler: vk::Sampler,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_sets: Vec<vk::DescriptorSet>,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    vertices: VertexRing<IconVertex>,
+    extent: vk::Extent2D,
+    cells: Vec<Vec<Option<IconPixels>>>, // Icon in each atlas cell, per image, so unchanged cells aren't rewritten
+}
+
+impl IconRenderer {
+    /// Creates the atlases and vertex buffers, both empty, and the icon pipeline.
+    pub fn new(
+        instance: &Instance,
+        device: &Device,
+        physical_device: vk::PhysicalDevice,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+        render_pass: vk::RenderPass,
+        extent: vk::Extent2D,
+        image_count: usize,
+    ) -> Result<Self, String> {
+        let mut images = Vec::with_capacity(image_count);
+        let mut memories = Vec::with_capacity(image_count);
+        let mut views = Vec::with_capacity(image_count);
+        for _ in 0..image_count {
+            let (image, memory) = create_atlas_image(instance, device, physical_device)?;
+            views.push(create_atlas_view(device, image)?);
+            images.push(image);
+            memories.push(memory);
+        }
+        transition_to_general(device, command_pool, queue, &images)?;
+
+        let subresource = vk::ImageSubresource {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level: 0,
+            array_layer: 0,
+        };
+        let layout = unsafe { device.get_image_subresource_layout(images[0], subresource) };
+
+        let sampler = create_sampler(device)?;
+        let descriptor_set_layout = create_descriptor_set_layout(device)?;
+        let (descriptor_pool, descriptor_sets) =
+            create_descriptor_sets(device, descriptor_set_layout, &views, sampler)?;
+        // Vertex input: position, uv
+        let (pipeline_layout, pipeline) = create_quad_pipeline(
+            device,
+            render_pass,
+            extent,
+            "icon",
+            &[vk::Format::R32G32_SFLOAT, vk::Format::R32G32_SFLOAT],
+            descriptor_set_layout,
+            &[],
+        )?;
+        let vertices = VertexRing::new(instance, device, physical_device, VERTEX_CAPACITY, image_count, "icon")?;
+
+        // The atlases are only sampled where a quad is drawn, so they can stay as they are
+        Ok(Self {
+            images,
+            memories,
+            views,
+            layout,
+            sampler,
+            descriptor_set_layout,
+            descriptor_pool,
+            descriptor_sets,
+            pipeline_layout,
+            pipeline,
+            vertices,
+            extent,
+            cells: vec![vec![None; MAX_SEGMENTS]; image_count],
+        })
+    }
+
+    /// Writes a frame's icons into the atlas and vertex buffer of the given swapchain image.
+    pub fn upload(&mut self, device: &Device, image_index: usize, icons: &[PlacedIcon]) -> Result<(), String> {
+        let mut vertices = vec![IconVertex::default(); VERTEX_CAPACITY];
+        let (width, height) = (self.extent.width as f32, self.extent.height as f32);
+        let to_ndc = |x: f32, y: f32| [x / width * 2.0 - 1.0, y / height * 2.0 - 1.0];
+        for icon in icons {
+            let cell = &mut self.cells[image_index][icon.segment];
+            if !cell.as_ref().map_or(false, |pixels| Arc::ptr_eq(pixels, &icon.pixels)) {
+                write_cell(device, self.memories[image_index], self.layout, icon.segment, &icon.pixels)?;
+                *cell = Some(icon.pixels.clone());
+            }
+
+            // Whole pixels keep the icon sharp
+            let half = ICON_SIZE as f32 / 2.0;
+            let (x0, y0) = ((icon.center.0 - half).round(), (icon.center.1 - half).round());
+            let (x1, y1) = (x0 + ICON_SIZE as f32, y0 + ICON_SIZE as f32);
+            let u0 = (icon.segment as u32 * ICON_SIZE) as f32 / ATLAS_WIDTH as f32;
+            let u1 = u0 + ICON_SIZE as f32 / ATLAS_WIDTH as f32;
+            let top_left = IconVertex { position: to_ndc(x0, y0), uv: [u0, 0.0] };
+            let top_right = IconVertex { position: to_ndc(x1, y0), uv: [u1, 0.0] };
+            let bottom_left = IconVertex { position: to_ndc(x0, y1), uv: [u0, 1.0] };
+            let bottom_right = IconVertex { position: to_ndc(x1, y1), uv: [u1, 1.0] };
+            let first = icon.segment * VERTICES_PER_QUAD;
+            vertices[first..first + VERTICES_PER_QUAD]
+                .copy_from_slice(&[top_left, top_right, bottom_left, bottom_left, top_right, bottom_right]);
+        }
+
+        self.vertices.upload(device, image_index, &vertices)
+    }
+
+    /// Records the icon draw into a command buffer inside an active render pass.
+    pub fn record(&self, device: &Device, command_buffer: vk::CommandBuffer, image_index: usize) {
+        unsafe {
+            device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.pipeline);
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline_layout,
+                0,
+                &[self.descriptor_sets[image_index]],
+                &[],
+            );
+        }
+        self.vertices.record_draw(device, command_buffer, image_index);
+    }
+
+    /// Destroys the icon resources. The device must be idle.
+    pub fn destroy(&self, device: &Device) {
+        self.vertices.destroy(device);
+        unsafe {
+            device.destroy_pipeline(self.pipeline, None);
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+            device.destroy_descriptor_pool(self.descriptor_pool, None);
+            device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+            device.destroy_sampler(self.sampler, None);
+            for &view in self.views.iter() {
+                device.destroy_image_view(view, None);
+            }
+            for &image in self.images.iter() {
+                device.destroy_image(image, None);
+            }
+        }
+        for &allocation in self.memories.iter() {
+            memory::free(device, allocation);
+        }
+    }
+}
+
+/// Copies an icon into a segment's cell of an atlas.
+fn write_cell(
+    device: &Device,
+    memory: Allocation,
+    layout: vk::SubresourceLayout,
+    segment: usize,
+    pixels: &[u32],
+) -> Result<(), String> {
+    let size = ICON_SIZE as usize;
+    let data_ptr = memory::mapped(device, &memory).map_err(|e| format!("Failed to map icon memory: {}", e))?;
+    unsafe {
+        for row in 0..size {
+            let row_ptr = data_ptr.add((layout.offset + row as u64 * layout.row_pitch) as usize) as *mut u32;
+            row_ptr.add(segment * size).copy_from_nonoverlapping(pixels[row * size..].as_ptr(), size);
+        }
+    }
+    Ok(())
+}
+
+/// Creates a linear atlas in host-visible memory, so icons are written without a copy.
+fn create_atlas_image(
+    instance: &Instance,
+    device: &Device,
+    physical_device: vk::PhysicalDevice,
+) -> Result<(vk::Image, Allocation), String> {
+    let image_info = vk::ImageCreateInfo::builder()
+        .image_type(vk::ImageType::TYPE_2D)
+        .format(vk::Format::B8G8R8A8_UNORM)
+        .extent(vk::Extent3D {
+            width: ATLAS_WIDTH,
+            height: ICON_SIZE,
+            depth: 1,
+        })
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .tiling(vk::ImageTiling::LINEAR)
+        .usage(vk::ImageUsageFlags::SAMPLED)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+        .initial_layout(vk::ImageLayout::PREINITIALIZED);
+
+    let image = unsafe {
+        device
+            .create_image(&image_info, None)
+            .map_err(|e| format!("Failed to create icon image: {:?}", e))?
+    };
+
+    let mem_requirements = unsafe { device.get_image_memory_requirements(image) };
+    let mem_properties = unsafe { instance.get_physical_device_memory_properties(physical_device) };
+    let memory = memory::allocate(
+        device,
+        mem_properties,
+        mem_requirements,
+        vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        Resource::Linear,
+    )
+    .map_err(|e| format!("Failed to allocate icon memory: {}", e))?;
+
+    unsafe {
+        device
+            .bind_image_memory(image, memory.memory, memory.offset)
+            .map_err(|e| format!("Failed to bind icon memory: {:?}", e))?;
+    }
+
+    Ok((image, memory))
+}
+
+/// Moves the atlases into GENERAL layout once, where the host writes them and the shader samples them.
+fn transition_to_general(
+    device: &Device,
+    command_pool: vk::CommandPool,
+    queue: vk::Queue,
+    images: &[vk::Image],
+) -> Result<(), String> {
+    let allocate_info = vk::CommandBufferAllocateInfo::builder()
+        .command_pool(command_pool)
+        .level(vk::CommandBufferLevel::PRIMARY)
+        .command_buffer_count(1);
+
+    let command_buffer = unsafe {
+        device
+            .allocate_command_buffers(&allocate_info)
+            .map_err(|e| format!("Failed to allocate icon command buffer: {:?}", e))?[0]
+    };
+
+    let subresource_range = vk::ImageSubresourceRange::builder()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .base_mip_level(0)
+        .level_count(1)
+        .base_array_layer(0)
+        .layer_count(1)
+        .build();
+    let barriers: Vec<vk::ImageMemoryBarrier> = images
+        .iter()
+        .map(|&image| {
+            vk::ImageMemoryBarrier::builder()
+                .old_layout(vk::ImageLayout::PREINITIALIZED)
+                .new_layout(vk::ImageLayout::GENERAL)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image)
+                .subresource_range(subresource_range)
+                .src_access_mask(vk::AccessFlags::HOST_WRITE)
+                .dst_access_mask(vk::AccessFlags::SHADER_READ)
+                .build()
+        })
+        .collect();
+
+    let begin_info = vk::CommandBufferBeginInfo::builder()
+        .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+
+    unsafe {
+        device
+            .begin_command_buffer(command_buffer, &begin_info)
+            .map_err(|e| format!("Failed to begin icon command buffer: {:?}", e))?;
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::HOST,
+            vk::PipelineStageFlags::FRAGMENT_SHADER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &barriers,
+        );
+        device
+            .end_command_buffer(command_buffer)
+            .map_err(|e| format!("Failed to end icon command buffer: {:?}", e))?;
+
+        let command_buffers = [command_buffer];
+        let submit_info = vk::SubmitInfo::builder()
+            .command_buffers(&command_buffers)
+            .build();
+        device
+            .queue_submit(queue, &[submit_info], vk::Fence::null())
+            .map_err(|e| format!("Failed to submit icon layout change: {:?}", e))?;
+        device
+            .queue_wait_idle(queue)
+            .map_err(|e| format!("Failed to wait for icon layout change: {:?}", e))?;
+        device.free_command_buffers(command_pool, &command_buffers);
+    }
+
+    Ok(())
+}
+
+fn create_atlas_view(device: &Device, image: vk::Image) -> Result<vk::ImageView, String> {
+    let create_info = vk::ImageViewCreateInfo::builder()
+        .image(image)
+        .view_type(vk::ImageViewType::TYPE_2D)
+        .format(vk::Format::B8G8R8A8_UNORM)
+        .subresource_range(
+            vk::ImageSubresourceRange::builder()
+                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                .base_mip_level(0)
+                .level_count(1)
+                .base_array_layer(0)
+                .layer_count(1)
+                .build(),
+        );
+
+    unsafe {
+        device
+            .create_image_view(&create_info, None)
+            .map_err(|e| format!("Failed to create icon image view: {:?}", e))
+    }
+}
+
+/// Icons are drawn at their own size, so nearest filtering copies them pixel for pixel.
+fn create_sampler(device: &Device) -> Result<vk::Sampler, String> {
+    let sampler_info = vk::SamplerCreateInfo::builder()
+        .mag_filter(vk::Filter::NEAREST)
+        .min_filter(vk::Filter::NEAREST)
+        .mipmap_mode(vk::SamplerMipmapMode::NEAREST)
+        .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .max_lod(0.0);
+
+    unsafe {
+        device
+            .create_sampler(&sampler_info, None)
+            .map_err(|e| format!("Failed to create icon sampler: {:?}", e))
+    }
+}
+
+fn create_descriptor_set_layout(device: &Device) -> Result<vk::DescriptorSetLayout, String> {
+    let sampler_binding = vk::DescriptorSetLayoutBinding::builder()
+        .binding(0)
+        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(1)
+        .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+        .build();
+
+    let bindings = [sampler_binding];
+    let layout_info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(&bindings);
+
+    unsafe {
+        device
+            .create_descriptor_set_layout(&layout_info, None)
+            .map_err(|e| format!("Failed to create icon descriptor set layout: {:?}", e))
+    }
+}
+
+/// Allocates a descriptor set per atlas.
+fn create_descriptor_sets(
+    device: &Device,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    views: &[vk::ImageView],
+    sampler: vk::Sampler,
+) -> Result<(vk::DescriptorPool, Vec<vk::DescriptorSet>), String> {
+    let pool_sizes = [vk::DescriptorPoolSize::builder()
+        .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(views.len() as u32)
+        .build()];
+
+    let pool_info = vk::DescriptorPoolCreateInfo::builder()
+        .pool_sizes(&pool_sizes)
+        .max_sets(views.len() as u32);
+
+    let descriptor_pool = unsafe {
+        device
+            .create_descriptor_pool(&pool_info, None)
+            .map_err(|e| format!("Failed to create icon descriptor pool: {:?}", e))?
+    };
+
+    let layouts = vec![descriptor_set_layout; views.len()];
+    let alloc_info = vk::DescriptorSetAllocateInfo::builder()
+        .descriptor_pool(descriptor_pool)
+        .set_layouts(&layouts);
+
+    let descriptor_sets = unsafe {
+        device
+            .allocate_descriptor_sets(&alloc_info)
+            .map_err(|e| format!("Failed to allocate icon descriptor sets: {:?}", e))?
+    };
+
+    for (&descriptor_set, &view) in descriptor_sets.iter().zip(views) {
+        let image_info = vk::DescriptorImageInfo::builder()
+            .sampler(sampler)
+            .image_view(view)
+            .image_layout(vk::ImageLayout::GENERAL);
+
+        let descriptor_write = vk::WriteDescriptorSet::builder()
+            .dst_set(descriptor_set)
+            .dst_binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(std::slice::from_ref(&image_info));
+
+        unsafe {
+            device.update_descriptor_sets(&[descriptor_write.build()], &[]);
+        }
+    }
+
+    Ok((descriptor_pool, descriptor_sets))
+}
+
@@ -3,10 +3,18 @@
 use winapi::um::winuser::*;
 use std::ptr::null_mut;
 use std::mem::zeroed;
+use std::sync::atomic::{AtomicBool, Ordering};
 use crate::overlay::OverlayContent;
-use crate::hotkey::WM_HOTKEY_ID;
+use crate::executor::Executor;
+use crate::hotkey::{WM_HOTKEY_ID, DIAGNOSTICS_HOTKEY_ID, PANEL_ACCEPT_HOTKEY_ID, PANEL_CANCEL_HOTKEY_ID, RECENT_HOTKEY_ID, SWITCHER_HOTKEY_ID};
+use crate::hotkey::{
+    CLIPBOARD_HOTKEY_ID, DEBUG_HOTKEY_ID, DISMISS_HOTKEY_ID, HELD_DISMISS_HOTKEY_ID, HELD_NUMBER_HOTKEY_BASE_ID, HUD_HOTKEY_ID, NUMBER_HOTKEY_BASE_ID, PAUSE_HOTKEY_ID, PROFILES_HOTKEY_ID,
+    RECENT_FILES_HOTKEY_ID, REPEAT_HOTKEY_ID,
+};
+use crate::provider::{MenuContext, Page};
+use crate::{animation, debug_panel, diagnostics, hud, panel, pause, picker, settings, suppress, toast};
 
-pub fn process_input(overlay_content: &mut OverlayContent) -> bool {
+pub fn process_input(overlay_content: &mut OverlayContent, executor: &Executor) -> bool {
     let mut msg: MSG = unsafe { zeroed() };
 
     unsafe {
@@ -20,9 +28,75 @@ pub fn process_input(overlay_content: &mut OverlayContent) -> bool {
                 WM_HOTKEY => {
                     println!("WM_HOTKEY received: wParam = {}", msg.wParam);
                     if msg.wParam as i32 == WM_HOTKEY_ID {
-                        // Show the overlay
-                        println!("Showing overlay");
-                        overlay_content.visible = true;
+                        if picker::is_active() {
+                            // The trigger picks the color instead of opening the menu over the loupe
+                            picker::accept();
+                        } else if overlay_content.visible && overlay_content.latched {
+                            // A press while the menu stays open closes it, selecting what is under the cursor
+                            println!("Hiding overlay");
+                            overlay_content.visible = false;
+                        } else {
+                            // Show the overlay
+                            println!("Showing overlay");
+                            open_menu(overlay_content, None);
+                        }
+                    } else if msg.wParam as i32 == RECENT_HOTKEY_ID {
+                        open_menu(overlay_content, Some(Page::Recent));
+                    } else if msg.wParam as i32 == SWITCHER_HOTKEY_ID {
+                        open_menu(overlay_content, Some(Page::Windows));
+                    } else if msg.wParam as i32 == CLIPBOARD_HOTKEY_ID {
+                        open_menu(overlay_content, Some(Page::Clipboard));
+                    } else if msg.wParam as i32 == RECENT_FILES_HOTKEY_ID {
+                        open_menu(overlay_content, Some(Page::Files));
+                    } else if msg.wParam as i32 == PAUSE_HOTKEY_ID {
+                        pause::request(pause::Request::Toggle);
+                    } else if msg.wParam as i32 == PROFILES_HOTKEY_ID {
+                        open_menu(overlay_content, Some(Page::Profiles));
+                    } else if msg.wParam as i32 == DISMISS_HOTKEY_ID || msg.wParam as i32 == HELD_DISMISS_HOTKEY_ID {
+                        if overlay_content.visible {
+                            println!("Overlay dismissed with Esc");
+                            overlay_content.dismiss();
+                        }
+                    } else if (NUMBER_HOTKEY_BASE_ID..NUMBER_HOTKEY_BASE_ID + 9).contains(&(msg.wParam as i32))
+                        || (HELD_NUMBER_HOTKEY_BASE_ID..HELD_NUMBER_HOTKEY_BASE_ID + 9).contains(&(msg.wParam as i32))
+                    {
+                        // Selecting and hiding runs the segment like releasing over it would
+                        let segment = if msg.wParam as i32 >= HELD_NUMBER_HOTKEY_BASE_ID {
+                            msg.wParam as i32 - HELD_NUMBER_HOTKEY_BASE_ID
+                        } else {
+                            msg.wParam as i32 - NUMBER_HOTKEY_BASE_ID
+                        };
+                        if overlay_content.visible
+                            && segment < overlay_content.segment_count()
+                            && overlay_content.is_segment_enabled(segment)
+                        {
+                            println!("Segment {} selected with its number key", segment);
+                            overlay_content.selected_segment = Some(segment);
+                            overlay_content.selected_edge = None;
+                            overlay_content.visible = false;
+                        }
+                    } else if msg.wParam as i32 == REPEAT_HOTKEY_ID {
+                        if !executor.repeat_last() {
+                            toast::push("Nothing run yet");
+                        }
+                    } else if msg.wParam as i32 == HUD_HOTKEY_ID {
+                        hud::toggle();
+                    } else if msg.wParam as i32 == DEBUG_HOTKEY_ID {
+                        debug_panel::toggle();
+                    } else if msg.wParam as i32 == DIAGNOSTICS_HOTKEY_ID {
+                        diagnostics::save_bundle_and_notify();
+                    } else if msg.wParam as i32 == PANEL_ACCEPT_HOTKEY_ID {
+                        if picker::is_active() {
+                            picker::accept();
+                        } else {
+                            panel::answer(true);
+                        }
+                    } else if msg.wParam as i32 == PANEL_CANCEL_HOTKEY_ID {
+                        if picker::is_active() {
+                            picker::cancel();
+                        } else {
+                            panel::answer(false);
+                        }
                     }
                 }
                 _ => {
@@ -32,5 +106,47 @@ pub fn process_input(overlay_content: &mut OverlayContent) -> bool {
             }
         }
     }
+    dismiss_on_click_outside(overlay_content);
     true
-}
\ No newline at end of file
+}
+
+/// Shows the menu, or a generated page in its place until it closes; a page can't open over the menu, and
+/// neither opens over the settings screen, in an app the overlay is suppressed in or while it is paused.
+/// A generated menu that would come out empty is toasted about instead.
+fn open_menu(overlay_content: &mut OverlayContent, page: Option<Page>) {
+    if overlay_content.visible || settings::is_open() || suppress::blocks_opening() || pause::is_paused() {
+        return;
+    }
+    if let Some(provider) = page.map(Page::provider).or(overlay_content.provider) {
+        if provider.build(&MenuContext::current()).items.is_empty() {
+            toast::push(provider.empty_message());
+            return;
+        }
+    }
+    overlay_content.page = page;
+    overlay_content.visible = true;
+}
+
+/// Left button state at the last check, so a held button counts as one click.
+static LEFT_BUTTON_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// Hides the overlay when the left button is pressed past the outer ring, or opens the settings screen in its
+/// place when it is pressed in the middle. The window is click-through, so the click also reaches whatever is
+/// below. Edge zones are part of the menu and don't dismiss it.
+fn dismiss_on_click_outside(overlay_content: &mut OverlayContent) {
+    let down = unsafe { (GetAsyncKeyState(VK_LBUTTON) as u16 & 0x8000) != 0 };
+    let was_down = LEFT_BUTTON_DOWN.swap(down, Ordering::SeqCst);
+    let pressed = down && !was_down;
+    if !pressed || !overlay_content.visible || overlay_content.selected_edge.is_some() {
+        return;
+    }
+    let layout = animation::posed_layout(overlay_content);
+    if overlay_content.cursor_distance > layout.radius() {
+        println!("Overlay dismissed by a click outside");
+        overlay_content.dismiss();
+    } else if !layout.rings.is_empty() && overlay_content.cursor_distance < layout.inner_radius {
+        println!("Settings opened from the middle of the menu");
+        overlay_content.dismiss();
+        settings::open();
+    }
+}
@@ -0,0 +1,144 @@
+// Named-pipe server that answers commands from the CLI or other tools
+
+use winapi::um::winbase::{CreateNamedPipeW, PIPE_ACCESS_DUPLEX, PIPE_TYPE_BYTE, PIPE_READMODE_BYTE, PIPE_WAIT, PIPE_UNLIMITED_INSTANCES};
+use winapi::um::namedpipeapi::ConnectNamedPipe;
+use winapi::um::fileapi::{ReadFile, WriteFile, FlushFileBuffers};
+use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::winnt::HANDLE;
+use winapi::shared::winerror::ERROR_PIPE_CONNECTED;
+use std::fs::OpenOptions;
+use std::io::{Read, Write};
+use std::ptr::null_mut;
+use std::thread;
+use crate::{diagnostics, pause, remote, replay, shutdown, status, variables};
+use crate::window::to_wstring;
+
+pub const PIPE_NAME: &str = r"\\.\pipe\radial_menu_overlay";
+const BUFFER_SIZE: u32 = 4096;
+
+/// Starts the server thread. Each client sends one command and receives one response.
+pub fn start_server() {
+    thread::spawn(|| loop {
+        let name = to_wstring(PIPE_NAME);
+        let pipe = unsafe {
+            CreateNamedPipeW(
+                name.as_ptr(),
+                PIPE_ACCESS_DUPLEX,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT,
+                PIPE_UNLIMITED_INSTANCES,
+                BUFFER_SIZE,
+                BUFFER_SIZE,
+                0,
+                null_mut(),
+            )
+        };
+        if pipe == INVALID_HANDLE_VALUE {
+            eprintln!("Failed to create IPC pipe. Error code: {}", unsafe { GetLastError() });
+            return;
+        }
+
+        // A client may connect between creation and this call, which is reported as ERROR_PIPE_CONNECTED
+        let connected = unsafe { ConnectNamedPipe(pipe, null_mut()) } != 0
+            || unsafe { GetLastError() } == ERROR_PIPE_CONNECTED;
+        if connected {
+            serve_client(pipe);
+        }
+
+        // Closing after the flush makes the client's read end with EOF
+        unsafe {
+            CloseHandle(pipe);
+        }
+    });
+}
+
+/// Reads a single command from the pipe and writes back the response.
+fn serve_client(pipe: HANDLE) {
+    let mut buffer = [0u8; BUFFER_SIZE as usize];
+    let mut read: u32 = 0;
+    let ok = unsafe { ReadFile(pipe, buffer.as_mut_ptr() as *mut _, BUFFER_SIZE, &mut read, null_mut()) };
+    if ok == 0 {
+        return;
+    }
+
+    let command = String::from_utf8_lossy(&buffer[..read as usize]).trim().to_string();
+    let response = handle_command(&command);
+
+    let mut written: u32 = 0;
+    unsafe {
+        WriteFile(pipe, response.as_ptr() as *const _, response.len() as u32, &mut written, null_mut());
+        FlushFileBuffers(pipe);
+    }
+}
+
+/// Produces the response for a command.
+fn handle_command(command: &str) -> String {
+    match command {
+        "status" => status::to_json(),
+        "health" => status::health_json(),
+        "pair" => match remote::pairing_url() {
+            Some(url) => serde_json::json!({ "url": url }).to_string(),
+            None => serde_json::json!({ "error": "Remote control is disabled" }).to_string(),
+        },
+        "get" => serde_json::json!(variables::snapshot()).to_string(),
+        "pause" | "resume" => {
+            pause::request(if command == "pause" { pause::Request::Pause } else { pause::Request::Resume });
+            serde_json::json!({ "ok": true }).to_string()
+        }
+        "quit" => {
+            shutdown::request("quit command");
+            serde_json::json!({ "ok": true }).to_string()
+        }
+        "diagnostics" => match diagnostics::save_bundle() {
+            Ok(path) => serde_json::json!({ "path": path }).to_string(),
+            Err(e) => serde_json::json!({ "error": e }).to_string(),
+        },
+        "record stop" => match replay::stop_recording() {
+            true => serde_json::json!({ "ok": true }).to_string(),
+            false => serde_json::json!({ "error": "Not recording" }).to_string(),
+        },
+        _ => {
+            // "record <file>" and "replay <file>"; paths should be absolute, the server's directory may differ
+            if let Some(path) = command.strip_prefix("record ") {
+                return json_result(replay::start_recording(path.trim()));
+            }
+            if let Some(path) = command.strip_prefix("replay ") {
+                return json_result(replay::start_replay(path.trim()));
+            }
+            // "set <name> <value>"; the value may contain spaces
+            if let Some(rest) = command.strip_prefix("set ") {
+                if let Some((name, value)) = rest.trim().split_once(' ') {
+                    variables::set(name, value.trim());
+                    return serde_json::json!({ "ok": true }).to_string();
+                }
+                return serde_json::json!({ "error": "Usage: set <name> <value>" }).to_string();
+            }
+            serde_json::json!({ "error": format!("Unknown command: {}", command) }).to_string()
+        }
+    }
+}
+
+fn json_result(result: Result<(), String>) -> String {
+    match result {
+        Ok(()) => serde_json::json!({ "ok": true }).to_string(),
+        Err(e) => serde_json::json!({ "error": e }).to_string(),
+    }
+}
+
+/// Sends a command to the running instance and returns its response.
+pub fn send_command(command: &str) -> Result<String, String> {
+    let mut pipe = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(PIPE_NAME)
+        .map_err(|e| format!("Failed to connect to the running overlay: {:?}", e))?;
+
+    pipe.write_all(command.as_bytes())
+        .map_err(|e| format!("Failed to send command: {:?}", e))?;
+
+    let mut response = String::new();
+    pipe.read_to_string(&mut response)
+        .map_err(|e| format!("Failed to read response: {:?}", e))?;
+
+    Ok(response)
+}
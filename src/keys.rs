@@ -0,0 +1,128 @@
+// Synthesizes keyboard input for key-combination and text actions
+
+use winapi::um::winuser::*;
+use winapi::um::errhandlingapi::GetLastError;
+use std::mem::{size_of, zeroed};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::hotkey::Hotkey;
+
+/// Presses and releases a key combination in the focused application.
+pub fn send_combo(hotkey: &Hotkey) -> Result<(), String> {
+    let mut keys: Vec<u16> = Vec::new();
+    for (modifier, vk) in [(MOD_CONTROL, VK_CONTROL), (MOD_ALT, VK_MENU), (MOD_SHIFT, VK_SHIFT), (MOD_WIN, VK_LWIN)] {
+        if hotkey.modifiers & modifier as u32 != 0 {
+            keys.push(vk as u16);
+        }
+    }
+    keys.push(hotkey.key as u16);
+
+    // Modifiers go down first and come up last
+    let mut inputs: Vec<INPUT> = keys.iter().map(|&vk| key_input(vk, 0)).collect();
+    inputs.extend(keys.iter().rev().map(|&vk| key_input(vk, KEYEVENTF_KEYUP)));
+    send(&mut inputs)
+}
+
+/// Presses and releases an unassigned key. Sent when a hook takes a key pressed with Alt or Win, so releasing the
+/// modifier alone doesn't open the focused app's menu bar or the Start menu.
+pub fn mask_modifier_release() {
+    // 0xE8 is unassigned
+    let mut inputs = [key_input(0xE8, 0), key_input(0xE8, KEYEVENTF_KEYUP)];
+    let _ = send(&mut inputs);
+}
+
+/// Presses and releases a single key, e.g. to replay a tap a hook held back.
+pub fn tap(vk: u16) {
+    let mut inputs = [key_input(vk, 0), key_input(vk, KEYEVENTF_KEYUP)];
+    let _ = send(&mut inputs);
+}
+
+/// Presses keys in order without releasing them, e.g. to replay a press a hook held back ahead of the key that
+/// interrupted it, while the user still holds both.
+pub fn press(vks: &[u16]) {
+    let mut inputs: Vec<INPUT> = vks.iter().map(|&vk| key_input(vk, 0)).collect();
+    let _ = send(&mut inputs);
+}
+
+/// Longest wait for the user to let go of modifier keys before typing anyway.
+const MODIFIER_RELEASE_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Types text into the focused application, independent of the keyboard layout.
+/// Line breaks and tabs are sent as the Enter and Tab keys, which more applications understand.
+pub fn type_text(text: &str) -> Result<(), String> {
+    // A still-held Alt or Ctrl, e.g. from the trigger during dwell selection, would turn characters into shortcuts
+    wait_for_modifiers_released();
+
+    let mut inputs = Vec::new();
+    for c in text.chars() {
+        match c {
+            '\r' => {}
+            '\n' | '\t' => {
+                let vk = if c == '\n' { VK_RETURN } else { VK_TAB } as u16;
+                inputs.push(key_input(vk, 0));
+                inputs.push(key_input(vk, KEYEVENTF_KEYUP));
+            }
+            _ => {
+                // Characters outside the BMP, such as most emoji, are sent as two surrogate units
+                let mut units = [0u16; 2];
+                for &unit in c.encode_utf16(&mut units).iter() {
+                    inputs.push(unicode_input(unit, 0));
+                    inputs.push(unicode_input(unit, KEYEVENTF_KEYUP));
+                }
+            }
+        }
+    }
+    send(&mut inputs)
+}
+
+fn wait_for_modifiers_released() {
+    let start = Instant::now();
+    let held = || {
+        [VK_CONTROL, VK_MENU, VK_SHIFT, VK_LWIN, VK_RWIN]
+            .iter()
+            .any(|&vk| unsafe { GetAsyncKeyState(vk) } as u16 & 0x8000 != 0)
+    };
+    while held() && start.elapsed() < MODIFIER_RELEASE_TIMEOUT {
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn key_input(vk: u16, flags: u32) -> INPUT {
+    let extended = matches!(
+        vk as i32,
+        VK_LEFT | VK_RIGHT | VK_UP | VK_DOWN | VK_INSERT | VK_DELETE | VK_HOME | VK_END | VK_PRIOR | VK_NEXT | VK_LWIN
+    );
+    keyboard_input(vk, 0, if extended { flags | KEYEVENTF_EXTENDEDKEY } else { flags })
+}
+
+fn unicode_input(unit: u16, flags: u32) -> INPUT {
+    keyboard_input(0, unit, flags | KEYEVENTF_UNICODE)
+}
+
+fn keyboard_input(vk: u16, scan: u16, flags: u32) -> INPUT {
+    unsafe {
+        let mut input: INPUT = zeroed();
+        input.type_ = INPUT_KEYBOARD;
+        *input.u.ki_mut() = KEYBDINPUT {
+            wVk: vk,
+            wScan: scan,
+            dwFlags: flags,
+            time: 0,
+            dwExtraInfo: 0,
+        };
+        input
+    }
+}
+
+fn send(inputs: &mut [INPUT]) -> Result<(), String> {
+    if inputs.is_empty() {
+        return Ok(());
+    }
+    let sent = unsafe { SendInput(inputs.len() as u32, inputs.as_mut_ptr(), size_of::<INPUT>() as i32) };
+    if sent as usize == inputs.len() {
+        Ok(())
+    } else {
+        // Input to elevated windows is blocked by UIPI
+        Err(format!("SendInput failed (error {})", unsafe { GetLastError() }))
+    }
+}
@@ -0,0 +1,406 @@
+// Magnified view of the screen around the cursor for the color picker, drawn with its own pipeline
+// from a small texture rewritten every frame
+
+use ash::{vk, Device, Instance};
+use std::mem::size_of;
+use crate::memory::{self, Allocation, Resource};
+use crate::render::create_quad_pipeline;
+
+/// Screen pixels captured on each side, odd so that one of them is in the middle.
+pub const CAPTURE_SIZE: u32 = 15;
+/// Window pixels each captured pixel is drawn as.
+const ZOOM: u32 = 10;
+/// Side of the loupe in window pixels; it is centered in the window.
+pub const LOUPE_PIXELS: f32 = (CAPTURE_SIZE * ZOOM) as f32;
+
+/// Vulkan objects for the loupe. Each swapchain image gets its own linear, host-visible texture,
+/// written directly while the frames using the others are still in flight.
+pub struct LoupeRenderer {
+    images: Vec<vk::Image>,
+    memories: Vec<Allocation>,
+    views: Vec<vk::ImageView>,
+    layout: vk::SubresourceLayout, // Where the rows are in each image's memory
+    sampler: vk::Sampler,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_sets: Vec<vk::DescriptorSet>,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    rect: [f32; 4], // Loupe in NDC: left, top, right, bottom
+    filled: Vec<bool>, // Whether each texture holds a capture; cleared ones draw nothing
+}
+
+impl LoupeRenderer {
+    /// Creates the textures, cleared, and the loupe pipeline.
+    pub fn new(
+        instance: &Instance,
+        device: &Device,
+        physical_device: vk::PhysicalDevice,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+        render_pass: vk::RenderPass,
+        extent: vk::Extent2D,
+        image_count: usize,
+    ) -> Result<Self, String> {
+        let mut images = Vec::with_capacity(image_count);
+        let mut memories = Vec::with_capacity(image_count);
+        let mut views = Vec::with_capacity(image_count);
+        for _ in 0..image_count {
+            let (image, memory) = create_capture_image(instance, device, physical_device)?;
+            views.push(create_capture_view(device, image)?);
+            images.push(image);
+            memories.push(memory);
+        }
+        transition_to_general(device, command_pool, queue, &images)?;
+
+        let subresource = vk::ImageSubresource {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level: 0,
+            array_layer: 0,
+        };
+        let layout = unsafe { device.get_image_subresource_layout(images[0], subresource) };
+
+        let sampler = create_sampler(device)?;
+        let descriptor_set_layout = create_descriptor_set_layout(device)?;
+        let (descriptor_pool, descriptor_sets) =
+            create_descriptor_sets(device, descriptor_set_layout, &views, sampler)?;
+        // The quad's corners come from the vertex index and a push constant with the loupe's rect
+        let push_constant_ranges = [vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::VERTEX,
+            offset: 0,
+            size: size_of::<[f32; 4]>() as u32,
+        }];
+        let (pipeline_layout, pipeline) = create_quad_pipeline(
+            device,
+            render_pass,
+            extent,
+            "loupe",
+            &[],
+            descriptor_set_layout,
+            &push_constant_ranges,
+        )?;
+
+        let half_width = LOUPE_PIXELS / extent.width as f32;
+        let half_height = LOUPE_PIXELS / extent.height as f32;
+        let mut loupe = Self {
+            images,
+            memories,
+            views,
+            layout,
+            sampler,
+            descriptor_set_layout,
+            descriptor_pool,
+            descriptor_sets,
+            pipeline_layout,
+            pipeline,
+            rect: [-half_width, -half_height, half_width, half_height],
+            filled: vec![true; image_count],
+        };
+
+        // Memory contents are undefined until written; start with nothing to draw
+        for image_index in 0..image_count {
+            loupe.upload(device, image_index, None)?;
+        }
+        Ok(loupe)
+    }
+
+    /// Writes a capture of CAPTURE_SIZE x CAPTURE_SIZE BGRA pixels, top row first, into the texture of the
+    /// given swapchain image, or clears it with None so the loupe disappears.
+    pub fn upload(&mut self, device: &Device, image_index: usize, pixels: Option<&[u32]>) -> Result<(), String> {
+        if pixels.is_none() && !self.filled[image_index] {
+            return Ok(());
+        }
+
+        let data_ptr = memory::mapped(device, &self.memories[image_index])
+            .map_err(|e| format!("Failed to map loupe memory: {}", e))?;
+        unsafe {
+            for row in 0..CAPTURE_SIZE as usize {
+                let row_ptr = data_ptr.add((self.layout.offset + row as u64 * self.layout.row_pitch) as usize) as *mut u32;
+                match pixels {
+                    Some(pixels) => {
+                        let start = row * CAPTURE_SIZE as usize;
+                        row_ptr.copy_from_nonoverlapping(pixels[start..].as_ptr(), CAPTURE_SIZE as usize);
+                    }
+                    None => row_ptr.write_bytes(0, CAPTURE_SIZE as usize),
+                }
+            }
+        }
+        self.filled[image_index] = pixels.is_some();
+        Ok(())
+    }
+
+    /// Records the loupe draw into a command buffer inside an active render pass.
+    pub fn record(&self, device: &Device, command_buffer: vk::CommandBuffer, image_index: usize) {
+        let rect: Vec<u8> = self.rect.iter().flat_map(|value| value.to_ne_bytes()).collect();
+        unsafe {
+            device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.pipeline);
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline_layout,
+                0,
+                &[self.descriptor_sets[image_index]],
+                &[],
+            );
+            device.cmd_push_constants(command_buffer, self.pipeline_layout, vk::ShaderStageFlags::VERTEX, 0, &rect);
+            device.cmd_draw(command_buffer, 6, 1, 0, 0);
+        }
+    }
+
+    /// Destroys the loupe resources. The device must be idle.
+    pub fn destroy(&self, device: &Device) {
+        unsafe {
+            device.destroy_pipeline(self.pipeline, None);
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+            device.destroy_descriptor_pool(self.descriptor_pool, None);
+            device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+            device.destroy_sampler(self.sampler, None);
+            for &view in self.views.iter() {
+                device.destroy_image_view(view, None);
+            }
+            for &image in self.images.iter() {
+                device.destroy_image(image, None);
+            }
+        }
+        for &allocation in self.memories.iter() {
+            memory::free(device, allocation);
+        }
+    }
+}
+
+/// Creates a linear texture in host-visible memory, so captures are written without a copy.
+fn create_capture_image(
+    instance: &Instance,
+    device: &Device,
+    physical_device: vk::PhysicalDevice,
+) -> Result<(vk::Image, Allocation), String> {
+    let image_info = vk::ImageCreateInfo::builder()
+        .image_type(vk::ImageType::TYPE_2D)
+        .format(vk::Format::B8G8R8A8_UNORM)
+        .extent(vk::Extent3D {
+            width: CAPTURE_SIZE,
+            height: CAPTURE_SIZE,
+            depth: 1,
+        })
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .tiling(vk::ImageTiling::LINEAR)
+        .usage(vk::ImageUsageFlags::SAMPLED)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+        .initial_layout(vk::ImageLayout::PREINITIALIZED);
+
+    let image = unsafe {
+        device
+            .create_image(&image_info, None)
+            .map_err(|e| format!("Failed to create loupe image: {:?}", e))?
+    };
+
+    let mem_requirements = unsafe { device.get_image_memory_requirements(image) };
+    let mem_properties = unsafe { instance.get_physical_device_memory_properties(physical_device) };
+    let memory = memory::allocate(
+        device,
+        mem_properties,
+        mem_requirements,
+        vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        Resource::Linear,
+    )
+    .map_err(|e| format!("Failed to allocate loupe memory: {}", e))?;
+
+    unsafe {
+        device
+            .bind_image_memory(image, memory.memory, memory.offset)
+            .map_err(|e| format!("Failed to bind loupe memory: {:?}", e))?;
+    }
+
+    Ok((image, memory))
+}
+
+/// Moves the textures into GENERAL layout once, where the host writes them and the shader samples them.
+fn transition_to_general(
+    device: &Device,
+    command_pool: vk::CommandPool,
+    queue: vk::Queue,
+    images: &[vk::Image],
+) -> Result<(), String> {
+    let allocate_info = vk::CommandBufferAllocateInfo::builder()
+        .command_pool(command_pool)
+        .level(vk::CommandBufferLevel::PRIMARY)
+        .command_buffer_count(1);
+
+    let command_buffer = unsafe {
+        device
+            .allocate_command_buffers(&allocate_info)
+            .map_err(|e| format!("Failed to allocate loupe command buffer: {:?}", e))?[0]
+    };
+
+    let subresource_range = vk::ImageSubresourceRange::builder()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .base_mip_level(0)
+        .level_count(1)
+        .base_array_layer(0)
+        .layer_count(1)
+        .build();
+    let barriers: Vec<vk::ImageMemoryBarrier> = images
+        .iter()
+        .map(|&image| {
+            vk::ImageMemoryBarrier::builder()
+                .old_layout(vk::ImageLayout::PREINITIALIZED)
+                .new_layout(vk::ImageLayout::GENERAL)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image)
+                .subresource_range(subresource_range)
+                .src_access_mask(vk::AccessFlags::HOST_WRITE)
+                .dst_access_mask(vk::AccessFlags::SHADER_READ)
+                .build()
+        })
+        .collect();
+
+    let begin_info = vk::CommandBufferBeginInfo::builder()
+        .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+
+    unsafe {
+        device
+            .begin_command_buffer(command_buffer, &begin_info)
+            .map_err(|e| format!("Failed to begin loupe command buffer: {:?}", e))?;
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::HOST,
+            vk::PipelineStageFlags::FRAGMENT_SHADER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &barriers,
+        );
+        device
+            .end_command_buffer(command_buffer)
+            .map_err(|e| format!("Failed to end loupe command buffer: {:?}", e))?;
+
+        let command_buffers = [command_buffer];
+        let submit_info = vk::SubmitInfo::builder()
+            .command_buffers(&command_buffers)
+            .build();
+        device
+            .queue_submit(queue, &[submit_info], vk::Fence::null())
+            .map_err(|e| format!("Failed to submit loupe layout change: {:?}", e))?;
+        device
+            .queue_wait_idle(queue)
+            .map_err(|e| format!("Failed to wait for loupe layout change: {:?}", e))?;
+        device.free_command_buffers(command_pool, &command_buffers);
+    }
+
+    Ok(())
+}
+
+fn create_capture_view(device: &Device, image: vk::Image) -> Result<vk::ImageView, String> {
+    let create_info = vk::ImageViewCreateInfo::builder()
+        .image(image)
+        .view_type(vk::ImageViewType::TYPE_2D)
+        .format(vk::Format::B8G8R8A8_UNORM)
+        .subresource_range(
+            vk::ImageSubresourceRange::builder()
+                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                .base_mip_level(0)
+                .level_count(1)
+                .base_array_layer(0)
+                .layer_count(1)
+                .build(),
+        );
+
+    unsafe {
+        device
+            .create_image_view(&create_info, None)
+            .map_err(|e| format!("Failed to create loupe image view: {:?}", e))
+    }
+}
+
+/// Nearest filtering shows each captured pixel as a sharp square.
+fn create_sampler(device: &Device) -> Result<vk::Sampler, String> {
+    let sampler_info = vk::SamplerCreateInfo::builder()
+        .mag_filter(vk::Filter::NEAREST)
+        .min_filter(vk::Filter::NEAREST)
+        .mipmap_mode(vk::SamplerMipmapMode::NEAREST)
+        .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .max_lod(0.0);
+
+    unsafe {
+        device
+            .create_sampler(&sampler_info, None)
+            .map_err(|e| format!("Failed to create loupe sampler: {:?}", e))
+    }
+}
+
+fn create_descriptor_set_layout(device: &Device) -> Result<vk::DescriptorSetLayout, String> {
+    let sampler_binding = vk::DescriptorSetLayoutBinding::builder()
+        .binding(0)
+        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(1)
+        .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+        .build();
+
+    let bindings = [sampler_binding];
+    let layout_info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(&bindings);
+
+    unsafe {
+        device
+            .create_descriptor_set_layout(&layout_info, None)
+            .map_err(|e| format!("Failed to create loupe descriptor set layout: {:?}", e))
+    }
+}
+
+/// Allocates a descriptor set per texture.
+fn create_descriptor_sets(
+    device: &Device,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    views: &[vk::ImageView],
+    sampler: vk::Sampler,
+) -> Result<(vk::DescriptorPool, Vec<vk::DescriptorSet>), String> {
+    let pool_sizes = [vk::DescriptorPoolSize::builder()
+        .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(views.len() as u32)
+        .build()];
+
+    let pool_info = vk::DescriptorPoolCreateInfo::builder()
+        .pool_sizes(&pool_sizes)
+        .max_sets(views.len() as u32);
+
+    let descriptor_pool = unsafe {
+        device
+            .create_descriptor_pool(&pool_info, None)
+            .map_err(|e| format!("Failed to create loupe descriptor pool: {:?}", e))?
+    };
+
+    let layouts = vec![descriptor_set_layout; views.len()];
+    let alloc_info = vk::DescriptorSetAllocateInfo::builder()
+        .descriptor_pool(descriptor_pool)
+        .set_layouts(&layouts);
+
+    let descriptor_sets = unsafe {
+        device
+            .allocate_descriptor_sets(&alloc_info)
+            .map_err(|e| format!("Failed to allocate loupe descriptor sets: {:?}", e))?
+    };
+
+    for (&descriptor_set, &view) in descriptor_sets.iter().zip(views) {
+        let image_info = vk::DescriptorImageInfo::builder()
+            .sampler(sampler)
+            .image_view(view)
+            .image_layout(vk::ImageLayout::GENERAL);
+
+        let descriptor_write = vk::WriteDescriptorSet::builder()
+            .dst_set(descriptor_set)
+            .dst_binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(std::slice::from_ref(&image_info));
+
+        unsafe {
+            device.update_descriptor_sets(&[descriptor_write.build()], &[]);
+        }
+    }
+
+    Ok((descriptor_pool, descriptor_sets))
+}
+
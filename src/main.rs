@@ -4,116 +4,863 @@ mod render;
 mod input;
 mod overlay;
 mod hotkey;
+mod config;
+mod menu;
+mod dial;
+mod brightness;
+mod action;
+mod fallback;
+mod feedback;
+mod text;
+mod toast;
+mod status;
+mod ipc;
+mod cli;
+mod diagnostics;
+mod tooltip;
+mod condition;
+mod variables;
+mod template;
+mod badge;
+mod validate;
+mod preset;
+mod panel;
+mod keys;
+mod builtin;
+mod adapter;
+mod edges;
+mod remote;
+mod replay;
+mod executor;
+mod rings;
+mod recent;
+mod provider;
+mod switcher;
+mod clipboard;
+mod recent_files;
+mod search;
+mod accessibility;
+mod speech;
+mod settings;
+mod icons;
+mod gui;
+mod mqtt;
+mod mouse;
+mod relative;
+mod gamepad;
+mod focus;
+mod foreground;
+mod shared_texture;
+mod offscreen;
+mod golden;
+mod preview;
+mod hud;
+mod render_thread;
+mod debug_panel;
+mod loupe;
+mod picker;
+mod wake;
+mod shutdown;
+mod crash;
+mod autostart;
+mod suppress;
+mod trigger_hook;
+mod power;
+mod process;
+mod profile_switcher;
+mod pause;
+mod mic;
+mod toggle;
+mod custom_shader;
+mod fill;
+mod animation;
+mod contrast;
+mod duplication;
+mod backdrop;
+mod memory;
 
-use window::create_overlay_window;
-use render::Renderer;
+use window::{create_overlay_window, center_on_cursor, error_box, keep_topmost, place_window, cursor_position, restore_cursor, set_interactive, size_for_menu, warp_cursor_to_center};
+use render::track_cursor;
+use render_thread::{RenderFailure, RenderThread};
 use input::process_input;
 use overlay::OverlayContent;
-use hotkey::{register_hotkey, unregister_hotkey};
+use hotkey::{register_pause_hotkey, unregister_hotkey, unregister_pause_hotkey, Hotkeys};
+use hotkey::{register_panel_hotkeys, unregister_panel_hotkeys, register_menu_hotkeys, unregister_menu_hotkeys};
+use config::{config_path, load_or_default, Anchor, Config, ConfigWatcher};
+use action::ActionEvent;
+use executor::Executor;
+use fallback::run_fallback_menu;
+use feedback::Feedback;
+use provider::{MenuContext, Page};
+use edges::EDGE_SEGMENT;
+use remote::RemoteRequest;
+use replay::SessionEvent;
+use std::sync::mpsc;
 use winapi::{shared::windef::HWND, um::{wingdi::RGB, winuser::{SetLayeredWindowAttributes, LWA_ALPHA, LWA_COLORKEY}}};
-use winapi::shared::windef::POINT;
-use winapi::um::winuser::{GetCursorPos, SetWindowPos, SWP_NOSIZE, SWP_NOZORDER, GetAsyncKeyState, VK_MENU};
+use winapi::um::wincon::GetConsoleWindow;
+use winapi::um::winuser::{DestroyWindow, ShowWindow, SW_HIDE, SW_SHOWMINNOACTIVE};
+
+/// How often the shown overlay puts itself back on top, in case a game made itself topmost after it.
+const TOPMOST_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+/// Time between frames while the overlay is drawn.
+const FRAME_INTERVAL: std::time::Duration = std::time::Duration::from_millis(16);
+/// Time between HUD updates while it is the only thing shown; its readings are sampled once a second.
+const HUD_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
 
 fn main() {
+    // `--config <file>` picks the configuration file for the overlay and the subcommands alike
+    let mut args: Vec<String> = std::env::args().collect();
+    if let Some(index) = args.iter().position(|arg| arg == config::CONFIG_ARG) {
+        match args.get(index + 1) {
+            Some(path) => config::set_config_path(path),
+            None => {
+                eprintln!("Usage: {} {} <file>", args[0], config::CONFIG_ARG);
+                std::process::exit(2);
+            }
+        }
+        args.drain(index..index + 2);
+    }
+
+    // Subcommands such as `status` talk to an already running instance
+    if let Some(exit_code) = cli::run(&args) {
+        std::process::exit(exit_code);
+    }
+
+    // Workers wake this thread when they hand it something
+    wake::init();
+
+    // Started at login; the console window goes to the taskbar rather than over the desktop
+    if args.iter().any(|arg| arg == autostart::MINIMIZED_ARG) {
+        unsafe { ShowWindow(GetConsoleWindow(), SW_SHOWMINNOACTIVE) };
+    }
+
+    // Load user configuration, writing the defaults out on first run
+    match config::create_default_config() {
+        Ok(true) => println!("Created {}", config_path()),
+        Ok(false) => {}
+        Err(e) => eprintln!("{}", e),
+    }
+    let mut config = load_or_default(config_path());
+    report_config_issues();
+
     // Create the transparent, click-through window
-    let hwnd: HWND = create_overlay_window("Radial Menu Overlay", 800, 600);
+    let (width, height) = size_for_menu(rings::OUTER_RADIUS);
+    let hwnd: HWND = create_overlay_window("Radial Menu Overlay", width, height);
+
+    // A panic from here on saves a crash report and takes the window and the hotkeys down with it
+    crash::install(hwnd);
 
-    // Register the global hotkey (Alt+R)
-    if !register_hotkey() {
+    // Register the global hotkeys (Alt+R by default)
+    let mut hotkeys = Hotkeys::from_config(&config);
+    if !hotkeys.register() {
         eprintln!("Failed to register hotkey");
     }
+    register_pause_hotkey(hotkeys.pause.as_ref());
+
+    // Initialize Vulkan renderer on its own thread, falling back to a plain popup menu if the overlay can't be shown.
+    // The render thread also draws the HUD, a clock, usage and frame rate widgets in a corner on a window of its own
+    let render_thread = if hwnd.is_null() {
+        Err("Failed to create overlay window".to_string())
+    } else {
+        custom_shader::configure(&config.shader);
+        fill::configure(&config.theme);
+        RenderThread::start(hwnd, &config.hook, &config.hud, &config.renderer)
+    };
+    let render_thread = match render_thread {
+        Ok(render_thread) => render_thread,
+        Err(e) => {
+            eprintln!("Failed to initialize Vulkan renderer: {}", e);
+            diagnostics::report_error(format!("Failed to initialize Vulkan renderer: {}", e));
+            eprintln!("Falling back to the popup menu");
+            // Hide the broken overlay; destroying it would post WM_QUIT and end the fallback loop
+            if !hwnd.is_null() {
+                unsafe { ShowWindow(hwnd, SW_HIDE); }
+            }
+            recent::configure(&config.recent);
+            run_fallback_menu(&config);
+            unregister_hotkey();
+            unregister_pause_hotkey();
+            return;
+        }
+    };
+
+    // Ctrl+C and closing the console stop the main loop, which cleans up, instead of ending the process
+    shutdown::handle_console_signals();
 
-    // Initialize Vulkan renderer
-    let mut renderer = Renderer::new(hwnd).expect("Failed to initialize Vulkan renderer");
+    // Report runtime state over IPC; the render thread reports its GPU once the renderer is created
+    ipc::start_server();
+    if config.remote.enabled {
+        remote::start_server(&config.remote);
+    }
 
     // Initialize overlay content
     let mut overlay_content = OverlayContent::new();
+    let mut active_profile = apply_config(&config, &mut overlay_content);
+    let mut hotkeys_released = false; // Released for a suppressed app in front with `pass_through`
+    follow_suppression(&hotkeys, &mut hotkeys_released);
+    relative::configure(hwnd, &config.relative);
+    clipboard::configure(hwnd, &config.clipboard);
+    recent_files::configure(&config.recent_files);
+    profile_switcher::configure(&config);
+    mouse::watch_wheel(profile_switcher::scroll_enabled());
 
     let mut prev_visibility = overlay_content.visible;
+    let mut shown_at = std::time::Instant::now();
+    let mut window_shown = false;
+    let mut prev_selected = overlay_content.selected_segment;
+
+    // Click sound / rumble on selection changes
+    let mut feedback = Feedback::new(&config.feedback);
+
+    // Live renderer stats over the overlay
+    debug_panel::set_shown(config.debug.enabled);
+
+    let mut trigger_held_prev = false;
+    let mut panel_keys = false;
+    let mut menu_keys = false;
+    let mut search_keys = false;
+    let mut interactive = false;
+    let mut menu_items_behind_page = None; // The menu's items while a generated page or the confirm ring replaces them
+    let mut confirming = None; // The item the confirm ring is asking about, and its segment, while it is shown
+    let mut cursor_before_warp = None; // Where the cursor was before the open menu moved it to the center
+    let mut topmost_at = std::time::Instant::now();
+    let mut window_size = (width, height); // Fitted to the menu each time it opens
+    let mut focus_before_show = None; // The window that had focus when the menu opened, which actions are meant for
+    let mut variables_generation = variables::generation();
+    let mut render_failure = None; // Why rendering stopped, if it couldn't be recovered
+    let mut fall_back = false; // Set when the renderer couldn't be created after startup, for the popup menu to take over
+    let mut hud_shown = false; // Whether the render thread was last told to update the HUD while it is shown
+    let mut window_alpha = None; // Alpha the shown window was last given, None until the first frame after showing
+
+    // Profiles listing apps follow the app in front
+    foreground::watch();
 
-    let mut alt_pressed_prev = false;
+    // Reloads the menu when the config file is edited
+    let mut config_watcher = ConfigWatcher::new(config_path());
+    config_watcher.watch(&config.sources);
 
-    // Main application loop
+    // Actions run on the executor; progress and outcomes come back over the channel
+    let (action_sender, action_receiver) = mpsc::channel();
+    let mut executor = Executor::new(action_sender, &config);
+
+    // Main application loop, woken by messages, hook events, workers handing it something, or its timer
     loop {
-        // Process user input
-        if !process_input(&mut overlay_content) {
+        let loop_start = std::time::Instant::now();
+
+        // Process user input; closing the window, Ctrl+C and the quit command end the loop too
+        if !process_input(&mut overlay_content, &executor) || shutdown::is_requested() {
             break;
         }
 
-        // Check the state of the "Alt" key
-        let alt_state = unsafe { GetAsyncKeyState(VK_MENU) };
-        let alt_pressed = ((alt_state as u16) & 0x8000) != 0;
+        // Collect progress and outcomes reported by running actions
+        while let Ok(event) = action_receiver.try_recv() {
+            if let ActionEvent::Finished { segment, label, outcome } = &event {
+                executor::report_outcome(*segment, label, outcome);
+            }
+            overlay_content.apply_action_event(event);
+        }
 
-        // Detect changes in the "Alt" key state
-        if alt_pressed != alt_pressed_prev {
-            if !alt_pressed {
-                // "Alt" key was released
-                if overlay_content.visible {
-                    // Hide the overlay
-                    overlay_content.visible = false;
+        // Play back a recorded session, if one was started
+        replay::advance(&mut overlay_content);
+
+        // Answer phones and other remote clients
+        for pending in remote::take_requests() {
+            let reply = handle_remote_request(pending.request, &mut overlay_content, &executor);
+            let _ = pending.reply.send(reply);
+        }
+
+        // Apply config edits; an invalid file keeps the current config
+        match config_watcher.poll() {
+            Some(Ok(reloaded)) => {
+                config = reloaded;
+                report_config_issues();
+                config_watcher.watch(&config.sources);
+                active_profile = apply_config(&config, &mut overlay_content);
+                // The reloaded menu replaces whatever a generated page was covering
+                menu_items_behind_page = None;
+                executor.configure(&config);
+                relative::configure(hwnd, &config.relative);
+                clipboard::configure(hwnd, &config.clipboard);
+                recent_files::configure(&config.recent_files);
+                profile_switcher::configure(&config);
+                mouse::watch_wheel(!pause::is_paused() && profile_switcher::scroll_enabled());
+                feedback = Feedback::new(&config.feedback);
+                render_thread.configure_hud(&config.hud);
+                let new_hotkeys = Hotkeys::from_config(&config);
+                if new_hotkeys != hotkeys {
+                    if !hotkeys_released {
+                        unregister_hotkey();
+                        new_hotkeys.register();
+                    }
+                    unregister_pause_hotkey();
+                    register_pause_hotkey(new_hotkeys.pause.as_ref());
+                    hotkeys = new_hotkeys;
                 }
+                follow_suppression(&hotkeys, &mut hotkeys_released);
+                toast::push("Config reloaded");
             }
-            alt_pressed_prev = alt_pressed;
+            Some(Err(e)) => diagnostics::report_error(format!("Config not reloaded: {}", e)),
+            None => {}
         }
 
-        // Check if visibility has changed
-        if overlay_content.visible != prev_visibility {
+        // Pausing closes the menu and lets go of the hotkeys, the hooks and the GPU until resumed
+        if let Some(paused) = pause::take_change() {
+            status::set_paused(paused);
+            if paused {
+                overlay_content.dismiss();
+            }
+            follow_suppression(&hotkeys, &mut hotkeys_released);
+            mouse::watch_wheel(!paused && profile_switcher::scroll_enabled());
+            if paused {
+                render_thread.pause();
+            } else {
+                render_thread.resume();
+            }
+        }
+
+        // Variables changed by actions or scripts can flip item conditions while the menu is open
+        if variables::generation() != variables_generation {
+            variables_generation = variables::generation();
             if overlay_content.visible {
+                overlay_content.refresh_items();
+            }
+        }
 
-                // Get mouse position
-                let mut point: POINT = POINT { x: 0, y: 0 };
-                unsafe {
-                    GetCursorPos(&mut point);
-                }
+        // Typed letters search every profile's items
+        search::update(&mut overlay_content);
 
-                // Adjust window position to center on mouse
-                let window_width = 800; // Your window width
-                let window_height = 600; // Your window height
+        // Keep placeholders like "{time}" current while the menu is open
+        overlay_content.refresh_text_if_due();
 
-                unsafe {
-                    SetWindowPos(
-                        hwnd,
-                        std::ptr::null_mut(),
-                        point.x - window_width as i32 / 2,
-                        point.y - window_height as i32 / 2,
-                        0,
-                        0,
-                        SWP_NOSIZE | SWP_NOZORDER,
-                    );
+        // Explain once when the app in front is elevated or in exclusive fullscreen, where the overlay can't work
+        foreground::check();
+
+        // Switch to the profile for the app now in front and keep out of suppressed apps; only while hidden, so
+        // the menu never changes while open
+        if !overlay_content.visible && foreground::take_change() {
+            let process = foreground::process_name();
+            suppress::update(process.as_deref());
+            follow_suppression(&hotkeys, &mut hotkeys_released);
+            let profile = config.profile_for(process.as_deref());
+            if profile != active_profile {
+                diagnostics::record_event(format!("Switched to profile \"{}\" for the app in front", profile));
+                apply_profile(&config, profile, &mut overlay_content);
+                active_profile = profile.to_string();
+            }
+        }
+
+        // Check whether the hotkey that opened the overlay is still held
+        let open_hotkey = overlay_content.page.and_then(|page| hotkeys.page(page)).unwrap_or(&hotkeys.trigger);
+        let trigger_held = open_hotkey.is_held()
+            || (overlay_content.page.is_none() && (mouse::is_held() || trigger_hook::is_held()));
+
+        // Detect changes in the hotkey state
+        if trigger_held != trigger_held_prev {
+            if !trigger_held {
+                // Hotkey was released; a tap may leave the menu open until the next press
+                if overlay_content.visible && !overlay_content.latched {
+                    if hotkeys.activation.stays_open(shown_at.elapsed(), hotkeys.tap) {
+                        overlay_content.latched = true;
+                    } else {
+                        // Hide the overlay
+                        overlay_content.visible = false;
+                    }
                 }
+            }
+            trigger_held_prev = trigger_held;
+        }
 
-                // Set window to fully opaque (alpha = magenta) //fix for OPAQUE not suporting transparency
-                unsafe {
-                    SetLayeredWindowAttributes(hwnd, RGB(255, 0, 255), 0, LWA_COLORKEY);
+        // Scrolling over the open menu shows the profiles in its place, staying open until one is picked
+        let scrolled = mouse::take_scroll();
+        let replaceable = overlay_content.page.is_none() && overlay_content.search.is_none() && confirming.is_none();
+        if scrolled && overlay_content.visible && replaceable {
+            let items = Page::Profiles.provider().build(&MenuContext::current()).items;
+            menu_items_behind_page = Some(std::mem::replace(&mut overlay_content.menu_items, items));
+            overlay_content.page = Some(Page::Profiles);
+            overlay_content.selected_segment = None;
+            overlay_content.latched = true;
+            overlay_content.opened_at = std::time::Instant::now();
+            overlay_content.refresh_items();
+        }
+
+        // Check if visibility has changed
+        if overlay_content.visible != prev_visibility {
+            mouse::set_menu_open(overlay_content.visible);
+            if overlay_content.visible {
+                diagnostics::record_event("Overlay shown");
+                shown_at = std::time::Instant::now();
+                overlay_content.opened_at = shown_at;
+                focus_before_show = focus::foreground();
+                relative::reset();
+                gamepad::reset();
+                accessibility::refresh();
+                // Generated menus are built now; a replay brings the items it recorded instead
+                let provider = match overlay_content.page {
+                    Some(page) => Some(page.provider()),
+                    None if !replay::is_replaying() && confirming.is_none() => overlay_content.provider,
+                    None => None,
+                };
+                if let Some(provider) = provider {
+                    let context = MenuContext { foreground: focus_before_show, cursor: MenuContext::current().cursor };
+                    let items = provider.build(&context).items;
+                    menu_items_behind_page = Some(std::mem::replace(&mut overlay_content.menu_items, items));
                 }
+                overlay_content.refresh_items();
+                replay::record(SessionEvent::Open {
+                    items: overlay_content.item_text.iter().map(|text| text.label.clone()).collect(),
+                });
+                // Fit the window to this menu, which may have more rings or wider edge zones than the last
+                window_size = size_for_menu(rings::menu_extent(&overlay_content));
+                let cursor_offset = place_window(hwnd, window_size.0, window_size.1, overlay_content.anchor);
+                // Sampled where the window now is, before anything is drawn in it. The frosted-glass snapshot has
+                // the luminance already; otherwise a quick GDI sample gives it
+                let snapshot_luminance = if fill::frosted_glass() {
+                    duplication::refresh(hwnd)
+                } else {
+                    duplication::discard();
+                    None
+                };
+                overlay_content.background_luminance = if fill::adaptive_contrast() {
+                    snapshot_luminance.or_else(|| contrast::background_luminance(hwnd))
+                } else {
+                    None
+                };
+                if overlay_content.warp_cursor {
+                    cursor_before_warp = Some(warp_cursor_to_center(hwnd));
+                }
+                // Selecting starts from wherever the cursor is when the ring isn't under it: a menu kept on screen
+                // near an edge, a pinned menu the cursor is outside of, or with `reanchor` any pinned menu, e.g.
+                // opened from the keyboard or a gamepad. Warping already put the cursor in the middle
+                let pinned = overlay_content.anchor != Anchor::Cursor;
+                let outside = cursor_offset.0.abs() > window_size.0 as i32 / 2 || cursor_offset.1.abs() > window_size.1 as i32 / 2;
+                let off_center = cursor_offset != (0, 0) && (!pinned || outside || overlay_content.reanchor);
+                overlay_content.cursor_origin = if off_center && !overlay_content.warp_cursor {
+                    Some(cursor_position())
+                } else {
+                    None
+                };
             } else {
+                // A dial applied its value while it was turned; closing on it runs nothing
+                if overlay_content.dial.take().is_some() {
+                    overlay_content.selected_segment = None;
+                }
+                diagnostics::record_event(format!("Overlay hidden, selection {:?}", overlay_content.selected_segment));
+                // Put the cursor back before the action runs, so clicks and window actions land where the user was
+                if let Some(point) = cursor_before_warp.take() {
+                    restore_cursor(point);
+                }
+                // Some apps lose focus to the overlay despite WS_EX_NOACTIVATE; sent keystrokes must reach them
+                if let Some(hwnd) = focus_before_show.take() {
+                    focus::restore(hwnd);
+                }
+                replay::record(SessionEvent::Close {
+                    segment: overlay_content.selected_segment,
+                    label: overlay_content
+                        .selected_segment
+                        .and_then(|segment| overlay_content.item_text(segment))
+                        .map(|text| text.label.clone()),
+                    edge: overlay_content.selected_edge.map(|edge| edge.label().to_string()),
+                });
                 // Overlay became hidden
                 // Execute action if an item was selected
+                let confirmed: Option<(i32, menu::MenuItem)> = confirming.take();
+                let mut to_confirm = None;
                 if let Some(selected_segment) = overlay_content.selected_segment {
-                    println!("Executing action for segment {}", selected_segment);
-                    // TODO: Call the function or perform the action associated with the segment
+                    let item = overlay_content.items.get(selected_segment as usize);
+                    if let Some(item) = item.filter(|item| item.confirm && confirmed.is_none()) {
+                        // Items marked `confirm` only run once Yes is picked on the ring shown after this one
+                        println!("Asking to confirm segment {}", selected_segment);
+                        to_confirm = Some((selected_segment, item.clone()));
+                    } else {
+                        println!("Executing action for segment {}", selected_segment);
+                        // On the confirm ring the item is the one asked about, not its Yes
+                        let answered = match &confirmed {
+                            Some((_, original)) if selected_segment == menu::CONFIRM_YES_SEGMENT => Some(original),
+                            Some(_) => None,
+                            None => item,
+                        };
+                        if let Some(item) = answered {
+                            recent::record(item);
+                        }
+                        let action = item.and_then(|item| item.action.clone());
+                        feedback.action_fired();
+                        if let Some(action) = action {
+                            // Progress shows on the segment asked about
+                            let (segment, label) = match &confirmed {
+                                Some((segment, original)) => (*segment, original.display_label()),
+                                None => (
+                                    selected_segment,
+                                    overlay_content
+                                        .item_text(selected_segment)
+                                        .map_or_else(String::new, |text| text.label.clone()),
+                                ),
+                            };
+                            if confirmed.is_some() {
+                                executor.submit_confirmed(segment, label, action);
+                            } else {
+                                executor.submit(segment, label, action);
+                            }
+                        }
+                    }
+                } else if replay::is_replaying() {
+                    // A replayed edge is only shown; replayed segments can't run anything either, having no actions
+                    overlay_content.selected_edge = None;
+                } else if let (Some(edge), Some(edges)) = (overlay_content.selected_edge, &overlay_content.edges) {
+                    println!("Executing action for edge {:?}", edge);
+                    feedback.action_fired();
+                    executor.submit(EDGE_SEGMENT, edge.label().to_string(), edge.action(edges));
                 }
 
                 // Reset the selected segment
                 overlay_content.selected_segment = None;
+                overlay_content.selected_edge = None;
+                overlay_content.ring_stage = 0;
+                overlay_content.latched = false;
+                overlay_content.cursor_distance = 0.0;
+                overlay_content.reset_dwell();
+
+                // Put back the menu the search results replaced, then the one behind the generated page
+                search::end(&mut overlay_content);
+                overlay_content.page = None;
+                if let Some(items) = menu_items_behind_page.take() {
+                    overlay_content.menu_items = items;
+                    overlay_content.refresh_items();
+                }
+
+                // The confirm ring replaces the menu until answered, staying open once the trigger is let go
+                if let Some((segment, item)) = to_confirm {
+                    menu_items_behind_page =
+                        Some(std::mem::replace(&mut overlay_content.menu_items, menu::confirm_ring(&item)));
+                    confirming = Some((segment, item));
+                }
+            }
+            prev_visibility = overlay_content.visible;
+            if confirming.is_some() && !overlay_content.visible {
+                overlay_content.visible = true;
+                overlay_content.latched = true;
+            }
+        }
+
+        // Esc dismisses the menu and 1-9 select, unless an open panel is waiting for keys; released first so the panel can claim Esc
+        let wants_menu_keys = overlay_content.visible && !panel::accepts_input();
+        if wants_menu_keys != menu_keys {
+            menu_keys = wants_menu_keys;
+            if menu_keys {
+                register_menu_hotkeys(hotkeys.number_keys, hotkeys.trigger.modifiers);
+            } else {
+                unregister_menu_hotkeys();
+            }
+        }
+
+        // Typing searches while the menu is open, unless a panel is waiting for keys
+        let wants_search_keys = wants_menu_keys && search::is_enabled();
+        if wants_search_keys != search_keys {
+            search_keys = wants_search_keys;
+            search::capture(search_keys);
+        }
+
+        // Enter and Esc answer an open panel or pick a color
+        let wants_panel_keys = panel::accepts_input() || picker::is_active();
+        if wants_panel_keys != panel_keys {
+            panel_keys = wants_panel_keys;
+            if panel_keys {
+                register_panel_hotkeys();
+            } else {
+                unregister_panel_hotkeys();
+            }
+        }
+
+        // The settings screen takes clicks and keys, so the window stops being click-through while it is open
+        if settings::is_open() != interactive {
+            interactive = settings::is_open();
+            set_interactive(hwnd, interactive);
+        }
+
+        // Keep the window up while the menu, a panel, the settings, the debug panel, a toast or a running action
+        // needs it
+        let window_needed = !pause::is_paused()
+            && (overlay_content.visible
+                || picker::is_active()
+                || settings::is_open()
+                || debug_panel::is_shown()
+                || panel::is_open()
+                || toast::has_active()
+                || !overlay_content.segment_progress.is_empty());
+        if window_needed != window_shown {
+            if window_needed {
+                // Toasts raised while the menu is closed appear near the cursor
+                if !overlay_content.visible {
+                    center_on_cursor(hwnd, window_size.0, window_size.1);
+                }
+                // Set window to fully opaque (alpha = magenta) //fix for OPAQUE not suporting transparency
+                unsafe {
+                    SetLayeredWindowAttributes(hwnd, RGB(255, 0, 255), 0, LWA_COLORKEY);
+                }
+            } else {
                 // Set window to fully transparent
                 unsafe {
                     SetLayeredWindowAttributes(hwnd, 0, 0, LWA_ALPHA);
                 }
             }
-            prev_visibility = overlay_content.visible;
+            window_shown = window_needed;
+            window_alpha = None;
         }
 
-        // Render the overlay if visible
-        if overlay_content.visible {
-            renderer.render(&mut overlay_content, hwnd).expect("Rendering failed");
+        // Render the overlay if visible, staying above games that make themselves topmost
+        if window_shown {
+            // The loupe is drawn in the middle of the window, which follows the cursor while picking
+            if picker::is_active() && !overlay_content.visible {
+                center_on_cursor(hwnd, window_size.0, window_size.1);
+            }
+            if topmost_at.elapsed() >= TOPMOST_INTERVAL {
+                keep_topmost(hwnd);
+                topmost_at = std::time::Instant::now();
+            }
+            // The menu fades as a whole window, so labels and icons go with the ring; translucent pixels would
+            // blend with the color key instead. Toasts shown without the menu stay opaque
+            let opacity = if overlay_content.visible { animation::current(&overlay_content).opacity } else { 1.0 };
+            let alpha = (opacity.clamp(0.0, 1.0) * 255.0).round() as u8;
+            if window_alpha != Some(alpha) {
+                unsafe {
+                    SetLayeredWindowAttributes(hwnd, RGB(255, 0, 255), alpha, LWA_COLORKEY | LWA_ALPHA);
+                }
+                window_alpha = Some(alpha);
+            }
+            // The selection follows the cursor here; the render thread only draws a snapshot of the result
+            let cursor = track_cursor(&mut overlay_content, hwnd);
+            render_thread.draw(&overlay_content, cursor);
+        }
+        // The render thread has already tried rebuilding the swapchain and the renderer by now
+        match render_thread.failure() {
+            Some(RenderFailure::Rendering(e)) => {
+                diagnostics::report_error(format!("Rendering failed: {}", e));
+                render_failure = Some(e);
+                break;
+            }
+            Some(RenderFailure::Creating(e)) => {
+                diagnostics::report_error(format!("Failed to initialize Vulkan renderer: {}", e));
+                eprintln!("Falling back to the popup menu");
+                fall_back = true;
+                break;
+            }
+            None => {}
+        }
+
+        // Show, hide and refresh the HUD; hidden, it needs no updates past the one taking it down
+        if hud::is_shown() || hud_shown {
+            render_thread.update_hud();
+        }
+        hud_shown = hud::is_shown();
+
+        // Give feedback when a new segment becomes hovered
+        if overlay_content.selected_segment != prev_selected {
+            replay::record(SessionEvent::Select { segment: overlay_content.selected_segment });
+            if overlay_content.visible && overlay_content.selected_segment.is_some() {
+                feedback.selection_changed();
+                if accessibility::announces() {
+                    announce_selection(&overlay_content);
+                }
+            }
+            prev_selected = overlay_content.selected_segment;
         }
 
-        // Sleep to reduce CPU usage
-        std::thread::sleep(std::time::Duration::from_millis(16));
+        // Sleep until something happens, config edits included; frames are only timed while the window is drawn or
+        // a replay plays, and HUD updates while the HUD is up
+        let interval = if window_shown || replay::is_replaying() {
+            Some(FRAME_INTERVAL)
+        } else if hud_shown {
+            Some(HUD_INTERVAL)
+        } else {
+            None
+        };
+        let timeout = interval
+            .map(|interval| interval.saturating_sub(loop_start.elapsed()))
+            .into_iter()
+            .chain(config_watcher.next_check())
+            .min();
+        wake::wait(timeout, config_watcher.notifications());
     }
 
-    // Clean up resources
+    // Clean up resources: hooks and hotkeys first so nothing new comes in, then the threads, then the window
+    diagnostics::record_event("Main loop stopped");
+    if let Some(point) = cursor_before_warp.take() {
+        restore_cursor(point);
+    }
+    unregister_panel_hotkeys();
+    unregister_menu_hotkeys();
     unregister_hotkey();
-    renderer.cleanup();
+    unregister_pause_hotkey();
+    search::capture(false);
+    executor.shutdown();
+    render_thread.stop();
+    if fall_back {
+        // As when the renderer fails at startup: the overlay is hidden, not destroyed, which would post WM_QUIT
+        unsafe { ShowWindow(hwnd, SW_HIDE); }
+        hotkeys.register();
+        run_fallback_menu(&config);
+        unregister_hotkey();
+    }
+    unsafe {
+        DestroyWindow(hwnd);
+    }
+    shutdown::finish();
+    if let Some(e) = render_failure {
+        error_box(&format!("The overlay stopped because rendering failed and could not be restarted:\n{}", e));
+        std::process::exit(1);
+    }
+}
+
+/// Reads out the selected segment's label, and whether it can't be selected.
+fn announce_selection(overlay_content: &OverlayContent) {
+    let segment = match overlay_content.selected_segment {
+        Some(segment) => segment,
+        None => return,
+    };
+    let label = match overlay_content.item_text(segment) {
+        Some(text) if !text.label.is_empty() => text.label.clone(),
+        _ => format!("Segment {}", segment + 1),
+    };
+    if overlay_content.is_segment_enabled(segment) {
+        speech::announce(&label);
+    } else {
+        speech::announce(&format!("{}, unavailable", label));
+    }
+}
+
+/// Prints validation problems in the config file, raises a toast pointing at them and keeps them for the health report.
+fn report_config_issues() {
+    if !std::path::Path::new(config_path()).exists() {
+        status::set_config_health(Vec::new());
+        return;
+    }
+    match validate::validate_file(config_path()) {
+        Ok(issues) => {
+            for issue in &issues {
+                eprintln!("{}: {}", config_path(), issue);
+            }
+            if let Some(first) = issues.first() {
+                toast::push(format!("{} problem(s) in {}, first at {}", issues.len(), config_path(), first));
+            }
+            status::set_config_health(issues.iter().map(ToString::to_string).collect());
+        }
+        Err(e) => {
+            status::set_config_health(vec![e.clone()]);
+            diagnostics::report_error(e);
+        }
+    }
+}
+
+/// Lists or runs items of the active menu for a remote client.
+fn handle_remote_request(
+    request: RemoteRequest,
+    overlay_content: &mut OverlayContent,
+    executor: &Executor,
+) -> String {
+    // Conditions are otherwise only evaluated when the overlay opens
+    if !overlay_content.visible {
+        overlay_content.refresh_items();
+    }
+    match request {
+        RemoteRequest::Items => {
+            let items: Vec<_> = (0..overlay_content.items.len())
+                .map(|index| {
+                    let segment = index as i32;
+                    serde_json::json!({
+                        "index": index,
+                        "label": overlay_content.item_text(segment).map_or("", |text| text.label.as_str()),
+                        "enabled": overlay_content.is_segment_enabled(segment),
+                        "on": overlay_content.is_segment_on(segment),
+                    })
+                })
+                .collect();
+            serde_json::json!(items).to_string()
+        }
+        RemoteRequest::Run(index) => {
+            let segment = index as i32;
+            let (action, confirm) = match overlay_content.items.get(index) {
+                Some(_) if !overlay_content.is_segment_enabled(segment) => {
+                    return serde_json::json!({ "error": "Item is disabled" }).to_string()
+                }
+                Some(item) => {
+                    recent::record(item);
+                    (item.action.clone(), item.confirm)
+                }
+                None => return serde_json::json!({ "error": format!("No item {}", index) }).to_string(),
+            };
+            if let Some(action) = action {
+                let label = overlay_content.item_text(segment).map_or_else(String::new, |text| text.label.clone());
+                diagnostics::record_event(format!("Remote client ran \"{}\"", label));
+                if confirm {
+                    executor.submit_confirmed(segment, label, action);
+                } else {
+                    executor.submit(segment, label, action);
+                }
+            }
+            serde_json::json!({ "ok": true }).to_string()
+        }
+    }
+}
+
+/// Applies the profile, dwell, edge zone, ring and recent page settings, both at startup and when the config file is reloaded.
+/// Returns the profile applied, the one for the app in front.
+fn apply_config(config: &Config, overlay_content: &mut OverlayContent) -> String {
+    let process = foreground::process_name();
+    let profile = config.profile_for(process.as_deref()).to_string();
+    apply_profile(config, &profile, overlay_content);
+    suppress::configure(&config.suppress);
+    suppress::update(process.as_deref());
+    overlay_content.dwell_time = if config.dwell.enabled {
+        Some(std::time::Duration::from_millis(config.dwell.time_ms))
+    } else {
+        None
+    };
+    overlay_content.edges = if config.edges.enabled { Some(config.edges.clone()) } else { None };
+    overlay_content.number_badges = config.hotkey.number_keys && config.hotkey.number_badges;
+    overlay_content.rings = if config.rings.enabled { Some(config.rings.clone()) } else { None };
+    overlay_content.orientation = config.orientation.clone();
+    overlay_content.ring_stage = 0;
+    overlay_content.hysteresis = config.selection.hysteresis_degrees.to_radians();
+    overlay_content.inner_grace = std::time::Duration::from_millis(config.selection.inner_grace_ms);
+    overlay_content.angle_smoothing = std::time::Duration::from_millis(config.selection.angle_smoothing_ms);
+    overlay_content.warp_cursor = config.selection.warp_cursor;
+    overlay_content.reanchor = config.selection.reanchor;
+    gamepad::configure(&config.gamepad);
+    recent::configure(&config.recent);
+    search::configure(config);
+    accessibility::configure(&config.accessibility);
+    fill::configure(&config.theme);
+    animation::configure(&config.theme.animation);
+    autostart::apply(&config.startup);
+    // The reloaded menu replaces whatever the search results were covering
+    search::discard(overlay_content);
+    profile
+}
+
+/// Releases the hotkeys and the mouse hook while a suppressed app in front should get the keys or the overlay is
+/// paused, and registers them again once neither is the case.
+fn follow_suppression(hotkeys: &Hotkeys, released: &mut bool) {
+    let release = suppress::releases_hotkeys() || pause::is_paused();
+    if release == *released {
+        return;
+    }
+    if release {
+        unregister_hotkey();
+    } else {
+        hotkeys.register();
+    }
+    *released = release;
+}
+
+/// Shows the named profile's items, or builds them on opening for a generated one.
+fn apply_profile(config: &Config, profile: &str, overlay_content: &mut OverlayContent) {
+    status::set_active_profile(profile);
+    overlay_content.provider = provider::find(profile);
+    overlay_content.menu_items = if overlay_content.provider.is_some() {
+        Vec::new()
+    } else {
+        config.profile_items(profile).unwrap_or(&config.items).to_vec()
+    };
+    overlay_content.refresh_items();
+    overlay_content.anchor = config.profile_anchor(profile);
+    trigger_hook::set_passthrough(config.profile_passthrough(profile));
 }
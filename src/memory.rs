@@ -0,0 +1,200 @@
+// Device memory suballocation: buffers and images share large blocks instead of each taking an allocation of its
+// own, so textures, glyph atlases and vertex buffers don't run into maxMemoryAllocationCount or fragment memory.
+// Blocks are kept per device and freed once empty. The texture shared with a present hook is exported, so it keeps
+// a dedicated allocation of its own
+
+use ash::vk::{self, Handle};
+use ash::Device;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use crate::render::find_memory_type;
+
+/// Size of a block; requests over half of it get a block of their own.
+const BLOCK_SIZE: vk::DeviceSize = 16 * 1024 * 1024;
+
+/// What the memory is bound to. Optimal-tiling images are kept apart from buffers and linear images, so
+/// bufferImageGranularity never has to be padded for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Resource {
+    Linear, // Buffers and linear images
+    Optimal, // Images with optimal tiling
+}
+
+/// A range of a block, bound at `offset` of `memory`.
+#[derive(Clone, Copy, Debug)]
+pub struct Allocation {
+    pub memory: vk::DeviceMemory,
+    pub offset: vk::DeviceSize,
+    size: vk::DeviceSize,
+}
+
+struct Block {
+    memory: vk::DeviceMemory,
+    size: vk::DeviceSize,
+    free: Vec<(vk::DeviceSize, vk::DeviceSize)>, // Offset and size of each free range, in order of offset
+    mapped: usize, // Start of the block in host memory, mapped for as long as it lives; 0 unless host-visible
+}
+
+#[derive(Default)]
+struct DeviceBlocks {
+    blocks: HashMap<(u32, Resource), Vec<Block>>, // By memory type
+}
+
+/// Blocks of every device, by its raw handle.
+static DEVICES: Mutex<Option<HashMap<u64, DeviceBlocks>>> = Mutex::new(None);
+
+/// Allocates memory meeting `requirements` with `properties`, from a block with room or a new one.
+/// Host-visible memory stays mapped; `mapped` points at it.
+pub(crate) fn allocate(
+    device: &Device,
+    memory_properties: vk::PhysicalDeviceMemoryProperties,
+    requirements: vk::MemoryRequirements,
+    properties: vk::MemoryPropertyFlags,
+    resource: Resource,
+) -> Result<Allocation, String> {
+    let memory_type = find_memory_type(requirements.memory_type_bits, properties, memory_properties)?;
+    let host_visible = memory_properties.memory_types[memory_type as usize]
+        .property_flags
+        .contains(vk::MemoryPropertyFlags::HOST_VISIBLE);
+
+    let mut devices = DEVICES.lock().unwrap();
+    let blocks = devices
+        .get_or_insert_with(HashMap::new)
+        .entry(device.handle().as_raw())
+        .or_default()
+        .blocks
+        .entry((memory_type, resource))
+        .or_default();
+
+    for block in blocks.iter_mut() {
+        if let Some(offset) = block.take(requirements.size, requirements.alignment) {
+            return Ok(Allocation { memory: block.memory, offset, size: requirements.size });
+        }
+    }
+
+    let size = if requirements.size > BLOCK_SIZE / 2 { requirements.size } else { BLOCK_SIZE };
+    let mut block = Block::new(device, memory_type, size, host_visible)?;
+    let offset = block.take(requirements.size, requirements.alignment).unwrap_or(0);
+    let allocation = Allocation { memory: block.memory, offset, size: requirements.size };
+    blocks.push(block);
+    Ok(allocation)
+}
+
+/// Gives the range back to its block, and frees the block once nothing is left in it.
+pub(crate) fn free(device: &Device, allocation: Allocation) {
+    let mut devices = DEVICES.lock().unwrap();
+    let device_blocks = match devices.as_mut().and_then(|devices| devices.get_mut(&device.handle().as_raw())) {
+        Some(device_blocks) => device_blocks,
+        None => return,
+    };
+    for blocks in device_blocks.blocks.values_mut() {
+        if let Some(index) = blocks.iter().position(|block| block.memory == allocation.memory) {
+            blocks[index].give_back(allocation.offset, allocation.size);
+            if blocks[index].is_empty() {
+                let block = blocks.swap_remove(index);
+                unsafe {
+                    device.free_memory(block.memory, None);
+                }
+            }
+            return;
+        }
+    }
+}
+
+/// The allocation's start in host memory, which stays valid until it is freed. Fails for memory that isn't
+/// host-visible.
+pub(crate) fn mapped(device: &Device, allocation: &Allocation) -> Result<*mut u8, String> {
+    let devices = DEVICES.lock().unwrap();
+    let block = devices
+        .as_ref()
+        .and_then(|devices| devices.get(&device.handle().as_raw()))
+        .and_then(|device_blocks| {
+            device_blocks.blocks.values().flatten().find(|block| block.memory == allocation.memory)
+        })
+        .ok_or_else(|| "Mapped memory that was never allocated".to_string())?;
+    if block.mapped == 0 {
+        return Err("Mapped memory that isn't host-visible".to_string());
+    }
+    Ok((block.mapped + allocation.offset as usize) as *mut u8)
+}
+
+/// Frees every block of a device about to be destroyed, e.g. ones whose resources leaked.
+pub(crate) fn release_device(device: &Device) {
+    let mut devices = DEVICES.lock().unwrap();
+    if let Some(device_blocks) = devices.as_mut().and_then(|devices| devices.remove(&device.handle().as_raw())) {
+        for block in device_blocks.blocks.into_values().flatten() {
+            unsafe {
+                device.free_memory(block.memory, None);
+            }
+        }
+    }
+}
+
+impl Block {
+    fn new(device: &Device, memory_type: u32, size: vk::DeviceSize, host_visible: bool) -> Result<Self, String> {
+        let alloc_info = vk::MemoryAllocateInfo::builder()
+            .allocation_size(size)
+            .memory_type_index(memory_type);
+        let memory = unsafe {
+            device
+                .allocate_memory(&alloc_info, None)
+                .map_err(|e| format!("Failed to allocate {} bytes of device memory: {:?}", size, e))?
+        };
+        let mapped = if host_visible {
+            match unsafe { device.map_memory(memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty()) } {
+                Ok(pointer) => pointer as usize,
+                Err(e) => {
+                    unsafe {
+                        device.free_memory(memory, None);
+                    }
+                    return Err(format!("Failed to map device memory: {:?}", e));
+                }
+            }
+        } else {
+            0
+        };
+        Ok(Self { memory, size, free: vec![(0, size)], mapped })
+    }
+
+    /// Carves `size` bytes aligned to `alignment` out of the first free range they fit in.
+    fn take(&mut self, size: vk::DeviceSize, alignment: vk::DeviceSize) -> Option<vk::DeviceSize> {
+        let alignment = alignment.max(1);
+        let index = self.free.iter().position(|&(offset, free)| {
+            let start = align(offset, alignment);
+            start + size <= offset + free
+        })?;
+        let (offset, free) = self.free.remove(index);
+        let start = align(offset, alignment);
+        let end = start + size;
+        // The padding before the start and the rest after the end stay free
+        if end < offset + free {
+            self.free.insert(index, (end, offset + free - end));
+        }
+        if start > offset {
+            self.free.insert(index, (offset, start - offset));
+        }
+        Some(start)
+    }
+
+    /// Frees the range again, merging it with free neighbours.
+    fn give_back(&mut self, offset: vk::DeviceSize, size: vk::DeviceSize) {
+        let index = self.free.partition_point(|&(free_offset, _)| free_offset < offset);
+        self.free.insert(index, (offset, size));
+        if index + 1 < self.free.len() && offset + size == self.free[index + 1].0 {
+            let (_, next) = self.free.remove(index + 1);
+            self.free[index].1 += next;
+        }
+        if index > 0 && self.free[index - 1].0 + self.free[index - 1].1 == offset {
+            let (_, merged) = self.free.remove(index);
+            self.free[index - 1].1 += merged;
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.free.len() == 1 && self.free[0] == (0, self.size)
+    }
+}
+
+fn align(offset: vk::DeviceSize, alignment: vk::DeviceSize) -> vk::DeviceSize {
+    (offset + alignment - 1) / alignment * alignment
+}
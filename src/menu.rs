@@ -0,0 +1,127 @@
+// Menu items shown as segments of the radial menu
+
+use serde::{Deserialize, Serialize};
+use crate::action::Action;
+use crate::condition::Condition;
+use crate::dial::Dial;
+use crate::template;
+use crate::toggle::ToggleState;
+
+/// Number of segments drawn when no items are configured.
+pub const DEFAULT_SEGMENTS: i32 = 6;
+
+/// Maximum number of segments the shader can hold per-segment state for.
+pub const MAX_SEGMENTS: usize = 16;
+
+/// `text` cut to `max_chars` with "..." at the end, for labels generated from titles and clips.
+pub fn shorten(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let short: String = text.chars().take(max_chars.saturating_sub(3)).collect();
+    format!("{}...", short.trim_end())
+}
+
+/// A single segment of the radial menu.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MenuItem {
+    /// May contain placeholders, e.g. "Volume: {volume}%" or "{time}".
+    pub label: String,
+    /// Shown in the tooltip; may contain placeholders like the label.
+    #[serde(default)]
+    pub description: String,
+    pub action: Option<Action>,
+    /// The item is drawn dimmed and can't be selected while this is false.
+    #[serde(default)]
+    pub enabled_if: Option<Condition>,
+    /// The item is left out of the ring entirely while this is false.
+    #[serde(default)]
+    pub visible_if: Option<Condition>,
+    /// Short text drawn at the segment's outer edge, hidden while it expands to nothing.
+    #[serde(default)]
+    pub badge: Option<String>,
+    /// Share of the ring relative to the other items, e.g. 2.0 for a twice as wide segment; 1.0 when unset.
+    #[serde(default)]
+    pub weight: Option<f32>,
+    /// Name of the `[shared]` definition this item was built from; its own keys override the definition's.
+    #[serde(default, rename = "use")]
+    pub uses: Option<String>,
+    /// Turns the segment into a dial while hovered, e.g. for the volume; the item's action isn't run for it.
+    #[serde(default)]
+    pub dial: Option<Dial>,
+    /// File, folder or program whose icon is drawn on the segment; a document shows its app's icon.
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Selecting the item opens a Yes/No ring first, e.g. for "Shutdown PC"; its action only runs on Yes.
+    #[serde(default)]
+    pub confirm: bool,
+    /// Shows whether something is on, e.g. `toggle = "dark_mode"`; the segment is drawn lit while it is.
+    #[serde(default)]
+    pub toggle: Option<ToggleState>,
+    /// The segment pulses to draw attention while this holds, e.g. while OBS is recording. Generated menus can set
+    /// `Condition::All(vec![])`, which always holds, to pulse an item outright.
+    #[serde(default)]
+    pub pulse_if: Option<Condition>,
+}
+
+/// Segment of the confirm ring that runs the item.
+pub const CONFIRM_YES_SEGMENT: i32 = 0;
+
+/// The Yes/No ring shown before an item marked `confirm` runs. Yes carries the item's action, No nothing.
+pub fn confirm_ring(item: &MenuItem) -> Vec<MenuItem> {
+    let answer = |label: String, description: String, action: Option<Action>| MenuItem {
+        label,
+        description,
+        action,
+        enabled_if: None,
+        visible_if: None,
+        badge: None,
+        weight: None,
+        uses: None,
+        dial: None,
+        icon: None,
+        confirm: false,
+        toggle: None,
+        pulse_if: None,
+    };
+    vec![
+        answer("Yes".to_string(), item.label.clone(), item.action.clone()),
+        answer("No".to_string(), String::new(), None),
+    ]
+}
+
+impl MenuItem {
+    /// The label with placeholders filled in.
+    pub fn display_label(&self) -> String {
+        template::expand(&self.label)
+    }
+
+    pub fn display_description(&self) -> String {
+        template::expand(&self.description)
+    }
+
+    /// The badge text with placeholders filled in, None when there is nothing to show.
+    pub fn display_badge(&self) -> Option<String> {
+        self.badge
+            .as_ref()
+            .map(|badge| template::expand(badge))
+            .filter(|badge| !badge.trim().is_empty())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled_if.as_ref().map_or(true, |condition| condition.evaluate())
+    }
+
+    /// Whether the item's toggle state is on; false for items that aren't toggles.
+    pub fn is_on(&self) -> bool {
+        self.toggle.as_ref().map_or(false, |toggle| toggle.read())
+    }
+
+    pub fn is_pulsing(&self) -> bool {
+        self.pulse_if.as_ref().map_or(false, |condition| condition.evaluate())
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible_if.as_ref().map_or(true, |condition| condition.evaluate())
+    }
+}
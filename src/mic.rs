@@ -0,0 +1,44 @@
+// Microphone mute: toggled on the default recording device, or through an app's own mute hotkey such as Discord's,
+// with the state shown by the "{mic_muted}" placeholder
+
+use winapi::shared::minwindef::{BOOL, FALSE};
+use winapi::shared::winerror::SUCCEEDED;
+use winapi::um::mmdeviceapi::eCapture;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::dial::with_endpoint_volume;
+use crate::hotkey::Hotkey;
+use crate::keys;
+
+/// Whether the app muted through its hotkey is muted. Apps can't be asked, so this counts the presses sent,
+/// starting unmuted.
+static APP_MUTED: AtomicBool = AtomicBool::new(false);
+
+/// Toggles the mute: with `keys`, by pressing the app's mute hotkey, otherwise on the default recording device.
+/// Returns whether the microphone is now muted.
+pub fn toggle(keys: Option<&str>) -> Result<bool, String> {
+    if let Some(combo) = keys {
+        keys::send_combo(&Hotkey::parse(combo)?)?;
+        return Ok(!APP_MUTED.fetch_xor(true, Ordering::SeqCst));
+    }
+    with_endpoint_volume(eCapture, |volume| {
+        let mut muted: BOOL = FALSE;
+        if !SUCCEEDED(unsafe { volume.GetMute(&mut muted) }) {
+            return Err("Failed to read the microphone's mute state".to_string());
+        }
+        if !SUCCEEDED(unsafe { volume.SetMute((muted == FALSE) as BOOL, null_mut()) }) {
+            return Err("Failed to mute or unmute the microphone".to_string());
+        }
+        Ok(muted == FALSE)
+    })
+    .unwrap_or_else(|| Err("No microphone found".to_string()))
+}
+
+/// Whether the default recording device or the app muted through its hotkey is muted.
+pub fn is_muted() -> bool {
+    let device_muted = with_endpoint_volume(eCapture, |volume| {
+        let mut muted: BOOL = FALSE;
+        SUCCEEDED(unsafe { volume.GetMute(&mut muted) }) && muted != FALSE
+    });
+    device_muted.unwrap_or(false) || APP_MUTED.load(Ordering::SeqCst)
+}
@@ -0,0 +1,132 @@
+// Low-level mouse hook that makes an extra mouse button, e.g. a thumb button, act as the trigger hotkey, and
+// catches the wheel while the menu is open for the profile switcher
+
+use winapi::shared::minwindef::{LPARAM, LRESULT, WPARAM};
+use winapi::shared::windef::HHOOK__;
+use winapi::um::libloaderapi::GetModuleHandleW;
+use winapi::um::processthreadsapi::GetCurrentThreadId;
+use winapi::um::winuser::*;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, AtomicU8, Ordering};
+use crate::diagnostics;
+use crate::hotkey::WM_HOTKEY_ID;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MouseButton {
+    Middle,
+    Back,    // Mouse4 / XButton1
+    Forward, // Mouse5 / XButton2
+}
+
+impl MouseButton {
+    /// Parses "Middle", "Mouse4" or "Mouse5" (also "XButton1", "XButton2"), case-insensitively.
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "middle" | "mouse3" => Ok(MouseButton::Middle),
+            "mouse4" | "xbutton1" | "back" => Ok(MouseButton::Back),
+            "mouse5" | "xbutton2" | "forward" => Ok(MouseButton::Forward),
+            _ => Err(format!("Unknown mouse button \"{}\" (Middle, Mouse4 or Mouse5)", name)),
+        }
+    }
+
+    fn code(self) -> u8 {
+        match self {
+            MouseButton::Middle => 3,
+            MouseButton::Back => 4,
+            MouseButton::Forward => 5,
+        }
+    }
+}
+
+static HOOK: AtomicPtr<HHOOK__> = AtomicPtr::new(null_mut());
+/// Code of the bound button; 0 while none is bound.
+static BOUND: AtomicU8 = AtomicU8::new(0);
+static HELD: AtomicBool = AtomicBool::new(false);
+/// Thread whose message loop receives the trigger, the one that installed the hook.
+static THREAD: AtomicU32 = AtomicU32::new(0);
+/// Whether the wheel is caught while the menu is open.
+static WHEEL: AtomicBool = AtomicBool::new(false);
+static MENU_OPEN: AtomicBool = AtomicBool::new(false);
+/// Set when the wheel turned over the open menu, until taken.
+static SCROLLED: AtomicBool = AtomicBool::new(false);
+
+/// Binds a button as the trigger, or unbinds with None. Must be called from the thread running the
+/// message loop, which the hook needs to receive events.
+pub fn bind(button: Option<MouseButton>) {
+    BOUND.store(button.map_or(0, MouseButton::code), Ordering::SeqCst);
+    HELD.store(false, Ordering::SeqCst);
+    update_hook();
+}
+
+/// Catches the wheel while the menu is open, or stops. Must be called from the thread running the message loop,
+/// like `bind`.
+pub fn watch_wheel(enabled: bool) {
+    WHEEL.store(enabled, Ordering::SeqCst);
+    SCROLLED.store(false, Ordering::SeqCst);
+    update_hook();
+}
+
+/// Tells the hook whether the menu is open; the wheel is left alone while it isn't.
+pub fn set_menu_open(open: bool) {
+    MENU_OPEN.store(open, Ordering::SeqCst);
+    SCROLLED.store(false, Ordering::SeqCst);
+}
+
+/// Whether the wheel turned over the open menu since the last call.
+pub fn take_scroll() -> bool {
+    SCROLLED.swap(false, Ordering::SeqCst)
+}
+
+/// Installs the hook while a button is bound or the wheel is watched, and removes it once neither is.
+fn update_hook() {
+    let wanted = BOUND.load(Ordering::SeqCst) != 0 || WHEEL.load(Ordering::SeqCst);
+    let hook = HOOK.load(Ordering::SeqCst);
+    if wanted && hook.is_null() {
+        THREAD.store(unsafe { GetCurrentThreadId() }, Ordering::SeqCst);
+        let hook = unsafe { SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_proc), GetModuleHandleW(null_mut()), 0) };
+        if hook.is_null() {
+            diagnostics::report_error("Failed to install the mouse hook; the mouse button trigger and wheel won't work");
+        }
+        HOOK.store(hook, Ordering::SeqCst);
+    } else if !wanted && !hook.is_null() {
+        unsafe { UnhookWindowsHookEx(hook) };
+        HOOK.store(null_mut(), Ordering::SeqCst);
+    }
+}
+
+/// Whether the bound button is down, the mouse counterpart of `Hotkey::is_held`.
+pub fn is_held() -> bool {
+    HELD.load(Ordering::SeqCst)
+}
+
+/// Swallows the bound button so other apps don't also see it, e.g. as browser back, and turns presses
+/// into the trigger hotkey's message so the overlay and the fallback menu need no separate handling.
+/// The watched wheel is swallowed too while the menu is open, so the app below doesn't scroll.
+unsafe extern "system" fn mouse_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION {
+        let info = &*(lparam as *const MSLLHOOKSTRUCT);
+        let message = wparam as u32;
+        if message == WM_MOUSEWHEEL && WHEEL.load(Ordering::SeqCst) && MENU_OPEN.load(Ordering::SeqCst) {
+            SCROLLED.store(true, Ordering::SeqCst);
+            return 1;
+        }
+        let button = match message {
+            WM_MBUTTONDOWN | WM_MBUTTONUP => 3,
+            WM_XBUTTONDOWN | WM_XBUTTONUP => match HIWORD(info.mouseData) {
+                XBUTTON1 => 4,
+                XBUTTON2 => 5,
+                _ => 0,
+            },
+            _ => 0,
+        };
+        if button != 0 && button == BOUND.load(Ordering::SeqCst) {
+            let down = message == WM_MBUTTONDOWN || message == WM_XBUTTONDOWN;
+            HELD.store(down, Ordering::SeqCst);
+            if down {
+                PostThreadMessageW(THREAD.load(Ordering::SeqCst), WM_HOTKEY, WM_HOTKEY_ID as WPARAM, 0);
+            }
+            return 1;
+        }
+    }
+    CallNextHookEx(null_mut(), code, wparam, lparam)
+}
@@ -0,0 +1,64 @@
+// MQTT broker connection for the `mqtt` action, so menu items can drive home automation
+
+use rumqttc::{Client, ConnectionError, MqttOptions, QoS};
+use std::thread;
+use std::time::Duration;
+use crate::config::MqttConfig;
+use crate::diagnostics;
+
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+/// Pause between reconnection attempts while the broker can't be reached.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+/// Publishes that may wait in the queue while disconnected.
+const QUEUE_CAPACITY: usize = 16;
+
+/// Connects to the configured broker in the background. None when no broker is configured.
+/// The connection is kept up, reconnecting as needed, until every clone of the client is dropped.
+pub fn connect(config: &MqttConfig) -> Option<Client> {
+    if config.host.is_empty() {
+        return None;
+    }
+    let mut options = MqttOptions::new(config.client_id.as_str(), config.host.as_str(), config.port);
+    options.set_keep_alive(KEEP_ALIVE);
+    if !config.username.is_empty() {
+        options.set_credentials(config.username.as_str(), config.password.as_str());
+    }
+
+    let (client, mut connection) = Client::new(options, QUEUE_CAPACITY);
+    let broker = format!("{}:{}", config.host, config.port);
+    thread::spawn(move || {
+        let mut connected = false;
+        for notification in connection.iter() {
+            match notification {
+                Ok(_) if !connected => {
+                    connected = true;
+                    diagnostics::record_event(format!("Connected to MQTT broker {}", broker));
+                }
+                Ok(_) => {}
+                Err(ConnectionError::RequestsDone) => break,
+                Err(e) => {
+                    if connected {
+                        diagnostics::record_event(format!("Lost MQTT broker {}: {}", broker, e));
+                    }
+                    connected = false;
+                    thread::sleep(RECONNECT_DELAY);
+                }
+            }
+        }
+    });
+    Some(client)
+}
+
+/// Queues a message for the broker; it is sent as soon as the connection allows.
+pub fn publish(client: &Client, topic: &str, payload: &str, qos: u8, retain: bool) -> Result<(), String> {
+    let qos = match qos {
+        0 => QoS::AtMostOnce,
+        1 => QoS::AtLeastOnce,
+        2 => QoS::ExactlyOnce,
+        _ => return Err(format!("Invalid QoS {} (0, 1 or 2)", qos)),
+    };
+    // try_publish fails instead of blocking the worker when the queue is full, e.g. while disconnected
+    client
+        .try_publish(topic, qos, retain, payload.as_bytes().to_vec())
+        .map_err(|e| format!("Failed to publish to {}: {}", topic, e))
+}
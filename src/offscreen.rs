@@ -0,0 +1,199 @@
+// Offscreen render target: an image drawn in place of a swapchain image, without a window or surface, and a
+// host-visible buffer it is copied into so the frame can be read back
+
+use ash::{vk, Device, Instance};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use crate::memory::{self, Allocation, Resource};
+use crate::render::create_buffer;
+
+/// The color image frames are drawn to and the buffer they are read back through.
+pub struct OffscreenTarget {
+    image: vk::Image,
+    memory: Allocation,
+    readback: vk::Buffer,
+    readback_memory: Allocation,
+    extent: vk::Extent2D,
+}
+
+impl OffscreenTarget {
+    pub fn new(
+        instance: &Instance,
+        device: &Device,
+        physical_device: vk::PhysicalDevice,
+        format: vk::Format,
+        extent: vk::Extent2D,
+    ) -> Result<Self, String> {
+        let image_info = vk::ImageCreateInfo::builder()
+            .image_type(vk::ImageType::TYPE_2D)
+            .format(format)
+            .extent(vk::Extent3D { width: extent.width, height: extent.height, depth: 1 })
+            .mip_levels(1)
+            .array_layers(1)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .usage(vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .initial_layout(vk::ImageLayout::UNDEFINED);
+        let image = unsafe {
+            device
+                .create_image(&image_info, None)
+                .map_err(|e| format!("Failed to create offscreen image: {:?}", e))?
+        };
+
+        let requirements = unsafe { device.get_image_memory_requirements(image) };
+        let memory_properties = unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        let memory = memory::allocate(
+            device,
+            memory_properties,
+            requirements,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            Resource::Optimal,
+        )
+        .map_err(|e| format!("Failed to allocate offscreen image memory: {}", e))?;
+        unsafe {
+            device
+                .bind_image_memory(image, memory.memory, memory.offset)
+                .map_err(|e| format!("Failed to bind offscreen image memory: {:?}", e))?;
+        }
+
+        let (readback, readback_memory) = create_buffer(
+            instance,
+            device,
+            physical_device,
+            (extent.width * extent.height * 4) as vk::DeviceSize,
+            vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+
+        Ok(Self { image, memory, readback, readback_memory, extent })
+    }
+
+    /// The image the render pass draws to, in place of a swapchain image.
+    pub fn image(&self) -> vk::Image {
+        self.image
+    }
+
+    /// Records copying the drawn image, left in PRESENT_SRC_KHR by the render pass like a swapchain image, into
+    /// the readback buffer. The image goes back to PRESENT_SRC_KHR for the next frame.
+    pub fn record_readback(&self, device: &Device, command_buffer: vk::CommandBuffer) {
+        let subresource_range = vk::ImageSubresourceRange::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .base_mip_level(0)
+            .level_count(1)
+            .base_array_layer(0)
+            .layer_count(1)
+            .build();
+        let barrier = |old_layout, new_layout, src_access_mask, dst_access_mask| {
+            vk::ImageMemoryBarrier::builder()
+                .old_layout(old_layout)
+                .new_layout(new_layout)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(self.image)
+                .subresource_range(subresource_range)
+                .src_access_mask(src_access_mask)
+                .dst_access_mask(dst_access_mask)
+                .build()
+        };
+        let before = barrier(
+            vk::ImageLayout::PRESENT_SRC_KHR,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            vk::AccessFlags::TRANSFER_READ,
+        );
+        let after = barrier(
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            vk::ImageLayout::PRESENT_SRC_KHR,
+            vk::AccessFlags::TRANSFER_READ,
+            vk::AccessFlags::empty(),
+        );
+        let region = vk::BufferImageCopy::builder()
+            .buffer_offset(0)
+            .buffer_row_length(0) // Tightly packed
+            .buffer_image_height(0)
+            .image_subresource(vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            })
+            .image_extent(vk::Extent3D { width: self.extent.width, height: self.extent.height, depth: 1 })
+            .build();
+        // The host reads the buffer once the frame's fence is signaled
+        let host_barrier = vk::BufferMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::HOST_READ)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .buffer(self.readback)
+            .offset(0)
+            .size(vk::WHOLE_SIZE)
+            .build();
+
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[before],
+            );
+            device.cmd_copy_image_to_buffer(
+                command_buffer,
+                self.image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                self.readback,
+                &[region],
+            );
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE | vk::PipelineStageFlags::HOST,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[host_barrier],
+                &[after],
+            );
+        }
+    }
+
+    /// The last frame read back, as RGBA rows from the top. The image is BGRA like the swapchain's.
+    pub fn read_pixels(&self, device: &Device) -> Result<Vec<u8>, String> {
+        let size = (self.extent.width * self.extent.height * 4) as usize;
+        let mut pixels = vec![0u8; size];
+        let data_ptr = memory::mapped(device, &self.readback_memory)
+            .map_err(|e| format!("Failed to map readback memory: {}", e))? as *const u8;
+        unsafe {
+            data_ptr.copy_to_nonoverlapping(pixels.as_mut_ptr(), size);
+        }
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+        Ok(pixels)
+    }
+
+    pub fn destroy(&self, device: &Device) {
+        unsafe {
+            device.destroy_buffer(self.readback, None);
+            device.destroy_image(self.image, None);
+        }
+        memory::free(device, self.readback_memory);
+        memory::free(device, self.memory);
+    }
+}
+
+/// Writes RGBA rows from the top as an 8-bit PNG.
+pub fn write_png(path: &Path, width: u32, height: u32, pixels: &[u8]) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(pixels))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
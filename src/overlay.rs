@@ -1,8 +1,72 @@
 // Manages overlay content and radial menu rendering
 
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use crate::action::ActionEvent;
+use crate::dial::DialState;
+use crate::config::{Anchor, EdgesConfig, OrientationConfig, RingsConfig};
+use crate::edges::Edge;
+use crate::icons::{self, IconPixels};
+use crate::menu::{MenuItem, DEFAULT_SEGMENTS};
+use crate::provider::{MenuProvider, Page};
+use crate::rings;
+use crate::search::SearchState;
+
+/// How often placeholders such as "{time}" are re-resolved while the overlay is visible.
+const TEXT_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// An item's text with placeholders resolved.
+#[derive(Clone)]
+pub struct ItemText {
+    pub label: String,
+    pub description: String,
+    pub badge: Option<String>,
+}
+
+#[derive(Clone)]
 pub struct OverlayContent {
     pub visible: bool,
     pub selected_segment: Option<i32>, // Track the selected segment of the radial menu
+    pub menu_items: Vec<MenuItem>, // All configured items
+    pub items: Vec<MenuItem>, // Currently visible items, shown as segments in order around the ring
+    pub segment_progress: HashMap<i32, Option<f32>>, // Running actions by segment, None while indeterminate
+    pub segment_enabled: Vec<bool>, // Item conditions, evaluated when the overlay opens
+    pub segment_on: Vec<bool>, // Toggle states, read with the text so they follow actions that flip them
+    pub segment_pulse: Vec<Option<Instant>>, // When each pulsing segment began to pulse, None for the others
+    pub item_text: Vec<ItemText>, // Resolved text of each visible item
+    pub item_icons: Vec<Option<IconPixels>>, // Icon of each visible item, None for items without one
+    pub dwell_time: Option<Duration>, // Dwell-to-select delay, None when the mode is disabled
+    pub dwell_progress: f32, // 0.0..1.0 progress of the dwell timer on the hovered segment
+    pub edges: Option<EdgesConfig>, // Snap zones past the ring, None when the mode is disabled
+    pub selected_edge: Option<Edge>, // Zone under the cursor; takes the place of a selected segment
+    pub rings: Option<RingsConfig>, // Staged concentric rings, None for the single ring
+    pub orientation: OrientationConfig, // Rotation and order of the segments, for drawing and hit testing alike
+    pub ring_stage: usize, // Index of the outermost ring revealed since the menu opened
+    pub provider: Option<&'static (dyn MenuProvider + Sync)>, // Builds the menu each time it opens, for generated profiles
+    pub page: Option<Page>, // Generated page shown instead of the menu, such as the recently run items
+    pub latched: bool, // Stays open after the trigger was released, until the next press
+    pub cursor_distance: f32, // From the ring's center in NDC, as of the last selection update
+    pub number_badges: bool, // Draw the number key that selects each of the first nine segments
+    pub hysteresis: f32, // Radians past a segment's boundary before the selection leaves it
+    pub inner_grace: Duration, // How long the selection is kept while the cursor is inside the inner radius
+    pub angle_smoothing: Duration, // Time constant of the cursor angle's smoothing, zero for none
+    pub anchor: Anchor, // Where the active menu opens
+    pub warp_cursor: bool, // Start every selection from the ring's center, putting the cursor back afterwards
+    pub reanchor: bool, // Measure the cursor of a pinned menu from where it was when the menu opened
+    pub cursor_origin: Option<(i32, i32)>, // Screen point standing in for the ring's center, when the ring opened off the cursor
+    pub opened_at: Instant, // When the menu was last shown, which its open animation runs from
+    pub background_luminance: Option<f32>, // Screen brightness under the menu when it opened, for adaptive contrast
+    pub dial: Option<DialState>, // Dial segment being turned; its segment stays selected meanwhile
+    pub search: Option<SearchState>, // Query being typed, whose matches replace the menu's items
+    dwell_segment: Option<i32>,
+    dwell_start: Instant,
+    hover_segment: Option<i32>,
+    hover_start: Instant,
+    text_refreshed: Instant,
+    center_since: Option<Instant>, // When the cursor last moved inside the inner radius, None while it is on a ring
+    cursor: (i32, i32),
+    cursor_moved: Instant,
+    smoothed_angle: Option<(f32, Instant)>, // Smoothed cursor angle and when it was last updated, None off the rings
     // Add other fields as needed
 }
 
@@ -11,6 +75,46 @@ impl OverlayContent {
         Self {
             visible: false,
             selected_segment: None,
+            menu_items: Vec::new(),
+            items: Vec::new(),
+            segment_progress: HashMap::new(),
+            segment_enabled: Vec::new(),
+            segment_on: Vec::new(),
+            segment_pulse: Vec::new(),
+            item_text: Vec::new(),
+            item_icons: Vec::new(),
+            dwell_time: None,
+            dwell_progress: 0.0,
+            edges: None,
+            selected_edge: None,
+            rings: None,
+            orientation: OrientationConfig::default(),
+            ring_stage: 0,
+            provider: None,
+            page: None,
+            latched: false,
+            cursor_distance: 0.0,
+            number_badges: false,
+            hysteresis: 0.0,
+            inner_grace: Duration::ZERO,
+            angle_smoothing: Duration::ZERO,
+            anchor: Anchor::Cursor,
+            warp_cursor: false,
+            reanchor: false,
+            cursor_origin: None,
+            opened_at: Instant::now(),
+            background_luminance: None,
+            dial: None,
+            search: None,
+            center_since: None,
+            cursor: (0, 0),
+            cursor_moved: Instant::now(),
+            smoothed_angle: None,
+            dwell_segment: None,
+            dwell_start: Instant::now(),
+            hover_segment: None,
+            hover_start: Instant::now(),
+            text_refreshed: Instant::now(),
             // Initialize other fields
         }
     }
@@ -18,4 +122,198 @@ impl OverlayContent {
     pub fn toggle_visibility(&mut self) {
         self.visible = !self.visible;
     }
-}
\ No newline at end of file
+
+    /// Number of segments to draw and hit-test.
+    pub fn segment_count(&self) -> i32 {
+        if self.menu_items.is_empty() {
+            DEFAULT_SEGMENTS
+        } else {
+            self.items.len() as i32
+        }
+    }
+
+    /// Share of the ring of each segment; segments without an item or a weight get 1.0.
+    pub fn segment_weights(&self) -> Vec<f32> {
+        (0..self.segment_count() as usize)
+            .map(|segment| self.items.get(segment).and_then(|item| item.weight).filter(|&weight| weight > 0.0).unwrap_or(1.0))
+            .collect()
+    }
+
+    /// Evaluates item conditions, rebuilding the visible items.
+    /// Called when the overlay is shown so checks don't run every frame.
+    pub fn refresh_items(&mut self) {
+        self.items = self.menu_items.iter().filter(|item| item.is_visible()).cloned().collect();
+        self.segment_enabled = self.items.iter().map(|item| item.is_enabled()).collect();
+        self.item_icons = icons::item_icons(self);
+        self.refresh_text();
+    }
+
+    /// Resolves the placeholders in every visible item's text and reads the toggle and pulse states. Segments that
+    /// keep pulsing keep their start, so the animation runs on smoothly.
+    pub fn refresh_text(&mut self) {
+        self.item_text = self
+            .items
+            .iter()
+            .map(|item| ItemText {
+                label: item.display_label(),
+                description: item.display_description(),
+                badge: item.display_badge(),
+            })
+            .collect();
+        self.segment_on = self.items.iter().map(|item| item.is_on()).collect();
+        let previous = std::mem::take(&mut self.segment_pulse);
+        self.segment_pulse = self
+            .items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let since = previous.get(index).copied().flatten();
+                item.is_pulsing().then(|| since.unwrap_or_else(Instant::now))
+            })
+            .collect();
+        self.text_refreshed = Instant::now();
+    }
+
+    /// Re-resolves item text on a timer while the overlay is visible, so clocks and players stay current.
+    pub fn refresh_text_if_due(&mut self) {
+        if self.visible && self.text_refreshed.elapsed() >= TEXT_REFRESH_INTERVAL {
+            self.refresh_text();
+        }
+    }
+
+    /// Resolved text of a segment's item.
+    pub fn item_text(&self, segment: i32) -> Option<&ItemText> {
+        self.item_text.get(segment as usize)
+    }
+
+    /// How long a segment has been pulsing, None while it isn't.
+    pub fn pulse_time(&self, segment: i32) -> Option<Duration> {
+        self.segment_pulse.get(segment as usize).copied().flatten().map(|since| since.elapsed())
+    }
+
+    /// Whether a toggle segment's state is on.
+    pub fn is_segment_on(&self, segment: i32) -> bool {
+        self.segment_on.get(segment as usize).copied().unwrap_or(false)
+    }
+
+    /// Whether a segment can be selected. Segments without an item are always enabled.
+    pub fn is_segment_enabled(&self, segment: i32) -> bool {
+        self.segment_enabled.get(segment as usize).copied().unwrap_or(true)
+    }
+
+    /// Tracks progress of running actions so their segments can show it.
+    pub fn apply_action_event(&mut self, event: ActionEvent) {
+        match event {
+            ActionEvent::Progress { segment, fraction } => {
+                self.segment_progress.insert(segment, fraction);
+            }
+            ActionEvent::Finished { segment, .. } => {
+                self.segment_progress.remove(&segment);
+            }
+        }
+    }
+
+    /// Restarts the hover timer whenever the selected segment changes.
+    pub fn track_hover(&mut self) {
+        if self.selected_segment != self.hover_segment {
+            self.hover_segment = self.selected_segment;
+            self.hover_start = Instant::now();
+        }
+    }
+
+    /// Notes where the cursor is, in window pixels, restarting the still timer when it has moved.
+    pub fn track_movement(&mut self, cursor: (i32, i32)) {
+        if cursor != self.cursor {
+            self.cursor = cursor;
+            self.cursor_moved = Instant::now();
+        }
+    }
+
+    /// How long the cursor has kept still over the menu, counted from when the menu opened at most.
+    pub fn still_duration(&self) -> Duration {
+        self.cursor_moved.elapsed().min(self.opened_at.elapsed())
+    }
+
+    /// How long the current segment has been hovered, None when nothing is selected.
+    pub fn hover_duration(&self) -> Option<Duration> {
+        self.hover_segment.map(|_| self.hover_start.elapsed())
+    }
+
+    /// Advances the dwell timer for the hovered segment.
+    /// Hides the overlay (which executes the selection) once the segment has been hovered long enough.
+    pub fn update_dwell(&mut self) {
+        let dwell_time = match self.dwell_time {
+            Some(dwell_time) => dwell_time,
+            None => return,
+        };
+
+        // Restart the timer whenever the hovered segment changes
+        if self.selected_segment != self.dwell_segment {
+            self.dwell_segment = self.selected_segment;
+            self.dwell_start = Instant::now();
+            self.dwell_progress = 0.0;
+        }
+
+        // A dial is turned by circling on its segment, which mustn't count as dwelling there
+        if self.selected_segment.is_none() || self.dial.is_some() {
+            self.dwell_progress = 0.0;
+            return;
+        }
+
+        let elapsed = self.dwell_start.elapsed().as_secs_f32();
+        self.dwell_progress = (elapsed / dwell_time.as_secs_f32()).min(1.0);
+
+        if self.dwell_progress >= 1.0 {
+            println!("Dwell selected segment {:?}", self.selected_segment);
+            self.visible = false;
+        }
+    }
+
+    /// Hides the overlay without running anything.
+    pub fn dismiss(&mut self) {
+        self.selected_segment = None;
+        self.dial = None;
+        self.selected_edge = None;
+        self.visible = false;
+    }
+
+    /// Called while the cursor is inside the inner radius. Returns whether the selection is kept, which it is
+    /// for `inner_grace` after the cursor got there, so slipping back towards the center doesn't drop it.
+    pub fn hold_in_center(&mut self) -> bool {
+        let since = *self.center_since.get_or_insert_with(Instant::now);
+        self.selected_segment.is_some() && since.elapsed() < self.inner_grace
+    }
+
+    /// Called while the cursor is on a ring.
+    pub fn left_center(&mut self) {
+        self.center_since = None;
+    }
+
+    /// The cursor's angle on the ring smoothed over `angle_smoothing`, so jitter at a boundary doesn't flick the
+    /// selection between neighbors. Smoothing starts over from the cursor each time it reaches a ring.
+    pub fn smooth_angle(&mut self, angle: f32) -> f32 {
+        if self.angle_smoothing.is_zero() {
+            return angle;
+        }
+        let now = Instant::now();
+        let smoothed = match self.smoothed_angle {
+            Some((previous, at)) if at >= self.opened_at => {
+                rings::smooth_angle(previous, angle, (now - at).as_secs_f32(), self.angle_smoothing.as_secs_f32())
+            }
+            _ => angle,
+        };
+        self.smoothed_angle = Some((smoothed, now));
+        smoothed
+    }
+
+    /// Called while the cursor is off the rings, so smoothing doesn't carry across from where it left.
+    pub fn reset_smoothing(&mut self) {
+        self.smoothed_angle = None;
+    }
+
+    /// Clears the dwell timer, e.g. when the overlay is hidden.
+    pub fn reset_dwell(&mut self) {
+        self.dwell_segment = None;
+        self.dwell_progress = 0.0;
+    }
+}
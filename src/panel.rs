@@ -0,0 +1,217 @@
+// Transient panels that actions open for follow-up UI: result lists, confirmations and progress
+
+use winapi::um::winuser::{MessageBoxW, IDYES, MB_ICONQUESTION, MB_OK, MB_YESNO};
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Mutex;
+use crate::accessibility;
+use crate::text::{TextBatch, TEXT_BACKGROUND};
+use crate::wake;
+use crate::window::to_wstring;
+
+/// Most lines shown in a panel; further lines are summarized.
+const MAX_LIST_LINES: usize = 20;
+const MAX_PANEL_WIDTH: f32 = 520.0;
+const PADDING: f32 = 10.0;
+
+const TITLE_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const BODY_COLOR: [f32; 4] = [0.85, 0.85, 0.85, 1.0];
+const HINT_COLOR: [f32; 4] = [0.55, 0.55, 0.6, 1.0];
+const PROGRESS_FILL: [f32; 4] = [0.35, 0.75, 1.0, 1.0];
+const PROGRESS_IDLE: [f32; 4] = [0.2, 0.4, 0.55, 1.0];
+
+/// What a panel shows.
+#[derive(Clone, Debug)]
+pub enum PanelContent {
+    /// Lines of output, closed with Enter or Esc.
+    List { title: String, lines: Vec<String> },
+    /// A yes/no question answered with Enter or Esc.
+    Confirm { title: String, message: String },
+    /// A progress bar; `fraction` is None while indeterminate. Closes when its scope ends.
+    Progress { title: String, fraction: Option<f32> },
+}
+
+struct ActivePanel {
+    id: u64,
+    content: PanelContent,
+    reply: Option<Sender<bool>>,
+}
+
+static PANEL: Mutex<Option<ActivePanel>> = Mutex::new(None);
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+/// Set by the fallback menu, which has no overlay to draw panels on.
+static MESSAGE_BOXES: AtomicBool = AtomicBool::new(false);
+
+/// An open panel that closes when dropped, unless another panel replaced it first.
+pub struct PanelScope {
+    id: u64,
+}
+
+impl PanelScope {
+    /// Replaces the panel's content, e.g. to advance a progress bar.
+    pub fn update(&self, content: PanelContent) {
+        if let Some(panel) = PANEL.lock().unwrap().as_mut().filter(|panel| panel.id == self.id) {
+            panel.content = content;
+        }
+    }
+}
+
+impl Drop for PanelScope {
+    fn drop(&mut self) {
+        let mut panel = PANEL.lock().unwrap();
+        if panel.as_ref().map_or(false, |panel| panel.id == self.id) {
+            *panel = None;
+        }
+    }
+}
+
+/// Shows lists and questions as message boxes instead, and progress not at all.
+pub fn use_message_boxes() {
+    MESSAGE_BOXES.store(true, Ordering::SeqCst);
+}
+
+/// Opens a panel for the lifetime of the returned scope. Any open panel is replaced.
+pub fn open(content: PanelContent) -> PanelScope {
+    if MESSAGE_BOXES.load(Ordering::SeqCst) {
+        return PanelScope { id: 0 };
+    }
+    PanelScope { id: replace(content, None) }
+}
+
+/// Opens a panel that stays up until the user dismisses it.
+pub fn show(content: PanelContent) {
+    if MESSAGE_BOXES.load(Ordering::SeqCst) {
+        if let PanelContent::List { title, lines } = content {
+            message_box(&title, &lines.join("\n"), MB_OK);
+        }
+        return;
+    }
+    replace(content, None);
+}
+
+/// Asks a yes/no question and blocks until it is answered. Call from a worker thread.
+/// Returns false if the panel is replaced before an answer.
+pub fn confirm(title: &str, message: &str) -> bool {
+    if MESSAGE_BOXES.load(Ordering::SeqCst) {
+        return message_box(title, message, MB_YESNO | MB_ICONQUESTION) == IDYES;
+    }
+    let (reply, answer) = channel();
+    let content = PanelContent::Confirm {
+        title: title.to_string(),
+        message: message.to_string(),
+    };
+    let _scope = PanelScope { id: replace(content, Some(reply)) };
+    answer.recv().unwrap_or(false)
+}
+
+fn replace(content: PanelContent, reply: Option<Sender<bool>>) -> u64 {
+    let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+    // Dropping a previous panel's reply sender cancels its question
+    *PANEL.lock().unwrap() = Some(ActivePanel { id, content, reply });
+    wake::wake();
+    id
+}
+
+fn message_box(title: &str, text: &str, style: u32) -> i32 {
+    let title = to_wstring(title);
+    let text = to_wstring(text);
+    unsafe { MessageBoxW(null_mut(), text.as_ptr(), title.as_ptr(), style) }
+}
+
+pub fn is_open() -> bool {
+    PANEL.lock().unwrap().is_some()
+}
+
+/// Whether the open panel is waiting for Enter or Esc; progress panels aren't.
+pub fn accepts_input() -> bool {
+    PANEL
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map_or(false, |panel| !matches!(panel.content, PanelContent::Progress { .. }))
+}
+
+/// Handles Enter (`accepted`) or Esc. Closes lists and questions; progress panels stay until their scope ends.
+pub fn answer(accepted: bool) {
+    let mut panel = PANEL.lock().unwrap();
+    if let Some(active) = panel.as_ref() {
+        if matches!(active.content, PanelContent::Progress { .. }) {
+            return;
+        }
+        if let Some(reply) = &active.reply {
+            let _ = reply.send(accepted);
+        }
+        *panel = None;
+    }
+}
+
+/// Lays out the open panel in the middle of the window.
+pub fn layout_panel(batch: &mut TextBatch, time: f32) {
+    let panel = PANEL.lock().unwrap();
+    let content = match panel.as_ref() {
+        Some(panel) => &panel.content,
+        None => return,
+    };
+
+    let max_text_width = MAX_PANEL_WIDTH - PADDING * 2.0;
+    let (title, mut body, hint, progress) = match content {
+        PanelContent::List { title, lines } => {
+            let body: Vec<String> = lines.iter().flat_map(|line| batch.wrap(line, max_text_width)).collect();
+            (title, body, "Enter / Esc to close", None)
+        }
+        PanelContent::Confirm { title, message } => {
+            (title, batch.wrap(message, max_text_width), "Enter to confirm, Esc to cancel", None)
+        }
+        PanelContent::Progress { title, fraction } => (title, Vec::new(), "", Some(*fraction)),
+    };
+    if body.len() > MAX_LIST_LINES {
+        let hidden = body.len() - MAX_LIST_LINES;
+        body.truncate(MAX_LIST_LINES);
+        body.push(format!("... {} more lines", hidden));
+    }
+
+    let line_height = batch.metrics().cell_height;
+    let content_width = std::iter::once(title.as_str())
+        .chain(body.iter().map(String::as_str))
+        .chain(std::iter::once(hint))
+        .map(|line| batch.measure(line).0)
+        .fold(160.0f32, f32::max)
+        .min(max_text_width);
+    let rows = 1 + body.len() + if hint.is_empty() { 0 } else { 1 } + if progress.is_some() { 1 } else { 0 };
+    let box_width = content_width + PADDING * 2.0;
+    let box_height = line_height * rows as f32 + PADDING * 2.0;
+
+    let (width, height) = batch.size();
+    let x = ((width - box_width) / 2.0).max(0.0);
+    let mut y = ((height - box_height) / 2.0).max(0.0);
+
+    batch.rect(x, y, box_width, box_height, TEXT_BACKGROUND);
+    y += PADDING;
+    batch.text(x + PADDING, y, title, TITLE_COLOR);
+    y += line_height;
+    for line in body.iter() {
+        batch.text(x + PADDING, y, line, BODY_COLOR);
+        y += line_height;
+    }
+    if let Some(fraction) = progress {
+        let bar_y = y + line_height / 2.0 - 2.0;
+        match fraction {
+            Some(fraction) => batch.rect(x + PADDING, bar_y, content_width * fraction.clamp(0.0, 1.0), 4.0, PROGRESS_FILL),
+            None if accessibility::reduced_motion() => {
+                // Indeterminate without motion: the whole bar, dimmed
+                batch.rect(x + PADDING, bar_y, content_width, 4.0, PROGRESS_IDLE);
+            }
+            None => {
+                // Indeterminate: a block sliding back and forth
+                let block = content_width * 0.25;
+                let offset = (time * 1.5).sin() * 0.5 + 0.5;
+                batch.rect(x + PADDING + (content_width - block) * offset, bar_y, block, 4.0, PROGRESS_FILL);
+            }
+        }
+        y += line_height;
+    }
+    if !hint.is_empty() {
+        batch.text(x + PADDING, y, hint, HINT_COLOR);
+    }
+}
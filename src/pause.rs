@@ -0,0 +1,44 @@
+// Pausing the overlay, e.g. before starting a game with anti-cheat or sharing the screen: the hotkeys and hooks
+// are released and the renderer gives up the GPU until resumed. The pause hotkey and the IPC commands still work
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use crate::{diagnostics, wake};
+
+/// A change asked for by the pause hotkey or an IPC command.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Request {
+    Pause,
+    Resume,
+    Toggle,
+}
+
+static PAUSED: AtomicBool = AtomicBool::new(false);
+static REQUEST: Mutex<Option<Request>> = Mutex::new(None);
+
+/// Asks the main loop to pause or resume. Safe to call from any thread.
+pub fn request(request: Request) {
+    *REQUEST.lock().unwrap() = Some(request);
+    wake::wake();
+}
+
+/// Applies the pending request, returning whether the overlay is now paused if that changed.
+pub fn take_change() -> Option<bool> {
+    let request = REQUEST.lock().unwrap().take()?;
+    let was_paused = PAUSED.load(Ordering::SeqCst);
+    let paused = match request {
+        Request::Pause => true,
+        Request::Resume => false,
+        Request::Toggle => !was_paused,
+    };
+    if paused == was_paused {
+        return None;
+    }
+    PAUSED.store(paused, Ordering::SeqCst);
+    diagnostics::record_event(if paused { "Overlay paused" } else { "Overlay resumed" });
+    Some(paused)
+}
+
+pub fn is_paused() -> bool {
+    PAUSED.load(Ordering::SeqCst)
+}
@@ -0,0 +1,168 @@
+// Color picker mode: a magnified loupe follows the cursor, and Enter or the trigger copies the color of
+// the pixel in its middle to the clipboard
+
+use serde::{Deserialize, Serialize};
+use winapi::ctypes::c_void;
+use winapi::shared::windef::{HGDIOBJ, POINT};
+use winapi::um::wingdi::*;
+use winapi::um::winuser::{GetCursorPos, GetDC, ReleaseDC};
+use std::mem::{size_of, zeroed};
+use std::ptr::null_mut;
+use std::sync::Mutex;
+use crate::loupe::{CAPTURE_SIZE, LOUPE_PIXELS};
+use crate::text::{TextBatch, TEXT_BACKGROUND};
+use crate::{clipboard, diagnostics, toast, wake};
+
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const HINT_COLOR: [f32; 4] = [0.55, 0.55, 0.6, 1.0];
+const PADDING: f32 = 6.0;
+/// Space between the loupe and the color label below it, in pixels.
+const LABEL_GAP: f32 = 8.0;
+
+/// How a picked color is written to the clipboard.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorFormat {
+    /// "#1E90FF"
+    #[default]
+    Hex,
+    /// "30, 144, 255"
+    Rgb,
+}
+
+impl ColorFormat {
+    fn format(self, [r, g, b]: [u8; 3]) -> String {
+        match self {
+            ColorFormat::Hex => format!("#{:02X}{:02X}{:02X}", r, g, b),
+            ColorFormat::Rgb => format!("{}, {}, {}", r, g, b),
+        }
+    }
+}
+
+struct State {
+    active: bool,
+    format: ColorFormat,
+    color: Option<[u8; 3]>, // Pixel under the cursor at the last capture
+}
+
+static STATE: Mutex<State> = Mutex::new(State { active: false, format: ColorFormat::Hex, color: None });
+
+/// Enters picker mode; called by the `pick_color` action from its worker thread.
+pub fn start(format: ColorFormat) {
+    let mut state = STATE.lock().unwrap();
+    state.active = true;
+    state.format = format;
+    state.color = None;
+    drop(state);
+    wake::wake();
+}
+
+pub fn is_active() -> bool {
+    STATE.lock().unwrap().active
+}
+
+/// Leaves picker mode without copying, on Esc.
+pub fn cancel() {
+    let mut state = STATE.lock().unwrap();
+    state.active = false;
+    state.color = None;
+}
+
+/// Copies the color under the cursor and leaves picker mode.
+pub fn accept() {
+    let (format, color) = {
+        let mut state = STATE.lock().unwrap();
+        state.active = false;
+        (state.format, state.color.take())
+    };
+    let text = match color {
+        Some(color) => format.format(color),
+        None => return,
+    };
+    match clipboard::set_text(&text) {
+        Ok(()) => toast::push(format!("Copied {}", text)),
+        Err(e) => diagnostics::report_error(e),
+    }
+}
+
+/// Captures the screen pixels around the cursor as BGRA, top row first, and remembers the middle one.
+/// The overlay is a layered window, which BitBlt leaves out, so the loupe never sees itself.
+pub fn capture() -> Option<Vec<u32>> {
+    let size = CAPTURE_SIZE as i32;
+    let mut pixels = vec![0u32; (CAPTURE_SIZE * CAPTURE_SIZE) as usize];
+    unsafe {
+        let mut cursor: POINT = POINT { x: 0, y: 0 };
+        GetCursorPos(&mut cursor);
+
+        let screen = GetDC(null_mut());
+        if screen.is_null() {
+            return None;
+        }
+        let memory_dc = CreateCompatibleDC(screen);
+        let mut info: BITMAPINFO = zeroed();
+        info.bmiHeader.biSize = size_of::<BITMAPINFOHEADER>() as u32;
+        info.bmiHeader.biWidth = size;
+        info.bmiHeader.biHeight = -size; // Top-down rows
+        info.bmiHeader.biPlanes = 1;
+        info.bmiHeader.biBitCount = 32;
+        info.bmiHeader.biCompression = BI_RGB;
+        let mut bits: *mut c_void = null_mut();
+        let bitmap = CreateDIBSection(memory_dc, &info, DIB_RGB_COLORS, &mut bits, null_mut(), 0);
+        let captured = !bitmap.is_null() && {
+            let previous = SelectObject(memory_dc, bitmap as HGDIOBJ);
+            let copied = BitBlt(memory_dc, 0, 0, size, size, screen, cursor.x - size / 2, cursor.y - size / 2, SRCCOPY);
+            GdiFlush();
+            if copied != 0 {
+                (bits as *const u32).copy_to_nonoverlapping(pixels.as_mut_ptr(), pixels.len());
+            }
+            SelectObject(memory_dc, previous);
+            DeleteObject(bitmap as HGDIOBJ);
+            copied != 0
+        };
+        DeleteDC(memory_dc);
+        ReleaseDC(null_mut(), screen);
+        if !captured {
+            return None;
+        }
+    }
+
+    // GDI leaves the alpha byte zero; the loupe shader treats zero alpha as nothing to draw
+    for pixel in pixels.iter_mut() {
+        *pixel |= 0xFF00_0000;
+    }
+    let middle = pixels[pixels.len() / 2];
+    STATE.lock().unwrap().color = Some([(middle >> 16) as u8, (middle >> 8) as u8, middle as u8]);
+    Some(pixels)
+}
+
+/// Lays out the picked color, a swatch of it and the keys under the loupe, which is centered in the window.
+pub fn layout_picker(batch: &mut TextBatch) {
+    let (active, format, color) = {
+        let state = STATE.lock().unwrap();
+        (state.active, state.format, state.color)
+    };
+    let color = match color {
+        Some(color) if active => color,
+        _ => return,
+    };
+
+    let value = format.format(color);
+    let hint = "Enter to copy, Esc to cancel";
+    let line_height = batch.metrics().cell_height;
+    let swatch = line_height;
+    let content_width = (swatch + PADDING + batch.measure(&value).0).max(batch.measure(hint).0);
+    let box_width = content_width + PADDING * 2.0;
+    let box_height = line_height * 2.0 + PADDING * 2.0;
+
+    let (width, height) = batch.size();
+    let x = (width - box_width) / 2.0;
+    let mut y = height / 2.0 + LOUPE_PIXELS / 2.0 + LABEL_GAP;
+    batch.rect(x, y, box_width, box_height, TEXT_BACKGROUND);
+    y += PADDING;
+    let [r, g, b] = color;
+    let swatch_color = [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0];
+    batch.rect(x + PADDING, y, swatch, swatch, swatch_color);
+    batch.text(x + PADDING * 2.0 + swatch, y, &value, TEXT_COLOR);
+    y += line_height;
+    batch.text(x + PADDING, y, hint, HINT_COLOR);
+}
@@ -0,0 +1,109 @@
+// System power actions: locking, sleep, hibernate, shutdown, restart and turning the displays off
+
+use serde::{Deserialize, Serialize};
+use winapi::shared::minwindef::FALSE;
+use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::handleapi::CloseHandle;
+use winapi::um::powrprof::SetSuspendState;
+use winapi::um::processthreadsapi::{GetCurrentProcess, OpenProcessToken};
+use winapi::um::reason::{SHTDN_REASON_FLAG_PLANNED, SHTDN_REASON_MAJOR_OTHER, SHTDN_REASON_MINOR_OTHER};
+use winapi::um::securitybaseapi::AdjustTokenPrivileges;
+use winapi::um::winbase::LookupPrivilegeValueW;
+use winapi::um::winnt::{
+    HANDLE, LUID_AND_ATTRIBUTES, SE_PRIVILEGE_ENABLED, SE_SHUTDOWN_NAME, TOKEN_ADJUST_PRIVILEGES, TOKEN_PRIVILEGES, TOKEN_QUERY,
+};
+use winapi::um::winuser::{
+    ExitWindowsEx, LockWorkStation, PostMessageW, EWX_POWEROFF, EWX_REBOOT, HWND_BROADCAST, SC_MONITORPOWER, WM_SYSCOMMAND,
+};
+use std::mem::zeroed;
+use std::ptr::null_mut;
+use std::thread;
+use std::time::Duration;
+use crate::window::to_wstring;
+
+/// `lParam` of SC_MONITORPOWER that turns the displays off.
+const MONITOR_OFF: isize = 2;
+/// Wait before the displays are turned off, so the mouse movement of closing the menu doesn't wake them right away.
+const DISPLAY_OFF_DELAY: Duration = Duration::from_millis(500);
+
+/// What the `power` action does, written in snake case like "display_off".
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerMode {
+    Lock,
+    Sleep,
+    Hibernate,
+    Shutdown,
+    Restart,
+    DisplayOff,
+}
+
+impl PowerMode {
+    /// What the toast says once done, for the modes the user is still there to see it.
+    pub fn done_message(self) -> Option<&'static str> {
+        match self {
+            PowerMode::DisplayOff => Some("Displays turned off"),
+            _ => None,
+        }
+    }
+}
+
+pub fn run(mode: PowerMode) -> Result<(), String> {
+    match mode {
+        PowerMode::Lock => {
+            if unsafe { LockWorkStation() } == 0 {
+                return Err(format!("Failed to lock the workstation (error {})", unsafe { GetLastError() }));
+            }
+        }
+        PowerMode::Sleep | PowerMode::Hibernate => {
+            enable_shutdown_privilege()?;
+            let hibernate = (mode == PowerMode::Hibernate) as u8;
+            if unsafe { SetSuspendState(hibernate, FALSE as u8, FALSE as u8) } == 0 {
+                let error = unsafe { GetLastError() };
+                return Err(format!("Failed to suspend (error {}); is it enabled in the power options?", error));
+            }
+        }
+        PowerMode::Shutdown | PowerMode::Restart => {
+            enable_shutdown_privilege()?;
+            let flags = if mode == PowerMode::Restart { EWX_REBOOT } else { EWX_POWEROFF };
+            let reason = SHTDN_REASON_MAJOR_OTHER | SHTDN_REASON_MINOR_OTHER | SHTDN_REASON_FLAG_PLANNED;
+            if unsafe { ExitWindowsEx(flags, reason) } == 0 {
+                let error = unsafe { GetLastError() };
+                let what = if mode == PowerMode::Restart { "restart" } else { "shut down" };
+                return Err(format!("Failed to {} (error {})", what, error));
+            }
+        }
+        PowerMode::DisplayOff => {
+            thread::sleep(DISPLAY_OFF_DELAY);
+            // Posted rather than sent, so a hung window can't block the action
+            if unsafe { PostMessageW(HWND_BROADCAST, WM_SYSCOMMAND, SC_MONITORPOWER, MONITOR_OFF) } == 0 {
+                return Err(format!("Failed to turn the displays off (error {})", unsafe { GetLastError() }));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Shutting down, restarting and suspending need the shutdown privilege, which every user has but no process has
+/// enabled to begin with.
+fn enable_shutdown_privilege() -> Result<(), String> {
+    unsafe {
+        let mut token: HANDLE = null_mut();
+        if OpenProcessToken(GetCurrentProcess(), TOKEN_ADJUST_PRIVILEGES | TOKEN_QUERY, &mut token) == 0 {
+            return Err(format!("Failed to open the process token (error {})", GetLastError()));
+        }
+        let mut privileges: TOKEN_PRIVILEGES = zeroed();
+        privileges.PrivilegeCount = 1;
+        privileges.Privileges[0] = LUID_AND_ATTRIBUTES { Luid: zeroed(), Attributes: SE_PRIVILEGE_ENABLED };
+        let name = to_wstring(SE_SHUTDOWN_NAME);
+        let result = if LookupPrivilegeValueW(null_mut(), name.as_ptr(), &mut privileges.Privileges[0].Luid) == 0 {
+            Err(format!("Failed to look up the shutdown privilege (error {})", GetLastError()))
+        } else if AdjustTokenPrivileges(token, FALSE, &mut privileges, 0, null_mut(), null_mut()) == 0 {
+            Err(format!("Failed to enable the shutdown privilege (error {})", GetLastError()))
+        } else {
+            Ok(())
+        };
+        CloseHandle(token);
+        result
+    }
+}
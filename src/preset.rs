@@ -0,0 +1,133 @@
+// Single-file menu presets for sharing profiles between users
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use crate::config::{load_config, Config, Profile};
+use crate::menu::MenuItem;
+
+/// Bumped when the preset layout changes incompatibly.
+const PRESET_FORMAT: u32 = 1;
+
+/// A preset file: a header naming the menu, followed by its items.
+#[derive(Serialize, Deserialize)]
+struct Preset {
+    preset: PresetInfo,
+    #[serde(default)]
+    items: Vec<MenuItem>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PresetInfo {
+    name: String,
+    format: u32,
+}
+
+/// Appended to the config file as one more `[[profiles]]` entry.
+#[derive(Serialize)]
+struct ProfileEntry<'a> {
+    profiles: [&'a Profile; 1],
+}
+
+/// Parses the text of a preset file into a profile named after the preset.
+pub fn parse_preset(text: &str) -> Result<Profile, String> {
+    let preset: Preset = toml::from_str(text).map_err(|e| format!("Failed to parse preset: {}", e))?;
+    if preset.preset.format > PRESET_FORMAT {
+        return Err(format!(
+            "Preset format {} is newer than this version supports ({})",
+            preset.preset.format, PRESET_FORMAT
+        ));
+    }
+    Ok(Profile {
+        name: preset.preset.name,
+        items: preset.items,
+        anchor: None,
+        processes: Vec::new(),
+        passthrough: None,
+    })
+}
+
+/// Writes a profile from the config file to a preset file.
+/// Uses the active profile when `profile` is None and "<profile>.preset.toml" when `output` is None.
+/// Returns the path written.
+pub fn export_preset(config_path: &str, profile: Option<&str>, output: Option<&str>) -> Result<String, String> {
+    let config = load_config(config_path)?;
+    let name = profile.unwrap_or(&config.profile);
+    let items = config
+        .profile_items(name)
+        .ok_or_else(|| format!("Profile \"{}\" is not defined", name))?;
+
+    let preset = Preset {
+        preset: PresetInfo {
+            name: name.to_string(),
+            format: PRESET_FORMAT,
+        },
+        // Presets stand alone, so items don't refer to the config's shared definitions
+        items: items
+            .iter()
+            .cloned()
+            .map(|item| MenuItem { uses: None, ..item })
+            .collect(),
+    };
+    let text = toml::to_string(&preset).map_err(|e| format!("Failed to write preset: {}", e))?;
+
+    let output = output.map_or_else(|| format!("{}.preset.toml", file_stem(name)), str::to_string);
+    fs::write(&output, text).map_err(|e| format!("Failed to write {}: {:?}", output, e))?;
+    Ok(output)
+}
+
+/// Adds a preset file to the config file as a new profile, renaming it if the name is taken.
+/// Returns the profile name used.
+pub fn import_preset(config_path: &str, preset_path: &str) -> Result<String, String> {
+    let text = fs::read_to_string(preset_path)
+        .map_err(|e| format!("Failed to read {}: {:?}", preset_path, e))?;
+    let preset = parse_preset(&text)?;
+
+    let (config_text, config) = if Path::new(config_path).exists() {
+        let text = fs::read_to_string(config_path).map_err(|e| format!("Failed to read {}: {:?}", config_path, e))?;
+        // Loaded with its includes so profiles defined in them count as taken
+        (text, load_config(config_path)?)
+    } else {
+        (String::new(), Config::default())
+    };
+
+    let profile = Profile {
+        name: unique_profile_name(&config, &preset.name),
+        items: preset.items,
+        anchor: None,
+        processes: Vec::new(),
+        passthrough: None,
+    };
+    let entry = toml::to_string(&ProfileEntry { profiles: [&profile] })
+        .map_err(|e| format!("Failed to write profile: {}", e))?;
+
+    // Appending keeps the user's formatting and comments; check the result still parses before saving
+    let mut new_text = config_text;
+    if !new_text.is_empty() && !new_text.ends_with("\n\n") {
+        new_text.push_str(if new_text.ends_with('\n') { "\n" } else { "\n\n" });
+    }
+    new_text.push_str(&entry);
+    toml::from_str::<Config>(&new_text).map_err(|e| format!("Imported preset would break the config: {}", e))?;
+    fs::write(config_path, new_text).map_err(|e| format!("Failed to write {}: {:?}", config_path, e))?;
+
+    Ok(profile.name)
+}
+
+/// `name`, or `name (2)`, `name (3)`, ... if a profile with that name already exists.
+fn unique_profile_name(config: &Config, name: &str) -> String {
+    let taken = |candidate: &str| config.profile_items(candidate).is_some();
+    if !taken(name) {
+        return name.to_string();
+    }
+    (2..)
+        .map(|number| format!("{} ({})", name, number))
+        .find(|candidate| !taken(candidate))
+        .unwrap()
+}
+
+/// Replaces characters that can't appear in file names.
+pub fn file_stem(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
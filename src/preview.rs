@@ -0,0 +1,45 @@
+// Menu previews: a profile's menu drawn offscreen, as it appears when opened, and saved as a PNG for docs and
+// screenshots
+
+use std::path::Path;
+use crate::config::load_config;
+use crate::fill;
+use crate::offscreen::write_png;
+use crate::overlay::OverlayContent;
+use crate::preset::file_stem;
+use crate::render::Renderer;
+use crate::rings;
+use crate::window::size_for_menu;
+
+/// Draws a profile's menu (the active one by default) with the cursor in its middle and saves it as a PNG,
+/// sized like the window the menu opens in. Returns the file written.
+pub fn save_preview(config_path: &str, profile: Option<&str>, output: Option<&str>) -> Result<String, String> {
+    let config = load_config(config_path)?;
+    let name = profile.unwrap_or(&config.profile);
+    let items = config
+        .profile_items(name)
+        .ok_or_else(|| format!("Profile \"{}\" is not defined", name))?;
+
+    // The parts of the config that change how the ring is drawn
+    let mut overlay_content = OverlayContent::new();
+    overlay_content.visible = true;
+    overlay_content.menu_items = items.to_vec();
+    overlay_content.edges = if config.edges.enabled { Some(config.edges.clone()) } else { None };
+    overlay_content.number_badges = config.hotkey.number_keys && config.hotkey.number_badges;
+    overlay_content.rings = if config.rings.enabled { Some(config.rings.clone()) } else { None };
+    overlay_content.orientation = config.orientation.clone();
+    overlay_content.refresh_items();
+    // Every staged ring is revealed
+    overlay_content.ring_stage = rings::all_rings(&overlay_content).len().saturating_sub(1);
+
+    fill::configure(&config.theme);
+
+    let (width, height) = size_for_menu(rings::menu_extent(&overlay_content));
+    let mut renderer = Renderer::new_offscreen(width, height)?;
+    let pixels = renderer.render_offscreen(&overlay_content);
+    renderer.cleanup();
+
+    let output = output.map_or_else(|| format!("{}.png", file_stem(name)), str::to_string);
+    write_png(Path::new(&output), width, height, &pixels?)?;
+    Ok(output)
+}
@@ -0,0 +1,100 @@
+// Ending processes by exe name and starting them again, e.g. to kill a hung game or restart explorer.exe
+
+use winapi::shared::minwindef::{DWORD, FALSE};
+use winapi::shared::winerror::WAIT_TIMEOUT;
+use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::handleapi::CloseHandle;
+use winapi::um::processthreadsapi::{OpenProcess, TerminateProcess};
+use winapi::um::synchapi::WaitForSingleObject;
+use winapi::um::winbase::QueryFullProcessImageNameW;
+use winapi::um::winnt::{HANDLE, PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_TERMINATE, SYNCHRONIZE};
+use std::path::PathBuf;
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::condition;
+
+/// Exit code the ended processes report. Not 0, so Windows treats an ended explorer.exe as crashed and restarts it.
+const KILLED_EXIT_CODE: u32 = 1;
+/// How long ended processes get to go away before restarting gives up on them.
+const EXIT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Wait after the processes have gone for Windows to start the process again by itself, as it does explorer.exe.
+const RESPAWN_WAIT: Duration = Duration::from_millis(1500);
+
+/// Ends every process with this exe name, ignoring case. Returns how many were ended.
+pub fn kill(name: &str) -> Result<usize, String> {
+    let handles = terminate(name)?;
+    let count = handles.len();
+    for (handle, _) in handles {
+        unsafe { CloseHandle(handle) };
+    }
+    Ok(count)
+}
+
+/// Ends every process with this exe name and starts the first one's exe again once they have gone, unless
+/// something else started it in the meantime.
+pub fn restart(name: &str) -> Result<(), String> {
+    let handles = terminate(name)?;
+    let path = handles.iter().find_map(|(_, path)| path.clone());
+    let deadline = Instant::now() + EXIT_TIMEOUT;
+    let mut exited = true;
+    for (handle, _) in handles {
+        let remaining = deadline.saturating_duration_since(Instant::now()).as_millis() as DWORD;
+        if unsafe { WaitForSingleObject(handle, remaining) } == WAIT_TIMEOUT {
+            exited = false;
+        }
+        unsafe { CloseHandle(handle) };
+    }
+    if !exited {
+        return Err(format!("{} didn't exit in time to be restarted", name));
+    }
+    let path = path.ok_or_else(|| format!("Ended {} but couldn't find its exe to restart it", name))?;
+    thread::sleep(RESPAWN_WAIT);
+    if condition::is_process_running(name) {
+        return Ok(());
+    }
+    Command::new(&path)
+        .spawn()
+        .map_err(|e| format!("Failed to restart {}: {:?}", path.display(), e))?;
+    Ok(())
+}
+
+/// Terminates the processes with this exe name. Returns the handles of those ended, to wait on, with their exe
+/// paths where they could be read; fails if none could be ended.
+fn terminate(name: &str) -> Result<Vec<(HANDLE, Option<PathBuf>)>, String> {
+    let ids = condition::process_ids(name);
+    if ids.is_empty() {
+        return Err(format!("{} isn't running", name));
+    }
+    let mut ended = Vec::new();
+    let mut last_error = 0;
+    for id in ids {
+        let handle = unsafe { OpenProcess(PROCESS_TERMINATE | SYNCHRONIZE | PROCESS_QUERY_LIMITED_INFORMATION, FALSE, id) };
+        if handle.is_null() {
+            last_error = unsafe { GetLastError() };
+            continue;
+        }
+        let path = image_path(handle);
+        if unsafe { TerminateProcess(handle, KILLED_EXIT_CODE) } == 0 {
+            last_error = unsafe { GetLastError() };
+            unsafe { CloseHandle(handle) };
+            continue;
+        }
+        ended.push((handle, path));
+    }
+    if ended.is_empty() {
+        // Access denied is the usual cause: the process runs as administrator and the overlay doesn't
+        return Err(format!("Failed to end {} (error {}); is it running as administrator?", name, last_error));
+    }
+    Ok(ended)
+}
+
+/// Full path of a process's exe, from a handle with at least PROCESS_QUERY_LIMITED_INFORMATION.
+pub fn image_path(process: HANDLE) -> Option<PathBuf> {
+    let mut buffer = [0u16; 1024];
+    let mut length = buffer.len() as DWORD;
+    if unsafe { QueryFullProcessImageNameW(process, 0, buffer.as_mut_ptr(), &mut length) } == 0 {
+        return None;
+    }
+    Some(PathBuf::from(String::from_utf16_lossy(&buffer[..length as usize])))
+}
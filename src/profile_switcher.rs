@@ -0,0 +1,88 @@
+// Profile switcher page: a segment for each configured profile, opened with its own hotkey or by scrolling over
+// the open menu. Picking one writes it into the config file as `profile`, which the config watcher then loads
+
+use toml_edit::{value, DocumentMut};
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use crate::action::Action;
+use crate::config::{config_path, Config, DEFAULT_PROFILE};
+use crate::menu::{shorten, MenuItem, MAX_SEGMENTS};
+use crate::provider::{Menu, MenuContext, MenuProvider};
+use crate::template;
+
+/// Longest label before the name is cut short; the whole name is in the tooltip.
+const MAX_LABEL_CHARS: usize = 20;
+
+/// Names of the profiles in the config, the top-level one first, and the one set as `profile`.
+static PROFILES: Mutex<(Vec<String>, String)> = Mutex::new((Vec::new(), String::new()));
+/// Whether scrolling over the open menu shows the profiles.
+static SCROLL: AtomicBool = AtomicBool::new(false);
+
+/// Takes the profiles from a newly loaded config.
+pub fn configure(config: &Config) {
+    let mut names = vec![DEFAULT_PROFILE.to_string()];
+    names.extend(config.profiles.iter().map(|profile| profile.name.clone()));
+    *PROFILES.lock().unwrap() = (names, config.profile.clone());
+    SCROLL.store(config.profile_switcher.enabled && config.profile_switcher.scroll, Ordering::SeqCst);
+}
+
+/// Whether the mouse wheel over the open menu switches to the profiles.
+pub fn scroll_enabled() -> bool {
+    SCROLL.load(Ordering::SeqCst)
+}
+
+/// Makes `name` the active profile by writing it into the config file, keeping the file's comments and
+/// formatting. Apps with a profile of their own still switch to it while they are in front.
+pub fn switch(name: &str) -> Result<Option<String>, String> {
+    let path = config_path();
+    let text = fs::read_to_string(path).unwrap_or_default();
+    let mut document = text
+        .parse::<DocumentMut>()
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    document["profile"] = value(name);
+    let text = document.to_string();
+    toml::from_str::<Config>(&text).map_err(|e| format!("Switching profiles would break the config: {}", e))?;
+    fs::write(path, text).map_err(|e| format!("Failed to write {}: {:?}", path, e))?;
+    Ok(Some(format!("Switched to profile \"{}\"", name)))
+}
+
+/// The profile switcher's provider; selecting a profile makes it the active one.
+pub struct ProfileSwitcher;
+
+impl MenuProvider for ProfileSwitcher {
+    fn name(&self) -> &'static str {
+        "profile_switcher"
+    }
+
+    fn build(&self, _context: &MenuContext) -> Menu {
+        let (names, active) = PROFILES.lock().unwrap().clone();
+        let items = names
+            .into_iter()
+            .take(MAX_SEGMENTS)
+            .map(|name| {
+                // Names are shown as they are, not as placeholders
+                MenuItem {
+                    label: template::escape(&shorten(&name, MAX_LABEL_CHARS)),
+                    description: template::escape(&name),
+                    badge: if name == active { Some("active".to_string()) } else { None },
+                    action: Some(Action::SwitchProfile { name }),
+                    enabled_if: None,
+                    visible_if: None,
+                    weight: None,
+                    uses: None,
+                    dial: None,
+                    icon: None,
+                    confirm: false,
+                    toggle: None,
+                    pulse_if: None,
+                }
+            })
+            .collect();
+        Menu { items }
+    }
+
+    fn empty_message(&self) -> &'static str {
+        "No profiles"
+    }
+}
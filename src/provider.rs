@@ -0,0 +1,77 @@
+// Menus built each time the overlay is shown instead of configured, such as the recent page and the open windows.
+// Providers are registered by name next to the static menus: `profile = "open_windows"` selects one like a profile.
+
+use winapi::shared::windef::{HWND, POINT};
+use winapi::um::winuser::GetCursorPos;
+use crate::clipboard::ClipboardHistory;
+use crate::focus;
+use crate::menu::MenuItem;
+use crate::profile_switcher::ProfileSwitcher;
+use crate::recent::RecentItems;
+use crate::recent_files::RecentFiles;
+use crate::switcher::WindowSwitcher;
+
+/// Every provider, selectable by name.
+static PROVIDERS: [&(dyn MenuProvider + Sync); 5] =
+    [&RecentItems, &WindowSwitcher, &ClipboardHistory, &RecentFiles, &ProfileSwitcher];
+
+/// What a provider knows about the moment its menu opens.
+pub struct MenuContext {
+    pub foreground: Option<HWND>, // The window the menu opens over
+    pub cursor: (i32, i32), // In screen pixels
+}
+
+impl MenuContext {
+    /// The context right now, before the overlay is shown.
+    pub fn current() -> Self {
+        let mut cursor: POINT = POINT { x: 0, y: 0 };
+        unsafe {
+            GetCursorPos(&mut cursor);
+        }
+        Self {
+            foreground: focus::foreground(),
+            cursor: (cursor.x, cursor.y),
+        }
+    }
+}
+
+/// A generated menu, shown as segments like a profile's items.
+pub struct Menu {
+    pub items: Vec<MenuItem>,
+}
+
+/// Produces a menu each time it opens.
+pub trait MenuProvider {
+    /// Selects the provider in place of a profile, e.g. "open_windows".
+    fn name(&self) -> &'static str;
+    fn build(&self, context: &MenuContext) -> Menu;
+    /// Toasted instead of opening an empty ring.
+    fn empty_message(&self) -> &'static str;
+}
+
+/// The provider registered under `name`, None for static menus.
+pub fn find(name: &str) -> Option<&'static (dyn MenuProvider + Sync)> {
+    PROVIDERS.iter().copied().find(|provider| provider.name() == name)
+}
+
+/// A generated page that replaces the menu until it closes, opened with its own hotkey.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Page {
+    Recent,
+    Windows,
+    Clipboard,
+    Files,
+    Profiles,
+}
+
+impl Page {
+    pub fn provider(self) -> &'static (dyn MenuProvider + Sync) {
+        match self {
+            Page::Recent => &RecentItems,
+            Page::Windows => &WindowSwitcher,
+            Page::Clipboard => &ClipboardHistory,
+            Page::Files => &RecentFiles,
+            Page::Profiles => &ProfileSwitcher,
+        }
+    }
+}
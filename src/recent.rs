@@ -0,0 +1,84 @@
+// Remembers the last items run from any menu, across restarts, and offers them as the "recent" page
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use crate::action::Action;
+use crate::config::{config_dir, RecentConfig};
+use crate::menu::MenuItem;
+use crate::provider::{Menu, MenuContext, MenuProvider};
+
+/// Name of the history file, kept next to the config file.
+pub const RECENT_FILE_NAME: &str = "recent.json";
+
+/// Most recent first; None until first loaded from disk.
+static RECENT: Mutex<Option<Vec<MenuItem>>> = Mutex::new(None);
+/// How many items are kept; 0 while the page is disabled, which also stops recording.
+static LIMIT: AtomicUsize = AtomicUsize::new(0);
+
+pub fn configure(config: &RecentConfig) {
+    LIMIT.store(if config.enabled { config.size } else { 0 }, Ordering::SeqCst);
+}
+
+/// Moves an item that just ran to the front of the history and saves it.
+pub fn record(item: &MenuItem) {
+    let limit = LIMIT.load(Ordering::SeqCst);
+    // Window handles from the switcher don't outlive their windows, and clips stay out of recent.json
+    if limit == 0 || matches!(item.action, None | Some(Action::ActivateWindow { .. }) | Some(Action::Paste { .. })) {
+        return;
+    }
+    let mut recent = RECENT.lock().unwrap();
+    let items = recent.get_or_insert_with(load);
+    // Items are told apart by label, so running one again doesn't list it twice
+    items.retain(|existing| existing.label != item.label);
+    let mut item = item.clone();
+    item.uses = None;
+    items.insert(0, item);
+    items.truncate(limit);
+
+    match serde_json::to_string_pretty(&*items) {
+        Ok(json) => {
+            if let Err(e) = fs::write(path(), json) {
+                eprintln!("Failed to save {}: {:?}", RECENT_FILE_NAME, e);
+            }
+        }
+        Err(e) => eprintln!("Failed to save {}: {}", RECENT_FILE_NAME, e),
+    }
+}
+
+/// The items for the recent page, most recent first.
+pub fn items() -> Vec<MenuItem> {
+    let limit = LIMIT.load(Ordering::SeqCst);
+    let mut recent = RECENT.lock().unwrap();
+    recent.get_or_insert_with(load).iter().take(limit).cloned().collect()
+}
+
+/// The recent page's provider.
+pub struct RecentItems;
+
+impl MenuProvider for RecentItems {
+    fn name(&self) -> &'static str {
+        "recent"
+    }
+
+    fn build(&self, _context: &MenuContext) -> Menu {
+        Menu { items: items() }
+    }
+
+    fn empty_message(&self) -> &'static str {
+        "Nothing run yet"
+    }
+}
+
+fn path() -> PathBuf {
+    config_dir().join(RECENT_FILE_NAME)
+}
+
+/// A missing or unreadable file starts an empty history.
+fn load() -> Vec<MenuItem> {
+    fs::read_to_string(path())
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
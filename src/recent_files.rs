@@ -0,0 +1,122 @@
+// Recent files page: the latest documents from the Windows Recent Items folder, or the entries of a chosen
+// folder, each opened with its associated app and drawn with that app's icon
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use crate::action::Action;
+use crate::config::RecentFilesConfig;
+use crate::menu::{shorten, MenuItem};
+use crate::provider::{Menu, MenuContext, MenuProvider};
+use crate::template;
+
+/// Longest label before the name is cut short; the whole name is in the tooltip.
+const MAX_LABEL_CHARS: usize = 20;
+
+static CONFIG: Mutex<RecentFilesConfig> = Mutex::new(RecentFilesConfig {
+    enabled: false,
+    hotkey: String::new(),
+    directory: String::new(),
+    size: 0,
+});
+
+/// Takes the folder and size from a newly loaded config.
+pub fn configure(config: &RecentFilesConfig) {
+    *CONFIG.lock().unwrap() = config.clone();
+}
+
+/// Where Windows keeps shortcuts to the files and folders opened lately.
+fn recent_items_folder() -> Option<PathBuf> {
+    env::var_os("APPDATA").map(|app_data| PathBuf::from(app_data).join(r"Microsoft\Windows\Recent"))
+}
+
+/// The folder's entries, most recently modified first. In the Recent Items folder only the shortcuts count;
+/// its subfolders hold jump lists.
+fn latest_entries(config: &RecentFilesConfig) -> Vec<PathBuf> {
+    let (folder, shortcuts_only) = if config.directory.is_empty() {
+        match recent_items_folder() {
+            Some(folder) => (folder, true),
+            None => return Vec::new(),
+        }
+    } else {
+        (PathBuf::from(&config.directory), false)
+    };
+    let entries = match fs::read_dir(&folder) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut entries: Vec<(SystemTime, PathBuf)> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let path = entry.path();
+            let is_shortcut = path.extension().map_or(false, |extension| extension.eq_ignore_ascii_case("lnk"));
+            if (shortcuts_only && !is_shortcut) || is_hidden(&path) {
+                return None;
+            }
+            Some((metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH), path))
+        })
+        .collect();
+    entries.sort_by(|a, b| b.0.cmp(&a.0));
+    entries.into_iter().take(config.size).map(|(_, path)| path).collect()
+}
+
+/// Shell bookkeeping such as desktop.ini isn't worth a segment.
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map_or(true, |name| name.starts_with('.') || name.eq_ignore_ascii_case("desktop.ini"))
+}
+
+/// The name shown for an entry: a shortcut's name without ".lnk" is the name of what it points to.
+fn display_name(path: &Path) -> String {
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    match name.len().checked_sub(4) {
+        Some(end) if name.is_char_boundary(end) && name[end..].eq_ignore_ascii_case(".lnk") => name[..end].to_string(),
+        _ => name,
+    }
+}
+
+/// The recent files' provider; selecting one opens it.
+pub struct RecentFiles;
+
+impl MenuProvider for RecentFiles {
+    fn name(&self) -> &'static str {
+        "recent_files"
+    }
+
+    fn build(&self, _context: &MenuContext) -> Menu {
+        let config = CONFIG.lock().unwrap().clone();
+        let items = latest_entries(&config)
+            .into_iter()
+            .map(|path| {
+                let name = display_name(&path);
+                let path = path.to_string_lossy().into_owned();
+                // Names are shown as they are, not as placeholders
+                MenuItem {
+                    label: template::escape(&shorten(&name, MAX_LABEL_CHARS)),
+                    description: template::escape(&name),
+                    action: Some(Action::Open { path: path.clone() }),
+                    enabled_if: None,
+                    visible_if: None,
+                    badge: None,
+                    weight: None,
+                    uses: None,
+                    dial: None,
+                    icon: Some(path),
+                    confirm: false,
+                    toggle: None,
+                    pulse_if: None,
+                }
+            })
+            .collect();
+        Menu { items }
+    }
+
+    fn empty_message(&self) -> &'static str {
+        "No recent files"
+    }
+}
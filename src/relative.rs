@@ -0,0 +1,90 @@
+// Relative selection for games that lock the cursor: raw mouse movement since the overlay opened
+// stands in for the cursor position
+
+use winapi::ctypes::c_void;
+use winapi::shared::minwindef::{LPARAM, UINT};
+use winapi::shared::windef::HWND;
+use winapi::um::winuser::*;
+use std::mem::{size_of, zeroed};
+use std::ptr::null_mut;
+use std::sync::Mutex;
+use crate::config::RelativeConfig;
+use crate::diagnostics;
+
+struct State {
+    enabled: bool,
+    sensitivity: f32, // Pixels per mouse count
+    offset: (f32, f32), // Accumulated since the last reset, in pixels, y down
+}
+
+static STATE: Mutex<State> = Mutex::new(State { enabled: false, sensitivity: 1.0, offset: (0.0, 0.0) });
+
+/// Starts or stops receiving raw mouse input on the overlay window, which gets it even in the background.
+pub fn configure(hwnd: HWND, config: &RelativeConfig) {
+    let mut state = STATE.lock().unwrap();
+    if state.enabled != config.enabled {
+        let device = RAWINPUTDEVICE {
+            usUsagePage: 0x01, // Generic desktop
+            usUsage: 0x02,     // Mouse
+            dwFlags: if config.enabled { RIDEV_INPUTSINK } else { RIDEV_REMOVE },
+            hwndTarget: if config.enabled { hwnd } else { null_mut() },
+        };
+        let registered = unsafe { RegisterRawInputDevices(&device, 1, size_of::<RAWINPUTDEVICE>() as UINT) };
+        if registered == 0 && config.enabled {
+            diagnostics::report_error("Failed to register for raw mouse input; relative selection won't work");
+        }
+    }
+    state.enabled = config.enabled;
+    state.sensitivity = config.sensitivity;
+}
+
+/// Starts measuring from the center again, when the overlay opens.
+pub fn reset() {
+    STATE.lock().unwrap().offset = (0.0, 0.0);
+}
+
+/// Movement since the last reset in pixels, None unless relative mode is on. Movement past `limit`
+/// is dropped, so turning back towards the center responds immediately.
+pub fn offset(limit: f32) -> Option<(f32, f32)> {
+    let mut state = STATE.lock().unwrap();
+    if !state.enabled {
+        return None;
+    }
+    let (x, y) = state.offset;
+    let length = (x * x + y * y).sqrt();
+    if length > limit {
+        state.offset = (x * limit / length, y * limit / length);
+    }
+    Some(state.offset)
+}
+
+/// Adds the movement from a WM_INPUT message.
+pub fn handle_raw_input(lparam: LPARAM) {
+    let mut state = STATE.lock().unwrap();
+    if !state.enabled {
+        return;
+    }
+
+    let mut raw: RAWINPUT = unsafe { zeroed() };
+    let mut size = size_of::<RAWINPUT>() as UINT;
+    let read = unsafe {
+        GetRawInputData(
+            lparam as HRAWINPUT,
+            RID_INPUT,
+            &mut raw as *mut RAWINPUT as *mut c_void,
+            &mut size,
+            size_of::<RAWINPUTHEADER>() as UINT,
+        )
+    };
+    if read == UINT::MAX || raw.header.dwType != RIM_TYPEMOUSE {
+        return;
+    }
+    let mouse = unsafe { raw.data.mouse() };
+    // Tablets and remote desktop report absolute positions, which have no use as deltas
+    if mouse.usFlags & MOUSE_MOVE_ABSOLUTE != 0 {
+        return;
+    }
+    let sensitivity = state.sensitivity;
+    state.offset.0 += mouse.lLastX as f32 * sensitivity;
+    state.offset.1 += mouse.lLastY as f32 * sensitivity;
+}
@@ -0,0 +1,295 @@
+// LAN remote control: a token-protected WebSocket API and a bundled web page, so a phone can run menu items
+
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{Read, Write};
+use std::net::{IpAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::StatusCode;
+use tungstenite::Message;
+use crate::config::RemoteConfig;
+use crate::{diagnostics, status, toast, wake};
+
+/// The page served to phones; it talks to the WebSocket at `/ws`.
+const PAGE: &str = include_str!("../remote/index.html");
+
+/// Commands a client may send per second, and how many may arrive in a burst.
+const COMMANDS_PER_SECOND: f32 = 5.0;
+const COMMAND_BURST: f32 = 10.0;
+/// Failed token checks from one address before it is locked out, and for how long.
+const MAX_FAILED_ATTEMPTS: u32 = 5;
+const LOCKOUT: Duration = Duration::from_secs(60);
+const MAX_CONNECTIONS: usize = 4;
+/// How long a new connection has to send its request and finish the handshake before it is dropped.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a reply may take to send before the client is dropped.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a request waits for the main loop to answer.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Requests that need the overlay's state, answered by the main loop.
+pub enum RemoteRequest {
+    /// Lists the active menu's visible items.
+    Items,
+    /// Runs the visible item at this index.
+    Run(usize),
+}
+
+pub struct PendingRequest {
+    pub request: RemoteRequest,
+    pub reply: Sender<String>,
+}
+
+static REQUESTS: Mutex<Vec<PendingRequest>> = Mutex::new(Vec::new());
+static PAIRING_URL: Mutex<Option<String>> = Mutex::new(None);
+static FAILED_ATTEMPTS: Mutex<Option<HashMap<IpAddr, (u32, Instant)>>> = Mutex::new(None);
+static CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Takes the requests queued since the last call. Each must be answered through its `reply`.
+pub fn take_requests() -> Vec<PendingRequest> {
+    std::mem::take(&mut *REQUESTS.lock().unwrap())
+}
+
+/// The address to open on a phone, including the token, once the server is running.
+pub fn pairing_url() -> Option<String> {
+    PAIRING_URL.lock().unwrap().clone()
+}
+
+/// Starts listening on the LAN. Without a configured token a random one is generated for this run;
+/// `radial_menu_overlay pair` prints the link with it.
+pub fn start_server(config: &RemoteConfig) {
+    let token = if config.token.is_empty() { random_token() } else { config.token.clone() };
+    let listener = match TcpListener::bind(("0.0.0.0", config.port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            diagnostics::report_error(format!("Failed to start remote control on port {}: {:?}", config.port, e));
+            return;
+        }
+    };
+
+    let address = format!("http://{}:{}/", lan_address(), config.port);
+    *PAIRING_URL.lock().unwrap() = Some(format!("{}?token={}", address, token));
+    // The token stays out of toasts, which are also written to the diagnostics log
+    toast::push(format!("Remote control on {}; run `pair` for the link", address));
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let token = token.clone();
+            thread::spawn(move || {
+                if let Err(e) = serve_client(stream, &token) {
+                    eprintln!("Remote client: {}", e);
+                }
+            });
+        }
+    });
+}
+
+/// Serves the page for plain HTTP requests and the command API for WebSocket upgrades on `/ws`. A connection
+/// only takes one of the `MAX_CONNECTIONS` slots once its request line has arrived, so idle ones can't use them up.
+fn serve_client(stream: TcpStream, token: &str) -> Result<(), String> {
+    let peer = stream.peer_addr().map_err(|e| format!("{:?}", e))?.ip();
+    if is_locked_out(peer) {
+        return Err(format!("{} is locked out after failed attempts", peer));
+    }
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT)).map_err(|e| format!("{:?}", e))?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT)).map_err(|e| format!("{:?}", e))?;
+
+    let request_line = peek_request_line(&stream)?;
+    if CONNECTIONS.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+        CONNECTIONS.fetch_sub(1, Ordering::SeqCst);
+        return Err(format!("Turned {} away: {} clients are connected already", peer, MAX_CONNECTIONS));
+    }
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let served = if path.starts_with("/ws") { serve_socket(stream, token, peer) } else { serve_page(stream) };
+    CONNECTIONS.fetch_sub(1, Ordering::SeqCst);
+    served
+}
+
+/// Waits for the first line of the request without consuming it, so the WebSocket handshake still sees the whole
+/// request. Gives up after `REQUEST_TIMEOUT`.
+fn peek_request_line(stream: &TcpStream) -> Result<String, String> {
+    let deadline = Instant::now() + REQUEST_TIMEOUT;
+    let mut head = [0u8; 1024];
+    loop {
+        let length = stream.peek(&mut head).map_err(|e| format!("No request arrived: {:?}", e))?;
+        if length == 0 {
+            return Err("Closed before sending a request".to_string());
+        }
+        if head[..length].contains(&b'\n') || length == head.len() {
+            return Ok(String::from_utf8_lossy(&head[..length]).lines().next().unwrap_or("").to_string());
+        }
+        if Instant::now() >= deadline {
+            return Err("The request line didn't arrive in time".to_string());
+        }
+        // Part of the line is in; peeking again right away would return the same bytes
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Checks the token in the WebSocket handshake, then answers commands until the client leaves.
+fn serve_socket(stream: TcpStream, token: &str, peer: IpAddr) -> Result<(), String> {
+
+    let authorized = Cell::new(false);
+    let check_token = |request: &Request, response: Response| -> Result<Response, ErrorResponse> {
+        let given = request
+            .uri()
+            .query()
+            .unwrap_or("")
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("token="))
+            .map(percent_decode)
+            .unwrap_or_default();
+        if constant_time_eq(&given, token.as_bytes()) {
+            authorized.set(true);
+            Ok(response)
+        } else {
+            let mut error = ErrorResponse::new(Some("Invalid token".to_string()));
+            *error.status_mut() = StatusCode::UNAUTHORIZED;
+            Err(error)
+        }
+    };
+    let handshake = tungstenite::accept_hdr(stream, check_token);
+    if !authorized.get() {
+        record_failed_attempt(peer);
+        return Err(format!("Rejected {}: invalid token", peer));
+    }
+    let mut socket = handshake.map_err(|e| format!("Handshake failed: {}", e))?;
+    // A connected client may stay quiet between commands
+    socket.get_ref().set_read_timeout(None).map_err(|e| format!("{:?}", e))?;
+    diagnostics::record_event(format!("Remote client connected from {}", peer));
+
+    let mut allowance = COMMAND_BURST;
+    let mut last_command = Instant::now();
+    loop {
+        let command = match socket.read() {
+            Ok(Message::Text(command)) => command,
+            Ok(Message::Close(_)) | Err(_) => break,
+            Ok(_) => continue,
+        };
+
+        // Token bucket: refill with time, spend one per command
+        allowance = (allowance + last_command.elapsed().as_secs_f32() * COMMANDS_PER_SECOND).min(COMMAND_BURST);
+        last_command = Instant::now();
+        let response = if allowance < 1.0 {
+            serde_json::json!({ "error": "Rate limit exceeded" }).to_string()
+        } else {
+            allowance -= 1.0;
+            handle_command(command.trim())
+        };
+        if socket.send(Message::Text(response)).is_err() {
+            break;
+        }
+    }
+    diagnostics::record_event(format!("Remote client {} disconnected", peer));
+    Ok(())
+}
+
+fn serve_page(mut stream: TcpStream) -> Result<(), String> {
+    // The request itself doesn't matter; read what arrived so closing doesn't reset the connection
+    let mut request = [0u8; 4096];
+    let _ = stream.read(&mut request);
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        PAGE.len(),
+        PAGE
+    );
+    stream.write_all(response.as_bytes()).map_err(|e| format!("{:?}", e))
+}
+
+/// Remote commands: "items", "run <index>" and "status". The named pipe's other commands write files, read
+/// recordings or stop the overlay, and stay local.
+fn handle_command(command: &str) -> String {
+    if command == "items" {
+        return request(RemoteRequest::Items);
+    }
+    if let Some(index) = command.strip_prefix("run ") {
+        return match index.trim().parse() {
+            Ok(index) => request(RemoteRequest::Run(index)),
+            Err(_) => serde_json::json!({ "error": "Usage: run <index>" }).to_string(),
+        };
+    }
+    if command == "status" {
+        return status::to_json();
+    }
+    serde_json::json!({ "error": format!("Unknown remote command: {}", command) }).to_string()
+}
+
+/// Queues a request for the main loop and waits for its answer.
+fn request(request: RemoteRequest) -> String {
+    let (reply, answer) = channel();
+    REQUESTS.lock().unwrap().push(PendingRequest { request, reply });
+    wake::wake();
+    answer
+        .recv_timeout(REPLY_TIMEOUT)
+        .unwrap_or_else(|_| serde_json::json!({ "error": "The overlay did not respond" }).to_string())
+}
+
+fn is_locked_out(peer: IpAddr) -> bool {
+    let mut failed = FAILED_ATTEMPTS.lock().unwrap();
+    let failed = failed.get_or_insert_with(HashMap::new);
+    failed.retain(|_, (_, last)| last.elapsed() < LOCKOUT);
+    failed.get(&peer).map_or(false, |(count, _)| *count >= MAX_FAILED_ATTEMPTS)
+}
+
+fn record_failed_attempt(peer: IpAddr) {
+    let mut failed = FAILED_ATTEMPTS.lock().unwrap();
+    let entry = failed.get_or_insert_with(HashMap::new).entry(peer).or_insert((0, Instant::now()));
+    entry.0 += 1;
+    entry.1 = Instant::now();
+    if entry.0 == MAX_FAILED_ATTEMPTS {
+        diagnostics::record_event(format!("Remote control: locked out {} after {} failed attempts", peer, entry.0));
+    }
+}
+
+/// Undoes the URL encoding of a query value, e.g. "%2B" and "+", which clients apply to tokens with symbols in
+/// them. A '%' not followed by two hex digits is kept as it is.
+fn percent_decode(value: &str) -> Vec<u8> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 3)
+            .filter(|hex| bytes[index] == b'%' && hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (bytes[index], escaped) {
+            (_, Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+                continue;
+            }
+            (b'+', None) => decoded.push(b' '),
+            (byte, None) => decoded.push(byte),
+        }
+        index += 1;
+    }
+    decoded
+}
+
+/// Compares without stopping at the first difference, so timing doesn't reveal the token.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |difference, (x, y)| difference | (x ^ y)) == 0
+}
+
+/// 32 hex digits from the randomly seeded std hasher.
+fn random_token() -> String {
+    (0..2)
+        .map(|_| format!("{:016x}", RandomState::new().build_hasher().finish()))
+        .collect()
+}
+
+/// This machine's address on the LAN, found by routing a UDP socket (nothing is sent).
+fn lan_address() -> String {
+    UdpSocket::bind("0.0.0.0:0")
+        .and_then(|socket| socket.connect("8.8.8.8:80").map(|_| socket))
+        .and_then(|socket| socket.local_addr())
+        .map(|address| address.ip().to_string())
+        .unwrap_or_else(|_| "localhost".to_string())
+}
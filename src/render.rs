@@ -2,16 +2,58 @@ use ash::{vk, Entry, Instance, Device};
 use ash::extensions::khr::{Surface, Win32Surface, Swapchain};
 use winapi::shared::windef::HWND;
 use crate::overlay::OverlayContent;
-use std::ffi::CString;
+use crate::menu::MAX_SEGMENTS;
+use crate::rings::{self, MenuLayout, MAX_RINGS, UNIT_PIXELS};
+use crate::text::{TextBatch, TextRenderer};
+use crate::loupe::LoupeRenderer;
+use crate::icons::{self, IconRenderer};
+use crate::gui::GuiRenderer;
+#[cfg(feature = "egui")]
+use crate::gui::{Gui, UiHook};
+use crate::picker;
+use crate::toast;
+use crate::tooltip;
+use crate::dial::{self, DialState};
+use crate::search;
+use crate::accessibility;
+use crate::custom_shader;
+use crate::fill::{FillTexture, FillUniforms};
+use crate::backdrop::BackdropTexture;
+use crate::animation::{self, Pose};
+use crate::badge::{self, BadgeRenderer};
+use crate::edges;
+use crate::memory::{self, Allocation, Resource};
+use crate::panel;
+use crate::settings;
+use crate::debug_panel;
+use crate::replay::{self, SessionEvent};
+use crate::relative;
+use crate::gamepad;
+use crate::adapter::{self, Luid};
+use crate::diagnostics;
+use crate::config::HookConfig;
+use crate::shared_texture::{self, SharedTexture};
+use crate::offscreen::OffscreenTarget;
+use crate::status;
+use crate::hud::Hud;
+use crate::window::client_size;
+use std::ffi::{CStr, CString};
 use std::fs::File;
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::ptr;
 use std::os::raw::c_void;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use winapi::um::winuser::GetCursorPos;
 use winapi::shared::windef::{POINT, RECT};
-use winapi::um::winuser::GetWindowRect;
+use winapi::um::winuser::{GetDpiForWindow, GetWindowRect};
+
+/// Longest a frame waits for the GPU or the compositor before it is skipped.
+const FRAME_TIMEOUT_NS: u64 = 100_000_000;
+/// Consecutive frames without a swapchain image before the swapchain is rebuilt.
+const STALLED_FRAMES_BEFORE_RECREATE: u32 = 10;
+/// Most swapchain images whose frames are timed on the GPU; swapchains rarely have more than three.
+const MAX_TIMED_IMAGES: usize = 8;
 
 /// Represents the data passed to the shader via uniform buffer.
 #[repr(C, align(16))]
@@ -23,8 +65,117 @@ struct UniformBufferObject {
     mouse_pos: [f32; 2],
     segment_gap: f32,
     item_selected: i32,
-    _padding0: [f32; 1],
-    _padding1: [f32; 4],    
+    dwell_progress: f32,    // 0.0..1.0 progress of the dwell-to-select timer
+    style: i32,             // Offset 36: 1 = high contrast, 2 = reduced motion
+    unit_scale: [f32; 2],   // Offset 40: ring units per NDC unit on each axis, from the window size
+    segment_state: [[f32; 4]; MAX_SEGMENTS], // Offset 48: x = progress, y = running, z = indeterminate, w = flags
+    ring_count: i32,        // Offset 304: revealed rings; zero hides the menu
+    _padding1: i32,
+    resolution: [f32; 2],   // Offset 312: window size in pixels, for custom shaders
+    rings: [[f32; 4]; MAX_RINGS], // Offset 320: x = inner radius, y = outer radius, z = first segment, w = segment count
+    ring_angles: [[f32; 4]; MAX_RINGS], // Offset 384: x = start angle of the first segment, y = 1 clockwise or -1 counter-clockwise
+    segment_angles: [[f32; 4]; MAX_SEGMENTS], // Offset 448: x = start, y = end of each segment's slice from its ring's start
+    segment_pulse: [[f32; 4]; MAX_SEGMENTS], // Offset 704: x = pulsing, y = seconds since the pulse began, for its phase
+    fill: [f32; 4],         // Offset 960: x = fill mode, y = linear gradient angle, zw = texture repeats per ring unit
+    fill_colors: [[f32; 4]; 2], // Offset 976: start and end of a gradient
+    shadow: [f32; 4],       // Offset 1008: x = size, y = opacity, zw = offset, in ring units; zero size for none
+    shadow_color: [f32; 4], // Offset 1024
+    backdrop: [f32; 4],     // Offset 1040: x = snapshot drawn, y = segment opacity over it, z = blur radius in pixels
+    animation: [f32; 4],    // Offset 1056: x = ring units the hovered segment reaches past its ring
+    segment_motion: [[f32; 4]; MAX_SEGMENTS], // Offset 1072: x = scale about the segment's middle, y = ring units slid outward
+    wedge: [f32; 4],        // Offset 1328: x = opacity of the direction wedge, zero for none
+}
+
+/// Vulkan objects for the HUD window: a surface and swapchain of its own on the renderer's device, drawn
+/// with a second text pipeline. The HUD is redrawn about once a second, so one frame in flight is enough.
+struct HudSurface {
+    hwnd: HWND,
+    surface: vk::SurfaceKHR,
+    swapchain: vk::SwapchainKHR,
+    extent: vk::Extent2D,
+    image_views: Vec<vk::ImageView>,
+    render_pass: vk::RenderPass,
+    framebuffers: Vec<vk::Framebuffer>,
+    command_buffers: Vec<vk::CommandBuffer>,
+    text: TextRenderer,
+    image_available: vk::Semaphore,
+    render_finished: vk::Semaphore,
+    in_flight: vk::Fence,
+    stale: bool, // Set when the swapchain must be rebuilt before the next frame
+}
+
+/// Timestamps written at the start and end of each swapchain image's command buffer, for the debug panel's GPU
+/// frame time. Results are read back the next time the image comes up, without waiting for them.
+struct FrameTimer {
+    pool: vk::QueryPool, // Two queries per image
+    period_ns: f32, // Nanoseconds per timestamp tick
+    submitted: [bool; MAX_TIMED_IMAGES], // Whether an image's queries have been reset and written yet
+}
+
+impl FrameTimer {
+    /// Returns None when the queue can't write timestamps.
+    fn new(instance: &Instance, device: &Device, physical_device: vk::PhysicalDevice, queue_family_index: u32) -> Result<Option<Self>, String> {
+        let queue_families = unsafe { instance.get_physical_device_queue_family_properties(physical_device) };
+        if queue_families[queue_family_index as usize].timestamp_valid_bits == 0 {
+            return Ok(None);
+        }
+        let period_ns = unsafe { instance.get_physical_device_properties(physical_device) }.limits.timestamp_period;
+        let create_info = vk::QueryPoolCreateInfo::builder()
+            .query_type(vk::QueryType::TIMESTAMP)
+            .query_count((MAX_TIMED_IMAGES * 2) as u32);
+        let pool = unsafe {
+            device
+                .create_query_pool(&create_info, None)
+                .map_err(|e| format!("Failed to create timestamp query pool: {:?}", e))?
+        };
+        Ok(Some(Self { pool, period_ns, submitted: [false; MAX_TIMED_IMAGES] }))
+    }
+
+    /// Resets the image's queries and writes the first timestamp; recorded before the render pass.
+    unsafe fn record_start(&self, device: &Device, command_buffer: vk::CommandBuffer, image_index: usize) {
+        if image_index < MAX_TIMED_IMAGES {
+            let first = (image_index * 2) as u32;
+            device.cmd_reset_query_pool(command_buffer, self.pool, first, 2);
+            device.cmd_write_timestamp(command_buffer, vk::PipelineStageFlags::TOP_OF_PIPE, self.pool, first);
+        }
+    }
+
+    /// Writes the second timestamp once all of the frame's work is done.
+    unsafe fn record_end(&self, device: &Device, command_buffer: vk::CommandBuffer, image_index: usize) {
+        if image_index < MAX_TIMED_IMAGES {
+            let last = (image_index * 2 + 1) as u32;
+            device.cmd_write_timestamp(command_buffer, vk::PipelineStageFlags::BOTTOM_OF_PIPE, self.pool, last);
+        }
+    }
+
+    fn mark_submitted(&mut self, image_index: usize) {
+        if image_index < MAX_TIMED_IMAGES {
+            self.submitted[image_index] = true;
+        }
+    }
+
+    /// Milliseconds the GPU spent on the image's last frame; None while that frame is still running.
+    fn read(&self, device: &Device, image_index: usize) -> Option<f32> {
+        if image_index >= MAX_TIMED_IMAGES || !self.submitted[image_index] {
+            return None;
+        }
+        let mut timestamps = [0u64; 2];
+        unsafe {
+            device.get_query_pool_results(
+                self.pool,
+                (image_index * 2) as u32,
+                2,
+                &mut timestamps,
+                vk::QueryResultFlags::TYPE_64,
+            )
+        }
+        .ok()?;
+        Some(timestamps[1].wrapping_sub(timestamps[0]) as f32 * self.period_ns / 1_000_000.0)
+    }
+
+    unsafe fn destroy(&self, device: &Device) {
+        device.destroy_query_pool(self.pool, None);
+    }
 }
 
 /// Renderer struct encapsulates Vulkan objects and handles rendering logic.
@@ -37,6 +188,7 @@ pub struct Renderer {
     physical_device: vk::PhysicalDevice,
     device: Device,
     graphics_queue: vk::Queue,
+    queue_family_index: u32,
     swapchain_loader: Swapchain,
     swapchain: vk::SwapchainKHR,
     swapchain_images: Vec<vk::Image>,
@@ -56,11 +208,27 @@ pub struct Renderer {
     max_frames_in_flight: usize,
     swapchain_image_count: usize,
     uniform_buffers: Vec<vk::Buffer>,
-    uniform_buffers_memory: Vec<vk::DeviceMemory>,
+    uniform_buffers_memory: Vec<Allocation>,
     descriptor_set_layout: vk::DescriptorSetLayout,
     descriptor_pool: vk::DescriptorPool,
     descriptor_sets: Vec<vk::DescriptorSet>,
+    fill_texture: FillTexture, // Sampled by the ring at binding 1; kept across swapchain rebuilds
+    backdrop: BackdropTexture, // Snapshot of the screen for frosted glass, sampled by the ring at binding 2
+    text: TextRenderer,
+    loupe: LoupeRenderer,
+    icons: IconRenderer,
+    badges: BadgeRenderer,
+    gui_renderer: Option<GuiRenderer>, // Present when built with egui
+    #[cfg(feature = "egui")]
+    gui: Gui,
+    frame_timer: Option<FrameTimer>, // None when the GPU can't time its work
     start_time: Instant,
+    stalled_frames: u32,
+    swapchain_stale: bool, // Set when the swapchain must be rebuilt before the next frame
+    hook_texture_name: Option<String>, // Name the frames are shared under for a present hook, None when not sharing
+    shared_texture: Option<SharedTexture>,
+    hud: Option<HudSurface>, // Present while the HUD is shown
+    offscreen: Option<OffscreenTarget>, // Drawn to instead of a swapchain by a renderer without a window
 }
 
 impl Renderer {
@@ -69,25 +237,17 @@ impl Renderer {
         current_image: usize,
         ubo: &UniformBufferObject,
     ) -> Result<(), String> {
-        let buffer_size = std::mem::size_of::<UniformBufferObject>();
-        let data_ptr = unsafe {
-            self.device.map_memory(
-                self.uniform_buffers_memory[current_image],
-                0,
-                buffer_size as vk::DeviceSize,
-                vk::MemoryMapFlags::empty(),
-            ).map_err(|e| format!("Failed to map uniform buffer memory: {:?}", e))?
-        } as *mut UniformBufferObject;
+        let data_ptr = memory::mapped(&self.device, &self.uniform_buffers_memory[current_image])
+            .map_err(|e| format!("Failed to map uniform buffer memory: {}", e))? as *mut UniformBufferObject;
     
         unsafe {
             data_ptr.copy_from_nonoverlapping(ubo, 1);
-            self.device.unmap_memory(self.uniform_buffers_memory[current_image]);
         }
     
         Ok(())
     }
     /// Initializes Vulkan, creates instance, selects physical device, creates logical device, and sets up swapchain.
-    pub fn new(hwnd: HWND) -> Result<Self, String> {
+    pub fn new(hwnd: HWND, hook: &HookConfig) -> Result<Self, String> {
         // Initialize Vulkan entry
         let entry = unsafe { Entry::load().map_err(|_| "Failed to load Vulkan entry".to_string())? };
 
@@ -97,64 +257,51 @@ impl Renderer {
         let layer_names: Vec<*const i8> = validation_layers.iter().map(|layer| layer.as_ptr()).collect();
 
         // Create Vulkan instance
-        let app_name = CString::new("Vulkan Overlay").unwrap();
-        let engine_name = CString::new("No Engine").unwrap();
-
-        let app_info = vk::ApplicationInfo::builder()
-            .application_name(&app_name)
-            .engine_name(&engine_name)
-            .application_version(0)
-            .engine_version(0)
-            .api_version(vk::API_VERSION_1_0);
-
-        // Required extensions for Windows surface
-        let extension_names = vec![
-            Surface::name().as_ptr(),
-            Win32Surface::name().as_ptr(),
-        ];
-
-        let mut instance_create_info = vk::InstanceCreateInfo::builder()
-            .application_info(&app_info)
-            .enabled_extension_names(&extension_names);
-
-        if enable_validation_layers {
-            instance_create_info = instance_create_info.enabled_layer_names(&layer_names);
-        }
-
-        let instance = unsafe {
-            entry
-                .create_instance(&instance_create_info, None)
-                .map_err(|e| format!("Failed to create Vulkan instance: {:?}", e))?
-        };
+        let instance = create_instance(&entry, enable_validation_layers, &layer_names)?;
 
         // Create surface for rendering
         let surface_loader = Surface::new(&entry, &instance);
         let win32_surface_loader = Win32Surface::new(&entry, &instance);
 
-        let hwnd_ptr = hwnd as *mut c_void;
-        let hinstance = unsafe { winapi::um::libloaderapi::GetModuleHandleW(ptr::null()) };
-
-        let win32_create_info = vk::Win32SurfaceCreateInfoKHR::builder()
-            .hinstance(hinstance as *mut c_void)
-            .hwnd(hwnd_ptr);
-
-        let surface = unsafe {
-            win32_surface_loader
-                .create_win32_surface(&win32_create_info, None)
-                .map_err(|e| format!("Failed to create Win32 surface: {:?}", e))?
-        };
+        let surface = create_surface(&win32_surface_loader, hwnd)?;
 
-        // Pick a physical device
-        let physical_device = pick_physical_device(&instance, &surface_loader, surface)?;
+        // Pick a physical device, preferring the adapter that drives the window's monitor
+        let presenting = adapter::presenting_adapter(hwnd);
+        let physical_device = pick_physical_device(&instance, &surface_loader, surface, presenting.as_ref())?;
 
         // Find queue family index
         let queue_family_index = find_queue_family_index(&instance, physical_device, &surface_loader, surface)?;
 
+        // Sharing frames with a present hook needs memory and a fence that can be exported to another process, and
+        // swapchain images the frames can be copied out of
+        let hook_texture_name = if !hook.enabled {
+            None
+        } else if !shared_texture::extension_names()
+            .iter()
+            .all(|&name| supports_extension(&instance, physical_device, name))
+        {
+            diagnostics::report_error("The GPU driver can't share memory with other processes; hook mode is off");
+            None
+        } else if !unsafe { surface_loader.get_physical_device_surface_capabilities(physical_device, surface) }
+            .map_or(false, |capabilities| capabilities.supported_usage_flags.contains(vk::ImageUsageFlags::TRANSFER_SRC))
+        {
+            diagnostics::report_error("The window's frames can't be copied out on this GPU; hook mode is off");
+            None
+        } else {
+            Some(hook.texture_name.clone())
+        };
+        let mut device_extensions = vec![Swapchain::name().as_ptr()];
+        if hook_texture_name.is_some() {
+            device_extensions.extend(shared_texture::extension_names().iter().map(|name| name.as_ptr()));
+        }
+
         // Create logical device and get graphics queue
         let (device, graphics_queue) = create_logical_device_and_queue(
             &instance,
             physical_device,
             queue_family_index,
+            &device_extensions,
+            hook_texture_name.is_some(),
             enable_validation_layers,
             &layer_names, // Pass layer_names to maintain their lifetime
         )?;
@@ -163,14 +310,16 @@ impl Renderer {
         let swapchain_loader = Swapchain::new(&instance, &device);
 
         // Create swapchain
-        let (swapchain, swapchain_image_format, swapchain_extent) = create_swapchain(
+        let (swapchain, swapchain_image_format, swapchain_extent, present_mode, composite_alpha) = create_swapchain(
             &surface_loader,
             &swapchain_loader,
             &device,
             physical_device,
             surface,
             queue_family_index,
+            client_size(hwnd),
         )?;
+        debug_panel::record_swapchain(swapchain_image_format, present_mode, composite_alpha);
 
         // Retrieve swapchain images
         let swapchain_images = unsafe {
@@ -179,6 +328,100 @@ impl Renderer {
                 .map_err(|e| format!("Failed to get swapchain images: {:?}", e))?
         };
 
+        Self::assemble(
+            entry,
+            instance,
+            surface_loader,
+            win32_surface_loader,
+            surface,
+            physical_device,
+            device,
+            graphics_queue,
+            queue_family_index,
+            swapchain_loader,
+            swapchain,
+            swapchain_images,
+            swapchain_image_format,
+            swapchain_extent,
+            hook_texture_name,
+            None,
+        )
+    }
+
+    /// Creates a renderer without a window that draws into an image of the given size instead of a swapchain,
+    /// e.g. for previews of a menu or golden-image tests. Frames are drawn and read back with `render_offscreen`
+    /// or `render_ring`; `render` needs a window.
+    pub fn new_offscreen(width: u32, height: u32) -> Result<Self, String> {
+        let entry = unsafe { Entry::load().map_err(|_| "Failed to load Vulkan entry".to_string())? };
+
+        let enable_validation_layers = cfg!(debug_assertions);
+        let validation_layers = [CString::new("VK_LAYER_KHRONOS_validation").unwrap()];
+        let layer_names: Vec<*const i8> = validation_layers.iter().map(|layer| layer.as_ptr()).collect();
+        let instance = create_instance(&entry, enable_validation_layers, &layer_names)?;
+
+        // The loaders are kept so the renderer is the same either way; without a surface they are never called
+        let surface_loader = Surface::new(&entry, &instance);
+        let win32_surface_loader = Win32Surface::new(&entry, &instance);
+
+        let (physical_device, queue_family_index) = pick_offscreen_device(&instance)?;
+
+        // The render pass leaves images ready to present, a layout that comes with the swapchain extension
+        let device_extensions = [Swapchain::name().as_ptr()];
+        let (device, graphics_queue) = create_logical_device_and_queue(
+            &instance,
+            physical_device,
+            queue_family_index,
+            &device_extensions,
+            false,
+            enable_validation_layers,
+            &layer_names,
+        )?;
+        let swapchain_loader = Swapchain::new(&instance, &device);
+
+        // The swapchain's usual format, so frames look the same as on screen
+        let format = vk::Format::B8G8R8A8_UNORM;
+        let extent = vk::Extent2D { width, height };
+        let offscreen = OffscreenTarget::new(&instance, &device, physical_device, format, extent)?;
+
+        Self::assemble(
+            entry,
+            instance,
+            surface_loader,
+            win32_surface_loader,
+            vk::SurfaceKHR::null(),
+            physical_device,
+            device,
+            graphics_queue,
+            queue_family_index,
+            swapchain_loader,
+            vk::SwapchainKHR::null(),
+            vec![offscreen.image()],
+            format,
+            extent,
+            None,
+            Some(offscreen),
+        )
+    }
+
+    /// Creates everything drawn with per swapchain image, or with the offscreen image when there is no swapchain.
+    fn assemble(
+        entry: Entry,
+        instance: Instance,
+        surface_loader: Surface,
+        win32_surface_loader: Win32Surface,
+        surface: vk::SurfaceKHR,
+        physical_device: vk::PhysicalDevice,
+        device: Device,
+        graphics_queue: vk::Queue,
+        queue_family_index: u32,
+        swapchain_loader: Swapchain,
+        swapchain: vk::SwapchainKHR,
+        swapchain_images: Vec<vk::Image>,
+        swapchain_image_format: vk::Format,
+        swapchain_extent: vk::Extent2D,
+        hook_texture_name: Option<String>,
+        offscreen: Option<OffscreenTarget>,
+    ) -> Result<Self, String> {
         let swapchain_image_count = swapchain_images.len();
         let max_frames_in_flight = 2; // Double buffering
 
@@ -207,6 +450,14 @@ impl Renderer {
             
         )?;
 
+        // Create command pool
+        let command_pool = create_command_pool(&device, queue_family_index)?;
+
+        // Load the theme's texture, which the ring's descriptor sets point at
+        let fill_texture = FillTexture::new(&instance, &device, physical_device, command_pool, graphics_queue)?;
+        let backdrop =
+            BackdropTexture::new(&instance, &device, physical_device, command_pool, graphics_queue, swapchain_extent)?;
+
         // Create descriptor pool
         let descriptor_pool = create_descriptor_pool(&device, swapchain_images.len())?;
 
@@ -216,6 +467,8 @@ impl Renderer {
             descriptor_pool,
             descriptor_set_layout,
             &uniform_buffers,
+            &fill_texture,
+            &backdrop,
         )?;
 
         // Initialize start time
@@ -224,12 +477,73 @@ impl Renderer {
         // Create graphics pipeline
         let (pipeline_layout, graphics_pipeline) = create_graphics_pipeline(&device, render_pass, swapchain_extent, descriptor_set_layout)?;
 
-        // Create command pool
-        let command_pool = create_command_pool(&device, queue_family_index)?;
+        // Create the texture frames are copied into for the present hook
+        let shared_texture = match &hook_texture_name {
+            Some(name) => Some(create_shared_texture(&instance, &device, physical_device, swapchain_image_format, swapchain_extent, name)?),
+            None => None,
+        };
 
         // Allocate command buffers
         let command_buffers = allocate_command_buffers(&device, command_pool, framebuffers.len())?;
 
+        // Create the timestamp queries the GPU frame time is measured with
+        let frame_timer = FrameTimer::new(&instance, &device, physical_device, queue_family_index)?;
+
+        // Create the text pipeline drawn on top of the ring
+        let text = TextRenderer::new(
+            &instance,
+            &device,
+            physical_device,
+            command_pool,
+            graphics_queue,
+            render_pass,
+            swapchain_extent,
+            framebuffers.len(),
+        )?;
+
+        // Create the color picker's loupe pipeline, drawn under the text
+        let loupe = LoupeRenderer::new(
+            &instance,
+            &device,
+            physical_device,
+            command_pool,
+            graphics_queue,
+            render_pass,
+            swapchain_extent,
+            framebuffers.len(),
+        )?;
+
+        // Create the segment icons' pipeline, drawn on the ring
+        let icons = IconRenderer::new(
+            &instance,
+            &device,
+            physical_device,
+            command_pool,
+            graphics_queue,
+            render_pass,
+            swapchain_extent,
+            framebuffers.len(),
+        )?;
+
+        // Create the badges' pipeline, drawn over the icons
+        let badges = BadgeRenderer::new(&instance, &device, physical_device, render_pass, swapchain_extent, framebuffers.len())?;
+
+        // Create the egui widgets' pipeline, drawn over everything else
+        let gui_renderer = if cfg!(feature = "egui") {
+            Some(GuiRenderer::new(
+                &instance,
+                &device,
+                physical_device,
+                command_pool,
+                graphics_queue,
+                render_pass,
+                swapchain_extent,
+                framebuffers.len(),
+            )?)
+        } else {
+            None
+        };
+
         // Record command buffers
         record_command_buffers(
             &device,
@@ -240,6 +554,15 @@ impl Renderer {
             swapchain_extent,
             pipeline_layout,
             &descriptor_sets,
+            &text,
+            &loupe,
+            &icons,
+            &badges,
+            gui_renderer.as_ref(),
+            frame_timer.as_ref(),
+            &swapchain_images,
+            shared_texture.as_ref(),
+            offscreen.as_ref(),
         )?;
 
         // Create synchronization objects
@@ -254,6 +577,7 @@ impl Renderer {
             physical_device,
             device,
             graphics_queue,
+            queue_family_index,
             swapchain_loader,
             swapchain,
             swapchain_images,
@@ -277,84 +601,102 @@ impl Renderer {
             descriptor_set_layout,
             descriptor_pool,
             descriptor_sets,
+            fill_texture,
+            backdrop,
+            text,
+            loupe,
+            icons,
+            badges,
+            gui_renderer,
+            #[cfg(feature = "egui")]
+            gui: Gui::new(),
+            frame_timer,
             start_time,
+            stalled_frames: 0,
+            swapchain_stale: false,
+            hook_texture_name,
+            shared_texture,
+            hud: None,
+            offscreen,
         })
     }
 
-    /// Renders a frame. This function should be called every frame when the overlay is visible.
-    pub fn render(&mut self, _overlay_content: &mut OverlayContent, hwnd: HWND) -> Result<(), String> {
+    /// Adds widgets drawn with egui over the overlay, described anew every frame the window is shown.
+    #[cfg(feature = "egui")]
+    pub fn add_ui(&mut self, hook: impl FnMut(&mut egui::Ui) + 'static) {
+        let hook: UiHook = Box::new(hook);
+        self.gui.add(hook);
+    }
+
+    /// Name of the GPU the renderer runs on.
+    pub fn gpu_name(&self) -> String {
+        device_name(&self.instance, self.physical_device)
+    }
+
+    /// Renders a frame of a snapshot of the overlay, with the cursor as `track_cursor` found it. This function
+    /// should be called every frame when the overlay is visible.
+    /// Returns false if the frame was skipped because the GPU or compositor didn't respond in time;
+    /// waits are bounded so a stall never freezes the render thread.
+    pub fn render(&mut self, overlay_content: &OverlayContent, cursor: &FrameCursor, hwnd: HWND) -> Result<bool, String> {
+        // The window is resized to fit each menu, and the swapchain follows it
+        let (width, height) = client_size(hwnd);
+        if width > 0 && height > 0 && (width, height) != (self.swapchain_extent.width, self.swapchain_extent.height) {
+            self.swapchain_stale = true;
+        }
+        if self.swapchain_stale && !self.recreate_swapchain(hwnd)? {
+            return Ok(false);
+        }
+
         // Wait for the fence of the current frame to be signaled
-        unsafe {
-            self.device
-                .wait_for_fences(&[self.in_flight_fences[self.current_frame]], true, std::u64::MAX)
-                .map_err(|e| format!("Failed to wait for fence: {:?}", e))?;
-            self.device
-                .reset_fences(&[self.in_flight_fences[self.current_frame]])
-                .map_err(|e| format!("Failed to reset fence: {:?}", e))?;
+        let fence = [self.in_flight_fences[self.current_frame]];
+        match unsafe { self.device.wait_for_fences(&fence, true, FRAME_TIMEOUT_NS) } {
+            Ok(()) => {}
+            Err(vk::Result::TIMEOUT) => return Ok(self.skip_frame("GPU did not finish the previous frame")),
+            Err(e) => return Err(format!("Failed to wait for fence: {:?}", e)),
         }
 
         // Acquire an image from the swapchain
-        let (image_index, _) = unsafe {
-            self.swapchain_loader
-                .acquire_next_image(
-                    self.swapchain,
-                    std::u64::MAX,
-                    self.image_available_semaphores[self.current_frame],
-                    vk::Fence::null(),
-                )
-                .map_err(|e| format!("Failed to acquire next image: {:?}", e))?
+        let acquired = unsafe {
+            self.swapchain_loader.acquire_next_image(
+                self.swapchain,
+                FRAME_TIMEOUT_NS,
+                self.image_available_semaphores[self.current_frame],
+                vk::Fence::null(),
+            )
         };
-
-        // Get mouse position
-        let mut point: POINT = POINT { x: 0, y: 0 };
-        unsafe {
-            GetCursorPos(&mut point);
+        let image_index = match acquired {
+            // A suboptimal image is still drawn; presenting it flags the swapchain for a rebuild
+            Ok((image_index, _)) => image_index,
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                self.swapchain_stale = true;
+                return Ok(false);
+            }
+            Err(vk::Result::TIMEOUT) | Err(vk::Result::NOT_READY) => {
+                if self.stalled_frames + 1 >= STALLED_FRAMES_BEFORE_RECREATE {
+                    self.swapchain_stale = true;
+                }
+                return Ok(self.skip_frame("No swapchain image available"));
+            }
+            Err(e) => return Err(format!("Failed to acquire next image: {:?}", e)),
+        };
+        if self.stalled_frames > 0 {
+            diagnostics::record_event(format!("Rendering resumed after {} skipped frames", self.stalled_frames));
+            self.stalled_frames = 0;
         }
 
-        // Get window position
-        let mut window_rect: RECT = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+        // The image's previous frame has usually finished by now; its timestamps are overwritten once it is submitted
+        let cpu_start = Instant::now();
+        let gpu_frame_ms = self.frame_timer.as_ref().and_then(|timer| timer.read(&self.device, image_index as usize));
+
+        // Only reset the fence once work will be submitted, so a skipped frame can't leave it unsignaled
         unsafe {
-            GetWindowRect(hwnd, &mut window_rect);
+            self.device
+                .reset_fences(&fence)
+                .map_err(|e| format!("Failed to reset fence: {:?}", e))?;
         }
 
-        // Calculate mouse position relative to the window
-        let mouse_x = point.x - window_rect.left;
-        let mouse_y = point.y - window_rect.top;
-
-        // Window dimensions
-        //let window_width_debug = self.swapchain_extent.width as f32; 
-        //let window_height_debug = self.swapchain_extent.height as f32;
-        
-        let window_width = window_rect.right - window_rect.left;
-        let window_height = window_rect.bottom - window_rect.top;
-        // Normalize mouse position to range [-1, 1]
-        // X goes from -1 (left) to 1 (right)
-        // Y goes from -1 (bottom) to 1 (top)
-        let normalized_mouse_x = (mouse_x as f32 / window_width as f32) * 2.0 - 1.0;
-        let normalized_mouse_y = 1.0 - (mouse_y as f32 / window_height as f32) * 2.0;  
-        //debug
-        //println!("Mouse X: {}, Normalized X: {}", mouse_x, normalized_mouse_x);
-        //println!("Mouse Y: {}, Normalized Y: {}", mouse_y, normalized_mouse_y);
-        //println!("window_width_deb: {}, window_width: {}", window_width_debug, window_width);
-        //println!("window_height_deb: {}, window_height: {}", window_height_debug, window_height);
-
-        update_selection(normalized_mouse_x, normalized_mouse_y, _overlay_content);
-
-        // Update the uniform buffer
-        let current_time = self.start_time.elapsed().as_secs_f32();
-        let ubo = UniformBufferObject {
-            radius: 0.25,
-            inner_radius: 0.08,
-            segments: 6,
-            time: current_time,
-            mouse_pos: [normalized_mouse_x, normalized_mouse_y],
-            segment_gap: 0.1,
-            item_selected: _overlay_content.selected_segment.unwrap_or(-1),
-            _padding0: [0.0],
-            _padding1: [0.0, 0.0, 0.0, 0.0],
-        };
-
-        self.update_uniform_buffer(image_index as usize, &ubo)?;
+        // Lay out and upload everything the frame draws
+        self.upload_frame(image_index as usize, overlay_content, cursor.mouse, cursor.normalized_mouse, cursor.unit_scale)?;
 
         // Submit the command buffer
         let wait_semaphores = [self.image_available_semaphores[self.current_frame]];
@@ -362,12 +704,23 @@ impl Renderer {
         let signal_semaphores = [self.render_finished_semaphores[self.current_frame]];
         let command_buffers_to_submit = [self.command_buffers[image_index as usize]];
 
-        let submit_info = vk::SubmitInfo::builder()
+        // With a present hook, also tell it which frame has been copied into the shared texture. The present semaphore
+        // is binary, which the timeline values ignore
+        let shared_fence = self.shared_texture.as_mut().map(SharedTexture::next_frame);
+        let all_signal_semaphores: Vec<vk::Semaphore> =
+            signal_semaphores.iter().copied().chain(shared_fence.map(|(fence, _)| fence)).collect();
+        let signal_values: Vec<u64> = std::iter::once(0).chain(shared_fence.map(|(_, value)| value)).collect();
+        let mut timeline_info = vk::TimelineSemaphoreSubmitInfo::builder().signal_semaphore_values(&signal_values);
+
+        let mut submit_info = vk::SubmitInfo::builder()
             .wait_semaphores(&wait_semaphores)
             .wait_dst_stage_mask(&wait_stages)
             .command_buffers(&command_buffers_to_submit)
-            .signal_semaphores(&signal_semaphores)
-            .build();
+            .signal_semaphores(&all_signal_semaphores);
+        if shared_fence.is_some() {
+            submit_info = submit_info.push_next(&mut timeline_info);
+        }
+        let submit_info = submit_info.build();
 
         unsafe {
             self.device
@@ -378,6 +731,10 @@ impl Renderer {
                 )
                 .map_err(|e| format!("Failed to submit queue: {:?}", e))?;
         }
+        if let Some(timer) = self.frame_timer.as_mut() {
+            timer.mark_submitted(image_index as usize);
+        }
+        debug_panel::record_frame(cpu_start.elapsed(), gpu_frame_ms);
 
         // Present the image
         let swapchains = [self.swapchain];
@@ -388,23 +745,569 @@ impl Renderer {
             .image_indices(&image_indices)
             .build();
 
-        unsafe {
-            self.swapchain_loader
-                .queue_present(self.graphics_queue, &present_info)
-                .map_err(|e| format!("Failed to present queue: {:?}", e))?;
+        match unsafe { self.swapchain_loader.queue_present(self.graphics_queue, &present_info) } {
+            Ok(false) => {}
+            Ok(true) | Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => self.swapchain_stale = true,
+            Err(e) => return Err(format!("Failed to present queue: {:?}", e)),
         }
 
         // Advance to the next frame
         self.current_frame = (self.current_frame + 1) % self.max_frames_in_flight;
 
+        Ok(true)
+    }
+
+    /// Updates the uniforms and uploads the loupe, icons, badges, text and egui widgets an image draws: the menu, with the
+    /// cursor at `mouse` in window pixels and at `normalized_mouse` in -1..1 with y up, and everything shown over it.
+    fn upload_frame(
+        &mut self,
+        image_index: usize,
+        overlay_content: &OverlayContent,
+        mouse: (i32, i32),
+        normalized_mouse: [f32; 2],
+        unit_scale: [f32; 2],
+    ) -> Result<(), String> {
+        // Update the uniform buffer
+        let current_time = self.start_time.elapsed().as_secs_f32();
+        // No rings hides the menu, e.g. for toasts only or when every item is hidden
+        let mut layout = MenuLayout::of(overlay_content);
+        let style = (accessibility::high_contrast() as i32) | ((accessibility::reduced_motion() as i32) << 1);
+        // The pose's opacity isn't drawn; the message thread fades the whole window with it
+        let pose = animation::current(overlay_content);
+        animation::apply(&mut layout, &pose);
+        self.backdrop.upload(&self.device)?;
+        let ubo = frame_uniforms(
+            overlay_content,
+            &layout,
+            normalized_mouse,
+            unit_scale,
+            self.resolution(),
+            current_time,
+            style,
+            &pose,
+            self.fill_texture.uniforms(overlay_content.background_luminance, self.backdrop.is_ready()),
+        );
+
+        self.update_uniform_buffer(image_index, &ubo)?;
+
+        // The color picker magnifies the screen around the cursor; without it the loupe draws nothing
+        let capture = if picker::is_active() { picker::capture() } else { None };
+        self.loupe.upload(&self.device, image_index, capture.as_deref())?;
+
+        let placed_icons = if overlay_content.visible {
+            icons::layout_icons(overlay_content, &layout, self.swapchain_extent)
+        } else {
+            Vec::new()
+        };
+        self.icons.upload(&self.device, image_index, &placed_icons)?;
+
+        // Lay out toasts and upload the text quads
+        let mut text_batch = TextBatch::new(self.text.metrics(), self.swapchain_extent);
+        toast::layout_toasts(&mut text_batch, overlay_content, current_time);
+        let mut placed_badges = Vec::new();
+        if overlay_content.visible {
+            placed_badges = badge::layout_badges(&mut text_batch, overlay_content, &layout);
+            badge::layout_number_badges(&mut text_batch, overlay_content, &layout);
+            edges::layout_edges(&mut text_batch, overlay_content);
+            tooltip::layout_tooltip(&mut text_batch, overlay_content, (mouse.0 as f32, mouse.1 as f32));
+            dial::layout_dial(&mut text_batch, overlay_content);
+            search::layout_search(&mut text_batch, overlay_content);
+        }
+        panel::layout_panel(&mut text_batch, current_time);
+        picker::layout_picker(&mut text_batch);
+        settings::layout_settings(&mut text_batch);
+        debug_panel::layout_debug(&mut text_batch);
+        self.text.upload_batch(&self.device, image_index, &text_batch)?;
+        self.badges.upload(&self.device, image_index, &placed_badges)?;
+
+        // Run the egui hooks and upload what they drew
+        #[cfg(feature = "egui")]
+        if let Some(gui_renderer) = self.gui_renderer.as_mut() {
+            let frame = self.gui.run(self.swapchain_extent, (mouse.0 as f32, mouse.1 as f32), current_time as f64);
+            gui_renderer.upload(&self.device, image_index, &frame)?;
+        }
+
         Ok(())
     }
 
+    /// Size of the swapchain images, or of the offscreen image, in pixels.
+    fn resolution(&self) -> [f32; 2] {
+        [self.swapchain_extent.width as f32, self.swapchain_extent.height as f32]
+    }
+
+    /// Draws just the ring of `overlay_content` into the offscreen image and reads it back as RGBA rows from the
+    /// top. The cursor, in -1..1 across the image with y up, the time and the style are fixed by the caller
+    /// rather than taken from the system, so the same inputs always give the same pixels.
+    pub fn render_ring(&mut self, overlay_content: &OverlayContent, mouse_pos: [f32; 2], time: f32, style: i32) -> Result<Vec<u8>, String> {
+        // Posed as `time` into the open and hover animations, so golden images stay put
+        let mut layout = MenuLayout::of(overlay_content);
+        // Opacity fades the window, which the offscreen image doesn't have
+        let pose = if style & 2 != 0 {
+            Pose::REST
+        } else {
+            let elapsed = Duration::from_secs_f32(time);
+            let hovered = overlay_content.selected_segment.map(|segment| (segment, elapsed));
+            animation::pose(elapsed, overlay_content.segment_count() as usize, hovered)
+        };
+        animation::apply(&mut layout, &pose);
+        let unit_scale = self.offscreen_unit_scale();
+        let ubo = frame_uniforms(
+            overlay_content,
+            &layout,
+            mouse_pos,
+            unit_scale,
+            self.resolution(),
+            time,
+            style,
+            &pose,
+            // Golden images and previews are drawn over nothing, without a snapshot of the screen
+            self.fill_texture.uniforms(overlay_content.background_luminance, false),
+        );
+        self.update_uniform_buffer(0, &ubo)?;
+
+        // Only the ring; whatever an earlier frame drew over it is cleared
+        self.loupe.upload(&self.device, 0, None)?;
+        self.icons.upload(&self.device, 0, &[])?;
+        self.badges.upload(&self.device, 0, &[])?;
+        self.text.upload_batch(&self.device, 0, &TextBatch::new(self.text.metrics(), self.swapchain_extent))?;
+        self.render_offscreen_image()
+    }
+
+    /// Draws the menu as it appears on screen with the cursor resting in its middle, icons, badges and anything
+    /// shown over it included, into the offscreen image and reads it back as RGBA rows from the top.
+    /// `overlay_content` must be visible for the ring to be drawn.
+    pub fn render_offscreen(&mut self, overlay_content: &OverlayContent) -> Result<Vec<u8>, String> {
+        let center = ((self.swapchain_extent.width / 2) as i32, (self.swapchain_extent.height / 2) as i32);
+        let unit_scale = self.offscreen_unit_scale();
+        self.upload_frame(0, overlay_content, center, [0.0, 0.0], unit_scale)?;
+        self.render_offscreen_image()
+    }
+
+    /// Ring units per NDC unit on each axis for the offscreen image, like the window's in `render`.
+    fn offscreen_unit_scale(&self) -> [f32; 2] {
+        [
+            self.swapchain_extent.width as f32 / 2.0 / UNIT_PIXELS,
+            self.swapchain_extent.height as f32 / 2.0 / UNIT_PIXELS,
+        ]
+    }
+
+    /// Submits the offscreen image's command buffer, waits for it and reads the frame back.
+    fn render_offscreen_image(&mut self) -> Result<Vec<u8>, String> {
+        let offscreen = self.offscreen.as_ref().ok_or_else(|| "The renderer draws to a window".to_string())?;
+        let fence = [self.in_flight_fences[0]];
+        let command_buffers_to_submit = [self.command_buffers[0]];
+        let submit_info = vk::SubmitInfo::builder().command_buffers(&command_buffers_to_submit).build();
+        unsafe {
+            self.device
+                .reset_fences(&fence)
+                .map_err(|e| format!("Failed to reset fence: {:?}", e))?;
+            self.device
+                .queue_submit(self.graphics_queue, &[submit_info], fence[0])
+                .map_err(|e| format!("Failed to submit queue: {:?}", e))?;
+            // Nothing else is waiting on the GPU, so there is no need to bound the wait
+            self.device
+                .wait_for_fences(&fence, true, u64::MAX)
+                .map_err(|e| format!("Failed to wait for fence: {:?}", e))?;
+        }
+        offscreen.read_pixels(&self.device)
+    }
+
+    /// Counts a skipped frame, logging when a stall starts.
+    fn skip_frame(&mut self, reason: &str) -> bool {
+        if self.stalled_frames == 0 {
+            eprintln!("Skipping frame: {}", reason);
+            diagnostics::record_event(format!("Skipping frames: {}", reason));
+        }
+        self.stalled_frames += 1;
+        false
+    }
+
+    /// Makes the next frame rebuild the swapchain first, e.g. to recover from a failed frame.
+    pub fn invalidate_swapchain(&mut self) {
+        self.swapchain_stale = true;
+    }
+
+    /// Rebuilds the swapchain and everything sized by it, e.g. after the compositor reports it out of date.
+    /// Returns false without changing anything if the GPU is still busy with earlier frames; the caller retries later.
+    fn recreate_swapchain(&mut self, hwnd: HWND) -> Result<bool, String> {
+        match unsafe { self.device.wait_for_fences(&self.in_flight_fences, true, FRAME_TIMEOUT_NS) } {
+            Ok(()) => {}
+            Err(vk::Result::TIMEOUT) => return Ok(self.skip_frame("GPU busy, swapchain rebuild postponed")),
+            Err(e) => return Err(format!("Failed to wait for fences: {:?}", e)),
+        }
+        diagnostics::record_event("Recreating swapchain");
+        unsafe { self.destroy_swapchain_resources() };
+
+        let (swapchain, swapchain_image_format, swapchain_extent, present_mode, composite_alpha) = create_swapchain(
+            &self.surface_loader,
+            &self.swapchain_loader,
+            &self.device,
+            self.physical_device,
+            self.surface,
+            self.queue_family_index,
+            client_size(hwnd),
+        )?;
+        debug_panel::record_swapchain(swapchain_image_format, present_mode, composite_alpha);
+        self.swapchain = swapchain;
+        self.swapchain_image_format = swapchain_image_format;
+        self.swapchain_extent = swapchain_extent;
+        self.swapchain_images = unsafe {
+            self.swapchain_loader
+                .get_swapchain_images(swapchain)
+                .map_err(|e| format!("Failed to get swapchain images: {:?}", e))?
+        };
+        self.swapchain_image_count = self.swapchain_images.len();
+        self.swapchain_image_views = create_image_views(&self.device, &self.swapchain_images, swapchain_image_format)?;
+        self.render_pass = create_render_pass(&self.device, swapchain_image_format)?;
+        self.framebuffers = create_framebuffers(&self.device, self.render_pass, &self.swapchain_image_views, swapchain_extent)?;
+
+        let (uniform_buffers, uniform_buffers_memory) =
+            create_uniform_buffers(&self.instance, &self.device, self.physical_device, self.swapchain_image_count)?;
+        self.uniform_buffers = uniform_buffers;
+        self.uniform_buffers_memory = uniform_buffers_memory;
+        self.backdrop = BackdropTexture::new(
+            &self.instance,
+            &self.device,
+            self.physical_device,
+            self.command_pool,
+            self.graphics_queue,
+            swapchain_extent,
+        )?;
+        self.descriptor_pool = create_descriptor_pool(&self.device, self.swapchain_image_count)?;
+        self.descriptor_sets = create_descriptor_sets(
+            &self.device,
+            self.descriptor_pool,
+            self.descriptor_set_layout,
+            &self.uniform_buffers,
+            &self.fill_texture,
+            &self.backdrop,
+        )?;
+
+        let (pipeline_layout, graphics_pipeline) =
+            create_graphics_pipeline(&self.device, self.render_pass, swapchain_extent, self.descriptor_set_layout)?;
+        self.pipeline_layout = pipeline_layout;
+        self.graphics_pipeline = graphics_pipeline;
+        self.command_buffers = allocate_command_buffers(&self.device, self.command_pool, self.framebuffers.len())?;
+        if let Some(name) = &self.hook_texture_name {
+            self.shared_texture = Some(create_shared_texture(
+                &self.instance,
+                &self.device,
+                self.physical_device,
+                swapchain_image_format,
+                swapchain_extent,
+                name,
+            )?);
+        }
+        self.text = TextRenderer::new(
+            &self.instance,
+            &self.device,
+            self.physical_device,
+            self.command_pool,
+            self.graphics_queue,
+            self.render_pass,
+            swapchain_extent,
+            self.framebuffers.len(),
+        )?;
+        self.loupe = LoupeRenderer::new(
+            &self.instance,
+            &self.device,
+            self.physical_device,
+            self.command_pool,
+            self.graphics_queue,
+            self.render_pass,
+            swapchain_extent,
+            self.framebuffers.len(),
+        )?;
+        self.icons = IconRenderer::new(
+            &self.instance,
+            &self.device,
+            self.physical_device,
+            self.command_pool,
+            self.graphics_queue,
+            self.render_pass,
+            swapchain_extent,
+            self.framebuffers.len(),
+        )?;
+        self.badges = BadgeRenderer::new(
+            &self.instance,
+            &self.device,
+            self.physical_device,
+            self.render_pass,
+            swapchain_extent,
+            self.framebuffers.len(),
+        )?;
+        if self.gui_renderer.is_some() {
+            self.gui_renderer = Some(GuiRenderer::new(
+                &self.instance,
+                &self.device,
+                self.physical_device,
+                self.command_pool,
+                self.graphics_queue,
+                self.render_pass,
+                swapchain_extent,
+                self.framebuffers.len(),
+            )?);
+        }
+        record_command_buffers(
+            &self.device,
+            &self.command_buffers,
+            self.render_pass,
+            &self.framebuffers,
+            self.graphics_pipeline,
+            swapchain_extent,
+            self.pipeline_layout,
+            &self.descriptor_sets,
+            &self.text,
+            &self.loupe,
+            &self.icons,
+            &self.badges,
+            self.gui_renderer.as_ref(),
+            self.frame_timer.as_ref(),
+            &self.swapchain_images,
+            self.shared_texture.as_ref(),
+            self.offscreen.as_ref(),
+        )?;
+
+        self.swapchain_stale = false;
+        Ok(true)
+    }
+
+    /// Destroys the swapchain and everything created per swapchain image. The GPU must be idle.
+    unsafe fn destroy_swapchain_resources(&mut self) {
+        // Destroy text pipeline, atlas and vertex buffers
+        self.text.destroy(&self.device);
+        self.loupe.destroy(&self.device);
+        self.icons.destroy(&self.device);
+        self.badges.destroy(&self.device);
+        self.backdrop.destroy(&self.device);
+        if let Some(gui_renderer) = &self.gui_renderer {
+            gui_renderer.destroy(&self.device);
+        }
+
+        self.device.free_command_buffers(self.command_pool, &self.command_buffers);
+
+        // Destroy the shared texture; it is sized like the swapchain
+        if let Some(shared_texture) = self.shared_texture.take() {
+            shared_texture.destroy(&self.device);
+        }
+
+        // Destroy the offscreen image, which takes the swapchain's place without a window
+        if let Some(offscreen) = self.offscreen.take() {
+            offscreen.destroy(&self.device);
+        }
+
+        // Destroy graphics pipeline and layout
+        self.device.destroy_pipeline(self.graphics_pipeline, None);
+        self.device.destroy_pipeline_layout(self.pipeline_layout, None);
+
+        // Destroy framebuffers
+        for &framebuffer in self.framebuffers.iter() {
+            self.device.destroy_framebuffer(framebuffer, None);
+        }
+
+        // Destroy render pass
+        self.device.destroy_render_pass(self.render_pass, None);
+
+        // Destroy image views
+        for &image_view in self.swapchain_image_views.iter() {
+            self.device.destroy_image_view(image_view, None);
+        }
+
+        // Destroy uniform buffers
+        for &buffer in self.uniform_buffers.iter() {
+            self.device.destroy_buffer(buffer, None);
+        }
+        for &allocation in self.uniform_buffers_memory.iter() {
+            memory::free(&self.device, allocation);
+        }
+
+        // Destroy descriptor pool; its sets go with it
+        self.device.destroy_descriptor_pool(self.descriptor_pool, None);
+
+        // Destroy swapchain
+        self.swapchain_loader.destroy_swapchain(self.swapchain, None);
+    }
+
+    /// Creates a surface and swapchain on the HUD window, which `render_hud` then draws to.
+    pub fn attach_hud(&mut self, hwnd: HWND) -> Result<(), String> {
+        self.detach_hud();
+        let surface = create_surface(&self.win32_surface_loader, hwnd)?;
+
+        // The queue presents to the menu's window; the HUD may be on a monitor it can't present to
+        let supported = unsafe {
+            self.surface_loader
+                .get_physical_device_surface_support(self.physical_device, self.queue_family_index, surface)
+                .unwrap_or(false)
+        };
+        if !supported {
+            unsafe { self.surface_loader.destroy_surface(surface, None) };
+            return Err("The GPU can't present to the HUD's monitor".to_string());
+        }
+
+        let (swapchain, format, extent, _, _) = create_swapchain(
+            &self.surface_loader,
+            &self.swapchain_loader,
+            &self.device,
+            self.physical_device,
+            surface,
+            self.queue_family_index,
+            client_size(hwnd),
+        )?;
+        let images = unsafe {
+            self.swapchain_loader
+                .get_swapchain_images(swapchain)
+                .map_err(|e| format!("Failed to get HUD swapchain images: {:?}", e))?
+        };
+        let image_views = create_image_views(&self.device, &images, format)?;
+        let render_pass = create_render_pass(&self.device, format)?;
+        let framebuffers = create_framebuffers(&self.device, render_pass, &image_views, extent)?;
+        let command_buffers = allocate_command_buffers(&self.device, self.command_pool, framebuffers.len())?;
+
+        // The second text pipeline, created for the HUD's render pass and size
+        let text = TextRenderer::new(
+            &self.instance,
+            &self.device,
+            self.physical_device,
+            self.command_pool,
+            self.graphics_queue,
+            render_pass,
+            extent,
+            framebuffers.len(),
+        )?;
+        record_hud_command_buffers(&self.device, &command_buffers, render_pass, &framebuffers, extent, &text)?;
+        let (image_available, render_finished, in_flight) = create_sync_objects(&self.device, 1)?;
+
+        self.hud = Some(HudSurface {
+            hwnd,
+            surface,
+            swapchain,
+            extent,
+            image_views,
+            render_pass,
+            framebuffers,
+            command_buffers,
+            text,
+            image_available: image_available[0],
+            render_finished: render_finished[0],
+            in_flight: in_flight[0],
+            stale: false,
+        });
+        Ok(())
+    }
+
+    /// Destroys the HUD's surface and everything drawn to it, when the HUD is hidden.
+    pub fn detach_hud(&mut self) {
+        let hud = match self.hud.take() {
+            Some(hud) => hud,
+            None => return,
+        };
+        unsafe {
+            // Hiding the HUD is rare; waiting for the GPU here keeps the teardown simple
+            let _ = self.device.device_wait_idle();
+            hud.text.destroy(&self.device);
+            self.device.free_command_buffers(self.command_pool, &hud.command_buffers);
+            self.device.destroy_semaphore(hud.image_available, None);
+            self.device.destroy_semaphore(hud.render_finished, None);
+            self.device.destroy_fence(hud.in_flight, None);
+            for &framebuffer in hud.framebuffers.iter() {
+                self.device.destroy_framebuffer(framebuffer, None);
+            }
+            self.device.destroy_render_pass(hud.render_pass, None);
+            for &image_view in hud.image_views.iter() {
+                self.device.destroy_image_view(image_view, None);
+            }
+            self.swapchain_loader.destroy_swapchain(hud.swapchain, None);
+            self.surface_loader.destroy_surface(hud.surface, None);
+        }
+    }
+
+    /// Draws the HUD's widgets to its window. Returns false if the frame was skipped, like `render`.
+    pub fn render_hud(&mut self, hud: &Hud) -> Result<bool, String> {
+        let (hwnd, stale, extent) = match &self.hud {
+            Some(surface) => (surface.hwnd, surface.stale, surface.extent),
+            None => return Ok(false),
+        };
+        // The HUD is resized when widgets are added or removed, and its swapchain follows
+        let (width, height) = client_size(hwnd);
+        if stale || (width > 0 && height > 0 && (width, height) != (extent.width, extent.height)) {
+            self.attach_hud(hwnd)?;
+        }
+        let surface = match self.hud.as_mut() {
+            Some(surface) => surface,
+            None => return Ok(false),
+        };
+
+        let fence = [surface.in_flight];
+        match unsafe { self.device.wait_for_fences(&fence, true, FRAME_TIMEOUT_NS) } {
+            Ok(()) => {}
+            Err(vk::Result::TIMEOUT) => return Ok(false),
+            Err(e) => return Err(format!("Failed to wait for HUD fence: {:?}", e)),
+        }
+        let acquired = unsafe {
+            self.swapchain_loader.acquire_next_image(
+                surface.swapchain,
+                FRAME_TIMEOUT_NS,
+                surface.image_available,
+                vk::Fence::null(),
+            )
+        };
+        let image_index = match acquired {
+            Ok((image_index, _)) => image_index,
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                surface.stale = true;
+                return Ok(false);
+            }
+            Err(vk::Result::TIMEOUT) | Err(vk::Result::NOT_READY) => return Ok(false),
+            Err(e) => return Err(format!("Failed to acquire HUD image: {:?}", e)),
+        };
+        unsafe {
+            self.device
+                .reset_fences(&fence)
+                .map_err(|e| format!("Failed to reset HUD fence: {:?}", e))?;
+        }
+
+        let mut text_batch = TextBatch::new(surface.text.metrics(), surface.extent);
+        hud.layout(&mut text_batch);
+        surface.text.upload_batch(&self.device, image_index as usize, &text_batch)?;
+
+        let wait_semaphores = [surface.image_available];
+        let wait_stages = [vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
+        let signal_semaphores = [surface.render_finished];
+        let command_buffers_to_submit = [surface.command_buffers[image_index as usize]];
+        let submit_info = vk::SubmitInfo::builder()
+            .wait_semaphores(&wait_semaphores)
+            .wait_dst_stage_mask(&wait_stages)
+            .command_buffers(&command_buffers_to_submit)
+            .signal_semaphores(&signal_semaphores)
+            .build();
+        unsafe {
+            self.device
+                .queue_submit(self.graphics_queue, &[submit_info], surface.in_flight)
+                .map_err(|e| format!("Failed to submit HUD frame: {:?}", e))?;
+        }
+
+        let swapchains = [surface.swapchain];
+        let image_indices = [image_index];
+        let present_info = vk::PresentInfoKHR::builder()
+            .wait_semaphores(&signal_semaphores)
+            .swapchains(&swapchains)
+            .image_indices(&image_indices)
+            .build();
+        match unsafe { self.swapchain_loader.queue_present(self.graphics_queue, &present_info) } {
+            Ok(false) => {}
+            Ok(true) | Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => surface.stale = true,
+            Err(e) => return Err(format!("Failed to present HUD frame: {:?}", e)),
+        }
+        Ok(true)
+    }
+
     /// Cleans up Vulkan resources in reverse order of creation.
     pub fn cleanup(&mut self) {
+        // The HUD shares the device and command pool
+        self.detach_hud();
         unsafe {
-            // Wait for the device to finish operations
-            self.device.device_wait_idle().unwrap();
+            // Wait for the device to finish operations; a lost device fails here, and is torn down all the same
+            let _ = self.device.device_wait_idle();
 
             // Destroy synchronization objects
             for &semaphore in self.image_available_semaphores.iter() {
@@ -417,53 +1320,188 @@ impl Renderer {
                 self.device.destroy_fence(fence, None);
             }
 
-            // Destroy command pool and command buffers
-            self.device.destroy_command_pool(self.command_pool, None);
-
-            // Destroy graphics pipeline and layout
-            self.device.destroy_pipeline(self.graphics_pipeline, None);
-            self.device.destroy_pipeline_layout(self.pipeline_layout, None);
-
-            // Destroy framebuffers
-            for &framebuffer in self.framebuffers.iter() {
-                self.device.destroy_framebuffer(framebuffer, None);
-            }
+            // Destroy the swapchain and everything sized by it
+            self.destroy_swapchain_resources();
 
-            // Destroy render pass
-            self.device.destroy_render_pass(self.render_pass, None);
-
-            // Destroy image views
-            for &image_view in self.swapchain_image_views.iter() {
-                self.device.destroy_image_view(image_view, None);
+            if let Some(frame_timer) = &self.frame_timer {
+                frame_timer.destroy(&self.device);
             }
 
-            // Destroy uniform buffers
-            for &buffer in self.uniform_buffers.iter() {
-                self.device.destroy_buffer(buffer, None);
-            }
-            for &memory in self.uniform_buffers_memory.iter() {
-                self.device.free_memory(memory, None);
-            }
+            self.fill_texture.destroy(&self.device);
 
-            // Destroy descriptor pool and set layout
-            self.device.destroy_descriptor_pool(self.descriptor_pool, None);
+            // Destroy command pool and descriptor set layout
+            self.device.destroy_command_pool(self.command_pool, None);
             self.device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
 
-            // Destroy swapchain
-            self.swapchain_loader.destroy_swapchain(self.swapchain, None);
-
-            // Destroy logical device
+            // Destroy logical device, with whatever memory blocks are left
+            memory::release_device(&self.device);
             self.device.destroy_device(None);
 
             // Destroy surface using surface_loader
             self.surface_loader.destroy_surface(self.surface, None);
 
-            // Destroy Vulkan instance
-            self.instance.destroy_instance(None);
+            // Destroy Vulkan instance
+            self.instance.destroy_instance(None);
+        }
+    }
+}
+
+/// The cursor a frame is drawn with, found on the message thread before the overlay is snapshotted.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameCursor {
+    pub mouse: (i32, i32), // Pixels from the window's top left
+    pub normalized_mouse: [f32; 2], // -1 to 1 across the window, y up
+    pub unit_scale: [f32; 2], // Ring units per NDC unit on each axis
+}
+
+/// Finds the cursor relative to the overlay window and, while the menu is shown, updates the selection, hover
+/// and dwell under it. Runs on the message thread, which acts on the selection; the frame only draws it.
+pub fn track_cursor(overlay_content: &mut OverlayContent, hwnd: HWND) -> FrameCursor {
+    // Get mouse position
+    let mut point: POINT = POINT { x: 0, y: 0 };
+    unsafe {
+        GetCursorPos(&mut point);
+    }
+
+    // Get window position
+    let mut window_rect: RECT = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+    unsafe {
+        GetWindowRect(hwnd, &mut window_rect);
+    }
+
+    // Calculate mouse position relative to the window; a replay supplies its own, a moved gamepad stick
+    // points from the center, and in relative mode the movement since the overlay opened is measured
+    // from the center instead. A menu opened off the cursor takes where the cursor was at open as its center
+    let (half_width, half_height) = ((window_rect.right - window_rect.left) / 2, (window_rect.bottom - window_rect.top) / 2);
+    let stick_position = if overlay_content.visible { gamepad::position() } else { None }.map(|(x, y)| {
+        // Full deflection reaches the outer edge of the outermost ring
+        let reach = rings::all_rings(overlay_content).last().map_or(rings::OUTER_RADIUS, |ring| ring.outer);
+        let length = reach * UNIT_PIXELS;
+        (half_width + (x * length) as i32, half_height + (y * length) as i32)
+    });
+    let relative_position = relative::offset(half_width.min(half_height) as f32)
+        .map(|(dx, dy)| (half_width + dx as i32, half_height + dy as i32));
+    let (mouse_x, mouse_y) = replay::cursor()
+        .or(stick_position)
+        .or(relative_position)
+        .unwrap_or(match overlay_content.cursor_origin {
+            Some((x, y)) => (half_width + point.x - x, half_height + point.y - y),
+            None => (point.x - window_rect.left, point.y - window_rect.top),
+        });
+    if overlay_content.visible {
+        replay::record(SessionEvent::Cursor { x: mouse_x, y: mouse_y });
+    }
+
+    // Window dimensions
+    let window_width = window_rect.right - window_rect.left;
+    let window_height = window_rect.bottom - window_rect.top;
+    // Normalize mouse position to range [-1, 1]
+    // X goes from -1 (left) to 1 (right)
+    // Y goes from -1 (bottom) to 1 (top)
+    let normalized_mouse_x = (mouse_x as f32 / window_width as f32) * 2.0 - 1.0;
+    let normalized_mouse_y = 1.0 - (mouse_y as f32 / window_height as f32) * 2.0;
+    debug_panel::record_cursor(
+        unsafe { GetDpiForWindow(hwnd) },
+        (mouse_x, mouse_y),
+        (normalized_mouse_x, normalized_mouse_y),
+    );
+
+    // Rings are sized in ring units, a fixed number of pixels whatever the window's size and shape
+    let unit_scale = [
+        window_width as f32 / 2.0 / UNIT_PIXELS,
+        window_height as f32 / 2.0 / UNIT_PIXELS,
+    ];
+
+    // The window also stays up for toasts after the menu closes; only track the ring while it is shown
+    if overlay_content.visible {
+        update_selection(normalized_mouse_x * unit_scale[0], normalized_mouse_y * unit_scale[1], overlay_content);
+        overlay_content.track_hover();
+        overlay_content.track_movement((mouse_x, mouse_y));
+        overlay_content.update_dwell();
+    }
+
+    FrameCursor {
+        mouse: (mouse_x, mouse_y),
+        normalized_mouse: [normalized_mouse_x, normalized_mouse_y],
+        unit_scale,
+    }
+}
+
+/// The ring's uniforms for a frame: the posed layout, the animation, the theme's fill, shadow and backdrop, and the selection, progress, enabled, toggle and
+/// pulse state of each segment.
+/// `style` is 1 for high contrast plus 2 for reduced motion.
+fn frame_uniforms(
+    overlay_content: &OverlayContent,
+    layout: &MenuLayout,
+    mouse_pos: [f32; 2],
+    unit_scale: [f32; 2],
+    resolution: [f32; 2],
+    time: f32,
+    style: i32,
+    pose: &Pose,
+    fill: FillUniforms,
+) -> UniformBufferObject {
+    // Progress of running actions, and flags of 1 for disabled plus 2 for toggled on, per segment
+    let mut segment_state = [[0.0; 4]; MAX_SEGMENTS];
+    for (&segment, &fraction) in overlay_content.segment_progress.iter() {
+        if segment >= 0 && (segment as usize) < MAX_SEGMENTS {
+            segment_state[segment as usize][0] = fraction.unwrap_or(0.0);
+            segment_state[segment as usize][1] = 1.0;
+            segment_state[segment as usize][2] = if fraction.is_none() { 1.0 } else { 0.0 };
+        }
+    }
+    for (segment, state) in segment_state.iter_mut().enumerate() {
+        if !overlay_content.is_segment_enabled(segment as i32) {
+            state[3] += 1.0;
+        }
+        if overlay_content.is_segment_on(segment as i32) {
+            state[3] += 2.0;
+        }
+    }
+    let mut segment_pulse = [[0.0; 4]; MAX_SEGMENTS];
+    for (segment, pulse) in segment_pulse.iter_mut().enumerate() {
+        if let Some(elapsed) = overlay_content.pulse_time(segment as i32) {
+            *pulse = [1.0, elapsed.as_secs_f32(), 0.0, 0.0];
         }
     }
+
+    let mut ring_uniforms = [[0.0; 4]; MAX_RINGS];
+    let mut ring_angles = [[0.0; 4]; MAX_RINGS];
+    for (index, ring) in layout.rings.iter().enumerate() {
+        ring_uniforms[index] = ring.as_uniform();
+        ring_angles[index] = ring.angles_uniform();
+    }
+    UniformBufferObject {
+        radius: layout.radius(),
+        inner_radius: layout.inner_radius,
+        segments: overlay_content.segment_count(),
+        time,
+        mouse_pos,
+        segment_gap: layout.segment_gap,
+        item_selected: overlay_content.selected_segment.unwrap_or(-1),
+        dwell_progress: overlay_content.dwell_progress,
+        style,
+        unit_scale,
+        segment_state,
+        ring_count: layout.rings.len() as i32,
+        _padding1: 0,
+        resolution,
+        rings: ring_uniforms,
+        ring_angles,
+        segment_angles: rings::segment_angles(&layout.rings),
+        segment_pulse,
+        fill: fill.fill,
+        fill_colors: fill.colors,
+        shadow: fill.shadow,
+        shadow_color: fill.shadow_color,
+        backdrop: fill.backdrop,
+        animation: [pose.grow, 0.0, 0.0, 0.0],
+        segment_motion: pose.segments.map(|motion| motion.as_uniform()),
+        wedge: fill.wedge,
+    }
 }
 
+/// Hit-tests a cursor position relative to the ring's center, in ring units with y up.
 fn update_selection(normalized_mouse_x: f32, normalized_mouse_y: f32, _overlay_content: &mut OverlayContent) {
 
     // Calculate mouse position relative to the center of the menu
@@ -472,28 +1510,63 @@ fn update_selection(normalized_mouse_x: f32, normalized_mouse_y: f32, _overlay_c
     // Invert Y-axis to match shader
 
     let dist = (coord_x.powi(2) + coord_y.powi(2)).sqrt();
+    _overlay_content.cursor_distance = dist;
+
+    // An engaged dial keeps its segment selected and turns with the cursor until it returns to the center
+    let inner_radius = animation::posed_layout(_overlay_content).inner_radius;
+    if let Some(dial) = _overlay_content.dial.as_mut() {
+        if dist >= inner_radius {
+            dial.turn(coord_y.atan2(coord_x));
+            return;
+        }
+        println!("Dial released");
+        _overlay_content.dial = None;
+        _overlay_content.selected_segment = None;
+    }
+
+    // Past the ring, the snap zones take over from the segments
+    _overlay_content.selected_edge = _overlay_content
+        .edges
+        .as_ref()
+        .and_then(|edges| edges::edge_at(coord_x, coord_y, edges.distance));
+    if _overlay_content.selected_edge.is_some() {
+        if _overlay_content.selected_segment.is_some() {
+            println!("No Segment Selected");
+            _overlay_content.selected_segment = None;
+        }
+        return;
+    }
 
-    // Check if mouse is outside the inner_radius
-    let inner_radius = 0.08;
-    // Should match the value in the shader or from the uniform
-    let outer_radius = 0.25;
-    // Should match ubo.radius
+    // Moving outward reveals further rings, which are then hit-tested like the first
+    rings::update_stage(_overlay_content, dist);
+    let layout = animation::posed_layout(_overlay_content);
 
     let mut selected_segment = None;
 
-    if dist >= inner_radius { //  && dist <= outer_radius
+    if let Some(ring) = layout.ring_at(dist) {
+        _overlay_content.left_center();
+
         // Calculate angle
         let mut angle = coord_y.atan2(coord_x);
         if angle < 0.0 {
             angle += 2.0 * std::f32::consts::PI;
         }
+        angle = _overlay_content.smooth_angle(angle);
 
-        // Calculate segment index
-        let segments = 6; // Should match ubo.segments
-        let segment_gap = 0.1; // Should match ubo.segment_gap
-        let segment_angle_with_gap = (2.0 * std::f32::consts::PI) / segments as f32;
+        // Calculate segment index; near a boundary the current segment is kept until the cursor is clearly past it
+        let segment_index = match _overlay_content.selected_segment {
+            Some(current) if ring.contains(current) && ring.spans(current, angle, _overlay_content.hysteresis) => current,
+            _ => ring.segment_at(angle),
+        };
 
-        let segment_index = (angle / segment_angle_with_gap).floor() as i32;
+        // Disabled segments can't be selected
+        if !_overlay_content.is_segment_enabled(segment_index) {
+            if _overlay_content.selected_segment.is_some() {
+                println!("No Segment Selected");
+                _overlay_content.selected_segment = None;
+            }
+            return;
+        }
 
         selected_segment = Some(segment_index);
 
@@ -501,9 +1574,17 @@ fn update_selection(normalized_mouse_x: f32, normalized_mouse_y: f32, _overlay_c
         if _overlay_content.selected_segment != Some(segment_index) {
             println!("Selected Segment: {}", segment_index);
             _overlay_content.selected_segment = Some(segment_index);
+            // Hovering a dial segment turns the ring into its dial
+            if let Some(dial) = _overlay_content.items.get(segment_index as usize).and_then(|item| item.dial.as_ref()) {
+                _overlay_content.dial = DialState::engage(segment_index, dial);
+            }
         }
     } else {
-        // Mouse is inside the inner radius or outside the outer radius
+        // Mouse is inside the inner radius, or there are no segments; a brief slip inward keeps the selection
+        _overlay_content.reset_smoothing();
+        if _overlay_content.hold_in_center() {
+            return;
+        }
         if _overlay_content.selected_segment.is_some() {
             println!("No Segment Selected");
             _overlay_content.selected_segment = None;
@@ -511,21 +1592,129 @@ fn update_selection(normalized_mouse_x: f32, normalized_mouse_y: f32, _overlay_c
     }
 }
 
+/// Creates the Vulkan instance with the surface extensions, and the validation layers if enabled.
+fn create_instance(entry: &Entry, enable_validation_layers: bool, layer_names: &[*const i8]) -> Result<Instance, String> {
+    let app_name = CString::new("Vulkan Overlay").unwrap();
+    let engine_name = CString::new("No Engine").unwrap();
+
+    let app_info = vk::ApplicationInfo::builder()
+        .application_name(&app_name)
+        .engine_name(&engine_name)
+        .application_version(0)
+        .engine_version(0)
+        .api_version(vk::API_VERSION_1_1); // 1.1 for device LUIDs
+
+    // Required extensions for Windows surface
+    let extension_names = vec![
+        Surface::name().as_ptr(),
+        Win32Surface::name().as_ptr(),
+    ];
+
+    let mut instance_create_info = vk::InstanceCreateInfo::builder()
+        .application_info(&app_info)
+        .enabled_extension_names(&extension_names);
+
+    if enable_validation_layers {
+        instance_create_info = instance_create_info.enabled_layer_names(layer_names);
+    }
+
+    unsafe {
+        entry
+            .create_instance(&instance_create_info, None)
+            .map_err(|e| format!("Failed to create Vulkan instance: {:?}", e))
+    }
+}
+
+/// Creates a Vulkan surface on a window, the menu's or the HUD's.
+fn create_surface(win32_surface_loader: &Win32Surface, hwnd: HWND) -> Result<vk::SurfaceKHR, String> {
+    let hwnd_ptr = hwnd as *mut c_void;
+    let hinstance = unsafe { winapi::um::libloaderapi::GetModuleHandleW(ptr::null()) };
+
+    let win32_create_info = vk::Win32SurfaceCreateInfoKHR::builder()
+        .hinstance(hinstance as *mut c_void)
+        .hwnd(hwnd_ptr);
+
+    unsafe {
+        win32_surface_loader
+            .create_win32_surface(&win32_create_info, None)
+            .map_err(|e| format!("Failed to create Win32 surface: {:?}", e))
+    }
+}
+
 /// Picks a suitable physical device that supports graphics and presentation.
-fn pick_physical_device(instance: &Instance, surface_loader: &Surface, surface: vk::SurfaceKHR) -> Result<vk::PhysicalDevice, String> {
+/// On hybrid-graphics machines the adapter that presents to the monitor is preferred, because rendering on the
+/// other GPU can leave the transparent overlay black or invisible.
+fn pick_physical_device(
+    instance: &Instance,
+    surface_loader: &Surface,
+    surface: vk::SurfaceKHR,
+    presenting: Option<&(Luid, String)>,
+) -> Result<vk::PhysicalDevice, String> {
     let physical_devices = unsafe {
         instance
             .enumerate_physical_devices()
             .map_err(|e| format!("Failed to enumerate physical devices: {:?}", e))?
     };
 
+    let mut suitable = Vec::new();
     for device in physical_devices {
         if is_device_suitable(instance, surface_loader, device, surface)? {
-            return Ok(device);
+            suitable.push(device);
         }
     }
+    let first = *suitable.first().ok_or_else(|| "Failed to find a suitable GPU!".to_string())?;
+
+    let (luid, adapter_name) = match presenting {
+        Some(presenting) => presenting,
+        None => return Ok(first),
+    };
+    if let Some(&device) = suitable.iter().find(|&&device| adapter::device_luid(instance, device) == Some(*luid)) {
+        return Ok(device);
+    }
+
+    // Only warn when the mismatch is certain; drivers without LUIDs can't be compared
+    if adapter::device_luid(instance, first).is_some() {
+        let message = format!(
+            "Rendering on {} but the display is driven by {}; if the ring is black or invisible, run the overlay on the integrated GPU",
+            device_name(instance, first),
+            adapter_name
+        );
+        eprintln!("{}", message);
+        diagnostics::record_event(message.as_str());
+        toast::push(message);
+    }
+    Ok(first)
+}
+
+/// Picks a GPU and graphics queue family for drawing without a window, preferring a discrete GPU.
+fn pick_offscreen_device(instance: &Instance) -> Result<(vk::PhysicalDevice, u32), String> {
+    let physical_devices = unsafe {
+        instance
+            .enumerate_physical_devices()
+            .map_err(|e| format!("Failed to enumerate physical devices: {:?}", e))?
+    };
+
+    let mut candidates: Vec<(vk::PhysicalDevice, u32)> = physical_devices
+        .into_iter()
+        .filter_map(|device| {
+            let queue_families = unsafe { instance.get_physical_device_queue_family_properties(device) };
+            queue_families
+                .iter()
+                .position(|family| family.queue_flags.contains(vk::QueueFlags::GRAPHICS))
+                .map(|index| (device, index as u32))
+        })
+        .collect();
+    candidates.sort_by_key(|&(device, _)| {
+        unsafe { instance.get_physical_device_properties(device) }.device_type != vk::PhysicalDeviceType::DISCRETE_GPU
+    });
+    candidates.first().copied().ok_or_else(|| "Failed to find a suitable GPU!".to_string())
+}
 
-    Err("Failed to find a suitable GPU!".to_string())
+fn device_name(instance: &Instance, device: vk::PhysicalDevice) -> String {
+    let properties = unsafe { instance.get_physical_device_properties(device) };
+    unsafe { CStr::from_ptr(properties.device_name.as_ptr()) }
+        .to_string_lossy()
+        .into_owned()
 }
 
 /// Checks if the physical device is suitable by verifying it supports required features.
@@ -559,6 +1748,28 @@ fn is_device_suitable(instance: &Instance, surface_loader: &Surface, device: vk:
     Ok(false)
 }
 
+/// Whether the device supports a device extension.
+fn supports_extension(instance: &Instance, physical_device: vk::PhysicalDevice, name: &CStr) -> bool {
+    let extensions = unsafe { instance.enumerate_device_extension_properties(physical_device) }.unwrap_or_default();
+    extensions
+        .iter()
+        .any(|extension| unsafe { CStr::from_ptr(extension.extension_name.as_ptr()) } == name)
+}
+
+/// Creates the texture frames are shared through and publishes how to open it in the status report.
+fn create_shared_texture(
+    instance: &Instance,
+    device: &Device,
+    physical_device: vk::PhysicalDevice,
+    format: vk::Format,
+    extent: vk::Extent2D,
+    name: &str,
+) -> Result<SharedTexture, String> {
+    let shared_texture = SharedTexture::new(instance, device, physical_device, format, extent, name)?;
+    status::set_shared_texture(shared_texture.description());
+    Ok(shared_texture)
+}
+
 /// Finds a suitable queue family index that supports graphics and presentation.
 fn find_queue_family_index(instance: &Instance, physical_device: vk::PhysicalDevice, surface_loader: &Surface, surface: vk::SurfaceKHR) -> Result<u32, String> {
     let queue_families = unsafe { instance.get_physical_device_queue_family_properties(physical_device) };
@@ -580,11 +1791,14 @@ fn find_queue_family_index(instance: &Instance, physical_device: vk::PhysicalDev
     Err("Failed to find a suitable queue family.".to_string())
 }
 
-/// Creates a logical device and retrieves the graphics queue.
+/// Creates a logical device and retrieves the graphics queue. `timeline_semaphores` enables the feature the shared
+/// texture's fence needs; its extension must be among `device_extension_names`.
 fn create_logical_device_and_queue(
     instance: &Instance,
     physical_device: vk::PhysicalDevice,
     queue_family_index: u32,
+    device_extension_names: &[*const i8],
+    timeline_semaphores: bool,
     enable_validation_layers: bool,
     layer_names: &[*const i8],
 ) -> Result<(Device, vk::Queue), String> {
@@ -597,11 +1811,13 @@ fn create_logical_device_and_queue(
 
     let queue_create_infos = [queue_create_info];
 
-    let device_extension_names = [Swapchain::name().as_ptr()];
-
+    let mut timeline_features = vk::PhysicalDeviceTimelineSemaphoreFeatures::builder().timeline_semaphore(true);
     let mut device_create_info_builder = vk::DeviceCreateInfo::builder()
         .queue_create_infos(&queue_create_infos)
-        .enabled_extension_names(&device_extension_names);
+        .enabled_extension_names(device_extension_names);
+    if timeline_semaphores {
+        device_create_info_builder = device_create_info_builder.push_next(&mut timeline_features);
+    }
 
     if enable_validation_layers {
         device_create_info_builder = device_create_info_builder.enabled_layer_names(layer_names);
@@ -628,7 +1844,8 @@ fn create_swapchain(
     physical_device: vk::PhysicalDevice,
     surface: vk::SurfaceKHR,
     _queue_family_index: u32,
-) -> Result<(vk::SwapchainKHR, vk::Format, vk::Extent2D), String> {
+    window_size: (u32, u32),
+) -> Result<(vk::SwapchainKHR, vk::Format, vk::Extent2D, vk::PresentModeKHR, vk::CompositeAlphaFlagsKHR), String> {
     // Query surface capabilities and formats
     let surface_capabilities = unsafe {
         surface_loader
@@ -674,8 +1891,8 @@ fn create_swapchain(
         surface_capabilities.current_extent
     } else {
         vk::Extent2D {
-            width: 800,  // Replace with actual window width
-            height: 600, // Replace with actual window height
+            width: window_size.0,
+            height: window_size.1,
         }
     };
 
@@ -701,6 +1918,12 @@ fn create_swapchain(
 
     println!("Selected composite alpha: {:?}", composite_alpha);
 
+    // Copying frames out, e.g. into the shared texture, needs the images to be transfer sources
+    let mut image_usage = vk::ImageUsageFlags::COLOR_ATTACHMENT;
+    if surface_capabilities.supported_usage_flags.contains(vk::ImageUsageFlags::TRANSFER_SRC) {
+        image_usage |= vk::ImageUsageFlags::TRANSFER_SRC;
+    }
+
     let swapchain_create_info = vk::SwapchainCreateInfoKHR::builder()
         .surface(surface)
         .min_image_count(image_count)
@@ -708,7 +1931,7 @@ fn create_swapchain(
         .image_color_space(surface_format.color_space)
         .image_extent(swap_extent)
         .image_array_layers(1)
-        .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
+        .image_usage(image_usage)
         .image_sharing_mode(vk::SharingMode::EXCLUSIVE)
         .pre_transform(surface_capabilities.current_transform)
         .composite_alpha(composite_alpha)
@@ -722,7 +1945,7 @@ fn create_swapchain(
             .map_err(|e| format!("Failed to create swapchain: {:?}", e))?
     };
 
-    Ok((swapchain, surface_format.format, swap_extent))
+    Ok((swapchain, surface_format.format, swap_extent, present_mode, composite_alpha))
 }
 
 /// Creates image views for each swapchain image.
@@ -849,7 +2072,7 @@ fn create_framebuffers(
 fn create_graphics_pipeline(device: &Device, render_pass: vk::RenderPass, swapchain_extent: vk::Extent2D, descriptor_set_layout: vk::DescriptorSetLayout,) -> Result<(vk::PipelineLayout, vk::Pipeline), String> {
     // Load shader modules
     let vert_shader_code = read_spirv_shader("shaders/vert.spv")?;
-    let frag_shader_code = read_spirv_shader("shaders/frag.spv")?;
+    let frag_shader_code = custom_shader::fragment_code()?;
 
     let vert_shader_module = unsafe {
         device
@@ -995,8 +2218,10 @@ fn create_graphics_pipeline(device: &Device, render_pass: vk::RenderPass, swapch
 }
 
 /// Reads a SPIR-V shader file and returns its contents as a Vec<u32>.
-fn read_spirv_shader<P: AsRef<Path>>(path: P) -> Result<Vec<u32>, String> {
-    let mut file = File::open(path.as_ref()).map_err(|e| format!("Failed to open shader file: {:?}", e))?;
+pub(crate) fn read_spirv_shader<P: AsRef<Path>>(path: P) -> Result<Vec<u32>, String> {
+    let path = asset_path(path.as_ref());
+    let mut file =
+        File::open(&path).map_err(|e| format!("Failed to open shader file {}: {:?}", path.display(), e))?;
     let mut buffer = Vec::new();
     file.read_to_end(&mut buffer).map_err(|e| format!("Failed to read shader file: {:?}", e))?;
 
@@ -1014,6 +2239,288 @@ fn read_spirv_shader<P: AsRef<Path>>(path: P) -> Result<Vec<u32>, String> {
     Ok(spirv)
 }
 
+/// Finds a file shipped next to the exe, like the compiled shaders: in the working directory if it is there, else
+/// in the exe's folder. Started with Windows, the overlay runs in System32.
+fn asset_path(path: &Path) -> PathBuf {
+    if path.is_absolute() || path.exists() {
+        return path.to_path_buf();
+    }
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|folder| folder.join(path)))
+        .filter(|beside_exe| beside_exe.exists())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// Size of a vertex attribute format the quad pipelines use.
+fn attribute_size(format: vk::Format) -> u32 {
+    match format {
+        vk::Format::R32_SFLOAT => 4,
+        vk::Format::R32G32_SFLOAT => 8,
+        vk::Format::R32G32B32A32_SFLOAT => 16,
+        _ => unreachable!("no quad vertex has a {:?} attribute", format),
+    }
+}
+
+/// Creates the pipeline of something drawn over the ring in the same render pass: the text, icons, badges, loupe
+/// or widgets named `name`, whose shaders are shaders/`name`_vert.spv and _frag.spv. Vertices are the
+/// `attributes` packed in order, at locations counting from 0; with none, the vertex shader makes the quad.
+/// Nothing is blended, as the fragment shaders keep pixels fully on or off; see `dithered` in
+/// shaders/indexFromRust.frag.glsl.
+pub(crate) fn create_quad_pipeline(
+    device: &Device,
+    render_pass: vk::RenderPass,
+    extent: vk::Extent2D,
+    name: &str,
+    attributes: &[vk::Format],
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    push_constant_ranges: &[vk::PushConstantRange],
+) -> Result<(vk::PipelineLayout, vk::Pipeline), String> {
+    let vert_shader_code = read_spirv_shader(format!("shaders/{}_vert.spv", name))?;
+    let frag_shader_code = read_spirv_shader(format!("shaders/{}_frag.spv", name))?;
+
+    let vert_shader_module = unsafe {
+        device
+            .create_shader_module(&vk::ShaderModuleCreateInfo::builder().code(&vert_shader_code), None)
+            .map_err(|e| format!("Failed to create {} vertex shader module: {:?}", name, e))?
+    };
+
+    let frag_shader_module = unsafe {
+        device
+            .create_shader_module(&vk::ShaderModuleCreateInfo::builder().code(&frag_shader_code), None)
+            .map_err(|e| format!("Failed to create {} fragment shader module: {:?}", name, e))?
+    };
+
+    let shader_entry_name = CString::new("main").unwrap();
+    let shader_stages = [
+        vk::PipelineShaderStageCreateInfo::builder()
+            .stage(vk::ShaderStageFlags::VERTEX)
+            .module(vert_shader_module)
+            .name(&shader_entry_name)
+            .build(),
+        vk::PipelineShaderStageCreateInfo::builder()
+            .stage(vk::ShaderStageFlags::FRAGMENT)
+            .module(frag_shader_module)
+            .name(&shader_entry_name)
+            .build(),
+    ];
+
+    let mut offset = 0;
+    let attribute_descriptions: Vec<vk::VertexInputAttributeDescription> = attributes
+        .iter()
+        .enumerate()
+        .map(|(location, &format)| {
+            let description = vk::VertexInputAttributeDescription { location: location as u32, binding: 0, format, offset };
+            offset += attribute_size(format);
+            description
+        })
+        .collect();
+    let binding_descriptions = [vk::VertexInputBindingDescription {
+        binding: 0,
+        stride: offset,
+        input_rate: vk::VertexInputRate::VERTEX,
+    }];
+    let vertex_input_info = if attributes.is_empty() {
+        vk::PipelineVertexInputStateCreateInfo::builder()
+    } else {
+        vk::PipelineVertexInputStateCreateInfo::builder()
+            .vertex_binding_descriptions(&binding_descriptions)
+            .vertex_attribute_descriptions(&attribute_descriptions)
+    };
+
+    let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::builder()
+        .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+        .primitive_restart_enable(false);
+
+    let viewports = [vk::Viewport {
+        x: 0.0,
+        y: 0.0,
+        width: extent.width as f32,
+        height: extent.height as f32,
+        min_depth: 0.0,
+        max_depth: 1.0,
+    }];
+    let scissors = [vk::Rect2D {
+        offset: vk::Offset2D { x: 0, y: 0 },
+        extent,
+    }];
+    let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+        .viewports(&viewports)
+        .scissors(&scissors);
+
+    // Quads are emitted without a consistent winding, so nothing is culled
+    let rasterizer = vk::PipelineRasterizationStateCreateInfo::builder()
+        .depth_clamp_enable(false)
+        .rasterizer_discard_enable(false)
+        .polygon_mode(vk::PolygonMode::FILL)
+        .line_width(1.0)
+        .cull_mode(vk::CullModeFlags::NONE)
+        .front_face(vk::FrontFace::CLOCKWISE)
+        .depth_bias_enable(false);
+
+    let multisampling = vk::PipelineMultisampleStateCreateInfo::builder()
+        .sample_shading_enable(false)
+        .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+
+    let color_blend_attachments = [vk::PipelineColorBlendAttachmentState::builder()
+        .color_write_mask(
+            vk::ColorComponentFlags::R
+                | vk::ColorComponentFlags::G
+                | vk::ColorComponentFlags::B
+                | vk::ColorComponentFlags::A,
+        )
+        .blend_enable(false)
+        .build()];
+    let color_blending = vk::PipelineColorBlendStateCreateInfo::builder()
+        .logic_op_enable(false)
+        .logic_op(vk::LogicOp::COPY)
+        .attachments(&color_blend_attachments)
+        .blend_constants([0.0, 0.0, 0.0, 0.0]);
+
+    let set_layouts = [descriptor_set_layout];
+    let set_layouts: &[vk::DescriptorSetLayout] =
+        if descriptor_set_layout == vk::DescriptorSetLayout::null() { &[] } else { &set_layouts };
+    let pipeline_layout_info = vk::PipelineLayoutCreateInfo::builder()
+        .set_layouts(set_layouts)
+        .push_constant_ranges(push_constant_ranges);
+
+    let pipeline_layout = unsafe {
+        device
+            .create_pipeline_layout(&pipeline_layout_info, None)
+            .map_err(|e| format!("Failed to create {} pipeline layout: {:?}", name, e))?
+    };
+
+    let pipeline_info = vk::GraphicsPipelineCreateInfo::builder()
+        .stages(&shader_stages)
+        .vertex_input_state(&vertex_input_info)
+        .input_assembly_state(&input_assembly)
+        .viewport_state(&viewport_state)
+        .rasterization_state(&rasterizer)
+        .multisample_state(&multisampling)
+        .color_blend_state(&color_blending)
+        .layout(pipeline_layout)
+        .render_pass(render_pass)
+        .subpass(0)
+        .base_pipeline_handle(vk::Pipeline::null());
+
+    let pipeline = unsafe {
+        device
+            .create_graphics_pipelines(vk::PipelineCache::null(), &[pipeline_info.build()], None)
+            .map_err(|e| format!("Failed to create {} pipeline: {:?}", name, e))?
+            .remove(0)
+    };
+
+    unsafe {
+        device.destroy_shader_module(vert_shader_module, None);
+        device.destroy_shader_module(frag_shader_module, None);
+    }
+
+    Ok((pipeline_layout, pipeline))
+}
+
+/// A host-visible vertex buffer per swapchain image, written directly while the frames using the others are still
+/// in flight. Every draw covers the whole buffer; vertices past the written ones are zeroed so they rasterize
+/// nothing.
+pub(crate) struct VertexRing<V> {
+    buffers: Vec<vk::Buffer>,
+    memories: Vec<Allocation>,
+    capacity: usize, // Vertices each buffer holds
+    uploaded: Vec<Vec<V>>, // Current contents of each buffer, beyond which it is zeroed
+    name: &'static str,
+}
+
+impl<V: Copy + PartialEq> VertexRing<V> {
+    /// Creates the buffers, zeroed. `name` goes into error messages, e.g. "text".
+    pub fn new(
+        instance: &Instance,
+        device: &Device,
+        physical_device: vk::PhysicalDevice,
+        capacity: usize,
+        image_count: usize,
+        name: &'static str,
+    ) -> Result<Self, String> {
+        let buffer_size = (capacity * std::mem::size_of::<V>()) as vk::DeviceSize;
+        let mut buffers = Vec::with_capacity(image_count);
+        let mut memories = Vec::with_capacity(image_count);
+        for _ in 0..image_count {
+            let (buffer, memory) = create_buffer(
+                instance,
+                device,
+                physical_device,
+                buffer_size,
+                vk::BufferUsageFlags::VERTEX_BUFFER,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            )?;
+            buffers.push(buffer);
+            memories.push(memory);
+        }
+        let ring = Self { buffers, memories, capacity, uploaded: vec![Vec::new(); image_count], name };
+
+        // Buffer contents are undefined until written; start with nothing to draw
+        for image_index in 0..image_count {
+            ring.write(device, image_index, 0, &[], capacity)?;
+        }
+        Ok(ring)
+    }
+
+    /// Copies a frame's vertices into the buffer of the given swapchain image, up to its capacity. Only vertices
+    /// from the first change onwards are written, so an unchanged frame costs nothing.
+    pub fn upload(&mut self, device: &Device, image_index: usize, vertices: &[V]) -> Result<(), String> {
+        let vertices = &vertices[..vertices.len().min(self.capacity)];
+        let previous = &self.uploaded[image_index];
+
+        let first_changed = previous
+            .iter()
+            .zip(vertices)
+            .position(|(old, new)| old != new)
+            .unwrap_or_else(|| previous.len().min(vertices.len()));
+        let end = previous.len().max(vertices.len());
+        if first_changed == end {
+            return Ok(());
+        }
+
+        self.write(device, image_index, first_changed, &vertices[first_changed..], end)?;
+        self.uploaded[image_index] = vertices.to_vec();
+        Ok(())
+    }
+
+    /// Writes `vertices` starting at vertex `first` and zeroes from their end up to vertex `end`.
+    fn write(&self, device: &Device, image_index: usize, first: usize, vertices: &[V], end: usize) -> Result<(), String> {
+        let data_ptr = memory::mapped(device, &self.memories[image_index])
+            .map_err(|e| format!("Failed to map {} vertex memory: {}", self.name, e))? as *mut V;
+        unsafe {
+            let data_ptr = data_ptr.add(first);
+            data_ptr.copy_from_nonoverlapping(vertices.as_ptr(), vertices.len());
+            let written = first + vertices.len();
+            if end > written {
+                data_ptr.add(vertices.len()).write_bytes(0, end - written);
+            }
+        }
+        Ok(())
+    }
+
+    /// Binds the buffer of the given swapchain image and draws all of it, inside an active render pass with the
+    /// pipeline bound.
+    pub fn record_draw(&self, device: &Device, command_buffer: vk::CommandBuffer, image_index: usize) {
+        unsafe {
+            device.cmd_bind_vertex_buffers(command_buffer, 0, &[self.buffers[image_index]], &[0]);
+            device.cmd_draw(command_buffer, self.capacity as u32, 1, 0, 0);
+        }
+    }
+
+    /// Destroys the buffers. The device must be idle.
+    pub fn destroy(&self, device: &Device) {
+        unsafe {
+            for &buffer in self.buffers.iter() {
+                device.destroy_buffer(buffer, None);
+            }
+        }
+        for &allocation in self.memories.iter() {
+            memory::free(device, allocation);
+        }
+    }
+}
+
 /// Creates a command pool for allocating command buffers.
 fn create_command_pool(device: &Device, queue_family_index: u32) -> Result<vk::CommandPool, String> {
     let pool_info = vk::CommandPoolCreateInfo::builder()
@@ -1055,6 +2562,15 @@ fn record_command_buffers(
     swapchain_extent: vk::Extent2D,
     pipeline_layout: vk::PipelineLayout,
     descriptor_sets: &[vk::DescriptorSet],
+    text: &TextRenderer,
+    loupe: &LoupeRenderer,
+    icons: &IconRenderer,
+    badges: &BadgeRenderer,
+    gui: Option<&GuiRenderer>,
+    timer: Option<&FrameTimer>,
+    swapchain_images: &[vk::Image],
+    shared_texture: Option<&SharedTexture>,
+    offscreen: Option<&OffscreenTarget>,
 ) -> Result<(), String> {
     for (i, &command_buffer) in command_buffers.iter().enumerate() {
         let begin_info = vk::CommandBufferBeginInfo::builder();
@@ -1081,6 +2597,9 @@ fn record_command_buffers(
             .clear_values(&clear_values);
 
         unsafe {
+            if let Some(timer) = timer {
+                timer.record_start(device, command_buffer, i);
+            }
             device.cmd_begin_render_pass(command_buffer, &render_pass_info, vk::SubpassContents::INLINE);
             device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, graphics_pipeline);
             
@@ -1096,7 +2615,31 @@ fn record_command_buffers(
 
             // Update the draw call to draw 4 vertices for the quad
             device.cmd_draw(command_buffer, 6, 1, 0, 0);
+
+            // Draw the segment icons and their badges and the color picker's loupe, then text and toasts over the
+            // ring, and egui's widgets over everything
+            icons.record(device, command_buffer, i);
+            badges.record(device, command_buffer, i);
+            loupe.record(device, command_buffer, i);
+            text.record(device, command_buffer, i);
+            if let Some(gui) = gui {
+                gui.record(device, command_buffer, i);
+            }
+
             device.cmd_end_render_pass(command_buffer);
+
+            // Hand the finished frame to the present hook too
+            if let Some(shared_texture) = shared_texture {
+                shared_texture.record_copy(device, command_buffer, swapchain_images[i]);
+            }
+            // Without a window the frame is read back instead of presented
+            if let Some(offscreen) = offscreen {
+                offscreen.record_readback(device, command_buffer);
+            }
+            if let Some(timer) = timer {
+                timer.record_end(device, command_buffer, i);
+            }
+
             device
                 .end_command_buffer(command_buffer)
                 .map_err(|e| format!("Failed to end command buffer: {:?}", e))?;
@@ -1106,6 +2649,47 @@ fn record_command_buffers(
     Ok(())
 }
 
+/// Records the HUD's command buffers: just the text pipeline over the color-keyed background.
+fn record_hud_command_buffers(
+    device: &Device,
+    command_buffers: &[vk::CommandBuffer],
+    render_pass: vk::RenderPass,
+    framebuffers: &[vk::Framebuffer],
+    extent: vk::Extent2D,
+    text: &TextRenderer,
+) -> Result<(), String> {
+    for (i, &command_buffer) in command_buffers.iter().enumerate() {
+        let begin_info = vk::CommandBufferBeginInfo::builder();
+        let clear_values = [vk::ClearValue {
+            color: vk::ClearColorValue {
+                float32: [1.0, 0.0, 1.0, 1.0], // Fully transparent
+            },
+        }];
+        let render_pass_info = vk::RenderPassBeginInfo::builder()
+            .render_pass(render_pass)
+            .framebuffer(framebuffers[i])
+            .render_area(vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent,
+            })
+            .clear_values(&clear_values);
+
+        unsafe {
+            device
+                .begin_command_buffer(command_buffer, &begin_info)
+                .map_err(|e| format!("Failed to begin HUD command buffer: {:?}", e))?;
+            device.cmd_begin_render_pass(command_buffer, &render_pass_info, vk::SubpassContents::INLINE);
+            text.record(device, command_buffer, i);
+            device.cmd_end_render_pass(command_buffer);
+            device
+                .end_command_buffer(command_buffer)
+                .map_err(|e| format!("Failed to end HUD command buffer: {:?}", e))?;
+        }
+    }
+
+    Ok(())
+}
+
 /// Creates synchronization objects: semaphores and fences.
 fn create_sync_objects(device: &Device, max_frames_in_flight: usize) -> Result<(Vec<vk::Semaphore>, Vec<vk::Semaphore>, Vec<vk::Fence>), String> {
     let semaphore_info = vk::SemaphoreCreateInfo::builder();
@@ -1141,7 +2725,7 @@ fn create_sync_objects(device: &Device, max_frames_in_flight: usize) -> Result<(
     Ok((image_available_semaphores, render_finished_semaphores, in_flight_fences))
 }
 
-/// Creates a descriptor set layout for the uniform buffer.
+/// Creates a descriptor set layout for the uniform buffer, the theme's texture and the backdrop.
 fn create_descriptor_set_layout(device: &Device) -> Result<vk::DescriptorSetLayout, String> {
     let ubo_layout_binding = vk::DescriptorSetLayoutBinding::builder()
         .binding(0)
@@ -1149,8 +2733,21 @@ fn create_descriptor_set_layout(device: &Device) -> Result<vk::DescriptorSetLayo
         .descriptor_count(1)
         .stage_flags(vk::ShaderStageFlags::FRAGMENT)
         .build();
+    let fill_layout_binding = vk::DescriptorSetLayoutBinding::builder()
+        .binding(1)
+        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(1)
+        .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+        .build();
+
+    let backdrop_layout_binding = vk::DescriptorSetLayoutBinding::builder()
+        .binding(2)
+        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(1)
+        .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+        .build();
 
-    let bindings = [ubo_layout_binding];
+    let bindings = [ubo_layout_binding, fill_layout_binding, backdrop_layout_binding];
 
     let layout_info = vk::DescriptorSetLayoutCreateInfo::builder()
         .bindings(&bindings);
@@ -1169,7 +2766,7 @@ fn create_uniform_buffers(
     device: &Device,
     physical_device: vk::PhysicalDevice,
     swapchain_image_count: usize,
-) -> Result<(Vec<vk::Buffer>, Vec<vk::DeviceMemory>), String> {
+) -> Result<(Vec<vk::Buffer>, Vec<Allocation>), String> {
     let buffer_size = std::mem::size_of::<UniformBufferObject>() as vk::DeviceSize;
     let mut uniform_buffers = Vec::with_capacity(swapchain_image_count);
     let mut uniform_buffers_memory = Vec::with_capacity(swapchain_image_count);
@@ -1190,7 +2787,7 @@ fn create_uniform_buffers(
     Ok((uniform_buffers, uniform_buffers_memory))
 }
 
-/// Creates a descriptor pool for uniform buffers.
+/// Creates a descriptor pool for uniform buffers, the theme's texture and the backdrop.
 fn create_descriptor_pool(
     device: &Device,
     swapchain_image_count: usize,
@@ -1199,8 +2796,12 @@ fn create_descriptor_pool(
         .ty(vk::DescriptorType::UNIFORM_BUFFER)
         .descriptor_count(swapchain_image_count as u32)
         .build();
+    let sampler_pool_size = vk::DescriptorPoolSize::builder()
+        .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(2 * swapchain_image_count as u32)
+        .build();
 
-    let pool_sizes = [pool_size];
+    let pool_sizes = [pool_size, sampler_pool_size];
 
     let pool_info = vk::DescriptorPoolCreateInfo::builder()
         .pool_sizes(&pool_sizes)
@@ -1214,12 +2815,14 @@ fn create_descriptor_pool(
     Ok(descriptor_pool)
 }
 
-/// Creates descriptor sets for uniform buffers.
+/// Creates descriptor sets for uniform buffers, the theme's texture and the backdrop.
 fn create_descriptor_sets(
     device: &Device,
     descriptor_pool: vk::DescriptorPool,
     descriptor_set_layout: vk::DescriptorSetLayout,
     uniform_buffers: &[vk::Buffer],
+    fill_texture: &FillTexture,
+    backdrop: &BackdropTexture,
 ) -> Result<Vec<vk::DescriptorSet>, String> {
     let layouts = vec![descriptor_set_layout; uniform_buffers.len()];
 
@@ -1244,23 +2847,37 @@ fn create_descriptor_sets(
             .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
             .buffer_info(std::slice::from_ref(&buffer_info));
 
+        let image_info = fill_texture.descriptor_info();
+        let fill_write = vk::WriteDescriptorSet::builder()
+            .dst_set(descriptor_set)
+            .dst_binding(1)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(std::slice::from_ref(&image_info));
+
+        let backdrop_info = backdrop.descriptor_info();
+        let backdrop_write = vk::WriteDescriptorSet::builder()
+            .dst_set(descriptor_set)
+            .dst_binding(2)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(std::slice::from_ref(&backdrop_info));
+
         unsafe {
-            device.update_descriptor_sets(&[descriptor_write.build()], &[]);
+            device.update_descriptor_sets(&[descriptor_write.build(), fill_write.build(), backdrop_write.build()], &[]);
         }
     }
 
     Ok(descriptor_sets)
 }
 
-/// Helper function to create a buffer.
-fn create_buffer(
+/// Helper function to create a buffer, bound to memory suballocated from a shared block.
+pub(crate) fn create_buffer(
     instance: &Instance,
     device: &Device,
     physical_device: vk::PhysicalDevice,
     size: vk::DeviceSize,
     usage: vk::BufferUsageFlags,
     properties: vk::MemoryPropertyFlags,
-) -> Result<(vk::Buffer, vk::DeviceMemory), String> {
+) -> Result<(vk::Buffer, Allocation), String> {
     let buffer_info = vk::BufferCreateInfo::builder()
         .size(size)
         .usage(usage)
@@ -1277,23 +2894,11 @@ fn create_buffer(
         instance.get_physical_device_memory_properties(physical_device)
     };
 
-    let memory_type = find_memory_type(
-        mem_requirements.memory_type_bits,
-        properties,
-        mem_properties,
-    )?;
-
-    let alloc_info = vk::MemoryAllocateInfo::builder()
-        .allocation_size(mem_requirements.size)
-        .memory_type_index(memory_type);
-
-    let buffer_memory = unsafe {
-        device.allocate_memory(&alloc_info, None)
-            .map_err(|e| format!("Failed to allocate buffer memory: {:?}", e))?
-    };
+    let buffer_memory = memory::allocate(device, mem_properties, mem_requirements, properties, Resource::Linear)
+        .map_err(|e| format!("Failed to allocate buffer memory: {}", e))?;
 
     unsafe {
-        device.bind_buffer_memory(buffer, buffer_memory, 0)
+        device.bind_buffer_memory(buffer, buffer_memory.memory, buffer_memory.offset)
             .map_err(|e| format!("Failed to bind buffer memory: {:?}", e))?;
     }
 
@@ -1301,7 +2906,7 @@ fn create_buffer(
 }
 
 /// Helper function to find a suitable memory type.
-fn find_memory_type(
+pub(crate) fn find_memory_type(
     type_filter: u32,
     properties: vk::MemoryPropertyFlags,
     mem_properties: vk::PhysicalDeviceMemoryProperties,
@@ -0,0 +1,325 @@
+// Render thread: owns the renderer and the HUD and draws snapshots of the overlay sent by the message thread,
+// so slow GPU frames and swapchain rebuilds never hold up hotkeys and input
+
+use winapi::shared::windef::HWND;
+use winapi::um::winuser::{DispatchMessageW, PeekMessageW, TranslateMessage, MSG, PM_REMOVE};
+use std::mem::zeroed;
+use std::ptr::null_mut;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use crate::config::{HookConfig, HudConfig, RendererConfig};
+use crate::hud::{self, Hud};
+use crate::overlay::OverlayContent;
+use crate::render::{FrameCursor, Renderer};
+use crate::{diagnostics, status, toast, wake};
+
+/// The thread's name, which crash reports and the panic hook go by.
+pub const THREAD_NAME: &str = "render";
+/// Frames in a row that may fail before rendering is given up. The first failure rebuilds the swapchain, later
+/// ones recreate the whole renderer, e.g. after the driver reset the GPU.
+const MAX_RECOVERIES: u32 = 3;
+/// Pause before the renderer is recreated, giving a reset driver time to come back.
+const RESTART_DELAY: Duration = Duration::from_millis(500);
+
+enum Command {
+    /// Draws the overlay as of this snapshot, with the cursor the message thread tracked the selection with.
+    Frame(Box<OverlayContent>, FrameCursor),
+    /// Shows or hides the HUD after its hotkey, and redraws it when its readings are due.
+    UpdateHud,
+    ConfigureHud(HudConfig),
+    /// Cleans up the renderer, releasing the GPU, until resumed; frames and HUD updates meanwhile are dropped.
+    Pause,
+    /// Ends a pause; the renderer is created again right away, or with the next frame if it is created lazily.
+    Resume,
+    Stop,
+}
+
+/// Why the render thread stopped.
+pub enum RenderFailure {
+    /// Frames kept failing and recreating the renderer didn't help.
+    Rendering(String),
+    /// The renderer couldn't be created when it was first needed, or again after a pause or a release; the popup
+    /// menu can take over as when it fails at startup.
+    Creating(String),
+}
+
+/// Handle to the render thread, held by the message thread.
+pub struct RenderThread {
+    commands: Sender<Command>,
+    failures: Receiver<RenderFailure>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// When the thread creates and destroys its renderer, from `[renderer]`.
+struct Lifetime {
+    lazy: bool, // Created for the first frame or HUD update that needs it, not at startup
+    release_after: Option<Duration>, // Destroyed after this long without frames or the HUD
+}
+
+impl RenderThread {
+    /// Starts the thread and creates the renderer on it for the overlay window, unless it is created lazily.
+    /// Returns the renderer's error, once the thread has ended, if it can't be created.
+    pub fn start(hwnd: HWND, hook: &HookConfig, hud: &HudConfig, lifetime: &RendererConfig) -> Result<Self, String> {
+        let (commands, queue) = channel();
+        let (failure_sender, failures) = channel();
+        let (started_sender, started) = channel();
+        let hook = hook.clone();
+        let hud = hud.clone();
+        // The hook's helper keeps the shared texture open, so the renderer lives as long as the thread in hook mode
+        let lifetime = Lifetime {
+            lazy: lifetime.lazy && !hook.enabled,
+            release_after: match lifetime.release_after_min {
+                0 => None,
+                _ if hook.enabled => None,
+                minutes => Some(Duration::from_secs(u64::from(minutes) * 60)),
+            },
+        };
+        // Window handles can be used from any thread, but aren't Send
+        let window = hwnd as usize;
+        let thread = thread::Builder::new()
+            .name(THREAD_NAME.to_string())
+            .spawn(move || {
+                let hwnd = window as HWND;
+                let renderer = if lifetime.lazy {
+                    None
+                } else {
+                    match create(hwnd, &hook) {
+                        Ok(renderer) => Some(renderer),
+                        Err(e) => {
+                            let _ = started_sender.send(Err(e));
+                            return;
+                        }
+                    }
+                };
+                let hud = Hud::new(&hud);
+                let _ = started_sender.send(Ok(()));
+                run(renderer, hud, hwnd, &hook, &lifetime, queue, failure_sender);
+            })
+            .map_err(|e| format!("Failed to start the render thread: {:?}", e))?;
+
+        match started.recv() {
+            Ok(Ok(())) => Ok(Self { commands, failures, thread: Some(thread) }),
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                Err(e)
+            }
+            Err(_) => Err("The render thread ended while creating the renderer".to_string()),
+        }
+    }
+
+    /// Queues a frame of the overlay. When frames are queued faster than they are drawn, only the newest is.
+    pub fn draw(&self, overlay_content: &OverlayContent, cursor: FrameCursor) {
+        let _ = self.commands.send(Command::Frame(Box::new(overlay_content.clone()), cursor));
+    }
+
+    /// Lets the HUD follow its hotkey and refresh; called every pass of the main loop while the HUD is shown, and
+    /// once more after it is hidden.
+    pub fn update_hud(&self) {
+        let _ = self.commands.send(Command::UpdateHud);
+    }
+
+    /// Applies reloaded HUD settings.
+    pub fn configure_hud(&self, config: &HudConfig) {
+        let _ = self.commands.send(Command::ConfigureHud(config.clone()));
+    }
+
+    /// Releases the renderer's GPU resources while the overlay is paused.
+    pub fn pause(&self) {
+        let _ = self.commands.send(Command::Pause);
+    }
+
+    /// Ends the pause, creating the renderer again; a failure comes back through `failure`.
+    pub fn resume(&self) {
+        let _ = self.commands.send(Command::Resume);
+    }
+
+    /// The error rendering stopped on, once recovering from it failed and the thread has cleaned up.
+    pub fn failure(&self) -> Option<RenderFailure> {
+        self.failures.try_recv().ok()
+    }
+
+    /// Cleans up the renderer and ends the thread.
+    pub fn stop(mut self) {
+        let _ = self.commands.send(Command::Stop);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Handles commands until told to stop or a frame fails past recovering. The renderer is None while paused, until
+/// it is first needed if created lazily, and after it was released for sitting unused.
+fn run(
+    mut renderer: Option<Renderer>,
+    mut hud: Hud,
+    hwnd: HWND,
+    hook: &HookConfig,
+    lifetime: &Lifetime,
+    queue: Receiver<Command>,
+    failures: Sender<RenderFailure>,
+) {
+    let mut failed_frames = 0;
+    let mut paused = false;
+    let mut used_at = Instant::now(); // Last frame drawn or HUD update while it was shown
+    loop {
+        // A renderer that may be released is waited on with a timeout, since nothing else comes while it sits unused
+        let release_in = lifetime
+            .release_after
+            .filter(|_| renderer.is_some())
+            .map(|after| after.saturating_sub(used_at.elapsed()));
+        let first = match release_in {
+            Some(timeout) => match queue.recv_timeout(timeout) {
+                Ok(command) => command,
+                Err(RecvTimeoutError::Timeout) => {
+                    release(&mut renderer, &mut hud, "Renderer released after sitting unused");
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            },
+            None => match queue.recv() {
+                Ok(command) => command,
+                Err(_) => break,
+            },
+        };
+        let commands: Vec<Command> = std::iter::once(first).chain(queue.try_iter()).collect();
+        // Frames already replaced by a newer snapshot aren't worth drawing
+        let newest_frame = commands.iter().rposition(|command| matches!(command, Command::Frame(..)));
+        for (index, command) in commands.into_iter().enumerate() {
+            match command {
+                Command::Frame(..) if Some(index) != newest_frame => {}
+                Command::Frame(..) if paused => {}
+                Command::Frame(overlay_content, cursor) => {
+                    if let Err(e) = create_if_missing(&mut renderer, &mut hud, hwnd, hook) {
+                        let _ = failures.send(RenderFailure::Creating(e));
+                        wake::wake();
+                        return;
+                    }
+                    let renderer = match renderer.as_mut() {
+                        Some(renderer) => renderer,
+                        None => continue,
+                    };
+                    used_at = Instant::now();
+                    let frame_start = Instant::now();
+                    match renderer.render(&overlay_content, &cursor, hwnd) {
+                        Ok(true) => {
+                            status::record_frame(frame_start.elapsed());
+                            hud.count_frame();
+                            failed_frames = 0;
+                        }
+                        Ok(false) => status::record_skipped_frame(),
+                        Err(e) => {
+                            failed_frames += 1;
+                            diagnostics::record_event(format!("Frame failed ({} in a row): {}", failed_frames, e));
+                            if failed_frames > MAX_RECOVERIES {
+                                renderer.cleanup();
+                                let _ = failures.send(RenderFailure::Rendering(e));
+                                wake::wake();
+                                return;
+                            }
+                            if failed_frames == 1 {
+                                renderer.invalidate_swapchain();
+                            } else if let Err(restart_error) = restart(renderer, hwnd, hook) {
+                                let _ = failures.send(RenderFailure::Rendering(format!(
+                                    "{}; restarting the renderer failed: {}",
+                                    e, restart_error
+                                )));
+                                wake::wake();
+                                return;
+                            } else {
+                                hud.renderer_replaced();
+                                toast::push("Rendering restarted after an error");
+                            }
+                        }
+                    }
+                }
+                Command::UpdateHud => {
+                    if hud::is_shown() && !paused {
+                        if let Err(e) = create_if_missing(&mut renderer, &mut hud, hwnd, hook) {
+                            let _ = failures.send(RenderFailure::Creating(e));
+                            wake::wake();
+                            return;
+                        }
+                        used_at = Instant::now();
+                    }
+                    if let Some(renderer) = renderer.as_mut() {
+                        hud.update(renderer);
+                    }
+                }
+                Command::ConfigureHud(config) => hud.configure(&config),
+                Command::Pause => {
+                    paused = true;
+                    release(&mut renderer, &mut hud, "Renderer released for the pause");
+                }
+                Command::Resume => {
+                    paused = false;
+                    if !lifetime.lazy {
+                        if let Err(e) = create_if_missing(&mut renderer, &mut hud, hwnd, hook) {
+                            let _ = failures.send(RenderFailure::Creating(e));
+                            wake::wake();
+                            return;
+                        }
+                    }
+                    used_at = Instant::now();
+                }
+                Command::Stop => {
+                    if let Some(renderer) = renderer.as_mut() {
+                        renderer.cleanup();
+                    }
+                    return;
+                }
+            }
+        }
+        pump_messages();
+    }
+    // The message thread went away without stopping the thread
+    if let Some(renderer) = renderer.as_mut() {
+        renderer.cleanup();
+    }
+}
+
+/// Creates the renderer and reports its GPU.
+fn create(hwnd: HWND, hook: &HookConfig) -> Result<Renderer, String> {
+    let renderer = Renderer::new(hwnd, hook)?;
+    status::set_gpu_name(renderer.gpu_name());
+    Ok(renderer)
+}
+
+/// Creates the renderer if there is none, the first time it is needed or after it was released.
+fn create_if_missing(renderer: &mut Option<Renderer>, hud: &mut Hud, hwnd: HWND, hook: &HookConfig) -> Result<(), String> {
+    if renderer.is_none() {
+        *renderer = Some(create(hwnd, hook).map_err(|e| format!("Creating the renderer failed: {}", e))?);
+        hud.renderer_replaced();
+        diagnostics::record_event("Renderer created");
+    }
+    Ok(())
+}
+
+/// Takes the HUD off the renderer and cleans it up, releasing the GPU until it is created again.
+fn release(renderer: &mut Option<Renderer>, hud: &mut Hud, event: &str) {
+    if let Some(mut released) = renderer.take() {
+        hud.detach(&mut released);
+        released.cleanup();
+        diagnostics::record_event(event);
+    }
+}
+
+/// Replaces a renderer whose frames keep failing with a new one. The old one is cleaned up either way.
+fn restart(renderer: &mut Renderer, hwnd: HWND, hook: &HookConfig) -> Result<(), String> {
+    renderer.cleanup();
+    thread::sleep(RESTART_DELAY);
+    *renderer = create(hwnd, hook)?;
+    diagnostics::record_event("Renderer restarted");
+    Ok(())
+}
+
+/// Handles messages for the HUD's window, which this thread created.
+fn pump_messages() {
+    let mut msg: MSG = unsafe { zeroed() };
+    unsafe {
+        while PeekMessageW(&mut msg, null_mut(), 0, 0, PM_REMOVE) != 0 {
+            TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+}
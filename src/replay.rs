@@ -0,0 +1,218 @@
+// Records overlay sessions to a file and replays them on screen, for tutorials and reproducing selection bugs
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::sync::Mutex;
+use std::time::Instant;
+use crate::diagnostics;
+use crate::menu::MenuItem;
+use crate::overlay::OverlayContent;
+use crate::toast;
+
+/// Something that happened while recording, in the order it happened.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SessionEvent {
+    /// The overlay opened with these item labels.
+    Open { items: Vec<String> },
+    /// Cursor position relative to the overlay window, in pixels.
+    Cursor { x: i32, y: i32 },
+    /// The selected segment changed.
+    Select { segment: Option<i32> },
+    /// The overlay closed; `label` is the item whose action ran, `edge` the label of the snap zone it closed on.
+    Close {
+        segment: Option<i32>,
+        label: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        edge: Option<String>,
+    },
+}
+
+/// One line of a recording file.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    ms: u64,
+    #[serde(flatten)]
+    event: SessionEvent,
+}
+
+struct Recorder {
+    file: BufWriter<File>,
+    start: Instant,
+    last_cursor: Option<(i32, i32)>,
+}
+
+struct Replay {
+    entries: VecDeque<Entry>,
+    start: Instant,
+    /// The configured items, put back when the replay ends.
+    saved_items: Option<Vec<MenuItem>>,
+    cursor: Option<(i32, i32)>,
+    mismatches: usize,
+}
+
+static RECORDER: Mutex<Option<Recorder>> = Mutex::new(None);
+static REPLAY: Mutex<Option<Replay>> = Mutex::new(None);
+
+/// Starts writing events to `path`, replacing an earlier recording there.
+pub fn start_recording(path: &str) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("Failed to create {}: {:?}", path, e))?;
+    *RECORDER.lock().unwrap() = Some(Recorder {
+        file: BufWriter::new(file),
+        start: Instant::now(),
+        last_cursor: None,
+    });
+    diagnostics::record_event(format!("Recording session to {}", path));
+    Ok(())
+}
+
+/// Stops recording. Returns false if nothing was being recorded.
+pub fn stop_recording() -> bool {
+    match RECORDER.lock().unwrap().take() {
+        Some(mut recorder) => {
+            let _ = recorder.file.flush();
+            diagnostics::record_event("Session recording stopped");
+            true
+        }
+        None => false,
+    }
+}
+
+/// Appends an event to the recording, if one is running. Replayed events aren't recorded again.
+pub fn record(event: SessionEvent) {
+    if is_replaying() {
+        return;
+    }
+    let mut recorder = RECORDER.lock().unwrap();
+    let recorder = match recorder.as_mut() {
+        Some(recorder) => recorder,
+        None => return,
+    };
+    if let SessionEvent::Cursor { x, y } = event {
+        // Only movement is interesting
+        if recorder.last_cursor == Some((x, y)) {
+            return;
+        }
+        recorder.last_cursor = Some((x, y));
+    }
+
+    let entry = Entry {
+        ms: recorder.start.elapsed().as_millis() as u64,
+        event,
+    };
+    if let Ok(line) = serde_json::to_string(&entry) {
+        let _ = writeln!(recorder.file, "{}", line);
+    }
+}
+
+/// Loads a recording to play back from the next `advance`.
+pub fn start_replay(path: &str) -> Result<(), String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {:?}", path, e))?;
+    let entries = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|e| format!("{} line {}: {}", path, index + 1, e))
+        })
+        .collect::<Result<VecDeque<Entry>, String>>()?;
+
+    *REPLAY.lock().unwrap() = Some(Replay {
+        entries,
+        start: Instant::now(),
+        saved_items: None,
+        cursor: None,
+        mismatches: 0,
+    });
+    toast::push(format!("Replaying {}", path));
+    Ok(())
+}
+
+pub fn is_replaying() -> bool {
+    REPLAY.lock().unwrap().is_some()
+}
+
+/// The replayed cursor position relative to the window, replacing the real cursor while a replay runs.
+pub fn cursor() -> Option<(i32, i32)> {
+    REPLAY.lock().unwrap().as_ref().and_then(|replay| replay.cursor)
+}
+
+/// Applies the replay's events that are due. Replayed items have no actions, so nothing really runs.
+/// Selections that differ from the recording are logged, which is how selection bugs show up.
+pub fn advance(overlay_content: &mut OverlayContent) {
+    let mut guard = REPLAY.lock().unwrap();
+    let replay = match guard.as_mut() {
+        Some(replay) => replay,
+        None => return,
+    };
+
+    // Finish one loop after the last event, so the main loop has handled the close with the replayed items
+    if replay.entries.is_empty() {
+        if overlay_content.visible {
+            // A recording stopped while the menu was open ends with it closed
+            overlay_content.visible = false;
+            return;
+        }
+        if let Some(items) = replay.saved_items.take() {
+            overlay_content.menu_items = items;
+            overlay_content.refresh_items();
+        }
+        toast::push(match replay.mismatches {
+            0 => "Replay finished".to_string(),
+            count => format!("Replay finished, {} selection(s) differed from the recording", count),
+        });
+        *guard = None;
+        return;
+    }
+
+    let elapsed = replay.start.elapsed().as_millis() as u64;
+    while replay.entries.front().map_or(false, |entry| entry.ms <= elapsed) {
+        let entry = replay.entries.pop_front().unwrap();
+        match entry.event {
+            SessionEvent::Open { items } => {
+                if replay.saved_items.is_none() {
+                    replay.saved_items = Some(std::mem::take(&mut overlay_content.menu_items));
+                }
+                overlay_content.menu_items = items.into_iter().map(replayed_item).collect();
+                overlay_content.visible = true;
+            }
+            SessionEvent::Cursor { x, y } => replay.cursor = Some((x, y)),
+            SessionEvent::Select { .. } => {}
+            SessionEvent::Close { segment, edge, .. } => {
+                let replayed_edge = overlay_content.selected_edge.map(|edge| edge.label().to_string());
+                if overlay_content.selected_segment != segment || replayed_edge != edge {
+                    replay.mismatches += 1;
+                    diagnostics::record_event(format!(
+                        "Replay at {} ms: recorded selection {:?} {:?}, replayed {:?} {:?}",
+                        entry.ms, segment, edge, overlay_content.selected_segment, replayed_edge
+                    ));
+                }
+                // The edge's action isn't run; the main loop drops the replayed edge, so it is only shown here
+                if let Some(edge) = edge {
+                    toast::push(format!("Replay: {}", edge));
+                }
+                overlay_content.visible = false;
+            }
+        }
+    }
+}
+
+fn replayed_item(label: String) -> MenuItem {
+    MenuItem {
+        label,
+        description: String::new(),
+        action: None,
+        enabled_if: None,
+        visible_if: None,
+        badge: None,
+        weight: None,
+        uses: None,
+        dial: None,
+        icon: None,
+        confirm: false,
+        toggle: None,
+        pulse_if: None,
+    }
+}
@@ -0,0 +1,291 @@
+// Concentric rings of segments that open in stages as the cursor moves outward
+
+use std::f32::consts::PI;
+use crate::animation::SegmentMotion;
+use crate::config::OrientationConfig;
+use crate::menu::MAX_SEGMENTS;
+use crate::overlay::OverlayContent;
+
+/// Most rings the shader can draw.
+pub const MAX_RINGS: usize = 4;
+
+/// Radii of the single ring, and of the innermost one when there are several, in NDC.
+pub const INNER_RADIUS: f32 = 0.08;
+pub const OUTER_RADIUS: f32 = 0.25;
+
+/// Window pixels per ring unit, the same both ways so rings stay round; the single ring's outer edge
+/// is 100 pixels from the center.
+pub const UNIT_PIXELS: f32 = 400.0;
+
+/// Angle left empty after each segment, in radians.
+pub const SEGMENT_GAP: f32 = 0.1;
+/// Narrowest slice a light weight gets, gap included, so something is still drawn after the gap.
+const MIN_SLICE: f32 = 3.0 * SEGMENT_GAP;
+
+/// One ring: segments `first..first + count` between two radii.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ring {
+    pub inner: f32,
+    pub outer: f32,
+    pub first: i32,
+    pub count: i32,
+    pub start: f32, // Screen angle where the first segment begins, in radians from +x towards +y (y down)
+    pub direction: f32, // 1.0 when the segments follow each other clockwise, -1.0 counter-clockwise
+    bounds: Vec<f32>, // Start of each segment's slice in segment order, from `start`, followed by 2π
+}
+
+impl Ring {
+    /// Start and end of a segment's slice including the gap after it, in segment order from `start`.
+    fn slice(&self, segment: i32) -> (f32, f32) {
+        let local = (segment - self.first) as usize;
+        (self.bounds[local], self.bounds[local + 1])
+    }
+
+    /// A screen angle measured from the start of the first segment in segment order, in 0..2π.
+    fn layout_angle(&self, angle: f32) -> f32 {
+        ((angle - self.start) * self.direction).rem_euclid(2.0 * PI)
+    }
+
+    /// The segment at `angle`, in radians from +x towards +y (y down).
+    pub fn segment_at(&self, angle: f32) -> i32 {
+        let layout = self.layout_angle(angle);
+        let local = self.bounds[1..].iter().position(|&end| layout < end).unwrap_or(self.count as usize - 1);
+        self.first + local as i32
+    }
+
+    /// Screen angle of the middle of the drawn part of a segment in this ring; `segment_gap` must match the shader's.
+    pub fn segment_middle(&self, segment: i32, segment_gap: f32) -> f32 {
+        let (start, end) = self.slice(segment);
+        let layout = start + (end - start - segment_gap) / 2.0;
+        self.start + layout * self.direction
+    }
+
+    /// Angle and distance from the center of the centroid of the drawn part of a segment in this ring. A segment
+    /// filling most of the ring would have its centroid in the dead zone, so it is kept on the ring.
+    pub fn segment_centroid(&self, segment: i32, segment_gap: f32) -> (f32, f32) {
+        let (start, end) = self.slice(segment);
+        let half_sweep = ((end - start - segment_gap) / 2.0).max(f32::EPSILON);
+        let (inner, outer) = (self.inner, self.outer);
+        let distance = 2.0 / 3.0 * (outer.powi(3) - inner.powi(3)) / (outer.powi(2) - inner.powi(2)) * half_sweep.sin()
+            / half_sweep;
+        (self.segment_middle(segment, segment_gap), distance.clamp(inner, outer))
+    }
+
+    /// Whether `angle` is within `margin` radians of a segment's slice, measured around the circle.
+    pub fn spans(&self, segment: i32, angle: f32, margin: f32) -> bool {
+        let (start, end) = self.slice(segment);
+        let middle = (start + end) / 2.0;
+        let offset = (self.layout_angle(angle) - middle + PI).rem_euclid(2.0 * PI) - PI;
+        offset.abs() <= (end - start) / 2.0 + margin
+    }
+
+    pub fn contains(&self, segment: i32) -> bool {
+        segment >= self.first && segment < self.first + self.count
+    }
+
+    /// Packed for the shader: x = inner radius, y = outer radius, z = first segment, w = segment count.
+    pub fn as_uniform(&self) -> [f32; 4] {
+        [self.inner, self.outer, self.first as f32, self.count as f32]
+    }
+
+    /// Orientation packed for the shader: x = start angle, y = direction.
+    pub fn angles_uniform(&self) -> [f32; 4] {
+        [self.start, self.direction, 0.0, 0.0]
+    }
+}
+
+/// The menu's geometry as revealed so far. The ring shader's uniforms, the hit test and everything placed on
+/// the segments all read it, so the ring is hit exactly where it is drawn.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MenuLayout {
+    pub rings: Vec<Ring>, // Revealed rings from the inside out; none hides the menu
+    pub inner_radius: f32, // Edge of the dead zone in the middle, in ring units
+    pub segment_gap: f32, // Angle left empty after each segment, in radians
+    pub motion: [SegmentMotion; MAX_SEGMENTS], // How the animation moves each segment from its place
+}
+
+impl MenuLayout {
+    /// The layout of the open menu's revealed rings; without rings while the menu is closed.
+    pub fn of(overlay_content: &OverlayContent) -> Self {
+        let rings = if overlay_content.visible { visible_rings(overlay_content) } else { Vec::new() };
+        Self { rings, inner_radius: INNER_RADIUS, segment_gap: SEGMENT_GAP, motion: [SegmentMotion::REST; MAX_SEGMENTS] }
+    }
+
+    /// Outer edge of the outermost revealed ring, zero without rings.
+    pub fn radius(&self) -> f32 {
+        self.rings.last().map_or(0.0, |ring| ring.outer)
+    }
+
+    /// The ring under a cursor `distance` from the center, see `ring_at`.
+    pub fn ring_at(&self, distance: f32) -> Option<&Ring> {
+        ring_at(&self.rings, distance)
+    }
+
+    /// The revealed ring holding a segment.
+    pub fn ring_of(&self, segment: i32) -> Option<&Ring> {
+        self.rings.iter().find(|ring| ring.contains(segment))
+    }
+
+    /// Screen angle of the middle of the drawn part of a segment, None while its ring is hidden.
+    pub fn segment_middle(&self, segment: i32) -> Option<f32> {
+        self.ring_of(segment).map(|ring| ring.segment_middle(segment, self.segment_gap))
+    }
+
+    /// Angle and distance of a segment's centroid, see `Ring::segment_centroid`; None while its ring is hidden.
+    pub fn segment_centroid(&self, segment: i32) -> Option<(f32, f32)> {
+        self.ring_of(segment).map(|ring| ring.segment_centroid(segment, self.segment_gap))
+    }
+
+    /// How far from the center a point `distance` out along a segment's middle is drawn, with the segment scaled
+    /// about its middle and slid outward as the shader moves it. None while its ring is hidden, and while the segment
+    /// is under half its size popping in, so what sits on it doesn't show before the segment does.
+    pub fn moved_distance(&self, segment: i32, distance: f32) -> Option<f32> {
+        let ring = self.ring_of(segment)?;
+        let motion = self.motion.get(segment as usize).copied().unwrap_or(SegmentMotion::REST);
+        if motion.scale < 0.5 {
+            return None;
+        }
+        let middle = (ring.inner + ring.outer) / 2.0;
+        Some(middle + (distance - middle) * motion.scale + motion.slide)
+    }
+}
+
+/// Every ring of the menu from the inside out, whether revealed yet or not.
+/// Without staged rings all segments share the one ring; without segments there is none.
+pub fn all_rings(overlay_content: &OverlayContent) -> Vec<Ring> {
+    let segments = overlay_content.segment_count();
+    if segments == 0 {
+        return Vec::new();
+    }
+    let weights = overlay_content.segment_weights();
+    let orientation = &overlay_content.orientation;
+    let config = match &overlay_content.rings {
+        Some(config) => config,
+        None => {
+            return vec![oriented(INNER_RADIUS, OUTER_RADIUS, 0, &weights, orientation)];
+        }
+    };
+
+    let mut rings = Vec::new();
+    let mut first = 0;
+    let mut inner = INNER_RADIUS;
+    while first < segments {
+        // Items past the configured sizes, or past what the shader can draw, share the outermost ring
+        let count = match config.sizes.get(rings.len()) {
+            Some(&size) if rings.len() + 1 < MAX_RINGS => (size as i32).clamp(1, segments - first),
+            _ => segments - first,
+        };
+        let ring_weights = &weights[first as usize..(first + count) as usize];
+        rings.push(oriented(inner, inner + config.width, first, ring_weights, orientation));
+        first += count;
+        inner += config.width;
+    }
+    rings
+}
+
+/// A ring whose segments share the circle by weight, turned and ordered as configured. Rotation 0 starts
+/// the first segment at 3 o'clock, as the menu always did; with `center_first` the rotation places the
+/// middle of the first segment instead.
+fn oriented(inner: f32, outer: f32, first: i32, weights: &[f32], orientation: &OrientationConfig) -> Ring {
+    let mut bounds = Vec::with_capacity(weights.len() + 1);
+    let mut angle = 0.0;
+    for slice in slices(weights) {
+        bounds.push(angle);
+        angle += slice;
+    }
+    bounds.push(2.0 * PI);
+
+    let direction = if orientation.clockwise { 1.0 } else { -1.0 };
+    let mut start = orientation.rotation_degrees.to_radians();
+    if orientation.center_first {
+        let drawn = bounds[1] - SEGMENT_GAP;
+        start -= direction * drawn / 2.0;
+    }
+    Ring { inner, outer, first, count: weights.len() as i32, start, direction, bounds }
+}
+
+/// Splits the circle by weight, giving each segment at least `MIN_SLICE` and the others the rest by
+/// weight. Segments that fall short are fixed at the minimum until none do.
+fn slices(weights: &[f32]) -> Vec<f32> {
+    let minimum = MIN_SLICE.min(2.0 * PI / weights.len().max(1) as f32);
+    let mut fixed = vec![false; weights.len()];
+    loop {
+        let left = 2.0 * PI - minimum * fixed.iter().filter(|&&fixed| fixed).count() as f32;
+        let total: f32 = weights.iter().zip(&fixed).filter(|(_, &fixed)| !fixed).map(|(weight, _)| weight).sum();
+        let slices: Vec<f32> =
+            weights.iter().zip(&fixed).map(|(weight, &fixed)| if fixed { minimum } else { left * weight / total }).collect();
+        let mut changed = false;
+        for (slice, fixed) in slices.iter().zip(fixed.iter_mut()) {
+            if !*fixed && *slice < minimum {
+                *fixed = true;
+                changed = true;
+            }
+        }
+        if !changed {
+            return slices;
+        }
+    }
+}
+
+/// Each segment's slice for the shader, x = start and y = end in segment order from its ring's start.
+pub fn segment_angles(rings: &[Ring]) -> [[f32; 4]; MAX_SEGMENTS] {
+    let mut angles = [[0.0; 4]; MAX_SEGMENTS];
+    for ring in rings {
+        for segment in ring.first..(ring.first + ring.count).min(MAX_SEGMENTS as i32) {
+            let (start, end) = ring.slice(segment);
+            angles[segment as usize] = [start, end, 0.0, 0.0];
+        }
+    }
+    angles
+}
+
+/// How far the menu reaches from its center with every ring revealed, edge zones included, in ring units.
+pub fn menu_extent(overlay_content: &OverlayContent) -> f32 {
+    let rings = all_rings(overlay_content).last().map_or(OUTER_RADIUS, |ring| ring.outer);
+    let edges = overlay_content.edges.as_ref().map_or(0.0, |edges| edges.distance);
+    rings.max(edges)
+}
+
+/// The rings revealed so far while the menu is open.
+pub fn visible_rings(overlay_content: &OverlayContent) -> Vec<Ring> {
+    let mut rings = all_rings(overlay_content);
+    rings.truncate(overlay_content.ring_stage + 1);
+    rings
+}
+
+/// Reveals further rings once the cursor is `distance` from the center past their breakpoints.
+/// Revealed rings stay until the menu closes, so moving back inward doesn't hide them again.
+pub fn update_stage(overlay_content: &mut OverlayContent, distance: f32) {
+    let breakpoints = match &overlay_content.rings {
+        Some(config) => config.breakpoints.clone(),
+        None => return,
+    };
+    let rings = all_rings(overlay_content);
+    while overlay_content.ring_stage + 1 < rings.len() {
+        let next = overlay_content.ring_stage + 1;
+        // By default a ring opens as the cursor crosses the outer edge of the one inside it
+        let breakpoint = breakpoints.get(next - 1).copied().unwrap_or(rings[next].inner);
+        if distance < breakpoint {
+            break;
+        }
+        overlay_content.ring_stage = next;
+    }
+}
+
+/// Moves a smoothed angle from `previous` towards `angle` the short way around, by the part of the gap an
+/// exponential filter with time constant `smoothing` closes in `elapsed`. Both are in radians; so is the result,
+/// in 0..2π.
+pub fn smooth_angle(previous: f32, angle: f32, elapsed: f32, smoothing: f32) -> f32 {
+    let weight = 1.0 - (-elapsed / smoothing.max(f32::EPSILON)).exp();
+    let difference = (angle - previous + PI).rem_euclid(2.0 * PI) - PI;
+    (previous + difference * weight).rem_euclid(2.0 * PI)
+}
+
+/// The ring under a cursor `distance` from the center. Past the outermost ring its segments still count,
+/// like the single ring always did; inside the innermost nothing is selected.
+pub fn ring_at(rings: &[Ring], distance: f32) -> Option<&Ring> {
+    if rings.first().map_or(true, |ring| distance < ring.inner) {
+        return None;
+    }
+    rings.iter().find(|ring| distance < ring.outer).or_else(|| rings.last())
+}
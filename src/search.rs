@@ -0,0 +1,224 @@
+// Search mode: typing a letter while the menu is open turns the center into a search field, and the items of
+// every profile that best match the query take the ring's place as it is typed
+
+use winapi::shared::minwindef::{LPARAM, LRESULT, WPARAM};
+use winapi::shared::windef::HHOOK__;
+use winapi::um::libloaderapi::GetModuleHandleW;
+use winapi::um::winuser::*;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use std::sync::Mutex;
+use crate::config::{Config, SearchConfig};
+use crate::diagnostics;
+use crate::menu::MenuItem;
+use crate::overlay::OverlayContent;
+use crate::text::{TextBatch, TEXT_BACKGROUND};
+
+const QUERY_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const HINT_COLOR: [f32; 4] = [0.9, 0.45, 0.45, 1.0];
+const PADDING: f32 = 4.0;
+
+/// A query being typed and the menu it replaced, put back when the query is cleared or the menu closes.
+#[derive(Clone)]
+pub struct SearchState {
+    pub query: String,
+    items_before: Vec<MenuItem>,
+}
+
+/// A key the hook took for the search field.
+enum Key {
+    Char(char),
+    Backspace,
+    Enter,
+}
+
+struct Search {
+    config: SearchConfig,
+    candidates: Vec<MenuItem>, // Items of the top-level menu and every profile, in config order
+    typed: Vec<Key>, // Taken by the hook, not yet applied
+}
+
+static SEARCH: Mutex<Search> = Mutex::new(Search {
+    config: SearchConfig { enabled: false, results: 0 },
+    candidates: Vec::new(),
+    typed: Vec::new(),
+});
+static HOOK: AtomicPtr<HHOOK__> = AtomicPtr::new(null_mut());
+/// Whether a query is being typed; until then only letters are taken, so number keys and Enter keep working.
+static TYPING: AtomicBool = AtomicBool::new(false);
+
+/// Takes the items to search from a newly loaded config.
+pub fn configure(config: &Config) {
+    let mut search = SEARCH.lock().unwrap();
+    search.config = config.search.clone();
+    search.candidates = config
+        .items
+        .iter()
+        .chain(config.profiles.iter().flat_map(|profile| profile.items.iter()))
+        .filter(|item| item.action.is_some() || item.dial.is_some())
+        .cloned()
+        .collect();
+}
+
+pub fn is_enabled() -> bool {
+    SEARCH.lock().unwrap().config.enabled
+}
+
+/// Starts or stops taking keys from other apps, while the menu is open. Must be called from the thread running
+/// the message loop, which the hook needs to receive events.
+pub fn capture(enabled: bool) {
+    SEARCH.lock().unwrap().typed.clear();
+    let hook = HOOK.load(Ordering::SeqCst);
+    if enabled && hook.is_null() {
+        let hook = unsafe { SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_proc), GetModuleHandleW(null_mut()), 0) };
+        if hook.is_null() {
+            diagnostics::report_error("Failed to install the keyboard hook; typing won't search");
+        }
+        HOOK.store(hook, Ordering::SeqCst);
+    } else if !enabled && !hook.is_null() {
+        unsafe { UnhookWindowsHookEx(hook) };
+        HOOK.store(null_mut(), Ordering::SeqCst);
+    }
+}
+
+/// Takes typing keys pressed without Ctrl, Alt or Win, so they edit the query instead of reaching the focused app.
+unsafe extern "system" fn keyboard_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION && (wparam as u32 == WM_KEYDOWN) {
+        let info = &*(lparam as *const KBDLLHOOKSTRUCT);
+        let modified = [VK_CONTROL, VK_MENU, VK_LWIN, VK_RWIN].iter().any(|&key| GetAsyncKeyState(key) < 0);
+        let typing = TYPING.load(Ordering::SeqCst);
+        let key = match info.vkCode as i32 {
+            _ if modified => None,
+            // Letter and digit virtual-key codes match their ASCII values
+            code @ 0x41..=0x5A => Some(Key::Char((code as u8 as char).to_ascii_lowercase())),
+            code @ 0x30..=0x39 if typing => Some(Key::Char(code as u8 as char)),
+            VK_SPACE if typing => Some(Key::Char(' ')),
+            VK_BACK if typing => Some(Key::Backspace),
+            VK_RETURN if typing => Some(Key::Enter),
+            _ => None,
+        };
+        if let Some(key) = key {
+            // Set right away, so a digit typed before the main loop catches up goes to the query too
+            TYPING.store(true, Ordering::SeqCst);
+            SEARCH.lock().unwrap().typed.push(key);
+            return 1;
+        }
+    }
+    CallNextHookEx(null_mut(), code, wparam, lparam)
+}
+
+/// Applies the keys typed since the last call: the ring shows the best matches for the query, Enter runs the
+/// selected match or the best one, and clearing the query brings the menu back.
+pub fn update(overlay_content: &mut OverlayContent) {
+    let typed = std::mem::take(&mut SEARCH.lock().unwrap().typed);
+    if typed.is_empty() || !overlay_content.visible {
+        return;
+    }
+
+    let mut query = overlay_content.search.as_ref().map_or_else(String::new, |search| search.query.clone());
+    for key in typed {
+        match key {
+            Key::Char(c) => query.push(c),
+            Key::Backspace => {
+                query.pop();
+            }
+            Key::Enter => {
+                if overlay_content.selected_segment.is_none() && !overlay_content.items.is_empty() {
+                    overlay_content.selected_segment = Some(0);
+                }
+                overlay_content.visible = false;
+                return;
+            }
+        }
+    }
+
+    if query.trim().is_empty() {
+        end(overlay_content);
+        return;
+    }
+    let items_before = match overlay_content.search.take() {
+        Some(search) => search.items_before,
+        None => std::mem::take(&mut overlay_content.menu_items),
+    };
+    overlay_content.menu_items = matches(&query);
+    overlay_content.search = Some(SearchState { query, items_before });
+    overlay_content.selected_segment = None;
+    overlay_content.dial = None;
+    overlay_content.ring_stage = 0;
+    overlay_content.reset_dwell();
+    overlay_content.refresh_items();
+}
+
+/// Leaves search mode, putting back the menu the matches replaced. Called when the menu closes.
+pub fn end(overlay_content: &mut OverlayContent) {
+    TYPING.store(false, Ordering::SeqCst);
+    if let Some(search) = overlay_content.search.take() {
+        overlay_content.menu_items = search.items_before;
+        overlay_content.refresh_items();
+    }
+}
+
+/// Leaves search mode without putting anything back, for a reloaded config whose menu replaces it.
+pub fn discard(overlay_content: &mut OverlayContent) {
+    TYPING.store(false, Ordering::SeqCst);
+    overlay_content.search = None;
+}
+
+/// The visible items that best match the query, best first, at most `results` of them.
+fn matches(query: &str) -> Vec<MenuItem> {
+    let search = SEARCH.lock().unwrap();
+    let mut scored: Vec<(i32, usize, &MenuItem)> = search
+        .candidates
+        .iter()
+        .filter(|item| item.is_visible())
+        .filter_map(|item| {
+            let label = item.display_label();
+            fuzzy_score(query, &label).map(|score| (score, label.chars().count(), item))
+        })
+        .collect();
+    // Stable, so equal matches keep their config order; shorter labels are closer matches
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    scored.into_iter().take(search.config.results).map(|(_, _, item)| item.clone()).collect()
+}
+
+/// How well `text` matches `query` as a subsequence, ignoring case and the query's spaces; None when some
+/// of its letters are missing. Runs of letters and letters starting a word count more, so "vu" ranks
+/// "Volume up" above "Previous".
+fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut next = 0; // Where the search for the next letter starts
+    let mut previous: Option<usize> = None;
+    for wanted in query.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase) {
+        let found = (next..text.len()).find(|&index| text[index] == wanted)?;
+        score += 1;
+        if previous.map_or(false, |previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        score -= (found - next).min(3) as i32;
+        previous = Some(found);
+        next = found + 1;
+    }
+    Some(score)
+}
+
+/// Lays out the query in a field in the middle of the ring, which is centered in the window.
+pub fn layout_search(batch: &mut TextBatch, overlay_content: &OverlayContent) {
+    let search = match &overlay_content.search {
+        Some(search) if overlay_content.dial.is_none() => search,
+        _ => return,
+    };
+    let field = format!("{}_", search.query);
+    let line_height = batch.metrics().cell_height;
+    let (width, height) = batch.size();
+    let box_width = batch.measure(&field).0 + PADDING * 2.0;
+    let top = height / 2.0 - line_height / 2.0 - PADDING;
+    batch.rect(width / 2.0 - box_width / 2.0, top, box_width, line_height + PADDING * 2.0, TEXT_BACKGROUND);
+    batch.text_centered(width / 2.0, top + PADDING, &field, QUERY_COLOR);
+    if overlay_content.items.is_empty() {
+        batch.text_centered(width / 2.0, top + line_height + PADDING * 3.0, "No matches", HINT_COLOR);
+    }
+}
@@ -0,0 +1,400 @@
+// Settings screen drawn by the overlay itself: the window stops being click-through and takes keys and clicks,
+// and the hotkeys, segment labels and display modes it edits are written back to the config file
+
+use toml_edit::{value, DocumentMut, Item};
+use winapi::um::winuser::{GetKeyState, VK_BACK, VK_CONTROL, VK_DOWN, VK_ESCAPE, VK_LEFT, VK_RETURN, VK_RIGHT, VK_SPACE, VK_TAB, VK_UP};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use crate::config::{config_path, load_or_default, Activation, Config};
+use crate::hotkey::Hotkey;
+use crate::mouse::MouseButton;
+use crate::text::{TextBatch, TEXT_BACKGROUND};
+use crate::toast;
+
+const PANEL_WIDTH: f32 = 480.0;
+const PADDING: f32 = 10.0;
+/// Width of the label column; values start after it.
+const LABEL_WIDTH: f32 = 170.0;
+
+const FOCUS_BACKGROUND: [f32; 4] = [0.2, 0.3, 0.45, 1.0];
+const BUTTON_BACKGROUND: [f32; 4] = [0.22, 0.22, 0.26, 1.0];
+const TITLE_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const LABEL_COLOR: [f32; 4] = [0.7, 0.7, 0.75, 1.0];
+const VALUE_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const HINT_COLOR: [f32; 4] = [0.55, 0.55, 0.6, 1.0];
+const ERROR_COLOR: [f32; 4] = [0.9, 0.45, 0.45, 1.0];
+
+/// Values of the display mode choices; "system" leaves the key out so the Windows setting applies.
+const MODE_CHOICES: &[&str] = &["system", "on", "off"];
+const ACTIVATION_CHOICES: &[&str] = &["hold", "toggle", "auto"];
+const SWITCH_CHOICES: &[&str] = &["off", "on"];
+
+/// Where a field's value lives in the config file.
+#[derive(Clone, Copy)]
+enum Target {
+    /// A string key in a table, such as `[hotkey] trigger`.
+    Text(&'static str, &'static str),
+    /// A key in a table that takes one of a few words.
+    Choice(&'static str, &'static str, &'static [&'static str]),
+    /// A display mode that follows Windows unless set to true or false.
+    Mode(&'static str, &'static str),
+    /// A key in a table that is true or false.
+    Switch(&'static str, &'static str),
+    /// The label of a top-level item, by its position in the file's `[[items]]`.
+    ItemLabel(usize),
+}
+
+struct Field {
+    name: String,
+    target: Target,
+    value: String,
+}
+
+impl Field {
+    fn choices(&self) -> Option<&'static [&'static str]> {
+        match self.target {
+            Target::Choice(_, _, choices) => Some(choices),
+            Target::Mode(_, _) => Some(MODE_CHOICES),
+            Target::Switch(_, _) => Some(SWITCH_CHOICES),
+            _ => None,
+        }
+    }
+}
+
+/// Something clickable on the screen, as laid out in the last frame.
+#[derive(Clone, Copy)]
+enum Hit {
+    Field(usize),
+    Save,
+    Close,
+}
+
+struct Editor {
+    document: DocumentMut,
+    fields: Vec<Field>,
+    focus: usize,
+    editing: Option<String>, // Text being typed into the focused field
+    changed: bool,
+    error: Option<String>, // Why the last save failed
+    hits: Vec<([f32; 4], Hit)>, // Window pixels as x, y, width, height
+}
+
+static EDITOR: Mutex<Option<Editor>> = Mutex::new(None);
+
+/// Opens the settings screen on the config file as it is now; called by the `settings` action from its worker
+/// thread, or when the middle of the open menu is clicked.
+pub fn open() {
+    let mut editor = EDITOR.lock().unwrap();
+    if editor.is_some() {
+        return;
+    }
+    let text = if Path::new(config_path()).exists() {
+        match fs::read_to_string(config_path()) {
+            Ok(text) => text,
+            Err(e) => return toast::push(format!("Failed to read {}: {:?}", config_path(), e)),
+        }
+    } else {
+        String::new()
+    };
+    let document = match text.parse::<DocumentMut>() {
+        Ok(document) => document,
+        Err(e) => return toast::push(format!("Settings unavailable, {} doesn't parse: {}", config_path(), e)),
+    };
+    let fields = fields(&document, &load_or_default(config_path()));
+    *editor = Some(Editor { document, fields, focus: 0, editing: None, changed: false, error: None, hits: Vec::new() });
+}
+
+pub fn is_open() -> bool {
+    EDITOR.lock().unwrap().is_some()
+}
+
+/// The fields shown, with the values the app runs with. Included files can set them too, so values come from
+/// the loaded config; the labels edited are only those of items in the file itself.
+fn fields(document: &DocumentMut, config: &Config) -> Vec<Field> {
+    let mode = |forced: Option<bool>| match forced {
+        None => "system",
+        Some(true) => "on",
+        Some(false) => "off",
+    };
+    let activation = match config.hotkey.activation {
+        Activation::Hold => "hold",
+        Activation::Toggle => "toggle",
+        Activation::Auto => "auto",
+    };
+    let mut fields = vec![
+        Field { name: "Trigger hotkey".into(), target: Target::Text("hotkey", "trigger"), value: config.hotkey.trigger.clone() },
+        Field { name: "Repeat hotkey".into(), target: Target::Text("hotkey", "repeat"), value: config.hotkey.repeat.clone() },
+        Field {
+            name: "Mouse button".into(),
+            target: Target::Text("hotkey", "mouse_button"),
+            value: config.hotkey.mouse_button.clone(),
+        },
+        Field {
+            name: "Activation".into(),
+            target: Target::Choice("hotkey", "activation", ACTIVATION_CHOICES),
+            value: activation.to_string(),
+        },
+        Field {
+            name: "High contrast".into(),
+            target: Target::Mode("accessibility", "high_contrast"),
+            value: mode(config.accessibility.high_contrast).to_string(),
+        },
+        Field {
+            name: "Reduced motion".into(),
+            target: Target::Mode("accessibility", "reduced_motion"),
+            value: mode(config.accessibility.reduced_motion).to_string(),
+        },
+        Field {
+            name: "Start with Windows".into(),
+            target: Target::Switch("startup", "with_windows"),
+            value: if config.startup.with_windows { "on" } else { "off" }.to_string(),
+        },
+    ];
+    let items = document.get("items").and_then(Item::as_array_of_tables);
+    for (index, item) in items.into_iter().flat_map(|items| items.iter()).enumerate() {
+        let label = item.get("label").and_then(Item::as_str).unwrap_or("").to_string();
+        fields.push(Field { name: format!("Segment {}", index + 1), target: Target::ItemLabel(index), value: label });
+    }
+    fields
+}
+
+/// Handles a key pressed while the settings window has focus.
+pub fn key_down(key: i32) {
+    let mut guard = EDITOR.lock().unwrap();
+    let editor = match guard.as_mut() {
+        Some(editor) => editor,
+        None => return,
+    };
+    let control = unsafe { GetKeyState(VK_CONTROL) } < 0;
+
+    if editor.editing.is_some() {
+        match key {
+            VK_RETURN => commit(editor),
+            VK_ESCAPE => editor.editing = None,
+            VK_BACK => {
+                if let Some(text) = editor.editing.as_mut() {
+                    text.pop();
+                }
+            }
+            _ => {}
+        }
+        return;
+    }
+    match key {
+        VK_ESCAPE => {
+            if editor.changed {
+                toast::push("Settings closed without saving");
+            }
+            *guard = None;
+        }
+        VK_UP => editor.focus = editor.focus.checked_sub(1).unwrap_or(editor.fields.len() - 1),
+        VK_DOWN | VK_TAB => editor.focus = (editor.focus + 1) % editor.fields.len(),
+        VK_LEFT => cycle(editor, -1),
+        VK_RIGHT => cycle(editor, 1),
+        VK_RETURN | VK_SPACE => activate(editor),
+        // Ctrl+S
+        0x53 if control => {
+            if save(editor) {
+                *guard = None;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Handles a typed character, which goes to the field being edited.
+pub fn char_typed(c: char) {
+    if let Some(text) = EDITOR.lock().unwrap().as_mut().and_then(|editor| editor.editing.as_mut()) {
+        if !c.is_control() {
+            text.push(c);
+        }
+    }
+}
+
+/// Handles a left click at window pixel coordinates.
+pub fn click(x: f32, y: f32) {
+    let mut guard = EDITOR.lock().unwrap();
+    let editor = match guard.as_mut() {
+        Some(editor) => editor,
+        None => return,
+    };
+    let hit = editor
+        .hits
+        .iter()
+        .find(|([left, top, width, height], _)| x >= *left && x < left + width && y >= *top && y < top + height)
+        .map(|(_, hit)| *hit);
+    match hit {
+        Some(Hit::Field(index)) => {
+            if editor.editing.is_some() {
+                commit(editor);
+            }
+            editor.focus = index;
+            activate(editor);
+        }
+        Some(Hit::Save) => {
+            if editor.editing.is_some() {
+                commit(editor);
+            }
+            if save(editor) {
+                *guard = None;
+            }
+        }
+        Some(Hit::Close) => *guard = None,
+        None => {}
+    }
+}
+
+/// Starts editing a text field, or steps a choice.
+fn activate(editor: &mut Editor) {
+    if editor.fields[editor.focus].choices().is_some() {
+        cycle(editor, 1);
+    } else {
+        editor.editing = Some(editor.fields[editor.focus].value.clone());
+    }
+}
+
+fn cycle(editor: &mut Editor, step: isize) {
+    let field = &mut editor.fields[editor.focus];
+    if let Some(choices) = field.choices() {
+        let current = choices.iter().position(|choice| *choice == field.value).unwrap_or(0) as isize;
+        let next = (current + step).rem_euclid(choices.len() as isize) as usize;
+        field.value = choices[next].to_string();
+        editor.changed = true;
+    }
+}
+
+fn commit(editor: &mut Editor) {
+    if let Some(text) = editor.editing.take() {
+        let field = &mut editor.fields[editor.focus];
+        let text = text.trim();
+        if field.value != text {
+            field.value = text.to_string();
+            editor.changed = true;
+        }
+    }
+}
+
+/// Checks the fields and writes them into the config file, keeping its comments and formatting; the config
+/// watcher then reloads it. Returns false, with the reason shown on the screen, if something is wrong.
+fn save(editor: &mut Editor) -> bool {
+    if let Err(e) = check(&editor.fields) {
+        editor.error = Some(e);
+        return false;
+    }
+    let mut document = editor.document.clone();
+    for field in &editor.fields {
+        match field.target {
+            Target::Text(table, key) | Target::Choice(table, key, _) => document[table][key] = value(field.value.as_str()),
+            Target::Mode(table, key) => match field.value.as_str() {
+                "on" => document[table][key] = value(true),
+                "off" => document[table][key] = value(false),
+                _ => {
+                    if let Some(table) = document.get_mut(table).and_then(Item::as_table_like_mut) {
+                        table.remove(key);
+                    }
+                }
+            },
+            Target::Switch(table, key) => document[table][key] = value(field.value == "on"),
+            Target::ItemLabel(index) => {
+                if let Some(item) = document["items"].as_array_of_tables_mut().and_then(|items| items.get_mut(index)) {
+                    item["label"] = value(field.value.as_str());
+                }
+            }
+        }
+    }
+
+    let text = document.to_string();
+    if let Err(e) = toml::from_str::<Config>(&text) {
+        editor.error = Some(format!("The edited config wouldn't load: {}", e));
+        return false;
+    }
+    if let Err(e) = fs::write(config_path(), text) {
+        editor.error = Some(format!("Failed to write {}: {:?}", config_path(), e));
+        return false;
+    }
+    toast::push("Settings saved");
+    true
+}
+
+/// The first field whose value wouldn't work.
+fn check(fields: &[Field]) -> Result<(), String> {
+    for field in fields {
+        match field.target {
+            Target::Text("hotkey", "trigger") => {
+                Hotkey::parse_sequence(&field.value).map_err(|e| format!("{}: {}", field.name, e))?;
+            }
+            Target::Text("hotkey", "repeat") if !field.value.is_empty() => {
+                Hotkey::parse(&field.value).map_err(|e| format!("{}: {}", field.name, e))?;
+            }
+            Target::Text("hotkey", "mouse_button") if !field.value.is_empty() => {
+                MouseButton::parse(&field.value).map_err(|e| format!("{}: {}", field.name, e))?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Lays out the settings screen in the middle of the window, remembering where its rows and buttons are for
+/// clicks.
+pub fn layout_settings(batch: &mut TextBatch) {
+    let mut guard = EDITOR.lock().unwrap();
+    let editor = match guard.as_mut() {
+        Some(editor) => editor,
+        None => return,
+    };
+
+    let line_height = batch.metrics().cell_height;
+    let hint = if editor.editing.is_some() {
+        "Type, then Enter to keep or Esc to undo"
+    } else {
+        "Up/Down to move, Enter to edit, Ctrl+S to save, Esc to close"
+    };
+    let error_lines = editor.error.as_ref().map_or_else(Vec::new, |error| batch.wrap(error, PANEL_WIDTH - PADDING * 2.0));
+    // Title, fields, buttons, errors and the hint
+    let rows = 1 + editor.fields.len() + 1 + error_lines.len() + 1;
+    let box_height = line_height * rows as f32 + PADDING * 3.0;
+
+    let (width, height) = batch.size();
+    let x = ((width - PANEL_WIDTH) / 2.0).max(0.0);
+    let mut y = ((height - box_height) / 2.0).max(0.0);
+    batch.rect(x, y, PANEL_WIDTH, box_height, TEXT_BACKGROUND);
+    y += PADDING;
+    batch.text(x + PADDING, y, "Settings", TITLE_COLOR);
+    y += line_height;
+
+    editor.hits.clear();
+    for (index, field) in editor.fields.iter().enumerate() {
+        let focused = index == editor.focus;
+        if focused {
+            batch.rect(x, y, PANEL_WIDTH, line_height, FOCUS_BACKGROUND);
+        }
+        batch.text(x + PADDING, y, &field.name, LABEL_COLOR);
+        let shown = match &editor.editing {
+            Some(text) if focused => format!("{}_", text),
+            _ if field.choices().is_some() => format!("< {} >", field.value),
+            _ if field.value.is_empty() => "(none)".to_string(),
+            _ => field.value.clone(),
+        };
+        batch.text(x + LABEL_WIDTH, y, &shown, VALUE_COLOR);
+        editor.hits.push(([x, y, PANEL_WIDTH, line_height], Hit::Field(index)));
+        y += line_height;
+    }
+
+    y += PADDING;
+    let mut button_x = x + PADDING;
+    for (label, hit) in [("Save", Hit::Save), ("Close", Hit::Close)] {
+        let button_width = batch.measure(label).0 + PADDING * 2.0;
+        batch.rect(button_x, y, button_width, line_height, BUTTON_BACKGROUND);
+        batch.text(button_x + PADDING, y, label, TITLE_COLOR);
+        editor.hits.push(([button_x, y, button_width, line_height], hit));
+        button_x += button_width + PADDING;
+    }
+    y += line_height;
+    for line in &error_lines {
+        batch.text(x + PADDING, y, line, ERROR_COLOR);
+        y += line_height;
+    }
+    batch.text(x + PADDING, y, hint, HINT_COLOR);
+}
@@ -0,0 +1,384 @@
+// Exports each rendered frame as named shared GPU memory, for a present hook injected into an exclusive
+// fullscreen game to composite. The hook itself is a separate helper, not part of this crate. The texture is exported
+// as a handle Direct3D opens, and a timeline semaphore exported as a D3D12 fence counts the frames copied into it.
+
+use ash::{vk, Device, Instance};
+use winapi::um::handleapi::CloseHandle;
+use winapi::um::winnt::{GENERIC_ALL, HANDLE};
+use std::ffi::CStr;
+use std::mem::transmute;
+use std::ptr::null_mut;
+use crate::render::find_memory_type;
+use crate::window::to_wstring;
+
+/// Read and write access for the other process, DXGI_SHARED_RESOURCE_READ | DXGI_SHARED_RESOURCE_WRITE.
+const SHARED_ACCESS: u32 = 0x8000_0000 | 0x1;
+
+/// Handle types Direct3D can open the texture from, most preferred first: NT handles the helper opens by name with
+/// ID3D11Device1::OpenSharedResourceByName or ID3D12Device::OpenSharedHandleByName, then a global D3D11 handle it is
+/// given as a number.
+const HANDLE_TYPES: [vk::ExternalMemoryHandleTypeFlags; 3] = [
+    vk::ExternalMemoryHandleTypeFlags::D3D11_TEXTURE,
+    vk::ExternalMemoryHandleTypeFlags::D3D12_RESOURCE,
+    vk::ExternalMemoryHandleTypeFlags::D3D11_TEXTURE_KMT,
+];
+
+const USAGE: vk::ImageUsageFlags = vk::ImageUsageFlags::from_raw(
+    vk::ImageUsageFlags::TRANSFER_DST.as_raw() | vk::ImageUsageFlags::SAMPLED.as_raw(),
+);
+
+/// Device extensions the export needs on top of Vulkan 1.1: the memory, the fence and its timeline.
+pub fn extension_names() -> [&'static CStr; 3] {
+    [
+        vk::KhrExternalMemoryWin32Fn::name(),
+        vk::KhrExternalSemaphoreWin32Fn::name(),
+        vk::KhrTimelineSemaphoreFn::name(),
+    ]
+}
+
+/// Image the frame is copied into after it is drawn, backed by memory opened elsewhere, and the fence that tells
+/// the helper a frame is complete in it.
+pub struct SharedTexture {
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    handle: vk::HANDLE, // Keeps the shared memory alive while the helper opens it
+    handle_type: vk::ExternalMemoryHandleTypeFlags,
+    fence: vk::Semaphore, // Timeline semaphore signaled with each frame's number once it is in the texture
+    fence_handle: vk::HANDLE,
+    frames: u64, // Frames copied so far
+    extent: vk::Extent2D,
+    format: vk::Format,
+    name: String,
+}
+
+impl SharedTexture {
+    pub fn new(
+        instance: &Instance,
+        device: &Device,
+        physical_device: vk::PhysicalDevice,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        name: &str,
+    ) -> Result<Self, String> {
+        let handle_type = exportable_handle_type(instance, physical_device, format)
+            .ok_or("The GPU driver can't export textures Direct3D can open")?;
+        // KMT handles are global and have no name; the helper is given the handle's value instead
+        let named = handle_type != vk::ExternalMemoryHandleTypeFlags::D3D11_TEXTURE_KMT;
+
+        let mut external_info = vk::ExternalMemoryImageCreateInfo::builder().handle_types(handle_type);
+        let image_info = vk::ImageCreateInfo::builder()
+            .push_next(&mut external_info)
+            .image_type(vk::ImageType::TYPE_2D)
+            .format(format)
+            .extent(vk::Extent3D { width: extent.width, height: extent.height, depth: 1 })
+            .mip_levels(1)
+            .array_layers(1)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .usage(USAGE)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .initial_layout(vk::ImageLayout::UNDEFINED);
+        let image = unsafe {
+            device
+                .create_image(&image_info, None)
+                .map_err(|e| format!("Failed to create shared texture: {:?}", e))?
+        };
+
+        let requirements = unsafe { device.get_image_memory_requirements(image) };
+        let memory_properties = unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        let memory_type = find_memory_type(
+            requirements.memory_type_bits,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            memory_properties,
+        )?;
+
+        // The name lets the helper open the memory without the handle being duplicated into its process
+        let wide_name = to_wstring(name);
+        let mut export_info = vk::ExportMemoryAllocateInfo::builder().handle_types(handle_type);
+        let mut win32_info = vk::ExportMemoryWin32HandleInfoKHR::builder()
+            .dw_access(SHARED_ACCESS)
+            .name(wide_name.as_ptr());
+        let mut dedicated_info = vk::MemoryDedicatedAllocateInfo::builder().image(image);
+        let mut alloc_info = vk::MemoryAllocateInfo::builder()
+            .allocation_size(requirements.size)
+            .memory_type_index(memory_type)
+            .push_next(&mut export_info)
+            .push_next(&mut dedicated_info);
+        if named {
+            alloc_info = alloc_info.push_next(&mut win32_info);
+        }
+        let memory = unsafe {
+            device.allocate_memory(&alloc_info, None).map_err(|e| {
+                device.destroy_image(image, None);
+                format!("Failed to allocate shared texture memory: {:?}", e)
+            })?
+        };
+        let destroy = || unsafe {
+            device.destroy_image(image, None);
+            device.free_memory(memory, None);
+        };
+        if let Err(e) = unsafe { device.bind_image_memory(image, memory, 0) } {
+            destroy();
+            return Err(format!("Failed to bind shared texture memory: {:?}", e));
+        }
+
+        let external_memory = vk::KhrExternalMemoryWin32Fn::load(|name| unsafe {
+            transmute((instance.fp_v1_0().get_device_proc_addr)(device.handle(), name.as_ptr()))
+        });
+        let handle_info = vk::MemoryGetWin32HandleInfoKHR::builder().memory(memory).handle_type(handle_type);
+        let mut handle: vk::HANDLE = null_mut();
+        let result = unsafe { (external_memory.get_memory_win32_handle_khr)(device.handle(), &*handle_info, &mut handle) };
+        if result != vk::Result::SUCCESS {
+            destroy();
+            return Err(format!("Failed to export shared texture: {:?}", result));
+        }
+
+        let (fence, fence_handle) = match create_fence(instance, device, physical_device, &fence_name(name)) {
+            Ok(fence) => fence,
+            Err(e) => {
+                if named {
+                    unsafe { CloseHandle(handle as HANDLE) };
+                }
+                destroy();
+                return Err(e);
+            }
+        };
+
+        Ok(Self {
+            image,
+            memory,
+            handle,
+            handle_type,
+            fence,
+            fence_handle,
+            frames: 0,
+            extent,
+            format,
+            name: name.to_string(),
+        })
+    }
+
+    /// What the helper needs to open the texture and its fence, e.g. "RadialMenuOverlayFrame 520x520
+    /// B8G8R8A8_UNORM D3D11_TEXTURE, fence RadialMenuOverlayFrameFence". The fence reaching a frame's number means the
+    /// frame is complete in the texture; the helper waits for it, then copies the texture out before compositing, as
+    /// the next frame is copied in without waiting for the helper.
+    pub fn description(&self) -> String {
+        let texture = match self.handle_type {
+            vk::ExternalMemoryHandleTypeFlags::D3D11_TEXTURE_KMT => format!("handle {:#x}", self.handle as usize),
+            _ => self.name.clone(),
+        };
+        format!(
+            "{} {}x{} {:?} {:?}, fence {}",
+            texture,
+            self.extent.width,
+            self.extent.height,
+            self.format,
+            self.handle_type,
+            fence_name(&self.name)
+        )
+    }
+
+    /// The fence and the value to signal it with once the frame being submitted has been copied in.
+    pub fn next_frame(&mut self) -> (vk::Semaphore, u64) {
+        self.frames += 1;
+        (self.fence, self.frames)
+    }
+
+    /// Records copying a drawn swapchain image, left in PRESENT_SRC_KHR by the render pass, into the texture.
+    /// The texture ends up in GENERAL layout for the helper to read.
+    pub fn record_copy(&self, device: &Device, command_buffer: vk::CommandBuffer, source: vk::Image) {
+        let subresource_range = vk::ImageSubresourceRange::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .base_mip_level(0)
+            .level_count(1)
+            .base_array_layer(0)
+            .layer_count(1)
+            .build();
+        let barrier = |image, old_layout, new_layout, src_access_mask, dst_access_mask| {
+            vk::ImageMemoryBarrier::builder()
+                .old_layout(old_layout)
+                .new_layout(new_layout)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image)
+                .subresource_range(subresource_range)
+                .src_access_mask(src_access_mask)
+                .dst_access_mask(dst_access_mask)
+                .build()
+        };
+
+        // The previous frame's contents are replaced entirely, so they can be discarded
+        let before = [
+            barrier(
+                source,
+                vk::ImageLayout::PRESENT_SRC_KHR,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                vk::AccessFlags::TRANSFER_READ,
+            ),
+            barrier(
+                self.image,
+                vk::ImageLayout::UNDEFINED,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::AccessFlags::empty(),
+                vk::AccessFlags::TRANSFER_WRITE,
+            ),
+        ];
+        let after = [
+            barrier(
+                source,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                vk::ImageLayout::PRESENT_SRC_KHR,
+                vk::AccessFlags::TRANSFER_READ,
+                vk::AccessFlags::empty(),
+            ),
+            barrier(
+                self.image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::ImageLayout::GENERAL,
+                vk::AccessFlags::TRANSFER_WRITE,
+                vk::AccessFlags::MEMORY_READ,
+            ),
+        ];
+        let layers = vk::ImageSubresourceLayers {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level: 0,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        let region = vk::ImageCopy::builder()
+            .src_subresource(layers)
+            .dst_subresource(layers)
+            .extent(vk::Extent3D { width: self.extent.width, height: self.extent.height, depth: 1 })
+            .build();
+
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &before,
+            );
+            device.cmd_copy_image(
+                command_buffer,
+                source,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                self.image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[region],
+            );
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &after,
+            );
+        }
+    }
+
+    pub fn destroy(&self, device: &Device) {
+        unsafe {
+            // A KMT handle isn't a kernel object and is released with the memory
+            if self.handle_type != vk::ExternalMemoryHandleTypeFlags::D3D11_TEXTURE_KMT {
+                CloseHandle(self.handle as HANDLE);
+            }
+            CloseHandle(self.fence_handle as HANDLE);
+            device.destroy_semaphore(self.fence, None);
+            device.destroy_image(self.image, None);
+            device.free_memory(self.memory, None);
+        }
+    }
+}
+
+fn fence_name(name: &str) -> String {
+    format!("{}Fence", name)
+}
+
+/// The first handle type the texture can be exported as, see `HANDLE_TYPES`; None if Direct3D can open none of them.
+fn exportable_handle_type(
+    instance: &Instance,
+    physical_device: vk::PhysicalDevice,
+    format: vk::Format,
+) -> Option<vk::ExternalMemoryHandleTypeFlags> {
+    HANDLE_TYPES.into_iter().find(|&handle_type| {
+        let mut external_info = vk::PhysicalDeviceExternalImageFormatInfo::builder().handle_type(handle_type);
+        let format_info = vk::PhysicalDeviceImageFormatInfo2::builder()
+            .push_next(&mut external_info)
+            .format(format)
+            .ty(vk::ImageType::TYPE_2D)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .usage(USAGE);
+        let mut external_properties = vk::ExternalImageFormatProperties::default();
+        let supported = {
+            let mut properties = vk::ImageFormatProperties2::builder().push_next(&mut external_properties);
+            unsafe { instance.get_physical_device_image_format_properties2(physical_device, &format_info, &mut properties) }
+                .is_ok()
+        };
+        supported
+            && external_properties
+                .external_memory_properties
+                .external_memory_features
+                .contains(vk::ExternalMemoryFeatureFlags::EXPORTABLE)
+    })
+}
+
+/// Creates the timeline semaphore frames are counted with, exported under `name` as a D3D12 fence, which D3D11.4
+/// opens as well.
+fn create_fence(
+    instance: &Instance,
+    device: &Device,
+    physical_device: vk::PhysicalDevice,
+    name: &str,
+) -> Result<(vk::Semaphore, vk::HANDLE), String> {
+    let handle_type = vk::ExternalSemaphoreHandleTypeFlags::D3D12_FENCE;
+    let mut timeline_info = vk::SemaphoreTypeCreateInfo::builder()
+        .semaphore_type(vk::SemaphoreType::TIMELINE)
+        .initial_value(0);
+    let mut external_properties = vk::ExternalSemaphoreProperties::default();
+    let semaphore_info = vk::PhysicalDeviceExternalSemaphoreInfo::builder()
+        .push_next(&mut timeline_info)
+        .handle_type(handle_type);
+    unsafe {
+        instance.get_physical_device_external_semaphore_properties(physical_device, &semaphore_info, &mut external_properties)
+    };
+    if !external_properties.external_semaphore_features.contains(vk::ExternalSemaphoreFeatureFlags::EXPORTABLE) {
+        return Err("The GPU driver can't export fences Direct3D can open".to_string());
+    }
+
+    let wide_name = to_wstring(name);
+    let mut timeline_info = vk::SemaphoreTypeCreateInfo::builder()
+        .semaphore_type(vk::SemaphoreType::TIMELINE)
+        .initial_value(0);
+    let mut export_info = vk::ExportSemaphoreCreateInfo::builder().handle_types(handle_type);
+    let mut win32_info = vk::ExportSemaphoreWin32HandleInfoKHR::builder()
+        .dw_access(GENERIC_ALL)
+        .name(wide_name.as_ptr());
+    let create_info = vk::SemaphoreCreateInfo::builder()
+        .push_next(&mut timeline_info)
+        .push_next(&mut export_info)
+        .push_next(&mut win32_info);
+    let fence = unsafe {
+        device
+            .create_semaphore(&create_info, None)
+            .map_err(|e| format!("Failed to create shared fence: {:?}", e))?
+    };
+
+    let external_semaphore = vk::KhrExternalSemaphoreWin32Fn::load(|name| unsafe {
+        transmute((instance.fp_v1_0().get_device_proc_addr)(device.handle(), name.as_ptr()))
+    });
+    let handle_info = vk::SemaphoreGetWin32HandleInfoKHR::builder().semaphore(fence).handle_type(handle_type);
+    let mut handle: vk::HANDLE = null_mut();
+    let result =
+        unsafe { (external_semaphore.get_semaphore_win32_handle_khr)(device.handle(), &*handle_info, &mut handle) };
+    if result != vk::Result::SUCCESS {
+        unsafe { device.destroy_semaphore(fence, None) };
+        return Err(format!("Failed to export shared fence: {:?}", result));
+    }
+    Ok((fence, handle))
+}
@@ -0,0 +1,59 @@
+// Orderly exit: closing the window, Ctrl+C in the console or the `quit` command ask the main loop to stop, which
+// then cleans up before the process ends
+
+use winapi::shared::minwindef::{BOOL, DWORD, FALSE, TRUE};
+use winapi::um::consoleapi::SetConsoleCtrlHandler;
+use winapi::um::wincon::{CTRL_BREAK_EVENT, CTRL_CLOSE_EVENT, CTRL_C_EVENT, CTRL_LOGOFF_EVENT, CTRL_SHUTDOWN_EVENT};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use crate::{diagnostics, wake};
+
+/// How long closing the console waits for the cleanup; Windows ends the process soon after anyway.
+const CLOSE_WAIT: Duration = Duration::from_secs(4);
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+static FINISHED: AtomicBool = AtomicBool::new(false);
+
+/// Asks the main loop to stop and clean up. Safe to call from any thread.
+pub fn request(reason: &str) {
+    if !REQUESTED.swap(true, Ordering::SeqCst) {
+        diagnostics::record_event(format!("Shutting down: {}", reason));
+        wake::wake();
+    }
+}
+
+pub fn is_requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Called once everything is cleaned up, letting a closing console end the process.
+pub fn finish() {
+    FINISHED.store(true, Ordering::SeqCst);
+}
+
+/// Turns Ctrl+C, Ctrl+Break and closing the console into a shutdown request instead of ending the process at once.
+pub fn handle_console_signals() {
+    if unsafe { SetConsoleCtrlHandler(Some(console_handler), TRUE) } == FALSE {
+        eprintln!("Failed to install the console control handler");
+    }
+}
+
+/// Runs on a thread of its own. For Ctrl+C the process carries on until the main loop stops; for a closing console,
+/// logoff or shutdown the process is ended when this returns, so it waits for the cleanup first.
+unsafe extern "system" fn console_handler(event: DWORD) -> BOOL {
+    match event {
+        CTRL_C_EVENT | CTRL_BREAK_EVENT => {
+            request("Ctrl+C");
+            TRUE
+        }
+        CTRL_CLOSE_EVENT | CTRL_LOGOFF_EVENT | CTRL_SHUTDOWN_EVENT => {
+            request("console closed");
+            let start = Instant::now();
+            while !FINISHED.load(Ordering::SeqCst) && start.elapsed() < CLOSE_WAIT {
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            TRUE
+        }
+        _ => FALSE,
+    }
+}
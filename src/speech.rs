@@ -0,0 +1,62 @@
+// Spoken announcements through SAPI, so the menu can be used without reading it: the hovered segment's label
+// is read out as the selection changes
+
+use winapi::ctypes::c_void;
+use winapi::shared::winerror::SUCCEEDED;
+use winapi::um::combaseapi::{CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_ALL};
+use winapi::um::objbase::COINIT_APARTMENTTHREADED;
+use winapi::um::sapi51::{ISpVoice, SpVoice, SPF_ASYNC, SPF_IS_NOT_XML, SPF_PURGEBEFORESPEAK};
+use winapi::{Class, Interface};
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Mutex;
+use std::thread;
+use crate::diagnostics;
+use crate::window::to_wstring;
+
+/// Feeds the speech thread, started with the first announcement.
+static SPEAKER: Mutex<Option<Sender<String>>> = Mutex::new(None);
+/// Set once the voice failed to start; announcements stay off until the next start, so the error shows once.
+static FAILED: AtomicBool = AtomicBool::new(false);
+
+/// Reads `text` out, cutting off whatever is still being said so fast hovering doesn't queue up.
+pub fn announce(text: &str) {
+    if FAILED.load(Ordering::SeqCst) {
+        return;
+    }
+    let mut speaker = SPEAKER.lock().unwrap();
+    let sender = speaker.get_or_insert_with(|| {
+        let (sender, receiver) = channel::<String>();
+        // The voice lives on its own COM thread; speaking asynchronously returns at once, but creating it doesn't
+        thread::spawn(move || unsafe {
+            let initialized = SUCCEEDED(CoInitializeEx(null_mut(), COINIT_APARTMENTTHREADED));
+            let mut voice: *mut ISpVoice = null_mut();
+            let created = SUCCEEDED(CoCreateInstance(
+                &SpVoice::uuidof(),
+                null_mut(),
+                CLSCTX_ALL,
+                &ISpVoice::uuidof(),
+                &mut voice as *mut _ as *mut *mut c_void,
+            ));
+            if created {
+                for text in receiver {
+                    let text_w = to_wstring(&text);
+                    (*voice).Speak(text_w.as_ptr(), SPF_ASYNC | SPF_PURGEBEFORESPEAK | SPF_IS_NOT_XML, null_mut());
+                }
+                (*voice).Release();
+            } else {
+                FAILED.store(true, Ordering::SeqCst);
+                diagnostics::report_error("Failed to start the speech voice; announcements are off");
+            }
+            if initialized {
+                CoUninitialize();
+            }
+        });
+        sender
+    });
+    if sender.send(text.to_string()).is_err() {
+        // The voice failed to start
+        *speaker = None;
+    }
+}
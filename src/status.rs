@@ -0,0 +1,119 @@
+// Runtime state reported by the `status` IPC command
+
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[derive(Serialize, Clone)]
+pub struct FrameStats {
+    pub frames: u64,
+    pub last_frame_ms: f32,
+    pub average_frame_ms: f32,
+    /// Frames dropped because the GPU or compositor stalled.
+    pub skipped_frames: u64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Status {
+    pub version: &'static str,
+    pub active_profile: String,
+    /// Whether the overlay is paused, with its hotkeys, hooks and GPU resources released.
+    pub paused: bool,
+    pub hotkeys: Vec<String>,
+    pub gpu: Option<String>,
+    /// Name, size and format of the texture shared with a present hook, None unless hook mode is on.
+    pub shared_texture: Option<String>,
+    pub last_error: Option<String>,
+    /// Problems found the last time the config file was loaded; empty when it is healthy.
+    pub config_health: Vec<String>,
+    pub frame_stats: FrameStats,
+}
+
+impl Status {
+    const fn new() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            active_profile: String::new(),
+            paused: false,
+            hotkeys: Vec::new(),
+            gpu: None,
+            shared_texture: None,
+            last_error: None,
+            config_health: Vec::new(),
+            frame_stats: FrameStats {
+                frames: 0,
+                last_frame_ms: 0.0,
+                average_frame_ms: 0.0,
+                skipped_frames: 0,
+            },
+        }
+    }
+}
+
+static STATUS: Mutex<Status> = Mutex::new(Status::new());
+
+pub fn set_active_profile<S: Into<String>>(name: S) {
+    STATUS.lock().unwrap().active_profile = name.into();
+}
+
+pub fn set_paused(paused: bool) {
+    STATUS.lock().unwrap().paused = paused;
+}
+
+/// Records a successfully registered hotkey.
+pub fn add_hotkey<S: Into<String>>(hotkey: S) {
+    STATUS.lock().unwrap().hotkeys.push(hotkey.into());
+}
+
+/// Forgets registered hotkeys, e.g. before they are registered again after a config reload.
+pub fn clear_hotkeys() {
+    STATUS.lock().unwrap().hotkeys.clear();
+}
+
+pub fn set_gpu_name(name: String) {
+    STATUS.lock().unwrap().gpu = Some(name);
+}
+
+pub fn set_shared_texture(description: String) {
+    STATUS.lock().unwrap().shared_texture = Some(description);
+}
+
+/// Remembers the most recent error for remote debugging.
+pub fn record_error<S: Into<String>>(error: S) {
+    STATUS.lock().unwrap().last_error = Some(error.into());
+}
+
+/// Replaces the config health report after the config file was (re)loaded.
+pub fn set_config_health(issues: Vec<String>) {
+    STATUS.lock().unwrap().config_health = issues;
+}
+
+/// Adds a rendered frame to the running frame-time statistics.
+pub fn record_frame(frame_time: Duration) {
+    let mut status = STATUS.lock().unwrap();
+    let stats = &mut status.frame_stats;
+    let frame_ms = frame_time.as_secs_f32() * 1000.0;
+    stats.frames += 1;
+    stats.last_frame_ms = frame_ms;
+    stats.average_frame_ms += (frame_ms - stats.average_frame_ms) / stats.frames as f32;
+}
+
+pub fn record_skipped_frame() {
+    STATUS.lock().unwrap().frame_stats.skipped_frames += 1;
+}
+
+/// Returns a copy of the current status.
+pub fn snapshot() -> Status {
+    STATUS.lock().unwrap().clone()
+}
+
+/// Serializes the config health report as JSON.
+pub fn health_json() -> String {
+    let issues = STATUS.lock().unwrap().config_health.clone();
+    serde_json::json!({ "healthy": issues.is_empty(), "issues": issues }).to_string()
+}
+
+/// Serializes the current status as JSON.
+pub fn to_json() -> String {
+    serde_json::to_string_pretty(&snapshot()).unwrap_or_else(|e| format!("{{\"error\": \"{}\"}}", e))
+}
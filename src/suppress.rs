@@ -0,0 +1,55 @@
+// Keeps the overlay out of chosen apps: while one is in front the trigger is swallowed, or with `pass_through` the
+// hotkeys and hooks are released so the app gets the keys
+
+use std::sync::Mutex;
+use crate::config::SuppressConfig;
+use crate::{diagnostics, foreground};
+
+static CONFIG: Mutex<Option<SuppressConfig>> = Mutex::new(None);
+/// The app in front the overlay is kept out of, None while it may open.
+static ACTIVE: Mutex<Option<String>> = Mutex::new(None);
+
+pub fn configure(config: &SuppressConfig) {
+    *CONFIG.lock().unwrap() = Some(config.clone());
+}
+
+/// Checks the app now in front, by exe name, against the lists. Called when another app comes to the front.
+pub fn update(process: Option<&str>) {
+    let suppressed = CONFIG.lock().unwrap().as_ref().map_or(false, |config| applies(config, process));
+    let name = process.unwrap_or("an unknown app");
+    let mut active = ACTIVE.lock().unwrap();
+    match (active.as_deref(), suppressed) {
+        (None, true) => diagnostics::record_event(format!("Overlay suppressed while {} is in front", name)),
+        (Some(previous), false) => diagnostics::record_event(format!("Overlay no longer suppressed, {} left the front", previous)),
+        _ => {}
+    }
+    *active = if suppressed { Some(name.to_string()) } else { None };
+}
+
+fn applies(config: &SuppressConfig, process: Option<&str>) -> bool {
+    let listed = |list: &[String]| process.map_or(false, |process| list.iter().any(|name| name.eq_ignore_ascii_case(process)));
+    listed(&config.blocklist)
+        || (!config.allowlist.is_empty() && !listed(&config.allowlist))
+        || (config.fullscreen && foreground::is_fullscreen())
+}
+
+/// Whether the overlay is kept out of the app in front.
+pub fn is_active() -> bool {
+    ACTIVE.lock().unwrap().is_some()
+}
+
+/// Whether the overlay is kept from opening; logs the press it swallows.
+pub fn blocks_opening() -> bool {
+    match ACTIVE.lock().unwrap().as_deref() {
+        Some(name) => {
+            diagnostics::record_event(format!("Trigger ignored, the overlay is suppressed while {} is in front", name));
+            true
+        }
+        None => false,
+    }
+}
+
+/// Whether the hotkeys should be released for the app in front.
+pub fn releases_hotkeys() -> bool {
+    ACTIVE.lock().unwrap().is_some() && CONFIG.lock().unwrap().as_ref().map_or(false, |config| config.pass_through)
+}
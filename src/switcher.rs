@@ -0,0 +1,104 @@
+// Window switcher page: a segment for each open top-level window, in the order Alt+Tab lists them
+
+use winapi::ctypes::c_void;
+use winapi::shared::minwindef::{BOOL, DWORD, LPARAM, TRUE};
+use winapi::shared::windef::HWND;
+use winapi::shared::winerror::SUCCEEDED;
+use winapi::um::dwmapi::{DwmGetWindowAttribute, DWMWA_CLOAKED};
+use winapi::um::processthreadsapi::GetCurrentProcessId;
+use winapi::um::winuser::{
+    EnumWindows, GetWindow, GetWindowLongW, GetWindowTextW, GetWindowThreadProcessId, IsWindowVisible, GWL_EXSTYLE,
+    GW_OWNER, WS_EX_TOOLWINDOW,
+};
+use std::mem::size_of;
+use crate::action::Action;
+use crate::menu::{shorten, MenuItem, MAX_SEGMENTS};
+use crate::provider::{Menu, MenuContext, MenuProvider};
+use crate::template;
+
+/// Longest label before the title is cut short; the full title is in the tooltip.
+const MAX_LABEL_CHARS: usize = 20;
+
+pub struct WindowSwitcher;
+
+impl MenuProvider for WindowSwitcher {
+    fn name(&self) -> &'static str {
+        "open_windows"
+    }
+
+    fn build(&self, _context: &MenuContext) -> Menu {
+        let items = open_windows()
+            .into_iter()
+            .take(MAX_SEGMENTS)
+            .map(|(hwnd, title)| {
+                // Titles are shown as they are, not as placeholders
+                MenuItem {
+                    label: template::escape(&shorten(&title, MAX_LABEL_CHARS)),
+                    description: template::escape(&title),
+                    action: Some(Action::ActivateWindow { handle: hwnd as usize }),
+                    enabled_if: None,
+                    visible_if: None,
+                    badge: None,
+                    weight: None,
+                    uses: None,
+                    dial: None,
+                    icon: None,
+                    confirm: false,
+                    toggle: None,
+                    pulse_if: None,
+                }
+            })
+            .collect();
+        Menu { items }
+    }
+
+    fn empty_message(&self) -> &'static str {
+        "No open windows"
+    }
+}
+
+/// Top-level windows that would show in Alt+Tab, topmost first, with their titles.
+fn open_windows() -> Vec<(HWND, String)> {
+    let mut windows: Vec<(HWND, String)> = Vec::new();
+    unsafe {
+        EnumWindows(Some(collect_window), &mut windows as *mut Vec<(HWND, String)> as LPARAM);
+    }
+    windows
+}
+
+unsafe extern "system" fn collect_window(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let windows = &mut *(lparam as *mut Vec<(HWND, String)>);
+    if !is_switchable(hwnd) {
+        return TRUE;
+    }
+    let mut buffer = [0u16; 256];
+    let length = GetWindowTextW(hwnd, buffer.as_mut_ptr(), buffer.len() as i32);
+    if length > 0 {
+        windows.push((hwnd, String::from_utf16_lossy(&buffer[..length as usize])));
+    }
+    TRUE
+}
+
+/// Visible, unowned windows that aren't tool windows, cloaked (e.g. suspended store apps or on another virtual
+/// desktop) or the overlay's own.
+unsafe fn is_switchable(hwnd: HWND) -> bool {
+    if IsWindowVisible(hwnd) == 0 || !GetWindow(hwnd, GW_OWNER).is_null() {
+        return false;
+    }
+    if GetWindowLongW(hwnd, GWL_EXSTYLE) as DWORD & WS_EX_TOOLWINDOW != 0 {
+        return false;
+    }
+    let mut process_id = 0;
+    GetWindowThreadProcessId(hwnd, &mut process_id);
+    if process_id == GetCurrentProcessId() {
+        return false;
+    }
+    let mut cloaked: DWORD = 0;
+    let read = DwmGetWindowAttribute(
+        hwnd,
+        DWMWA_CLOAKED,
+        &mut cloaked as *mut DWORD as *mut c_void,
+        size_of::<DWORD>() as DWORD,
+    );
+    !(SUCCEEDED(read) && cloaked != 0)
+}
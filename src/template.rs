@@ -0,0 +1,113 @@
+// Placeholders in labels, descriptions, badges and typed snippets, e.g. "{time}" or "Volume: {volume}%"
+
+use winapi::shared::minwindef::{BOOL, LPARAM, TRUE};
+use winapi::shared::windef::HWND;
+use winapi::um::minwinbase::SYSTEMTIME;
+use winapi::um::sysinfoapi::GetLocalTime;
+use winapi::um::winuser::{EnumWindows, GetWindowTextW, GetWindowThreadProcessId, IsWindowVisible};
+use std::mem::zeroed;
+use crate::condition::process_ids;
+use crate::{mic, variables};
+
+/// Player whose window title is shown by "{now_playing}".
+const MEDIA_PLAYER: &str = "Spotify.exe";
+
+/// Fills in placeholders: the built-ins `time`, `date`, `user`, `now_playing` and `mic_muted`, then variables.
+/// `mic_muted` is "Muted" while the microphone is, otherwise nothing, so a badge of it only shows then.
+pub fn expand(template: &str) -> String {
+    expand_with(template, |name| match name {
+        "time" => Some(local_time_string(false)),
+        "date" => Some(local_time_string(true)),
+        "user" => std::env::var("USERNAME").ok(),
+        "now_playing" => Some(now_playing().unwrap_or_default()),
+        "mic_muted" => Some(if mic::is_muted() { "Muted" } else { "" }.to_string()),
+        _ => variables::get(name),
+    })
+}
+
+/// Doubles the braces in `text`, so it is shown as it is rather than read as placeholders.
+pub fn escape(text: &str) -> String {
+    text.replace('{', "{{").replace('}', "}}")
+}
+
+/// Replaces "{name}" with `lookup(name)`; unknown names expand to nothing.
+/// "{{" and "}}" produce literal braces.
+pub fn expand_with<F: Fn(&str) -> Option<String>>(template: &str, lookup: F) -> String {
+    if !template.contains(['{', '}']) {
+        return template.to_string();
+    }
+
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        result.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            result.push_str(&tail[..1]);
+            rest = &tail[2..];
+        } else if let (true, Some(end)) = (tail.starts_with('{'), tail.find('}')) {
+            if let Some(value) = lookup(tail[1..end].trim()) {
+                result.push_str(&value);
+            }
+            rest = &tail[end + 1..];
+        } else {
+            // Unbalanced brace, kept as written
+            result.push_str(&tail[..1]);
+            rest = &tail[1..];
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// "HH:MM", or "YYYY-MM-DD" when `date` is set.
+fn local_time_string(date: bool) -> String {
+    let mut time: SYSTEMTIME = unsafe { zeroed() };
+    unsafe {
+        GetLocalTime(&mut time);
+    }
+    if date {
+        format!("{:04}-{:02}-{:02}", time.wYear, time.wMonth, time.wDay)
+    } else {
+        format!("{:02}:{:02}", time.wHour, time.wMinute)
+    }
+}
+
+struct PlayerSearch {
+    process_ids: Vec<u32>,
+    title: Option<String>,
+}
+
+/// "Artist - Title" from the media player's window, None when it isn't playing.
+fn now_playing() -> Option<String> {
+    let mut search = PlayerSearch {
+        process_ids: process_ids(MEDIA_PLAYER),
+        title: None,
+    };
+    if search.process_ids.is_empty() {
+        return None;
+    }
+    unsafe {
+        EnumWindows(Some(find_player_window), &mut search as *mut PlayerSearch as LPARAM);
+    }
+    search.title
+}
+
+unsafe extern "system" fn find_player_window(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let search = &mut *(lparam as *mut PlayerSearch);
+    let mut process_id = 0;
+    GetWindowThreadProcessId(hwnd, &mut process_id);
+    if IsWindowVisible(hwnd) == 0 || !search.process_ids.contains(&process_id) {
+        return TRUE;
+    }
+
+    let mut buffer = [0u16; 256];
+    let length = GetWindowTextW(hwnd, buffer.as_mut_ptr(), buffer.len() as i32);
+    let title = String::from_utf16_lossy(&buffer[..length.max(0) as usize]);
+    // While paused the title is just the player's name
+    if title.contains(" - ") {
+        search.title = Some(title);
+        return 0;
+    }
+    TRUE
+}
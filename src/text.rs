@@ -0,0 +1,681 @@
+// Draws text and flat rectangles on top of the ring using a GDI-rasterized glyph atlas
+
+use ash::{vk, Device, Instance};
+use winapi::um::wingdi::*;
+use winapi::shared::windef::HGDIOBJ;
+use std::mem::{size_of, zeroed};
+use std::os::raw::c_void;
+use std::ptr::null_mut;
+use crate::memory::{self, Allocation, Resource};
+use crate::render::{create_buffer, create_quad_pipeline, VertexRing};
+use crate::window::to_wstring;
+
+/// First and last printable ASCII characters stored in the atlas; others are drawn as '?'.
+const FIRST_CHAR: u32 = 32;
+const LAST_CHAR: u32 = 126;
+/// Atlas cell that is fully covered, used to draw solid rectangles.
+const SOLID_CELL: u32 = 127;
+const ATLAS_COLUMNS: u32 = 16;
+const ATLAS_ROWS: u32 = 6;
+const FONT_HEIGHT: i32 = 16;
+const FONT_FACE: &str = "Consolas";
+
+/// Number of quads the vertex buffers can hold. Unused quads are zeroed so they rasterize nothing,
+/// which lets the command buffers be recorded once with a fixed draw count.
+pub const MAX_TEXT_QUADS: usize = 1024;
+const VERTICES_PER_QUAD: usize = 6;
+
+/// Background of the boxes text is drawn on: panels, the HUD, the search field and the picker's label.
+pub const TEXT_BACKGROUND: [f32; 4] = [0.1, 0.1, 0.12, 1.0];
+
+/// Vertex consumed by shaders/text.vert.glsl.
+#[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct TextVertex {
+    position: [f32; 2], // NDC, Y pointing down
+    uv: [f32; 2],
+    color: [f32; 4],
+}
+
+/// Whether `ch` has a glyph in the atlas. Only printable ASCII does; emoji, accented letters and
+/// everything else outside it are drawn as '?'.
+pub fn is_drawable(ch: char) -> bool {
+    (FIRST_CHAR..=LAST_CHAR).contains(&(ch as u32))
+}
+
+/// Size of the glyph cells and of the atlas texture, in pixels.
+#[derive(Clone, Copy)]
+pub struct GlyphMetrics {
+    pub cell_width: f32,
+    pub cell_height: f32,
+    atlas_width: f32,
+    atlas_height: f32,
+}
+
+/// Single-channel coverage bitmap holding one monospaced cell per character.
+struct GlyphAtlas {
+    pixels: Vec<u8>,
+    width: u32,
+    height: u32,
+    cell_width: u32,
+    cell_height: u32,
+}
+
+/// Collects the quads for one frame, with positions given in window pixels.
+pub struct TextBatch {
+    vertices: Vec<TextVertex>,
+    metrics: GlyphMetrics,
+    width: f32,
+    height: f32,
+}
+
+impl TextBatch {
+    pub fn new(metrics: GlyphMetrics, extent: vk::Extent2D) -> Self {
+        Self {
+            vertices: Vec::new(),
+            metrics,
+            width: extent.width as f32,
+            height: extent.height as f32,
+        }
+    }
+
+    pub fn metrics(&self) -> GlyphMetrics {
+        self.metrics
+    }
+
+    /// Size of the target surface in pixels.
+    pub fn size(&self) -> (f32, f32) {
+        (self.width, self.height)
+    }
+
+    /// Size of `text` in pixels when drawn on a single line.
+    pub fn measure(&self, text: &str) -> (f32, f32) {
+        (text.chars().count() as f32 * self.metrics.cell_width, self.metrics.cell_height)
+    }
+
+    /// Splits `text` into lines no wider than `max_width`, breaking between words.
+    pub fn wrap(&self, text: &str, max_width: f32) -> Vec<String> {
+        let max_chars = ((max_width / self.metrics.cell_width) as usize).max(1);
+        let mut lines = Vec::new();
+        let mut line = String::new();
+
+        for word in text.split_whitespace() {
+            let needed = if line.is_empty() { word.chars().count() } else { line.chars().count() + 1 + word.chars().count() };
+            if needed > max_chars && !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        if !line.is_empty() {
+            lines.push(line);
+        }
+
+        lines
+    }
+
+    /// Draws a solid rectangle.
+    pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: [f32; 4]) {
+        let (u0, v0, u1, v1) = self.cell_uv(SOLID_CELL);
+        // Sample the middle of the solid cell so filtering never reaches its neighbours
+        let (du, dv) = ((u1 - u0) * 0.25, (v1 - v0) * 0.25);
+        self.quad(x, y, x + width, y + height, [u0 + du, v0 + dv, u1 - du, v1 - dv], color);
+    }
+
+    /// Draws `text` with its top-left corner at (x, y).
+    pub fn text(&mut self, x: f32, y: f32, text: &str, color: [f32; 4]) {
+        let mut pen_x = x;
+        for ch in text.chars() {
+            let code = if is_drawable(ch) { ch as u32 } else { '?' as u32 };
+            if code != FIRST_CHAR {
+                let (u0, v0, u1, v1) = self.cell_uv(code);
+                self.quad(
+                    pen_x,
+                    y,
+                    pen_x + self.metrics.cell_width,
+                    y + self.metrics.cell_height,
+                    [u0, v0, u1, v1],
+                    color,
+                );
+            }
+            pen_x += self.metrics.cell_width;
+        }
+    }
+
+    /// Draws `text` centered horizontally on `center_x`.
+    pub fn text_centered(&mut self, center_x: f32, y: f32, text: &str, color: [f32; 4]) {
+        let (width, _) = self.measure(text);
+        self.text(center_x - width / 2.0, y, text, color);
+    }
+
+    fn cell_uv(&self, code: u32) -> (f32, f32, f32, f32) {
+        let (x, y) = cell_origin(code, self.metrics.cell_width as u32, self.metrics.cell_height as u32);
+        let u0 = x as f32 / self.metrics.atlas_width;
+        let v0 = y as f32 / self.metrics.atlas_height;
+        let u1 = u0 + self.metrics.cell_width / self.metrics.atlas_width;
+        let v1 = v0 + self.metrics.cell_height / self.metrics.atlas_height;
+        (u0, v0, u1, v1)
+    }
+
+    fn quad(&mut self, x0: f32, y0: f32, x1: f32, y1: f32, uv: [f32; 4], color: [f32; 4]) {
+        if self.vertices.len() >= MAX_TEXT_QUADS * VERTICES_PER_QUAD {
+            return;
+        }
+
+        // Convert window pixels to NDC
+        let to_ndc = |x: f32, y: f32| [x / self.width * 2.0 - 1.0, y / self.height * 2.0 - 1.0];
+        let top_left = TextVertex { position: to_ndc(x0, y0), uv: [uv[0], uv[1]], color };
+        let top_right = TextVertex { position: to_ndc(x1, y0), uv: [uv[2], uv[1]], color };
+        let bottom_left = TextVertex { position: to_ndc(x0, y1), uv: [uv[0], uv[3]], color };
+        let bottom_right = TextVertex { position: to_ndc(x1, y1), uv: [uv[2], uv[3]], color };
+
+        self.vertices.extend_from_slice(&[
+            top_left,
+            top_right,
+            bottom_left,
+            bottom_left,
+            top_right,
+            bottom_right,
+        ]);
+    }
+}
+
+/// Vulkan objects for the text pipeline. Drawn in the same render pass, after the ring.
+pub struct TextRenderer {
+    metrics: GlyphMetrics,
+    atlas_image: vk::Image,
+    atlas_memory: Allocation,
+    atlas_view: vk::ImageView,
+    sampler: vk::Sampler,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set: vk::DescriptorSet,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    vertices: VertexRing<TextVertex>,
+}
+
+impl TextRenderer {
+    /// Rasterizes the glyph atlas, uploads it and creates the text pipeline and per-image vertex buffers.
+    pub fn new(
+        instance: &Instance,
+        device: &Device,
+        physical_device: vk::PhysicalDevice,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+        render_pass: vk::RenderPass,
+        extent: vk::Extent2D,
+        image_count: usize,
+    ) -> Result<Self, String> {
+        let atlas = rasterize_atlas()?;
+        let metrics = GlyphMetrics {
+            cell_width: atlas.cell_width as f32,
+            cell_height: atlas.cell_height as f32,
+            atlas_width: atlas.width as f32,
+            atlas_height: atlas.height as f32,
+        };
+
+        let (atlas_image, atlas_memory) =
+            upload_atlas(instance, device, physical_device, command_pool, queue, &atlas)?;
+        let atlas_view = create_atlas_view(device, atlas_image)?;
+        let sampler = create_sampler(device)?;
+
+        let descriptor_set_layout = create_descriptor_set_layout(device)?;
+        let (descriptor_pool, descriptor_set) =
+            create_descriptor_set(device, descriptor_set_layout, atlas_view, sampler)?;
+        // Textured quads for text and rectangles. Vertex input: position, uv, color
+        let (pipeline_layout, pipeline) = create_quad_pipeline(
+            device,
+            render_pass,
+            extent,
+            "text",
+            &[vk::Format::R32G32_SFLOAT, vk::Format::R32G32_SFLOAT, vk::Format::R32G32B32A32_SFLOAT],
+            descriptor_set_layout,
+            &[],
+        )?;
+        let capacity = MAX_TEXT_QUADS * VERTICES_PER_QUAD;
+        let vertices = VertexRing::new(instance, device, physical_device, capacity, image_count, "text")?;
+
+        Ok(Self {
+            metrics,
+            atlas_image,
+            atlas_memory,
+            atlas_view,
+            sampler,
+            descriptor_set_layout,
+            descriptor_pool,
+            descriptor_set,
+            pipeline_layout,
+            pipeline,
+            vertices,
+        })
+    }
+
+    pub fn metrics(&self) -> GlyphMetrics {
+        self.metrics
+    }
+
+    /// Copies a frame's quads into the vertex buffer of the given swapchain image.
+    pub fn upload_batch(&mut self, device: &Device, image_index: usize, batch: &TextBatch) -> Result<(), String> {
+        self.vertices.upload(device, image_index, &batch.vertices)
+    }
+
+    /// Records the text draw into a command buffer inside an active render pass.
+    pub fn record(&self, device: &Device, command_buffer: vk::CommandBuffer, image_index: usize) {
+        unsafe {
+            device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.pipeline);
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline_layout,
+                0,
+                &[self.descriptor_set],
+                &[],
+            );
+        }
+        self.vertices.record_draw(device, command_buffer, image_index);
+    }
+
+    /// Destroys the text resources. The device must be idle.
+    pub fn destroy(&self, device: &Device) {
+        self.vertices.destroy(device);
+        unsafe {
+            device.destroy_pipeline(self.pipeline, None);
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+            device.destroy_descriptor_pool(self.descriptor_pool, None);
+            device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+            device.destroy_sampler(self.sampler, None);
+            device.destroy_image_view(self.atlas_view, None);
+            device.destroy_image(self.atlas_image, None);
+        }
+        memory::free(device, self.atlas_memory);
+    }
+}
+
+/// Top-left pixel of a character's cell in the atlas.
+fn cell_origin(code: u32, cell_width: u32, cell_height: u32) -> (u32, u32) {
+    let index = code - FIRST_CHAR;
+    ((index % ATLAS_COLUMNS) * cell_width, (index / ATLAS_COLUMNS) * cell_height)
+}
+
+/// Renders the printable ASCII range into a coverage bitmap with GDI.
+fn rasterize_atlas() -> Result<GlyphAtlas, String> {
+    unsafe {
+        let dc = CreateCompatibleDC(null_mut());
+        if dc.is_null() {
+            return Err("Failed to create glyph atlas DC".to_string());
+        }
+
+        let face = to_wstring(FONT_FACE);
+        let font = CreateFontW(
+            -FONT_HEIGHT,
+            0,
+            0,
+            0,
+            FW_NORMAL,
+            0,
+            0,
+            0,
+            DEFAULT_CHARSET,
+            OUT_DEFAULT_PRECIS,
+            CLIP_DEFAULT_PRECIS,
+            ANTIALIASED_QUALITY,
+            FIXED_PITCH | FF_MODERN,
+            face.as_ptr(),
+        );
+        let old_font = SelectObject(dc, font as HGDIOBJ);
+
+        let mut text_metrics: TEXTMETRICW = zeroed();
+        GetTextMetricsW(dc, &mut text_metrics);
+        let cell_width = text_metrics.tmAveCharWidth.max(1) as u32;
+        let cell_height = text_metrics.tmHeight.max(1) as u32;
+        let width = cell_width * ATLAS_COLUMNS;
+        let height = cell_height * ATLAS_ROWS;
+
+        let mut bitmap_info: BITMAPINFO = zeroed();
+        bitmap_info.bmiHeader.biSize = size_of::<BITMAPINFOHEADER>() as u32;
+        bitmap_info.bmiHeader.biWidth = width as i32;
+        bitmap_info.bmiHeader.biHeight = -(height as i32); // Top-down rows
+        bitmap_info.bmiHeader.biPlanes = 1;
+        bitmap_info.bmiHeader.biBitCount = 32;
+        bitmap_info.bmiHeader.biCompression = BI_RGB;
+
+        let mut bits: *mut c_void = null_mut();
+        let bitmap = CreateDIBSection(dc, &bitmap_info, DIB_RGB_COLORS, &mut bits, null_mut(), 0);
+        if bitmap.is_null() || bits.is_null() {
+            SelectObject(dc, old_font);
+            DeleteObject(font as HGDIOBJ);
+            DeleteDC(dc);
+            return Err("Failed to create glyph atlas bitmap".to_string());
+        }
+        let old_bitmap = SelectObject(dc, bitmap as HGDIOBJ);
+
+        // White on black, so any color channel holds the coverage
+        SetTextColor(dc, RGB(255, 255, 255));
+        SetBkColor(dc, RGB(0, 0, 0));
+        SetBkMode(dc, OPAQUE as i32);
+        for code in FIRST_CHAR..=LAST_CHAR {
+            let (x, y) = cell_origin(code, cell_width, cell_height);
+            let glyph = [code as u16];
+            TextOutW(dc, x as i32, y as i32, glyph.as_ptr(), 1);
+        }
+        GdiFlush();
+
+        let bgra = std::slice::from_raw_parts(bits as *const u8, (width * height * 4) as usize);
+        let mut pixels: Vec<u8> = bgra.chunks_exact(4).map(|pixel| pixel[2]).collect();
+
+        let (solid_x, solid_y) = cell_origin(SOLID_CELL, cell_width, cell_height);
+        for row in solid_y..solid_y + cell_height {
+            for column in solid_x..solid_x + cell_width {
+                pixels[(row * width + column) as usize] = 255;
+            }
+        }
+
+        SelectObject(dc, old_bitmap);
+        SelectObject(dc, old_font);
+        DeleteObject(bitmap as HGDIOBJ);
+        DeleteObject(font as HGDIOBJ);
+        DeleteDC(dc);
+
+        Ok(GlyphAtlas {
+            pixels,
+            width,
+            height,
+            cell_width,
+            cell_height,
+        })
+    }
+}
+
+/// Copies the atlas into a device-local image through a staging buffer.
+fn upload_atlas(
+    instance: &Instance,
+    device: &Device,
+    physical_device: vk::PhysicalDevice,
+    command_pool: vk::CommandPool,
+    queue: vk::Queue,
+    atlas: &GlyphAtlas,
+) -> Result<(vk::Image, Allocation), String> {
+    let size = atlas.pixels.len() as vk::DeviceSize;
+    let (staging_buffer, staging_memory) = create_buffer(
+        instance,
+        device,
+        physical_device,
+        size,
+        vk::BufferUsageFlags::TRANSFER_SRC,
+        vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+    )?;
+
+    let data_ptr =
+        memory::mapped(device, &staging_memory).map_err(|e| format!("Failed to map staging memory: {}", e))?;
+    unsafe {
+        data_ptr.copy_from_nonoverlapping(atlas.pixels.as_ptr(), atlas.pixels.len());
+    }
+
+    let image_info = vk::ImageCreateInfo::builder()
+        .image_type(vk::ImageType::TYPE_2D)
+        .format(vk::Format::R8_UNORM)
+        .extent(vk::Extent3D {
+            width: atlas.width,
+            height: atlas.height,
+            depth: 1,
+        })
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .tiling(vk::ImageTiling::OPTIMAL)
+        .usage(vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+        .initial_layout(vk::ImageLayout::UNDEFINED);
+
+    let image = unsafe {
+        device
+            .create_image(&image_info, None)
+            .map_err(|e| format!("Failed to create atlas image: {:?}", e))?
+    };
+
+    let mem_requirements = unsafe { device.get_image_memory_requirements(image) };
+    let mem_properties = unsafe { instance.get_physical_device_memory_properties(physical_device) };
+    let image_memory = memory::allocate(
+        device,
+        mem_properties,
+        mem_requirements,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        Resource::Optimal,
+    )
+    .map_err(|e| format!("Failed to allocate atlas memory: {}", e))?;
+
+    unsafe {
+        device
+            .bind_image_memory(image, image_memory.memory, image_memory.offset)
+            .map_err(|e| format!("Failed to bind atlas memory: {:?}", e))?;
+    }
+
+    // Record a one-time copy with the layout transitions around it
+    let allocate_info = vk::CommandBufferAllocateInfo::builder()
+        .command_pool(command_pool)
+        .level(vk::CommandBufferLevel::PRIMARY)
+        .command_buffer_count(1);
+
+    let command_buffer = unsafe {
+        device
+            .allocate_command_buffers(&allocate_info)
+            .map_err(|e| format!("Failed to allocate upload command buffer: {:?}", e))?[0]
+    };
+
+    let subresource_range = vk::ImageSubresourceRange::builder()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .base_mip_level(0)
+        .level_count(1)
+        .base_array_layer(0)
+        .layer_count(1)
+        .build();
+
+    let to_transfer = vk::ImageMemoryBarrier::builder()
+        .old_layout(vk::ImageLayout::UNDEFINED)
+        .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(subresource_range)
+        .src_access_mask(vk::AccessFlags::empty())
+        .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+        .build();
+
+    let to_shader_read = vk::ImageMemoryBarrier::builder()
+        .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+        .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(subresource_range)
+        .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+        .dst_access_mask(vk::AccessFlags::SHADER_READ)
+        .build();
+
+    let region = vk::BufferImageCopy::builder()
+        .buffer_offset(0)
+        .buffer_row_length(0)
+        .buffer_image_height(0)
+        .image_subresource(vk::ImageSubresourceLayers {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level: 0,
+            base_array_layer: 0,
+            layer_count: 1,
+        })
+        .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
+        .image_extent(vk::Extent3D {
+            width: atlas.width,
+            height: atlas.height,
+            depth: 1,
+        })
+        .build();
+
+    let begin_info = vk::CommandBufferBeginInfo::builder()
+        .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+
+    unsafe {
+        device
+            .begin_command_buffer(command_buffer, &begin_info)
+            .map_err(|e| format!("Failed to begin upload command buffer: {:?}", e))?;
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[to_transfer],
+        );
+        device.cmd_copy_buffer_to_image(
+            command_buffer,
+            staging_buffer,
+            image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &[region],
+        );
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::FRAGMENT_SHADER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[to_shader_read],
+        );
+        device
+            .end_command_buffer(command_buffer)
+            .map_err(|e| format!("Failed to end upload command buffer: {:?}", e))?;
+
+        let command_buffers = [command_buffer];
+        let submit_info = vk::SubmitInfo::builder()
+            .command_buffers(&command_buffers)
+            .build();
+        device
+            .queue_submit(queue, &[submit_info], vk::Fence::null())
+            .map_err(|e| format!("Failed to submit atlas upload: {:?}", e))?;
+        device
+            .queue_wait_idle(queue)
+            .map_err(|e| format!("Failed to wait for atlas upload: {:?}", e))?;
+
+        device.free_command_buffers(command_pool, &command_buffers);
+        device.destroy_buffer(staging_buffer, None);
+    }
+    memory::free(device, staging_memory);
+
+    Ok((image, image_memory))
+}
+
+/// Creates the view used to sample the atlas.
+fn create_atlas_view(device: &Device, image: vk::Image) -> Result<vk::ImageView, String> {
+    let create_info = vk::ImageViewCreateInfo::builder()
+        .image(image)
+        .view_type(vk::ImageViewType::TYPE_2D)
+        .format(vk::Format::R8_UNORM)
+        .subresource_range(
+            vk::ImageSubresourceRange::builder()
+                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                .base_mip_level(0)
+                .level_count(1)
+                .base_array_layer(0)
+                .layer_count(1)
+                .build(),
+        );
+
+    unsafe {
+        device
+            .create_image_view(&create_info, None)
+            .map_err(|e| format!("Failed to create atlas image view: {:?}", e))
+    }
+}
+
+/// Nearest filtering keeps glyphs crisp at their native size.
+fn create_sampler(device: &Device) -> Result<vk::Sampler, String> {
+    let sampler_info = vk::SamplerCreateInfo::builder()
+        .mag_filter(vk::Filter::NEAREST)
+        .min_filter(vk::Filter::NEAREST)
+        .mipmap_mode(vk::SamplerMipmapMode::NEAREST)
+        .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .max_lod(0.0);
+
+    unsafe {
+        device
+            .create_sampler(&sampler_info, None)
+            .map_err(|e| format!("Failed to create atlas sampler: {:?}", e))
+    }
+}
+
+/// Creates a descriptor set layout for the atlas sampler.
+fn create_descriptor_set_layout(device: &Device) -> Result<vk::DescriptorSetLayout, String> {
+    let sampler_binding = vk::DescriptorSetLayoutBinding::builder()
+        .binding(0)
+        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(1)
+        .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+        .build();
+
+    let bindings = [sampler_binding];
+    let layout_info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(&bindings);
+
+    unsafe {
+        device
+            .create_descriptor_set_layout(&layout_info, None)
+            .map_err(|e| format!("Failed to create text descriptor set layout: {:?}", e))
+    }
+}
+
+/// Allocates the single descriptor set pointing at the atlas.
+fn create_descriptor_set(
+    device: &Device,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    atlas_view: vk::ImageView,
+    sampler: vk::Sampler,
+) -> Result<(vk::DescriptorPool, vk::DescriptorSet), String> {
+    let pool_sizes = [vk::DescriptorPoolSize::builder()
+        .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(1)
+        .build()];
+
+    let pool_info = vk::DescriptorPoolCreateInfo::builder()
+        .pool_sizes(&pool_sizes)
+        .max_sets(1);
+
+    let descriptor_pool = unsafe {
+        device
+            .create_descriptor_pool(&pool_info, None)
+            .map_err(|e| format!("Failed to create text descriptor pool: {:?}", e))?
+    };
+
+    let layouts = [descriptor_set_layout];
+    let alloc_info = vk::DescriptorSetAllocateInfo::builder()
+        .descriptor_pool(descriptor_pool)
+        .set_layouts(&layouts);
+
+    let descriptor_set = unsafe {
+        device
+            .allocate_descriptor_sets(&alloc_info)
+            .map_err(|e| format!("Failed to allocate text descriptor set: {:?}", e))?[0]
+    };
+
+    let image_info = vk::DescriptorImageInfo::builder()
+        .sampler(sampler)
+        .image_view(atlas_view)
+        .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+
+    let descriptor_write = vk::WriteDescriptorSet::builder()
+        .dst_set(descriptor_set)
+        .dst_binding(0)
+        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .image_info(std::slice::from_ref(&image_info));
+
+    unsafe {
+        device.update_descriptor_sets(&[descriptor_write.build()], &[]);
+    }
+
+    Ok((descriptor_pool, descriptor_set))
+}
+
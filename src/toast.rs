@@ -0,0 +1,111 @@
+// Queue of short notifications drawn over the overlay
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::accessibility;
+use crate::diagnostics;
+use crate::wake;
+use crate::overlay::OverlayContent;
+use crate::rings::{self, UNIT_PIXELS};
+use crate::text::TextBatch;
+
+/// How long a toast stays on screen.
+const TOAST_DURATION: Duration = Duration::from_millis(2500);
+/// Older toasts are dropped once this many are queued.
+const MAX_TOASTS: usize = 4;
+
+const TOAST_BACKGROUND: [f32; 4] = [0.12, 0.12, 0.12, 1.0];
+const TOAST_TEXT: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const PROGRESS_FILL: [f32; 4] = [0.35, 0.75, 1.0, 1.0];
+const PROGRESS_IDLE: [f32; 4] = [0.2, 0.4, 0.55, 1.0];
+const PADDING: f32 = 6.0;
+/// Space between the menu's outer edge and the first toast, in pixels.
+const MENU_GAP: f32 = 24.0;
+
+struct Toast {
+    message: String,
+    created: Instant,
+}
+
+static TOASTS: Mutex<VecDeque<Toast>> = Mutex::new(VecDeque::new());
+
+/// Queues a message for display. Safe to call from any thread, e.g. action workers and error paths.
+pub fn push<S: Into<String>>(message: S) {
+    let message = message.into();
+    println!("Toast: {}", message);
+    diagnostics::record_event(format!("Toast: {}", message));
+
+    let mut toasts = TOASTS.lock().unwrap();
+    toasts.push_back(Toast {
+        message,
+        created: Instant::now(),
+    });
+    while toasts.len() > MAX_TOASTS {
+        toasts.pop_front();
+    }
+    drop(toasts);
+    wake::wake();
+}
+
+/// Drops expired toasts and returns whether any are left to show.
+pub fn has_active() -> bool {
+    let mut toasts = TOASTS.lock().unwrap();
+    toasts.retain(|toast| toast.created.elapsed() < TOAST_DURATION);
+    !toasts.is_empty()
+}
+
+/// Lays out queued toasts and running-action progress, stacked below the ring.
+pub fn layout_toasts(batch: &mut TextBatch, overlay_content: &OverlayContent, time: f32) {
+    let (width, height) = batch.size();
+    let line_height = batch.metrics().cell_height + PADDING * 2.0;
+    let center_x = width / 2.0;
+    let mut y = height / 2.0 + rings::menu_extent(overlay_content) * UNIT_PIXELS + MENU_GAP;
+
+    // Running actions first, each with a progress bar under its label
+    let mut running: Vec<(&i32, &Option<f32>)> = overlay_content.segment_progress.iter().collect();
+    running.sort_by_key(|(segment, _)| **segment);
+    for (&segment, &fraction) in running {
+        let label = overlay_content
+            .item_text(segment)
+            .map(|text| text.label.clone())
+            .unwrap_or_else(|| format!("Segment {}", segment));
+        let message = format!("Running {}", label);
+
+        let (text_width, text_height) = batch.measure(&message);
+        let box_width = text_width + PADDING * 2.0;
+        let box_x = center_x - box_width / 2.0;
+        batch.rect(box_x, y, box_width, line_height + PADDING, TOAST_BACKGROUND);
+        batch.text(box_x + PADDING, y + PADDING, &message, TOAST_TEXT);
+
+        let bar_y = y + PADDING + text_height + 2.0;
+        let bar_width = text_width;
+        match fraction {
+            Some(fraction) => {
+                batch.rect(box_x + PADDING, bar_y, bar_width * fraction.clamp(0.0, 1.0), 3.0, PROGRESS_FILL);
+            }
+            None if accessibility::reduced_motion() => {
+                // Indeterminate without motion: the whole bar, dimmed
+                batch.rect(box_x + PADDING, bar_y, bar_width, 3.0, PROGRESS_IDLE);
+            }
+            None => {
+                // Indeterminate: a block sliding back and forth
+                let block = bar_width * 0.25;
+                let offset = (time * 1.5).sin() * 0.5 + 0.5;
+                batch.rect(box_x + PADDING + (bar_width - block) * offset, bar_y, block, 3.0, PROGRESS_FILL);
+            }
+        }
+
+        y += line_height + PADDING * 2.0;
+    }
+
+    let toasts = TOASTS.lock().unwrap();
+    for toast in toasts.iter() {
+        let (text_width, _) = batch.measure(&toast.message);
+        let box_width = text_width + PADDING * 2.0;
+        let box_x = center_x - box_width / 2.0;
+        batch.rect(box_x, y, box_width, line_height, TOAST_BACKGROUND);
+        batch.text(box_x + PADDING, y + PADDING, &toast.message, TOAST_TEXT);
+        y += line_height + PADDING;
+    }
+}
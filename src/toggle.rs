@@ -0,0 +1,97 @@
+// Toggle segments: items whose segment shows whether something is on, such as dark mode or the microphone's mute,
+// read when the overlay opens and every second while it is visible
+
+use serde::{Deserialize, Serialize};
+use winapi::shared::minwindef::DWORD;
+use winapi::shared::winerror::ERROR_SUCCESS;
+use winapi::um::wlanapi::{
+    wlan_interface_state_connected, WlanCloseHandle, WlanEnumInterfaces, WlanFreeMemory, WlanOpenHandle,
+    PWLAN_INTERFACE_INFO_LIST,
+};
+use winapi::um::winnt::HANDLE;
+use winapi::um::winreg::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD};
+use std::ptr::null_mut;
+use crate::window::to_wstring;
+use crate::{mic, variables};
+
+const PERSONALIZE_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Themes\Personalize";
+const LIGHT_THEME_VALUE: &str = "AppsUseLightTheme";
+/// Version of the WLAN API asked for; 2 is Vista and later.
+const WLAN_CLIENT_VERSION: DWORD = 2;
+
+/// What a toggle segment's state is read from, e.g. `toggle = "dark_mode"`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ToggleState {
+    /// The microphone is muted, as by the `toggle_mic` action.
+    MicMuted,
+    /// Apps use the dark theme.
+    DarkMode,
+    /// A Wi-Fi adapter is connected to a network.
+    Wifi,
+    /// A variable equals `equals`, or is set to a non-empty value when `equals` is omitted, for states that
+    /// scripts keep through `set`.
+    Variable {
+        name: String,
+        #[serde(default)]
+        equals: Option<String>,
+    },
+}
+
+impl ToggleState {
+    /// Whether the state is on; off when it can't be read.
+    pub fn read(&self) -> bool {
+        match self {
+            ToggleState::MicMuted => mic::is_muted(),
+            ToggleState::DarkMode => light_theme().map_or(false, |light| !light),
+            ToggleState::Wifi => wifi_connected(),
+            ToggleState::Variable { name, equals } => match (variables::get(name), equals) {
+                (Some(value), Some(expected)) => value == *expected,
+                (Some(value), None) => !value.is_empty(),
+                (None, _) => false,
+            },
+        }
+    }
+}
+
+/// Whether apps use the light theme, None before Windows 10 or when the setting was never written.
+fn light_theme() -> Option<bool> {
+    let key = to_wstring(PERSONALIZE_KEY);
+    let name = to_wstring(LIGHT_THEME_VALUE);
+    let mut value: DWORD = 0;
+    let mut size = std::mem::size_of::<DWORD>() as DWORD;
+    let status = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            key.as_ptr(),
+            name.as_ptr(),
+            RRF_RT_REG_DWORD,
+            null_mut(),
+            &mut value as *mut DWORD as *mut _,
+            &mut size,
+        )
+    };
+    (status == ERROR_SUCCESS as i32).then_some(value != 0)
+}
+
+/// Whether any Wi-Fi adapter is connected; false without one or without the WLAN service.
+fn wifi_connected() -> bool {
+    unsafe {
+        let mut version: DWORD = 0;
+        let mut client: HANDLE = null_mut();
+        if WlanOpenHandle(WLAN_CLIENT_VERSION, null_mut(), &mut version, &mut client) != ERROR_SUCCESS {
+            return false;
+        }
+        let mut list: PWLAN_INTERFACE_INFO_LIST = null_mut();
+        let mut connected = false;
+        if WlanEnumInterfaces(client, null_mut(), &mut list) == ERROR_SUCCESS {
+            // The list is declared with one entry but holds dwNumberOfItems of them
+            let count = (*list).dwNumberOfItems as usize;
+            let interfaces = std::slice::from_raw_parts((*list).InterfaceInfo.as_ptr(), count);
+            connected = interfaces.iter().any(|interface| interface.isState == wlan_interface_state_connected);
+            WlanFreeMemory(list as *mut _);
+        }
+        WlanCloseHandle(client, null_mut());
+        connected
+    }
+}
@@ -0,0 +1,59 @@
+// Tooltip bubble describing the hovered segment
+
+use std::time::Duration;
+use crate::overlay::OverlayContent;
+use crate::text::TextBatch;
+
+/// How long a segment must stay hovered before its tooltip appears.
+const TOOLTIP_DELAY: Duration = Duration::from_millis(300);
+/// Longest line before the description wraps, in pixels.
+const MAX_TOOLTIP_WIDTH: f32 = 260.0;
+const CURSOR_OFFSET: f32 = 16.0;
+const PADDING: f32 = 6.0;
+
+const TOOLTIP_BACKGROUND: [f32; 4] = [0.08, 0.08, 0.1, 1.0];
+const TITLE_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const DESCRIPTION_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 1.0];
+
+/// Lays out the tooltip for the hovered item next to the cursor, given in window pixels.
+pub fn layout_tooltip(batch: &mut TextBatch, overlay_content: &OverlayContent, cursor: (f32, f32)) {
+    let hovered_for = match overlay_content.hover_duration() {
+        Some(duration) => duration,
+        None => return,
+    };
+    if hovered_for < TOOLTIP_DELAY {
+        return;
+    }
+
+    let item = match overlay_content
+        .selected_segment
+        .and_then(|segment| overlay_content.item_text(segment))
+    {
+        Some(item) => item,
+        None => return,
+    };
+
+    // One title line followed by the wrapped description
+    let mut lines = vec![(item.label.clone(), TITLE_COLOR)];
+    for line in batch.wrap(&item.description, MAX_TOOLTIP_WIDTH) {
+        lines.push((line, DESCRIPTION_COLOR));
+    }
+
+    let line_height = batch.metrics().cell_height;
+    let content_width = lines
+        .iter()
+        .map(|(line, _)| batch.measure(line).0)
+        .fold(0.0, f32::max);
+    let box_width = content_width + PADDING * 2.0;
+    let box_height = line_height * lines.len() as f32 + PADDING * 2.0;
+
+    // Below-right of the cursor, kept inside the window
+    let (width, height) = batch.size();
+    let x = (cursor.0 + CURSOR_OFFSET).min(width - box_width).max(0.0);
+    let y = (cursor.1 + CURSOR_OFFSET).min(height - box_height).max(0.0);
+
+    batch.rect(x, y, box_width, box_height, TOOLTIP_BACKGROUND);
+    for (index, (line, color)) in lines.iter().enumerate() {
+        batch.text(x + PADDING, y + PADDING + line_height * index as f32, line, *color);
+    }
+}
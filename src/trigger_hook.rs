@@ -0,0 +1,390 @@
+// Hook-based trigger: a registered hotkey is a single combination taken from every other app, so sequence triggers
+// like "Ctrl+Space, R" or a double-tap of Alt, and profiles that want the trigger passed through, watch the keyboard
+// with a low-level hook instead. With passthrough a press is only taken when the overlay opens on it.
+// The hook also holds back presses of the long-press key until it is clear whether they are a tap or part of a
+// shortcut, which are then replayed to the focused app, or a hold that opens the overlay.
+
+use winapi::shared::basetsd::UINT_PTR;
+use winapi::shared::minwindef::{DWORD, LPARAM, LRESULT, UINT, WPARAM};
+use winapi::shared::windef::{HHOOK__, HWND};
+use winapi::um::libloaderapi::GetModuleHandleW;
+use winapi::um::processthreadsapi::GetCurrentThreadId;
+use winapi::um::winuser::*;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicPtr, AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::hotkey::{register_hotkey, Hotkey, Hotkeys, WM_HOTKEY_ID};
+use crate::{diagnostics, keys, settings, suppress};
+
+/// Where the trigger's current press went; repeats and the release go the same way.
+#[derive(Clone, Copy, PartialEq)]
+enum Press {
+    Up,
+    Passed,
+    /// Held back from the focused app until the long-press delay tells a tap from a hold.
+    Pending,
+    Taken,
+}
+
+struct State {
+    /// Steps of the registered trigger, the last one being the trigger itself; empty while none is registered.
+    steps: Vec<Hotkey>,
+    /// Longest pause between steps.
+    timeout: Duration,
+    /// Whether the active profile wants the trigger passed through.
+    passthrough: bool,
+    /// Step the next key press has to match, and until when.
+    next_step: usize,
+    deadline: Option<Instant>,
+    /// Keys down now, so auto-repeats aren't taken for presses.
+    held: Vec<DWORD>,
+    press: Press,
+    /// Set when a taken press needs its Alt or Win release masked, which is done once the lock is let go.
+    mask_release: bool,
+    /// Key that opens the overlay when held, e.g. CapsLock, and for how long.
+    long_press: Option<DWORD>,
+    long_press_delay: Duration,
+    long_press_state: Press,
+    /// Timer ending the long-press delay, 0 while none runs.
+    long_press_timer: UINT_PTR,
+    /// Set when a held back tap is to be replayed, which is done once the lock is let go.
+    replay_tap: Option<DWORD>,
+    /// Set when a held back press is to be replayed still held, followed by the key that interrupted it.
+    replay_press: Vec<DWORD>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    steps: Vec::new(),
+    timeout: Duration::ZERO,
+    passthrough: false,
+    next_step: 0,
+    deadline: None,
+    held: Vec::new(),
+    press: Press::Up,
+    mask_release: false,
+    long_press: None,
+    long_press_delay: Duration::ZERO,
+    long_press_state: Press::Up,
+    long_press_timer: 0,
+    replay_tap: None,
+    replay_press: Vec::new(),
+});
+static HOOK: AtomicPtr<HHOOK__> = AtomicPtr::new(null_mut());
+/// Thread whose message loop receives the trigger, the one that installed the hook.
+static THREAD: AtomicU32 = AtomicU32::new(0);
+
+impl State {
+    /// Whether the trigger is watched by the hook rather than registered.
+    fn trigger_needs_hook(&self) -> bool {
+        self.steps.len() > 1 || self.passthrough
+    }
+
+    fn needs_hook(&self) -> bool {
+        self.trigger_needs_hook() || self.long_press.is_some()
+    }
+
+    /// Follows a key event through the long-press key and the trigger's steps. Returns whether it is taken from the
+    /// focused app.
+    fn handle(&mut self, key: DWORD, down: bool) -> bool {
+        let Some(long_press) = self.long_press else {
+            return self.handle_steps(key, down);
+        };
+        if long_press == key {
+            return self.handle_long_press(key, down);
+        }
+        if !down || self.long_press_state != Press::Pending {
+            return self.handle_steps(key, down);
+        }
+        // Another key while the long-press key is held back makes it part of a shortcut, not a hold. The held key
+        // has to reach the app before this one, so both are taken and replayed in order
+        self.stop_long_press_timer();
+        self.long_press_state = Press::Passed;
+        self.replay_press.push(long_press);
+        if !self.handle_steps(key, down) {
+            self.replay_press.push(key);
+        }
+        true
+    }
+
+    /// Follows a key event through the trigger's steps. Returns whether it is taken from the focused app.
+    fn handle_steps(&mut self, key: DWORD, down: bool) -> bool {
+        let Some(trigger) = self.steps.last() else {
+            return false;
+        };
+        let last_key = trigger.key;
+        if !down {
+            self.held.retain(|&held| held != key);
+            if key == last_key {
+                return std::mem::replace(&mut self.press, Press::Up) == Press::Taken;
+            }
+            return false;
+        }
+        if self.held.contains(&key) {
+            // Auto-repeat
+            return key == last_key && self.press == Press::Taken;
+        }
+        self.held.push(key);
+
+        if self.deadline.map_or(false, |deadline| Instant::now() > deadline) {
+            self.next_step = 0;
+        }
+        let step = if matches(&self.steps[self.next_step], key) {
+            self.next_step
+        } else if matches(&self.steps[0], key) {
+            0
+        } else {
+            // Modifiers pressed on the way to the next combination don't break the sequence
+            if !is_modifier(key) {
+                self.next_step = 0;
+            }
+            return false;
+        };
+        if step + 1 < self.steps.len() {
+            self.next_step = step + 1;
+            self.deadline = Some(Instant::now() + self.timeout);
+            // Earlier steps are taken like a registered hotkey, except bare modifiers and with passthrough
+            return !self.passthrough && !is_modifier(key);
+        }
+
+        self.next_step = 0;
+        if self.passthrough && !would_open() {
+            diagnostics::record_event("Trigger passed through, the overlay wouldn't open");
+            self.press = Press::Passed;
+            return false;
+        }
+        self.press = Press::Taken;
+        self.mask_release = !is_modifier(key) && trigger.modifiers & (MOD_ALT | MOD_WIN) as UINT != 0;
+        post_trigger();
+        true
+    }
+
+    /// Holds back a press of the long-press key and starts the delay; its release before the delay ends replays the
+    /// tap, after it the overlay has opened and the release is taken too.
+    fn handle_long_press(&mut self, key: DWORD, down: bool) -> bool {
+        if !down {
+            self.stop_long_press_timer();
+            match std::mem::replace(&mut self.long_press_state, Press::Up) {
+                Press::Pending => {
+                    self.replay_tap = Some(key);
+                    true
+                }
+                Press::Taken => true,
+                _ => false,
+            }
+        } else if self.long_press_state != Press::Up {
+            // Auto-repeat goes where the press went
+            self.long_press_state != Press::Passed
+        } else if !would_open() {
+            self.long_press_state = Press::Passed;
+            false
+        } else {
+            let millis = self.long_press_delay.as_millis().max(1) as UINT;
+            self.long_press_timer = unsafe { SetTimer(null_mut(), 0, millis, Some(long_press_elapsed)) };
+            self.long_press_state = Press::Pending;
+            true
+        }
+    }
+
+    fn stop_long_press_timer(&mut self) {
+        if self.long_press_timer != 0 {
+            unsafe { KillTimer(null_mut(), self.long_press_timer) };
+            self.long_press_timer = 0;
+        }
+    }
+}
+
+/// Posts the trigger hotkey's message, as if the registered hotkey had been pressed.
+fn post_trigger() {
+    unsafe { PostThreadMessageW(THREAD.load(Ordering::SeqCst), WM_HOTKEY, WM_HOTKEY_ID as WPARAM, 0) };
+}
+
+/// The long-press key is still down once the delay is over, so it opens the overlay.
+unsafe extern "system" fn long_press_elapsed(_hwnd: HWND, _message: UINT, _timer: UINT_PTR, _time: DWORD) {
+    let mut state = STATE.lock().unwrap();
+    state.stop_long_press_timer();
+    if state.long_press_state == Press::Pending {
+        state.long_press_state = Press::Taken;
+        post_trigger();
+    }
+}
+
+/// Registers the trigger: through the hook for a sequence or while the active profile wants it passed through,
+/// as a hotkey otherwise, and the long-press key if there is one. Returns false if the hotkey is taken or the hook
+/// can't be installed.
+pub fn register_trigger(hotkeys: &Hotkeys) -> bool {
+    let mut state = STATE.lock().unwrap();
+    state.steps = hotkeys.trigger_steps.iter().chain([&hotkeys.trigger]).cloned().collect();
+    state.timeout = hotkeys.sequence_timeout;
+    state.next_step = 0;
+    state.long_press = hotkeys.long_press.as_ref().map(|hotkey| hotkey.key);
+    state.long_press_delay = hotkeys.long_press_delay;
+    let (trigger_needs_hook, needs_hook) = (state.trigger_needs_hook(), state.needs_hook());
+    drop(state);
+    let registered = trigger_needs_hook || register_hotkey(&hotkeys.trigger);
+    if needs_hook {
+        hook(&hotkeys.trigger.name) && registered
+    } else {
+        registered
+    }
+}
+
+/// Removes the hook; the registered hotkey is unregistered along with the others.
+pub fn unregister_trigger() {
+    {
+        let mut state = STATE.lock().unwrap();
+        state.steps.clear();
+        state.long_press = None;
+        state.stop_long_press_timer();
+    }
+    unhook();
+}
+
+/// Whether a press the hook took is still held. A taken key never reaches the key state `Hotkey::is_held` reads.
+pub fn is_held() -> bool {
+    let state = STATE.lock().unwrap();
+    state.press == Press::Taken || state.long_press_state == Press::Taken
+}
+
+/// Switches between the hook and the registered hotkey when the active profile changes. Must be called from the
+/// thread running the message loop, which the hook needs to receive events.
+pub fn set_passthrough(enabled: bool) {
+    let mut state = STATE.lock().unwrap();
+    let needed_hook = state.trigger_needs_hook();
+    state.passthrough = enabled;
+    if state.steps.is_empty() || state.trigger_needs_hook() == needed_hook {
+        return;
+    }
+    let trigger = state.steps[state.steps.len() - 1].clone();
+    let keep_hook = state.long_press.is_some();
+    drop(state);
+    if needed_hook {
+        if !keep_hook {
+            unhook();
+        }
+        register_hotkey(&trigger);
+    } else {
+        unsafe { UnregisterHotKey(null_mut(), WM_HOTKEY_ID) };
+        hook(&trigger.name);
+    }
+}
+
+fn hook(name: &str) -> bool {
+    {
+        let mut state = STATE.lock().unwrap();
+        state.press = Press::Up;
+        state.long_press_state = Press::Up;
+        state.held.clear();
+    }
+    if !HOOK.load(Ordering::SeqCst).is_null() {
+        return true;
+    }
+    THREAD.store(unsafe { GetCurrentThreadId() }, Ordering::SeqCst);
+    let hook = unsafe { SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_proc), GetModuleHandleW(null_mut()), 0) };
+    HOOK.store(hook, Ordering::SeqCst);
+    if hook.is_null() {
+        diagnostics::report_error(format!("Failed to install the keyboard hook; {} won't open the overlay", name));
+        return false;
+    }
+    true
+}
+
+fn unhook() {
+    let hook = HOOK.swap(null_mut(), Ordering::SeqCst);
+    if !hook.is_null() {
+        unsafe { UnhookWindowsHookEx(hook) };
+    }
+}
+
+/// Whether a key press is this step: its key with exactly its modifiers, besides the key itself for a bare
+/// modifier like the "Alt" of "Alt, Alt".
+fn matches(step: &Hotkey, key: DWORD) -> bool {
+    let own = modifier_flag(key);
+    step.key == key && modifiers_down() & !own == step.modifiers & !own
+}
+
+fn is_modifier(key: DWORD) -> bool {
+    modifier_flag(key) != 0
+}
+
+fn modifier_flag(key: DWORD) -> UINT {
+    (match key as i32 {
+        VK_MENU => MOD_ALT,
+        VK_CONTROL => MOD_CONTROL,
+        VK_SHIFT => MOD_SHIFT,
+        VK_LWIN => MOD_WIN,
+        _ => 0,
+    }) as UINT
+}
+
+/// The hook reports left and right modifiers apart; hotkeys name them without a side.
+fn sideless(key: DWORD) -> DWORD {
+    (match key as i32 {
+        VK_LMENU | VK_RMENU => VK_MENU,
+        VK_LCONTROL | VK_RCONTROL => VK_CONTROL,
+        VK_LSHIFT | VK_RSHIFT => VK_SHIFT,
+        VK_RWIN => VK_LWIN,
+        other => other,
+    }) as DWORD
+}
+
+/// The modifiers held now, as `MOD_*` flags.
+fn modifiers_down() -> UINT {
+    let down = |vk: i32| unsafe { (GetAsyncKeyState(vk) as u16 & 0x8000) != 0 };
+    let mut modifiers = 0;
+    if down(VK_MENU) {
+        modifiers |= MOD_ALT as UINT;
+    }
+    if down(VK_CONTROL) {
+        modifiers |= MOD_CONTROL as UINT;
+    }
+    if down(VK_SHIFT) {
+        modifiers |= MOD_SHIFT as UINT;
+    }
+    if down(VK_LWIN) || down(VK_RWIN) {
+        modifiers |= MOD_WIN as UINT;
+    }
+    modifiers
+}
+
+/// Whether a trigger press now would open the overlay, the same checks the menu makes before opening.
+fn would_open() -> bool {
+    !suppress::is_active() && !settings::is_open()
+}
+
+/// Turns the trigger's last step into the trigger hotkey's message, like the mouse hook does, and takes the keys
+/// the trigger uses; the rest reach the focused app untouched. Runs on the thread that installed it, during its
+/// message loop, so the state lock is never contended.
+unsafe extern "system" fn keyboard_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION {
+        let info = &*(lparam as *const KBDLLHOOKSTRUCT);
+        if info.flags & LLKHF_INJECTED == 0 {
+            let message = wparam as u32;
+            let down = message == WM_KEYDOWN || message == WM_SYSKEYDOWN;
+            let (taken, mask_release, replay_tap, replay_press) = {
+                let mut state = STATE.lock().unwrap();
+                let taken = state.handle(sideless(info.vkCode), down);
+                (
+                    taken,
+                    std::mem::take(&mut state.mask_release),
+                    state.replay_tap.take(),
+                    std::mem::take(&mut state.replay_press),
+                )
+            };
+            if mask_release {
+                keys::mask_modifier_release();
+            }
+            // Injected, so the hook lets the replayed tap and press through; their releases pass on their own
+            if let Some(key) = replay_tap {
+                keys::tap(key as u16);
+            }
+            if !replay_press.is_empty() {
+                keys::press(&replay_press.iter().map(|&key| key as u16).collect::<Vec<_>>());
+            }
+            if taken {
+                return 1;
+            }
+        }
+    }
+    CallNextHookEx(null_mut(), code, wparam, lparam)
+}
@@ -0,0 +1,716 @@
+// Checks the configuration file for mistakes that parse fine but don't do what was meant
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use toml_edit::{ImDocument, Item, TableLike};
+use crate::action::{is_url, Action};
+use crate::condition::{parse_time, Condition};
+use crate::dial::DialTarget;
+use crate::config::{load_config, Config, Fill, DEFAULT_PROFILE};
+use crate::custom_shader;
+use crate::fill;
+use crate::hotkey::Hotkey;
+use crate::menu::MAX_SEGMENTS;
+use crate::mouse::MouseButton;
+use crate::provider;
+use crate::render::read_spirv_shader;
+use crate::rings::{INNER_RADIUS, MAX_RINGS};
+use crate::text;
+
+/// A problem found in the config file, located by line and column (both 1-based).
+pub struct Issue {
+    pub file: Option<PathBuf>, // The included file the location is in; None for the config file itself
+    pub location: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "in {}, ", file.display())?;
+        }
+        match self.location {
+            Some((line, column)) => write!(f, "line {}, column {}: {}", line, column, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Reads, parses and validates the config file and the files it includes.
+/// Returns Err if one can't be read or parsed; the parse error already names the file, line and column.
+pub fn validate_file<P: AsRef<Path>>(path: P) -> Result<Vec<Issue>, String> {
+    let config = load_config(path.as_ref())?;
+    let documents = config
+        .sources
+        .iter()
+        .map(|source| {
+            let text = fs::read_to_string(source)
+                .map_err(|e| format!("Failed to read {}: {:?}", source.display(), e))?;
+            ImDocument::parse(text).map_err(|e| format!("Failed to parse {}: {}", source.display(), e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(validate(&config, &documents))
+}
+
+/// Runs every check against a parsed config and the documents it came from, in `config.sources` order.
+fn validate(config: &Config, documents: &[ImDocument<String>]) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let at = |found: Option<(usize, &Item)>, message: String| Issue {
+        file: found.filter(|&(document, _)| document > 0).map(|(document, _)| config.sources[document].clone()),
+        location: found
+            .and_then(|(document, item)| item.span().map(|span| line_column(documents[document].raw(), span))),
+        message,
+    };
+    let find = |path: &[&str]| lookup(documents, path);
+
+    // Keys that were ignored while deserializing are missing from the re-serialized config
+    match toml::Value::try_from(config) {
+        Ok(known) => unknown_keys(documents[0].as_table(), known.as_table(), "", documents[0].raw(), &mut issues),
+        Err(e) => issues.push(at(None, format!("Failed to check for unknown keys: {}", e))),
+    }
+
+    // Hotkeys
+    match last_step(&config.hotkey.trigger) {
+        Ok(trigger) => {
+            let diagnostics = Hotkey::diagnostics();
+            if (trigger.modifiers, trigger.key) == (diagnostics.modifiers, diagnostics.key) {
+                issues.push(at(
+                    find(&["hotkey", "trigger"]),
+                    format!("hotkey.trigger {} is already used to save diagnostics", trigger.name),
+                ));
+            }
+        }
+        Err(e) => issues.push(at(find(&["hotkey", "trigger"]), e)),
+    }
+    if !config.hotkey.pause.is_empty() {
+        if let Err(e) = Hotkey::parse(&config.hotkey.pause) {
+            issues.push(at(find(&["hotkey", "pause"]), e));
+        }
+    }
+    if !config.hotkey.repeat.is_empty() {
+        if let Err(e) = Hotkey::parse(&config.hotkey.repeat) {
+            issues.push(at(find(&["hotkey", "repeat"]), e));
+        }
+    }
+    if !config.hotkey.long_press.is_empty() {
+        match Hotkey::parse(&config.hotkey.long_press) {
+            Ok(key) if key.modifiers != 0 => issues.push(at(
+                find(&["hotkey", "long_press"]),
+                format!("hotkey.long_press {} must be a single key without modifiers", key.name),
+            )),
+            Ok(_) => {}
+            Err(e) => issues.push(at(find(&["hotkey", "long_press"]), e)),
+        }
+    }
+    if !config.hotkey.mouse_button.is_empty() {
+        if let Err(e) = MouseButton::parse(&config.hotkey.mouse_button) {
+            issues.push(at(find(&["hotkey", "mouse_button"]), e));
+        }
+    }
+    if !config.hud.hotkey.is_empty() {
+        if let Err(e) = Hotkey::parse(&config.hud.hotkey) {
+            issues.push(at(find(&["hud", "hotkey"]), e));
+        }
+    }
+    if !config.debug.hotkey.is_empty() {
+        if let Err(e) = Hotkey::parse(&config.debug.hotkey) {
+            issues.push(at(find(&["debug", "hotkey"]), e));
+        }
+    }
+    if config.recent.enabled {
+        match (Hotkey::parse(&config.recent.hotkey), last_step(&config.hotkey.trigger)) {
+            (Ok(recent), Ok(trigger)) if (recent.modifiers, recent.key) == (trigger.modifiers, trigger.key) => {
+                issues.push(at(
+                    find(&["recent", "hotkey"]),
+                    format!("recent.hotkey {} is the same as hotkey.trigger", recent.name),
+                ));
+            }
+            (Err(e), _) => issues.push(at(find(&["recent", "hotkey"]), e)),
+            _ => {}
+        }
+    }
+    if config.clipboard.enabled && !config.clipboard.hotkey.is_empty() {
+        if let Err(e) = Hotkey::parse(&config.clipboard.hotkey) {
+            issues.push(at(find(&["clipboard", "hotkey"]), e));
+        }
+    }
+    if config.recent_files.enabled && !config.recent_files.hotkey.is_empty() {
+        if let Err(e) = Hotkey::parse(&config.recent_files.hotkey) {
+            issues.push(at(find(&["recent_files", "hotkey"]), e));
+        }
+    }
+    if config.profile_switcher.enabled && !config.profile_switcher.hotkey.is_empty() {
+        if let Err(e) = Hotkey::parse(&config.profile_switcher.hotkey) {
+            issues.push(at(find(&["profile_switcher", "hotkey"]), e));
+        }
+    }
+    if config.switcher.enabled {
+        match (Hotkey::parse(&config.switcher.hotkey), last_step(&config.hotkey.trigger)) {
+            (Ok(switcher), Ok(trigger)) if (switcher.modifiers, switcher.key) == (trigger.modifiers, trigger.key) => {
+                issues.push(at(
+                    find(&["switcher", "hotkey"]),
+                    format!("switcher.hotkey {} is the same as hotkey.trigger", switcher.name),
+                ));
+            }
+            (Err(e), _) => issues.push(at(find(&["switcher", "hotkey"]), e)),
+            _ => {}
+        }
+    }
+
+    // Profiles
+    if config.profile_items(&config.profile).is_none() && provider::find(&config.profile).is_none() {
+        issues.push(at(find(&["profile"]), format!("Profile \"{}\" is not defined", config.profile)));
+    }
+    for (index, profile) in config.profiles.iter().enumerate() {
+        let duplicate = profile.name == DEFAULT_PROFILE
+            || config.profiles[..index].iter().any(|other| other.name == profile.name);
+        if duplicate {
+            issues.push(at(
+                find(&["profiles", &index.to_string(), "name"]),
+                format!("Profile name \"{}\" is used more than once", profile.name),
+            ));
+        }
+        for process in &profile.processes {
+            let claimed = config.profiles[..index]
+                .iter()
+                .any(|other| other.processes.iter().any(|name| name.eq_ignore_ascii_case(process)));
+            if claimed {
+                issues.push(at(
+                    find(&["profiles", &index.to_string(), "processes"]),
+                    format!("\"{}\" is listed by an earlier profile, which wins", process),
+                ));
+            }
+        }
+    }
+
+    // Ranges
+    if !(50..=10_000).contains(&config.dwell.time_ms) {
+        issues.push(at(
+            find(&["dwell", "time_ms"]),
+            format!("dwell.time_ms {} is out of range (50-10000)", config.dwell.time_ms),
+        ));
+    }
+    if !(1..=MAX_SEGMENTS).contains(&config.clipboard.size) {
+        issues.push(at(
+            find(&["clipboard", "size"]),
+            format!("clipboard.size {} is out of range (1-{})", config.clipboard.size, MAX_SEGMENTS),
+        ));
+    }
+    if !(1..=MAX_SEGMENTS).contains(&config.search.results) {
+        issues.push(at(
+            find(&["search", "results"]),
+            format!("search.results {} is out of range (1-{})", config.search.results, MAX_SEGMENTS),
+        ));
+    }
+    if !(1..=MAX_SEGMENTS).contains(&config.recent_files.size) {
+        issues.push(at(
+            find(&["recent_files", "size"]),
+            format!("recent_files.size {} is out of range (1-{})", config.recent_files.size, MAX_SEGMENTS),
+        ));
+    }
+    let directory = &config.recent_files.directory;
+    if config.recent_files.enabled && !directory.is_empty() && !Path::new(directory).is_dir() {
+        issues.push(at(
+            find(&["recent_files", "directory"]),
+            format!("recent_files.directory {} is not a folder", directory),
+        ));
+    }
+    if !(0.0..=1.0).contains(&config.feedback.volume) {
+        issues.push(at(
+            find(&["feedback", "volume"]),
+            format!("feedback.volume {} is out of range (0.0-1.0)", config.feedback.volume),
+        ));
+    }
+    if !(-360.0..=360.0).contains(&config.orientation.rotation_degrees) {
+        issues.push(at(
+            find(&["orientation", "rotation_degrees"]),
+            format!("orientation.rotation_degrees {} is out of range (-360-360)", config.orientation.rotation_degrees),
+        ));
+    }
+    if !(0.0..=0.9).contains(&config.gamepad.dead_zone) {
+        issues.push(at(
+            find(&["gamepad", "dead_zone"]),
+            format!("gamepad.dead_zone {} is out of range (0.0-0.9)", config.gamepad.dead_zone),
+        ));
+    }
+    if !(0.0..=30.0).contains(&config.selection.hysteresis_degrees) {
+        issues.push(at(
+            find(&["selection", "hysteresis_degrees"]),
+            format!("selection.hysteresis_degrees {} is out of range (0-30)", config.selection.hysteresis_degrees),
+        ));
+    }
+    if config.selection.angle_smoothing_ms > 500 {
+        issues.push(at(
+            find(&["selection", "angle_smoothing_ms"]),
+            format!("selection.angle_smoothing_ms {} is out of range (0-500)", config.selection.angle_smoothing_ms),
+        ));
+    }
+    if config.selection.inner_grace_ms > 5_000 {
+        issues.push(at(
+            find(&["selection", "inner_grace_ms"]),
+            format!("selection.inner_grace_ms {} is out of range (0-5000)", config.selection.inner_grace_ms),
+        ));
+    }
+    if let Some(sound_file) = &config.feedback.sound_file {
+        if !Path::new(sound_file).exists() {
+            issues.push(at(find(&["feedback", "sound_file"]), format!("Sound file not found: {}", sound_file)));
+        }
+    }
+
+    if !config.shader.fragment.is_empty() {
+        if let Err(e) = read_spirv_shader(&config.shader.fragment).and_then(|code| custom_shader::check(&code)) {
+            issues.push(at(find(&["shader", "fragment"]), format!("shader.fragment {}: {}", config.shader.fragment, e)));
+        }
+    }
+
+    // Theme
+    for (index, color) in config.theme.colors.iter().enumerate() {
+        if let Err(e) = fill::parse_color(color) {
+            issues.push(at(find(&["theme", "colors"]), format!("theme.colors[{}]: {}", index, e)));
+        }
+    }
+    if matches!(config.theme.fill, Fill::Linear | Fill::Radial) && config.theme.colors.len() != 2 {
+        issues.push(at(
+            find(&["theme", "colors"]),
+            format!("theme.colors has {} colors; gradients take two", config.theme.colors.len()),
+        ));
+    }
+    if config.theme.fill == Fill::Texture {
+        if config.theme.texture.is_empty() {
+            issues.push(at(find(&["theme", "fill"]), "theme.fill is texture but theme.texture is empty".to_string()));
+        } else if let Err(e) = fill::read_texture(&config.theme.texture) {
+            issues.push(at(find(&["theme", "texture"]), format!("theme.texture: {}", e)));
+        }
+    }
+    if let Err(e) = fill::parse_color(&config.theme.shadow_color) {
+        issues.push(at(find(&["theme", "shadow_color"]), format!("theme.shadow_color: {}", e)));
+    }
+    if !(0.0..=1.0).contains(&config.theme.shadow_opacity) {
+        issues.push(at(
+            find(&["theme", "shadow_opacity"]),
+            format!("theme.shadow_opacity {} is out of range (0-1)", config.theme.shadow_opacity),
+        ));
+    }
+    // The window leaves room around the menu; a larger shadow would be cut off at its edges
+    if !(1.0..=64.0).contains(&config.theme.shadow_size) {
+        issues.push(at(
+            find(&["theme", "shadow_size"]),
+            format!("theme.shadow_size {} is out of range (1-64)", config.theme.shadow_size),
+        ));
+    }
+    if config.theme.shadow_offset.iter().any(|offset| offset.abs() > 32.0) {
+        issues.push(at(
+            find(&["theme", "shadow_offset"]),
+            format!("theme.shadow_offset {:?} is out of range (-32 to 32)", config.theme.shadow_offset),
+        ));
+    }
+    if !(0.0..=1.0).contains(&config.theme.frosted_opacity) {
+        issues.push(at(
+            find(&["theme", "frosted_opacity"]),
+            format!("theme.frosted_opacity {} is out of range (0-1)", config.theme.frosted_opacity),
+        ));
+    }
+    if !(0.0..=32.0).contains(&config.theme.frosted_blur) {
+        issues.push(at(
+            find(&["theme", "frosted_blur"]),
+            format!("theme.frosted_blur {} is out of range (0-32)", config.theme.frosted_blur),
+        ));
+    }
+    if !(0.0..=1.0).contains(&config.theme.wedge_opacity) {
+        issues.push(at(
+            find(&["theme", "wedge_opacity"]),
+            format!("theme.wedge_opacity {} is out of range (0-1)", config.theme.wedge_opacity),
+        ));
+    }
+    let animation = &config.theme.animation;
+    for (name, timeline) in [("open", &animation.open), ("idle", &animation.idle), ("hover", &animation.hover)] {
+        let tracks = [
+            ("scale", &timeline.scale),
+            ("opacity", &timeline.opacity),
+            ("rotation_degrees", &timeline.rotation_degrees),
+            ("grow", &timeline.grow),
+            ("segment_scale", &timeline.segment_scale),
+            ("segment_slide", &timeline.segment_slide),
+        ];
+        for (track, keyframes) in tracks {
+            let location = find(&["theme", "animation", name, track]);
+            if keyframes.windows(2).any(|pair| pair[1].at_ms <= pair[0].at_ms) {
+                issues.push(at(
+                    location,
+                    format!("theme.animation.{}.{} keyframes must be in order of at_ms, without repeats", name, track),
+                ));
+            }
+            let out_of_range = match track {
+                "scale" | "segment_scale" => keyframes.iter().any(|keyframe| !(0.0..=4.0).contains(&keyframe.value)),
+                "opacity" => keyframes.iter().any(|keyframe| !(0.0..=1.0).contains(&keyframe.value)),
+                _ => false,
+            };
+            if out_of_range {
+                let range = if track == "opacity" { "0-1" } else { "0-4" };
+                issues.push(at(location, format!("theme.animation.{}.{} has a value out of range ({})", name, track, range)));
+            }
+        }
+    }
+    for (name, value) in [("opacity", animation.opacity), ("inactive_opacity", animation.inactive_opacity)] {
+        if !(0.0..=1.0).contains(&value) {
+            issues.push(at(
+                find(&["theme", "animation", name]),
+                format!("theme.animation.{} {} is out of range (0-1)", name, value),
+            ));
+        }
+    }
+    if animation.inactive_fade_ms > 10_000 {
+        issues.push(at(
+            find(&["theme", "animation", "inactive_fade_ms"]),
+            format!("theme.animation.inactive_fade_ms {} is out of range (0-10000)", animation.inactive_fade_ms),
+        ));
+    }
+    for (name, timeline) in [("open", &animation.open), ("idle", &animation.idle)] {
+        if timeline.stagger_ms > 1000 {
+            issues.push(at(
+                find(&["theme", "animation", name, "stagger_ms"]),
+                format!("theme.animation.{}.stagger_ms {} is out of range (0-1000)", name, timeline.stagger_ms),
+            ));
+        }
+    }
+    if !(0.01..=100.0).contains(&config.theme.texture_scale) {
+        issues.push(at(
+            find(&["theme", "texture_scale"]),
+            format!("theme.texture_scale {} is out of range (0.01-100)", config.theme.texture_scale),
+        ));
+    }
+
+    // Remote control
+    if config.remote.enabled && !config.remote.token.is_empty() && config.remote.token.len() < 12 {
+        issues.push(at(
+            find(&["remote", "token"]),
+            "remote.token is shorter than 12 characters; anyone on the network could guess it".to_string(),
+        ));
+    }
+
+    // Renderer lifetime
+    if config.renderer.release_after_min > 1440 {
+        issues.push(at(
+            find(&["renderer", "release_after_min"]),
+            format!("renderer.release_after_min {} is out of range (0-1440)", config.renderer.release_after_min),
+        ));
+    }
+    if config.hook.enabled && (config.renderer.lazy || config.renderer.release_after_min > 0) {
+        issues.push(at(
+            find(&["renderer"]),
+            "renderer.lazy and renderer.release_after_min have no effect while hook.enabled is set".to_string(),
+        ));
+    }
+
+    // Edge zones
+    if config.edges.enabled && !(0.3..=1.0).contains(&config.edges.distance) {
+        issues.push(at(
+            find(&["edges", "distance"]),
+            format!("edges.distance {} is out of range (0.3-1.0, outside the ring)", config.edges.distance),
+        ));
+    }
+    for (key, action) in [
+        ("left", &config.edges.left),
+        ("right", &config.edges.right),
+        ("top", &config.edges.top),
+        ("bottom", &config.edges.bottom),
+    ] {
+        if let Some(action) = action {
+            for (keys, message) in action_problems(action, vec!["edges".to_string(), key.to_string()]) {
+                let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+                issues.push(at(find(&keys), format!("edges.{} {}", key, message)));
+            }
+        }
+    }
+
+    // Staged rings
+    if config.rings.enabled {
+        if !(0.05..=0.3).contains(&config.rings.width) {
+            issues.push(at(
+                find(&["rings", "width"]),
+                format!("rings.width {} is out of range (0.05-0.3)", config.rings.width),
+            ));
+        }
+        if config.rings.sizes.contains(&0) {
+            issues.push(at(find(&["rings", "sizes"]), "rings.sizes can't contain 0".to_string()));
+        }
+        if config.rings.sizes.len() >= MAX_RINGS {
+            issues.push(at(
+                find(&["rings", "sizes"]),
+                format!("Only {} rings are drawn; items past the first {} sizes share the last ring", MAX_RINGS, MAX_RINGS - 1),
+            ));
+        }
+        // The outermost ring a full menu could use
+        let outer = INNER_RADIUS + config.rings.width * (config.rings.sizes.len() + 1).min(MAX_RINGS) as f32;
+        if config.edges.enabled && config.edges.distance < outer {
+            issues.push(at(
+                find(&["edges", "distance"]),
+                format!("edges.distance {} is inside the outer rings, which end at {:.2}", config.edges.distance, outer),
+            ));
+        }
+    }
+
+    // Items, in the top-level menu and in every profile
+    let mut menus = vec![(vec!["items".to_string()], &config.items)];
+    for (index, profile) in config.profiles.iter().enumerate() {
+        menus.push((vec!["profiles".to_string(), index.to_string(), "items".to_string()], &profile.items));
+    }
+    for (prefix, items) in menus {
+        let prefix: Vec<&str> = prefix.iter().map(String::as_str).collect();
+        if items.len() > MAX_SEGMENTS {
+            issues.push(at(
+                find(&prefix),
+                format!("{} items configured, only {} segments fit the ring", items.len(), MAX_SEGMENTS),
+            ));
+        }
+        for (index, item) in items.iter().enumerate() {
+            let index = index.to_string();
+            let find_in_item = |keys: &[&str]| {
+                let path: Vec<&str> = prefix.iter().copied().chain([index.as_str()]).chain(keys.iter().copied()).collect();
+                lookup(documents, &path)
+            };
+            for (key, text) in [("label", Some(&item.label)), ("description", Some(&item.description)), ("badge", item.badge.as_ref())] {
+                if text.map_or(false, |text| !text.chars().all(text::is_drawable)) {
+                    issues.push(at(
+                        find_in_item(&[key]),
+                        format!("\"{}\" {} has characters outside printable ASCII, which are drawn as '?'", item.label, key),
+                    ));
+                }
+            }
+            if let Some(weight) = item.weight {
+                if !(0.1..=10.0).contains(&weight) {
+                    issues.push(at(
+                        find_in_item(&["weight"]),
+                        format!("\"{}\" weight {} is out of range (0.1-10.0)", item.label, weight),
+                    ));
+                }
+            }
+            if let Some(dial) = &item.dial {
+                if let Some(sweep) = dial.sweep {
+                    if !(30.0..=1440.0).contains(&sweep) {
+                        issues.push(at(
+                            find_in_item(&["dial", "sweep"]),
+                            format!("\"{}\" dial sweep {} is out of range (30-1440)", item.label, sweep),
+                        ));
+                    }
+                }
+                if let DialTarget::Variable { min, max, .. } = dial.target {
+                    if min >= max {
+                        issues.push(at(
+                            find_in_item(&["dial", "max"]),
+                            format!("\"{}\" dial max {} must be above its min {}", item.label, max, min),
+                        ));
+                    }
+                }
+            }
+            if let Some(action) = &item.action {
+                for (keys, message) in action_problems(action, vec!["action".to_string()]) {
+                    let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+                    issues.push(at(find_in_item(&keys), format!("\"{}\" {}", item.label, message)));
+                }
+            }
+            for (key, condition) in [("enabled_if", &item.enabled_if), ("visible_if", &item.visible_if)] {
+                if let Some(condition) = condition {
+                    for message in condition_problems(condition) {
+                        issues.push(at(find_in_item(&[key]), format!("\"{}\" {}: {}", item.label, key, message)));
+                    }
+                }
+            }
+        }
+    }
+
+    issues
+}
+
+/// The trigger hotkey of a possibly multi-step trigger, what other hotkeys must not clash with.
+fn last_step(trigger: &str) -> Result<Hotkey, String> {
+    Hotkey::parse_sequence(trigger).map(|mut steps| steps.pop().unwrap_or_default())
+}
+
+/// Problems with an action's target, each with the key path to it from the item.
+/// Catches what would otherwise only fail once the user selects the item.
+fn action_problems(action: &Action, keys: Vec<String>) -> Vec<(Vec<String>, String)> {
+    let with_key = |key: &str| keys.iter().cloned().chain([key.to_string()]).collect::<Vec<_>>();
+    match action {
+        Action::Launch { path, .. } if is_url(path) => match url_problem(path) {
+            Some(problem) => vec![(with_key("path"), format!("opens {}, which {}", path, problem))],
+            None => Vec::new(),
+        },
+        Action::Launch { path, .. } if !program_exists(path) => {
+            vec![(with_key("path"), format!("launches {}, which was not found", path))]
+        }
+        Action::Open { path } if !Path::new(path).exists() => {
+            vec![(with_key("path"), format!("opens {}, which was not found", path))]
+        }
+        Action::Http { url, .. } if !url.starts_with("http://") && !url.starts_with("https://") => {
+            vec![(with_key("url"), format!("requests {}, which is not an http(s) URL", url))]
+        }
+        Action::Http { url, .. } => match url_problem(url) {
+            Some(problem) => vec![(with_key("url"), format!("requests {}, which {}", url, problem))],
+            None => Vec::new(),
+        },
+        Action::Mqtt { qos, .. } if *qos > 2 => {
+            vec![(with_key("qos"), format!("publishes with QoS {}, which must be 0, 1 or 2", qos))]
+        }
+        Action::SendKeys { keys: combo } => match Hotkey::parse(combo) {
+            Ok(_) => Vec::new(),
+            Err(e) => vec![(with_key("keys"), format!("sends keys that can't be pressed: {}", e))],
+        },
+        Action::ToggleMic { keys: Some(combo) } => match Hotkey::parse(combo) {
+            Ok(_) => Vec::new(),
+            Err(e) => vec![(with_key("keys"), format!("sends keys that can't be pressed: {}", e))],
+        },
+        Action::Confirm { action, .. } => action_problems(action, with_key("action")),
+        Action::Macro { steps } => steps
+            .iter()
+            .enumerate()
+            .flat_map(|(index, step)| {
+                let mut step_keys = with_key("steps");
+                step_keys.push(index.to_string());
+                action_problems(step, step_keys)
+            })
+            .collect(),
+        Action::Brightness { level: Some(level), .. } if *level > 100 => {
+            vec![(with_key("level"), format!("sets brightness {}, which is out of range (0-100)", level))]
+        }
+        // The process list holds bare exe names, so a path or a name without ".exe" never matches
+        Action::KillProcess { name, .. } if name.contains(['\\', '/']) || !name.to_lowercase().ends_with(".exe") => {
+            vec![(with_key("name"), format!("ends {}, which is not an exe name like \"game.exe\"", name))]
+        }
+        Action::Wait { ms } if *ms > 60_000 => {
+            vec![(with_key("ms"), format!("waits {} ms, over a minute", ms))]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Why a URL won't open, if it looks malformed.
+fn url_problem(url: &str) -> Option<&'static str> {
+    let (scheme, rest) = url.split_once("://")?;
+    if url.chars().any(char::is_whitespace) {
+        return Some("contains spaces (use %20)");
+    }
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host = authority.rsplit('@').next().unwrap_or("");
+    let web = scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https");
+    if web && host.is_empty() {
+        return Some("has no host name");
+    }
+    let port = host.rsplit_once(':').map(|(_, port)| port).filter(|port| !port.ends_with(']'));
+    if web && port.map_or(false, |port| port.parse::<u16>().is_err()) {
+        return Some("has an invalid port");
+    }
+    None
+}
+
+/// Reports keys present in `table` but not in `known`, recursing into nested tables and arrays.
+fn unknown_keys(table: &dyn TableLike, known: Option<&toml::Table>, path: &str, source: &str, issues: &mut Vec<Issue>) {
+    for (key, item) in table.iter() {
+        let key_path = if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+        match known.and_then(|known| known.get(key)) {
+            Some(known) => unknown_keys_in_item(item, known, &key_path, source, issues),
+            None => {
+                let span = table.key(key).and_then(|key| key.span()).or_else(|| item.span());
+                issues.push(Issue {
+                    file: None,
+                    location: span.map(|span| line_column(source, span)),
+                    message: format!("Unknown key `{}`", key_path),
+                });
+            }
+        }
+    }
+}
+
+fn unknown_keys_in_item(item: &Item, known: &toml::Value, path: &str, source: &str, issues: &mut Vec<Issue>) {
+    if let Some(table) = item.as_table_like() {
+        unknown_keys(table, known.as_table(), path, source, issues);
+        return;
+    }
+    // Arrays of tables and inline arrays
+    let mut index = 0;
+    while let (Some(element), Some(known)) = (item.get(index), known.get(index)) {
+        unknown_keys_in_item(element, known, &format!("{}[{}]", path, index), source, issues);
+        index += 1;
+    }
+}
+
+/// Follows table keys and array indices through the documents the config was merged from, returning the
+/// index of the document the entry is in. Tables are merged with the earlier documents winning, and arrays
+/// are appended in document order, so an index past one document's array continues in the next.
+fn lookup<'a>(documents: &'a [ImDocument<String>], path: &[&str]) -> Option<(usize, &'a Item)> {
+    let mut found: Vec<(usize, &Item)> = documents.iter().map(ImDocument::as_item).enumerate().collect();
+    for key in path {
+        found = match key.parse::<usize>() {
+            Ok(mut index) => {
+                let mut entry = None;
+                for (document, item) in found {
+                    let len = item.as_array().map_or(0, |array| array.len())
+                        + item.as_array_of_tables().map_or(0, |tables| tables.len());
+                    if index < len {
+                        entry = item.get(index).map(|item| (document, item));
+                        break;
+                    }
+                    index -= len;
+                }
+                entry.into_iter().collect()
+            }
+            Err(_) => found.into_iter().filter_map(|(document, item)| Some((document, item.get(*key)?))).collect(),
+        };
+    }
+    found.into_iter().next()
+}
+
+/// Converts a byte range into the 1-based line and column of its start.
+fn line_column(source: &str, span: Range<usize>) -> (usize, usize) {
+    let before = &source[..span.start.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |line| line.chars().count()) + 1;
+    (line, column)
+}
+
+/// Whether a program path exists, either as given or on the PATH.
+fn program_exists(program: &str) -> bool {
+    let path = Path::new(program);
+    if path.exists() {
+        return true;
+    }
+    if path.components().count() > 1 {
+        return false;
+    }
+
+    let extensions: Vec<String> = if path.extension().is_some() {
+        vec![String::new()]
+    } else {
+        env::var("PATHEXT")
+            .unwrap_or_else(|_| ".EXE;.BAT;.CMD;.COM".to_string())
+            .split(';')
+            .map(|extension| extension.to_string())
+            .collect()
+    };
+    env::var_os("PATH").map_or(false, |paths| {
+        env::split_paths(&paths).any(|dir| {
+            extensions
+                .iter()
+                .any(|extension| dir.join(format!("{}{}", program, extension)).exists())
+        })
+    })
+}
+
+/// Problems inside a condition expression, such as malformed times.
+fn condition_problems(condition: &Condition) -> Vec<String> {
+    match condition {
+        Condition::TimeBetween(start, end) => [start, end]
+            .iter()
+            .filter(|time| parse_time(time).is_none())
+            .map(|time| format!("invalid time \"{}\", expected HH:MM", time))
+            .collect(),
+        Condition::All(conditions) | Condition::Any(conditions) => {
+            conditions.iter().flat_map(condition_problems).collect()
+        }
+        Condition::Not(condition) => condition_problems(condition),
+        _ => Vec::new(),
+    }
+}
@@ -0,0 +1,35 @@
+// Key/value variables set by actions and scripts, read by labels, badges and conditions
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use crate::diagnostics;
+
+static VARIABLES: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Sets a variable. Safe to call from any thread, e.g. action workers and the IPC server.
+pub fn set<N: Into<String>, V: Into<String>>(name: N, value: V) {
+    let (name, value) = (name.into(), value.into());
+    let mut variables = VARIABLES.lock().unwrap();
+    if variables.get(&name) == Some(&value) {
+        return;
+    }
+    diagnostics::record_event(format!("Variable {} = {}", name, value));
+    variables.insert(name, value);
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+pub fn get(name: &str) -> Option<String> {
+    VARIABLES.lock().unwrap().get(name).cloned()
+}
+
+/// Returns a copy of every variable.
+pub fn snapshot() -> BTreeMap<String, String> {
+    VARIABLES.lock().unwrap().clone()
+}
+
+/// Incremented on every change, so the main loop can tell when visible content needs refreshing.
+pub fn generation() -> u64 {
+    GENERATION.load(Ordering::SeqCst)
+}
@@ -0,0 +1,46 @@
+// Sleeping and waking the main loop: it waits for messages instead of polling, and threads that hand it work
+// post a message to end the wait
+
+use winapi::shared::minwindef::DWORD;
+use winapi::um::processthreadsapi::GetCurrentThreadId;
+use winapi::um::winbase::INFINITE;
+use winapi::um::winnt::{HANDLE, MAXIMUM_WAIT_OBJECTS};
+use winapi::um::winuser::{MsgWaitForMultipleObjectsEx, PostThreadMessageW, MWMO_INPUTAVAILABLE, QS_ALLINPUT, WM_NULL};
+use std::ptr::null;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+/// The thread running the main loop, 0 until it starts.
+static MAIN_THREAD: AtomicU32 = AtomicU32::new(0);
+
+/// Makes the calling thread the one `wake` reaches. Called at startup by the thread that runs the main loop.
+pub fn init() {
+    MAIN_THREAD.store(unsafe { GetCurrentThreadId() }, Ordering::SeqCst);
+}
+
+/// Whether the caller is the thread running the main loop.
+pub fn on_main_thread() -> bool {
+    MAIN_THREAD.load(Ordering::SeqCst) == unsafe { GetCurrentThreadId() }
+}
+
+/// Ends the main loop's wait early, e.g. when an action reports progress or a toast is queued.
+/// Safe to call from any thread; does nothing before the main loop starts.
+pub fn wake() {
+    let thread = MAIN_THREAD.load(Ordering::SeqCst);
+    if thread != 0 {
+        unsafe { PostThreadMessageW(thread, WM_NULL, 0, 0) };
+    }
+}
+
+/// Sleeps until a message or hook event arrives for this thread, `wake` is called, one of `handles` is signaled,
+/// or `timeout` passes; without a timeout, only something happening ends the wait.
+/// Messages already queued end the wait at once, so none is left waiting for the next one.
+pub fn wait(timeout: Option<Duration>, handles: &[HANDLE]) {
+    let millis = timeout.map_or(INFINITE, |timeout| timeout.as_millis().min((INFINITE - 1) as u128) as DWORD);
+    // The wait takes one handle fewer than other waits, its message queue counting as one
+    let count = handles.len().min(MAXIMUM_WAIT_OBJECTS as usize - 1) as DWORD;
+    let handles = if handles.is_empty() { null() } else { handles.as_ptr() };
+    unsafe {
+        MsgWaitForMultipleObjectsEx(count, handles, millis, QS_ALLINPUT, MWMO_INPUTAVAILABLE);
+    }
+}
@@ -1,12 +1,26 @@
-// Creates a transparent, click-through overlay window
+// Creates a transparent, click-through overlay window, which takes input only while the settings screen is open
 
 use std::os::windows::ffi::OsStrExt;
 use winapi::um::winuser::*;
-use winapi::shared::windef::HWND;
+use winapi::shared::windef::{HWND, POINT, RECT};
 use winapi::um::libloaderapi::GetModuleHandleW;
 use winapi::shared::minwindef::{UINT, WPARAM, LPARAM, LRESULT};
+use std::mem::{size_of, zeroed};
 use std::ptr::null_mut;
 use winapi::shared::minwindef::HINSTANCE;
+use crate::config::Anchor;
+use crate::clipboard;
+use crate::relative;
+use crate::settings;
+use crate::shutdown;
+use crate::rings::UNIT_PIXELS;
+
+/// Room left around the menu for badges, edge labels, tooltips and the toasts below it, in pixels.
+const MENU_MARGIN: f32 = 160.0;
+/// Smallest window size, which still fits panels.
+const MIN_WIDTH: u32 = 560;
+const MIN_HEIGHT: u32 = 400;
+
 pub fn create_overlay_window(title: &str, width: u32, height: u32) -> HWND {
     unsafe {
         let h_instance: HINSTANCE = GetModuleHandleW(null_mut());
@@ -51,6 +65,153 @@ pub fn create_overlay_window(title: &str, width: u32, height: u32) -> HWND {
     }
 }
 
+/// Window size that fits a menu reaching `extent` ring units from its center.
+pub fn size_for_menu(extent: f32) -> (u32, u32) {
+    let side = (2.0 * (extent * UNIT_PIXELS + MENU_MARGIN)).ceil() as u32;
+    (side.max(MIN_WIDTH), side.max(MIN_HEIGHT))
+}
+
+/// Size of the window's client area in pixels.
+pub fn client_size(hwnd: HWND) -> (u32, u32) {
+    let mut rect: RECT = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+    unsafe {
+        GetClientRect(hwnd, &mut rect);
+    }
+    ((rect.right - rect.left) as u32, (rect.bottom - rect.top) as u32)
+}
+
+/// Resizes the window and moves it to where a menu with `anchor` opens. Returns the cursor's offset from the
+/// window's center, where the ring is drawn, which is zero unless the menu is pinned or was kept on screen.
+pub fn place_window(hwnd: HWND, width: u32, height: u32, anchor: Anchor) -> (i32, i32) {
+    if anchor == Anchor::Cursor {
+        return center_on_cursor(hwnd, width, height);
+    }
+    unsafe {
+        let mut point: POINT = POINT { x: 0, y: 0 };
+        GetCursorPos(&mut point);
+        let work = match work_area(point) {
+            Some(work) => work,
+            None => return center_on_cursor(hwnd, width, height),
+        };
+
+        let (width_i, height_i) = (width as i32, height as i32);
+        let (x, y) = match anchor {
+            Anchor::TopLeft => (work.left, work.top),
+            Anchor::TopRight => (work.right - width_i, work.top),
+            Anchor::BottomLeft => (work.left, work.bottom - height_i),
+            Anchor::BottomRight => (work.right - width_i, work.bottom - height_i),
+            _ => ((work.left + work.right - width_i) / 2, (work.top + work.bottom - height_i) / 2),
+        };
+        SetWindowPos(hwnd, null_mut(), x, y, width_i, height_i, SWP_NOZORDER | SWP_NOACTIVATE);
+        (point.x - (x + width_i / 2), point.y - (y + height_i / 2))
+    }
+}
+
+/// Work area of the monitor nearest to `point`, the screen without the taskbar and docked toolbars.
+fn work_area(point: POINT) -> Option<RECT> {
+    unsafe {
+        let mut monitor_info: MONITORINFO = zeroed();
+        monitor_info.cbSize = size_of::<MONITORINFO>() as u32;
+        let monitor = MonitorFromPoint(point, MONITOR_DEFAULTTONEAREST);
+        if GetMonitorInfoW(monitor, &mut monitor_info) != 0 {
+            Some(monitor_info.rcWork)
+        } else {
+            None
+        }
+    }
+}
+
+/// Resizes the window and moves it so its center is under the mouse cursor, but kept inside the work area of
+/// the cursor's monitor so a menu opened near a screen edge or the taskbar is still fully visible. The ring is
+/// then off the cursor by the returned offset; the caller measures the cursor from where it was instead, see
+/// `OverlayContent::cursor_origin`.
+pub fn center_on_cursor(hwnd: HWND, width: u32, height: u32) -> (i32, i32) {
+    unsafe {
+        // Get mouse position
+        let mut point: POINT = POINT { x: 0, y: 0 };
+        GetCursorPos(&mut point);
+
+        let mut x = point.x - width as i32 / 2;
+        let mut y = point.y - height as i32 / 2;
+        if let Some(work) = work_area(point) {
+            // A window larger than the work area keeps its top left corner visible
+            x = x.min(work.right - width as i32).max(work.left);
+            y = y.min(work.bottom - height as i32).max(work.top);
+        }
+
+        SetWindowPos(
+            hwnd,
+            null_mut(),
+            x,
+            y,
+            width as i32,
+            height as i32,
+            SWP_NOZORDER | SWP_NOACTIVATE,
+        );
+        (point.x - (x + width as i32 / 2), point.y - (y + height as i32 / 2))
+    }
+}
+
+/// Puts the window back on top of other topmost windows, e.g. a game that made itself topmost after the overlay.
+pub fn keep_topmost(hwnd: HWND) {
+    unsafe {
+        SetWindowPos(hwnd, HWND_TOPMOST, 0, 0, 0, 0, SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE);
+    }
+}
+
+/// Makes the window take clicks and keys, for the settings screen, or click-through again.
+pub fn set_interactive(hwnd: HWND, interactive: bool) {
+    unsafe {
+        let style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE) as u32;
+        let style = if interactive {
+            style & !(WS_EX_TRANSPARENT | WS_EX_NOACTIVATE)
+        } else {
+            style | WS_EX_TRANSPARENT | WS_EX_NOACTIVATE
+        };
+        SetWindowLongPtrW(hwnd, GWL_EXSTYLE, style as isize);
+        if interactive {
+            SetForegroundWindow(hwnd);
+        }
+    }
+}
+
+/// Where the cursor is on the screen.
+pub fn cursor_position() -> (i32, i32) {
+    let mut point: POINT = POINT { x: 0, y: 0 };
+    unsafe {
+        GetCursorPos(&mut point);
+    }
+    (point.x, point.y)
+}
+
+/// Moves the cursor to the middle of the window and returns where it was, for `restore_cursor`.
+pub fn warp_cursor_to_center(hwnd: HWND) -> POINT {
+    unsafe {
+        let mut previous: POINT = POINT { x: 0, y: 0 };
+        GetCursorPos(&mut previous);
+
+        let mut rect: RECT = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+        GetWindowRect(hwnd, &mut rect);
+        SetCursorPos((rect.left + rect.right) / 2, (rect.top + rect.bottom) / 2);
+        previous
+    }
+}
+
+/// Shows an error in a message box, for when the overlay can't show it itself.
+pub fn error_box(message: &str) {
+    let text = to_wstring(message);
+    let title = to_wstring("Radial Menu Overlay");
+    unsafe {
+        MessageBoxW(null_mut(), text.as_ptr(), title.as_ptr(), MB_OK | MB_ICONERROR);
+    }
+}
+
+pub fn restore_cursor(point: POINT) {
+    unsafe {
+        SetCursorPos(point.x, point.y);
+    }
+}
+
 extern "system" fn window_proc(
     hwnd: HWND,
     msg: UINT,
@@ -58,16 +219,47 @@ extern "system" fn window_proc(
     l_param: LPARAM,
 ) -> LRESULT {
     match msg {
+        // Closing the window, e.g. from Task Manager, ends the app through the main loop so it cleans up
+        WM_CLOSE => {
+            shutdown::request("window closed");
+            0
+        }
         WM_DESTROY => {
             unsafe { PostQuitMessage(0); }
             0
         }
+        WM_CLIPBOARDUPDATE => {
+            clipboard::handle_update();
+            0
+        }
+        WM_KEYDOWN => {
+            settings::key_down(w_param as i32);
+            0
+        }
+        WM_CHAR => {
+            if let Some(c) = char::from_u32(w_param as u32) {
+                settings::char_typed(c);
+            }
+            0
+        }
+        WM_LBUTTONDOWN => {
+            // Client coordinates, signed since they can be negative on multi-monitor setups
+            let x = (l_param & 0xFFFF) as i16 as f32;
+            let y = ((l_param >> 16) & 0xFFFF) as i16 as f32;
+            settings::click(x, y);
+            0
+        }
+        WM_INPUT => {
+            relative::handle_raw_input(l_param);
+            // DefWindowProc frees the raw input buffer
+            unsafe { DefWindowProcW(hwnd, msg, w_param, l_param) }
+        }
         _ => unsafe { DefWindowProcW(hwnd, msg, w_param, l_param) },
     }
 }
 
 // Helper function to convert &str to wide string
-fn to_wstring(value: &str) -> Vec<u16> {
+pub fn to_wstring(value: &str) -> Vec<u16> {
     std::ffi::OsStr::new(value)
                     .encode_wide()
                     .chain(std::iter::once(0))